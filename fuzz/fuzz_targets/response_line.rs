@@ -0,0 +1,26 @@
+//! Parses a single line with every response parser.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mpvsock::command::response::{MpvResponse, MpvResponseEvent, MpvResponseRef};
+
+fuzz_target!(|line: &[u8]| {
+	if let Ok(MpvResponse::Event(event)) = serde_json::from_slice::<MpvResponse>(line) {
+		let _ = event.name();
+		let _ = event.kind();
+		// events serialize back into the model they were parsed from
+		let serialized = serde_json::to_vec(&event).unwrap();
+		serde_json::from_slice::<MpvResponseEvent>(&serialized).unwrap();
+	}
+
+	match MpvResponseRef::from_slice(line) {
+		Ok(MpvResponseRef::Event(event)) => {
+			let _ = event.parse_data::<serde_json::Value>();
+			let _ = event.to_owned_event();
+		}
+		Ok(MpvResponseRef::Result(result)) => {
+			let _ = result.to_owned_result::<serde_json::Value>();
+		}
+		Err(_) => ()
+	}
+});
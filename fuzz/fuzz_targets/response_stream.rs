@@ -0,0 +1,52 @@
+//! Feeds arbitrary chunks into `ResponseBuffer` and parses the lines it splits them into.
+#![no_main]
+
+use std::io::{self, Read};
+
+use libfuzzer_sys::fuzz_target;
+use mpvsock::{command::response::MpvResponse, response_buffer::ResponseBuffer};
+
+/// Stream returning the chunks one read at a time, then `WouldBlock`.
+struct Chunks<'a> {
+	chunks: Vec<&'a [u8]>
+}
+impl Read for Chunks<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let chunk = match self.chunks.first_mut() {
+			None => return Err(io::ErrorKind::WouldBlock.into()),
+			Some(chunk) => chunk
+		};
+
+		let count = chunk.len().min(buf.len());
+		buf[.. count].copy_from_slice(&chunk[.. count]);
+		*chunk = &chunk[count ..];
+		if chunk.is_empty() {
+			self.chunks.remove(0);
+		}
+
+		Ok(count)
+	}
+}
+
+fuzz_target!(|data: &[u8]| {
+	// the first byte chooses the chunk size so that lines get split at every position
+	let (chunk_size, data) = match data.split_first() {
+		None => return,
+		Some((&size, data)) => (usize::from(size).max(1), data)
+	};
+	let mut stream = Chunks {
+		chunks: data.chunks(chunk_size).collect()
+	};
+
+	let mut buffer = ResponseBuffer::new();
+	let mut lines = 0;
+	while buffer.read_nonblocking(&mut stream).unwrap_or(0) > 0 {
+		while let Some(line) = buffer.consume_line() {
+			lines += 1;
+			let _ = serde_json::from_slice::<MpvResponse>(line);
+		}
+	}
+
+	// every delimiter ends exactly one line
+	assert_eq!(lines, data.iter().filter(|&&b| b == b'\n').count());
+});
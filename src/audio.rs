@@ -0,0 +1,131 @@
+use std::borrow::Cow;
+
+use crate::{
+	command::{
+		commands::{CmdGetProperty, CmdSetProperty},
+		property::WritableMpvProperty,
+		ParseError
+	},
+	link::{CommandError, MpvLink}
+};
+
+/// Named set of audio property values applied together, such as a "night" profile with normalized downmix
+/// and album ReplayGain.
+///
+/// ```
+/// use mpvsock::{audio::AudioProfile, command::property, model::ReplayGain};
+///
+/// let night = AudioProfile::new("night").with(property::AudioNormalizeDownmix, true);
+/// let night = night.with(property::Replaygain, ReplayGain::Album);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioProfile {
+	name: String,
+	/// Property names and values, later values of the same property replace earlier ones.
+	values: Vec<(String, serde_json::Value)>
+}
+impl AudioProfile {
+	pub fn new(name: impl Into<String>) -> Self {
+		AudioProfile {
+			name: name.into(),
+			values: Vec::new()
+		}
+	}
+
+	/// Adds `property` set to `value` to the profile.
+	///
+	/// # Panics
+	/// Panics if `value` does not serialize to JSON, which does not happen for the value types of known properties.
+	pub fn with<P: WritableMpvProperty>(mut self, property: P, value: P::Value) -> Self {
+		let name = property.name().into_owned();
+		let value = serde_json::to_value(value).expect("property value must serialize to JSON");
+
+		self.values.retain(|(existing, _)| *existing != name);
+		self.values.push((name, value));
+		self
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Property names and values of the profile, in the order they are set.
+	pub fn values(&self) -> &[(String, serde_json::Value)] {
+		&self.values
+	}
+
+	/// Sets all values of the profile in one batch, see `MpvLink::run_commands_batched`.
+	///
+	/// The previous values are read first in another batch. If any value is rejected, the properties which
+	/// were already set are restored so that the profile is applied completely or not at all, and the first
+	/// error is returned.
+	pub fn apply(&self, link: &mut MpvLink) -> Result<(), CommandError<ParseError>> {
+		let commands: Vec<_> = self
+			.values
+			.iter()
+			.map(|(name, _)| CmdGetProperty::new(Cow::Borrowed(name.as_str())))
+			.collect();
+		let previous = link
+			.run_commands_batched(&commands)?
+			.into_iter()
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let commands: Vec<_> = self
+			.values
+			.iter()
+			.map(|(name, value)| CmdSetProperty::new(Cow::Borrowed(name.as_str()), value.clone()))
+			.collect();
+		let results = link.run_commands_batched(&commands)?;
+		if results.iter().all(Result::is_ok) {
+			log::debug!("Applied audio profile {}", self.name);
+			return Ok(())
+		}
+
+		let restore: Vec<_> = self
+			.values
+			.iter()
+			.zip(previous)
+			.zip(results.iter())
+			.filter(|(_, result)| result.is_ok())
+			.map(|(((name, _), previous), _)| {
+				CmdSetProperty::new(Cow::Borrowed(name.as_str()), previous)
+			})
+			.collect();
+		for result in link.run_commands_batched(&restore)? {
+			if let Err(err) = result {
+				log::warn!(
+					"Could not restore property after failing to apply audio profile {}: {}",
+					self.name,
+					err
+				);
+			}
+		}
+
+		match results.into_iter().find_map(Result::err) {
+			Some(err) => Err(err),
+			None => Ok(())
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::AudioProfile;
+	use crate::{command::property, model::ReplayGain};
+
+	#[test]
+	fn test_profile_values() {
+		let profile = AudioProfile::new("night")
+			.with(property::VolumeGain, -6.0)
+			.with(property::Replaygain, ReplayGain::Album)
+			.with(property::VolumeGain, -3.0);
+
+		assert_eq!(
+			profile.values(),
+			[
+				("replaygain".to_string(), serde_json::json!("album")),
+				("volume-gain".to_string(), serde_json::json!(-3.0))
+			]
+		);
+	}
+}
@@ -0,0 +1,119 @@
+use std::{
+	collections::HashMap,
+	ffi::OsString,
+	fs,
+	io,
+	path::{Path, PathBuf}
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+use serde::Deserialize;
+
+/// Named sequences of lines in the interactive syntax.
+pub type Aliases = HashMap<String, Vec<String>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkMode {
+	Connect,
+	Auto,
+	SpawnServer,
+	SpawnClient
+}
+
+/// Defaults of `mpv-client` read from `~/.config/mpvsock/config.toml`, or `config.json` next to it.
+///
+/// Options given on the command line take precedence, except for `mpv-args` which are passed to a spawned
+/// mpv before the arguments after `--`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+	/// How to reach mpv when no link option is given.
+	pub link: Option<LinkMode>,
+	/// Socket path for `connect` and `spawn-server`.
+	pub socket: Option<String>,
+	pub launcher: Option<String>,
+	/// Arguments passed to a spawned mpv.
+	pub mpv_args: Vec<String>,
+	/// `text` or `json`.
+	pub output: Option<String>,
+	pub aliases: Aliases
+}
+impl Config {
+	/// Reads the config from `path`, or from the default location if there is a file.
+	///
+	/// Files ending with `.json` are parsed as JSON, others as TOML.
+	pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+		let path = match path {
+			Some(path) => path.to_path_buf(),
+			None => match Self::default_path() {
+				Some(path) => path,
+				None => return Ok(Config::default())
+			}
+		};
+		log::debug!("Reading config from {}", path.display());
+
+		let text = fs::read_to_string(&path)
+			.with_context(|| format!("Could not read config {}", path.display()))?;
+		let config = match path.extension() {
+			Some(extension) if extension == "json" => {
+				serde_json::from_str(&text).map_err(anyhow::Error::from)
+			}
+			_ => toml::from_str(&text).map_err(anyhow::Error::from)
+		};
+
+		config.with_context(|| format!("Could not parse config {}", path.display()))
+	}
+
+	/// First existing of `config.toml` and `config.json` in `$XDG_CONFIG_HOME/mpvsock` or `~/.config/mpvsock`.
+	fn default_path() -> Option<PathBuf> {
+		let config_home = std::env::var_os("XDG_CONFIG_HOME")
+			.filter(|home| !home.is_empty())
+			.map(PathBuf::from)
+			.or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+		let directory = config_home.join("mpvsock");
+
+		["config.toml", "config.json"]
+			.into_iter()
+			.map(|name| directory.join(name))
+			.find(|path| match fs::metadata(path) {
+				Ok(_) => true,
+				Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+				// reported when reading it
+				Err(_) => true
+			})
+	}
+
+	/// Returns options for the settings not given in `matches`, to be inserted before the command line
+	/// arguments.
+	pub fn default_args(&self, matches: &ArgMatches) -> anyhow::Result<Vec<OsString>> {
+		let mut args: Vec<OsString> = Vec::new();
+
+		let has_link = ["connect", "auto", "spawn_server", "spawn_client"]
+			.iter()
+			.any(|name| matches.is_present(name));
+		if let (false, Some(link)) = (has_link, self.link) {
+			let socket = || {
+				self.socket
+					.clone()
+					.with_context(|| format!("Config link {:?} requires socket", link))
+			};
+			match link {
+				LinkMode::Connect => args.extend(["--connect".into(), socket()?.into()]),
+				LinkMode::Auto => args.push("--auto".into()),
+				LinkMode::SpawnServer => args.extend(["--spawn-server".into(), socket()?.into()]),
+				LinkMode::SpawnClient => args.push("--spawn-client".into())
+			}
+		}
+		if let (false, Some(launcher)) = (matches.is_present("launcher"), self.launcher.as_ref()) {
+			args.extend(["--launcher".into(), launcher.into()]);
+		}
+		// `output` has a default value, so it is always present
+		if let (0, Some(output)) = (matches.occurrences_of("output"), self.output.as_ref()) {
+			args.extend(["--output".into(), output.into()]);
+		}
+
+		Ok(args)
+	}
+}
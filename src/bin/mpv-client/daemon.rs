@@ -0,0 +1,224 @@
+use std::{
+	fs,
+	io::{self, Write},
+	os::unix::{
+		fs::FileTypeExt,
+		net::{UnixListener, UnixStream}
+	},
+	path::Path,
+	time::Duration
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+
+use mpvsock::{
+	command::response::MpvResponseEvent,
+	link::MpvLink,
+	response_buffer::ResponseBuffer
+};
+
+use crate::{
+	config::Aliases,
+	interactive::{InteractiveContext, LineOutcome, ObserverIds}
+};
+
+/// How often the control socket and mpv are polled.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How many times reopening the link is attempted before giving up.
+const REOPEN_ATTEMPTS: u32 = 5;
+const REOPEN_DELAY: Duration = Duration::from_secs(1);
+
+struct ControlClient {
+	stream: UnixStream,
+	buffer: ResponseBuffer,
+	context: InteractiveContext
+}
+
+/// Keeps the link open and executes commands received on a control socket.
+///
+/// Each connection to the control socket accepts lines with the same syntax as the interactive mode
+/// and receives their output, along with changes of the properties it observes. When the link fails, for
+/// example because mpv exited, it is reopened with `open_link`, which respawns mpv when it was spawned by
+/// this process.
+pub fn run(
+	matches: &ArgMatches,
+	mpv: &mut MpvLink,
+	aliases: &Aliases,
+	mut open_link: impl FnMut() -> anyhow::Result<MpvLink>
+) -> anyhow::Result<()> {
+	let control_path = Path::new(matches.value_of("control").unwrap());
+	let listener = bind_control(control_path)?;
+	listener.set_nonblocking(true)?;
+	log::info!("Listening on {}", control_path.display());
+
+	#[cfg(feature = "prometheus")]
+	let mut metrics = match matches.value_of("metrics") {
+		None => None,
+		Some(address) => {
+			let mut metrics = crate::metrics::MetricsExporter::bind(address)?;
+			metrics.attach(mpv)?;
+			log::info!("Exporting metrics on {}", address);
+
+			Some(metrics)
+		}
+	};
+
+	// observer ids are unique across clients, since they share the link
+	let observer_ids = ObserverIds::new(1.into());
+	let mut clients: Vec<ControlClient> = Vec::new();
+	loop {
+		loop {
+			match listener.accept() {
+				Ok((stream, _)) => {
+					stream.set_nonblocking(true)?;
+
+					let mut context = InteractiveContext::new(matches)
+						.with_aliases(aliases.clone())
+						.with_shared_link(observer_ids.clone());
+					context.load_property_list(mpv);
+					clients.push(ControlClient {
+						stream,
+						buffer: ResponseBuffer::new(),
+						context
+					});
+					log::info!("Accepted control client");
+				}
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+				Err(err) => return Err(err.into())
+			}
+		}
+
+		clients.retain_mut(|client| match serve_client(client, mpv) {
+			Ok(keep) => keep,
+			Err(err) => {
+				log::warn!("Dropping control client: {}", err);
+				false
+			}
+		});
+
+		match mpv.poll_events() {
+			Ok(()) => {
+				for event in mpv.drain_events() {
+					log::debug!("Event: {:?}", event);
+
+					#[cfg(feature = "prometheus")]
+					if let Some(metrics) = metrics.as_mut() {
+						metrics.update(&event);
+					}
+
+					clients.retain_mut(|client| match route_event(client, &event) {
+						Ok(()) => true,
+						Err(err) => {
+							log::warn!("Dropping control client: {}", err);
+							false
+						}
+					});
+				}
+			}
+			Err(err) => {
+				log::warn!("Link failed, reopening: {}", err);
+				*mpv = reopen(&mut open_link)?;
+				// the observers belonged to the previous link
+				for client in clients.iter_mut() {
+					client.context.clear_observers();
+				}
+
+				#[cfg(feature = "prometheus")]
+				if let Some(metrics) = metrics.as_mut() {
+					metrics.attach(mpv)?;
+				}
+			}
+		}
+
+		#[cfg(feature = "prometheus")]
+		if let Some(metrics) = metrics.as_mut() {
+			metrics.serve(mpv)?;
+		}
+
+		std::thread::sleep(POLL_INTERVAL);
+	}
+}
+
+/// Executes all complete lines received from `client`.
+///
+/// Returns `false` when the client disconnected or quit.
+fn serve_client(client: &mut ControlClient, mpv: &mut MpvLink) -> anyhow::Result<bool> {
+	let connected = match client.buffer.read_nonblocking(&client.stream) {
+		Ok(_) => true,
+		Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => false,
+		Err(err) => return Err(err.into())
+	};
+
+	while let Some(line) = client.buffer.consume_line() {
+		let line = String::from_utf8_lossy(line).trim().to_string();
+		if line.is_empty() {
+			continue
+		}
+
+		let mut output = Vec::new();
+		let outcome = client.context.execute(&line, mpv, &mut output)?;
+		write_output(&client.stream, &output)?;
+
+		if outcome == LineOutcome::Quit {
+			return Ok(false)
+		}
+	}
+
+	Ok(connected)
+}
+
+/// Passes `event` to the context of `client`, writing the changes of its observed properties to it.
+fn route_event(client: &mut ControlClient, event: &MpvResponseEvent) -> anyhow::Result<()> {
+	let mut output = Vec::new();
+	client.context.route_event(event, &mut output)?;
+	write_output(&client.stream, &output)?;
+
+	Ok(())
+}
+
+/// Writes `output` to the nonblocking `stream`, waiting while it is full.
+fn write_output(mut stream: &UnixStream, mut output: &[u8]) -> io::Result<()> {
+	while !output.is_empty() {
+		match stream.write(output) {
+			Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+			Ok(written) => output = &output[written ..],
+			Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+				std::thread::sleep(POLL_INTERVAL)
+			}
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+			Err(err) => return Err(err)
+		}
+	}
+
+	Ok(())
+}
+
+fn reopen(open_link: &mut impl FnMut() -> anyhow::Result<MpvLink>) -> anyhow::Result<MpvLink> {
+	let mut attempt = 1;
+	loop {
+		match open_link() {
+			Ok(link) => return Ok(link),
+			Err(err) if attempt < REOPEN_ATTEMPTS => {
+				log::warn!("Could not reopen link (attempt {}): {}", attempt, err);
+				attempt += 1;
+				std::thread::sleep(REOPEN_DELAY);
+			}
+			Err(err) => return Err(err.context("Could not reopen link"))
+		}
+	}
+}
+
+/// Binds the control socket, removing a stale socket left at `path`.
+fn bind_control(path: &Path) -> anyhow::Result<UnixListener> {
+	if fs::metadata(path)
+		.map(|m| m.file_type().is_socket())
+		.unwrap_or(false)
+	{
+		log::info!("Removing existing socket at {}", path.display());
+		fs::remove_file(path)?;
+	}
+
+	UnixListener::bind(path)
+		.with_context(|| format!("Could not bind control socket at {}", path.display()))
+}
@@ -0,0 +1,118 @@
+use std::{
+	fs,
+	os::unix::{fs::FileTypeExt, net::UnixStream},
+	path::{Path, PathBuf},
+	time::SystemTime
+};
+
+/// A live mpv socket found by `find_sockets`.
+#[derive(Debug)]
+pub struct SocketCandidate {
+	pub path: PathBuf,
+	pub modified: SystemTime
+}
+
+/// Searches the standard locations for live mpv sockets.
+///
+/// Looks at `$XDG_RUNTIME_DIR/mpv*`, `/tmp/mpvsocket*` and `input-ipc-server=` lines in `mpv.conf`.
+/// Only sockets which accept a connection are returned, the most recently modified first.
+pub fn find_sockets() -> Vec<SocketCandidate> {
+	let mut paths = Vec::new();
+
+	if let Some(runtime_dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+		list_prefixed(Path::new(&runtime_dir), "mpv", &mut paths);
+	}
+	list_prefixed(Path::new("/tmp"), "mpvsocket", &mut paths);
+	if let Some(config_path) = config_path() {
+		if let Ok(config) = fs::read_to_string(config_path) {
+			paths.extend(parse_ipc_server_paths(&config));
+		}
+	}
+
+	paths.sort();
+	paths.dedup();
+
+	let mut candidates: Vec<SocketCandidate> = paths
+		.into_iter()
+		.filter_map(|path| {
+			let metadata = fs::metadata(&path).ok()?;
+			if !metadata.file_type().is_socket() {
+				return None
+			}
+
+			// stale sockets of exited instances refuse connections
+			if let Err(err) = UnixStream::connect(&path) {
+				log::debug!("Skipping socket {}: {}", path.display(), err);
+				return None
+			}
+
+			Some(SocketCandidate {
+				modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+				path
+			})
+		})
+		.collect();
+	candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.modified));
+
+	candidates
+}
+
+/// Finds the socket to connect to, listing all candidates on stderr when there is more than one.
+pub fn find_socket() -> anyhow::Result<PathBuf> {
+	let mut candidates = find_sockets();
+
+	match candidates.len() {
+		0 => anyhow::bail!("Could not find a live mpv socket"),
+		1 => (),
+		_ => {
+			eprintln!("Found multiple mpv sockets, using the most recent one:");
+			for candidate in candidates.iter() {
+				eprintln!("\t{}", candidate.path.display());
+			}
+		}
+	}
+
+	Ok(candidates.swap_remove(0).path)
+}
+
+fn list_prefixed(dir: &Path, prefix: &str, paths: &mut Vec<PathBuf>) {
+	let entries = match fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(err) => {
+			log::debug!("Could not read {}: {}", dir.display(), err);
+			return
+		}
+	};
+
+	for entry in entries.flatten() {
+		if entry.file_name().to_string_lossy().starts_with(prefix) {
+			paths.push(entry.path());
+		}
+	}
+}
+
+/// Returns `$XDG_CONFIG_HOME/mpv/mpv.conf`, defaulting to `~/.config/mpv/mpv.conf`.
+fn config_path() -> Option<PathBuf> {
+	let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+		Some(path) if !path.is_empty() => PathBuf::from(path),
+		_ => PathBuf::from(std::env::var_os("HOME")?).join(".config")
+	};
+
+	Some(config_home.join("mpv").join("mpv.conf"))
+}
+
+/// Parses `input-ipc-server=path` lines of a `mpv.conf`, expanding a leading `~/`.
+fn parse_ipc_server_paths(config: &str) -> impl Iterator<Item = PathBuf> + '_ {
+	config.lines().filter_map(|line| {
+		let value = line.trim().strip_prefix("input-ipc-server=")?;
+		let value = value.trim().trim_matches('"');
+		if value.is_empty() {
+			return None
+		}
+
+		match value.strip_prefix("~/") {
+			Some(rest) => Some(PathBuf::from(std::env::var_os("HOME")?).join(rest)),
+			None => Some(PathBuf::from(value))
+		}
+	})
+}
@@ -0,0 +1,61 @@
+use std::io::{self, Write};
+
+use clap::ArgMatches;
+
+use mpvsock::{command::response::MpvEventKind, link::MpvLink};
+
+use crate::output::OutputFormat;
+
+/// Prints all or only the selected events as they arrive.
+///
+/// Runs until interrupted or until mpv shuts down.
+pub fn run(matches: &ArgMatches, mpv: &mut MpvLink) -> anyhow::Result<()> {
+	let filter: Option<Vec<MpvEventKind>> = matches.values_of("filter").map(|values| {
+		values
+			.map(|name| match name.parse() {
+				Ok(kind) => kind,
+				Err(never) => match never {}
+			})
+			.collect()
+	});
+	let json =
+		matches.is_present("json") || OutputFormat::from_matches(matches) == OutputFormat::Json;
+
+	let stdout = io::stdout();
+	let mut stdout = stdout.lock();
+	let mut shutdown = false;
+	while !shutdown {
+		let mut write_result = Ok(());
+		mpv.poll_events_with(|event| {
+			if write_result.is_err() {
+				return
+			}
+			if event.event == "shutdown" {
+				shutdown = true;
+			}
+
+			if let Some(filter) = filter.as_ref() {
+				if !filter.iter().any(|kind| kind.as_str() == event.event) {
+					return
+				}
+			}
+
+			write_result = if json {
+				stdout.write_all(event.raw).and_then(|_| writeln!(stdout))
+			} else {
+				match event.to_owned_event() {
+					Ok(event) => writeln!(stdout, "{:?}", event),
+					Err(_) => writeln!(stdout, "{}", String::from_utf8_lossy(event.raw))
+				}
+			};
+		})?;
+		write_result?;
+		stdout.flush()?;
+
+		if !shutdown {
+			mpv.wait_read(None)?;
+		}
+	}
+
+	Ok(())
+}
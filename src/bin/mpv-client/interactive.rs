@@ -0,0 +1,632 @@
+use std::{
+	cell::Cell,
+	fmt::Write as FmtWrite,
+	io::{self, Write},
+	rc::Rc
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+
+use mpvsock::{
+	command::{
+		commands::{
+			CmdClientName,
+			CmdCycleProperty,
+			CmdGetProperty,
+			CmdGetVersion,
+			CmdObserveProperty,
+			CmdRawJsonArgs,
+			CmdRawText,
+			CmdSeek,
+			CmdSetProperty,
+			CmdUnobserveProperty,
+			SeekMode,
+			SeekPrecision
+		},
+		property::{self, KnownMpvProperty},
+		response::MpvResponseEvent
+	},
+	link::MpvLink
+};
+
+use crate::{config::Aliases, output::OutputFormat, prompt::Prompt};
+
+enum InputMode {
+	Raw,
+	String,
+	Known
+}
+
+/// The outcome of executing one line of input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineOutcome {
+	Success,
+	Failure,
+	Quit
+}
+
+/// Next observer id of `#observe`, shared by contexts which use the same link.
+pub type ObserverIds = Rc<Cell<u32>>;
+
+pub struct InteractiveContext {
+	line: String,
+	command: String,
+	mode: InputMode,
+	/// Properties supported by the connected mpv, queried on start.
+	property_list: Option<Vec<String>>,
+	output: OutputFormat,
+	/// Observer ids and names of properties observed with `#observe`.
+	observers: Vec<(u32, String)>,
+	next_observer_id: ObserverIds,
+	/// Events routed to this context with `route_event` when the link is shared, formatted for `#events`.
+	///
+	/// `None` when `#events` drains the link instead.
+	routed_events: Option<Vec<Vec<u8>>>,
+	/// Whether the round-trip time of commands is printed, toggled with `#timing`.
+	timing: bool,
+	aliases: Aliases
+}
+macro_rules! write_result_and_bail {
+	(
+		$out: expr, $output: expr; $result: expr
+	) => {
+		match $result {
+			Ok(result) => {
+				$output.write_result(&mut $out, &result)?;
+
+				return Ok(LineOutcome::Success)
+			}
+			Err(err) => {
+				$output.write_error(&mut $out, err)?;
+
+				return Ok(LineOutcome::Failure)
+			}
+		}
+	};
+}
+macro_rules! write_error_and_bail {
+	(
+		$out: expr, $output: expr; $result: expr
+	) => {
+		match $result {
+			Ok(result) => result,
+			Err(err) => {
+				$output.write_error(&mut $out, err)?;
+
+				return Ok(LineOutcome::Failure)
+			}
+		}
+	};
+}
+impl InteractiveContext {
+	pub fn new(matches: &ArgMatches) -> Self {
+		InteractiveContext {
+			line: String::new(),
+			command: String::new(),
+			mode: InputMode::String,
+			property_list: None,
+			output: OutputFormat::from_matches(matches),
+			observers: Vec::new(),
+			next_observer_id: Rc::new(Cell::new(1)),
+			routed_events: None,
+			timing: false,
+			aliases: Aliases::new()
+		}
+	}
+
+	/// Sets the aliases which expand a line consisting only of their name into their lines.
+	pub fn with_aliases(mut self, aliases: Aliases) -> Self {
+		self.aliases = aliases;
+		self
+	}
+
+	/// Shares the link with other contexts, which take observer ids from `observer_ids` as well.
+	///
+	/// The events are drained from the link by the owner of the contexts and passed to `route_event`.
+	pub fn with_shared_link(mut self, observer_ids: ObserverIds) -> Self {
+		self.next_observer_id = observer_ids;
+		self.routed_events = Some(Vec::new());
+		self
+	}
+
+	/// Routes an event drained from a shared link to this context.
+	///
+	/// Changes of properties observed with `#observe` by this context are printed to `out` right away. They and
+	/// events other than property changes are kept for `#events`.
+	pub fn route_event(
+		&mut self,
+		event: &MpvResponseEvent,
+		mut out: impl Write
+	) -> anyhow::Result<()> {
+		/// How many events are kept for `#events` before the oldest are discarded.
+		const ROUTED_EVENTS_CAPACITY: usize = 256;
+
+		let observed = match event {
+			MpvResponseEvent::PropertyChange { id, .. } if self.is_observer(*id) => true,
+			// observed by another context
+			MpvResponseEvent::PropertyChange { .. } => return Ok(()),
+			_ => false
+		};
+
+		let mut formatted = Vec::new();
+		self.output.write_event(&mut formatted, event)?;
+		if observed {
+			out.write_all(&formatted)?;
+		}
+
+		if let Some(events) = self.routed_events.as_mut() {
+			if events.len() >= ROUTED_EVENTS_CAPACITY {
+				events.remove(0);
+			}
+			events.push(formatted);
+		}
+
+		Ok(())
+	}
+
+	/// Forgets the observers of `#observe`, for when the link was reopened and they no longer exist.
+	pub fn clear_observers(&mut self) {
+		self.observers.clear();
+	}
+
+	fn is_observer(&self, id: i64) -> bool {
+		self.observers
+			.iter()
+			.any(|(observer_id, _)| i64::from(*observer_id) == id)
+	}
+
+	pub fn run(&mut self, mpv: &mut MpvLink) -> anyhow::Result<()> {
+		let mut prompt = Prompt::new()?;
+		let mut stdout = io::stdout();
+
+		self.load_property_list(mpv);
+		if let Some(property_list) = self.property_list.as_ref() {
+			prompt.set_properties(property_list.clone());
+		}
+
+		self.write_help(&mut stdout)?;
+
+		loop {
+			self.write_property_changes(mpv, &mut stdout)?;
+
+			if !prompt.read_line("Input: ", &mut self.line)? {
+				break
+			}
+			if self.line.trim().is_empty() {
+				continue
+			}
+
+			if self.execute_line(mpv, &mut stdout)? == LineOutcome::Quit {
+				break
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Queries the properties supported by mpv, which are then used to validate property names.
+	pub fn load_property_list(&mut self, mpv: &mut MpvLink) {
+		match mpv.run_command(&CmdGetProperty::new(property::PropertyList)) {
+			Ok(property_list) => {
+				self.property_list = Some(property_list);
+			}
+			Err(err) => log::warn!("Could not query property-list: {}", err)
+		}
+	}
+
+	/// Prints changes of properties observed with `#observe` received since the last call.
+	///
+	/// The events stay queued, so they are printed by `#events` as well.
+	fn write_property_changes(&self, mpv: &mut MpvLink, mut out: impl Write) -> anyhow::Result<()> {
+		if self.observers.is_empty() {
+			return Ok(())
+		}
+
+		for event in mpv.poll_new_events()? {
+			if let MpvResponseEvent::PropertyChange { id, .. } = event {
+				if self.is_observer(*id) {
+					self.output.write_event(&mut out, event)?;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Executes `line` as if it was entered into the prompt.
+	pub fn execute(
+		&mut self,
+		line: &str,
+		mpv: &mut MpvLink,
+		out: impl Write
+	) -> anyhow::Result<LineOutcome> {
+		self.line.clear();
+		self.line.push_str(line);
+
+		self.execute_line(mpv, out)
+	}
+
+	fn execute_line(
+		&mut self,
+		mpv: &mut MpvLink,
+		mut out: impl Write
+	) -> anyhow::Result<LineOutcome> {
+		let lines = match self.aliases.get(self.line.trim()) {
+			None => return self.execute_single_line(mpv, out),
+			Some(lines) => lines.clone()
+		};
+
+		// lines of aliases are not expanded again, so that aliases cannot recurse
+		for line in lines {
+			if line.trim().is_empty() {
+				continue
+			}
+			self.line = line;
+
+			match self.execute_single_line(mpv, &mut out)? {
+				LineOutcome::Success => (),
+				outcome => return Ok(outcome)
+			}
+		}
+
+		Ok(LineOutcome::Success)
+	}
+
+	fn execute_single_line(
+		&mut self,
+		mpv: &mut MpvLink,
+		mut out: impl Write
+	) -> anyhow::Result<LineOutcome> {
+		if self.line.starts_with('#') {
+			return self.handle_input_command(out, mpv)
+		}
+
+		let results_before = self.timing.then(|| mpv.stats().results_received);
+
+		let outcome = match self.mode {
+			InputMode::Raw => self.run_raw_command(mpv, &mut out),
+			InputMode::String => self.run_string_command(mpv, &mut out),
+			InputMode::Known => self.run_known_command(mpv, &mut out)
+		}?;
+
+		// raw commands do not wait for their result, so there is nothing to measure
+		let stats = mpv.stats();
+		if let (Some(results_before), Some(latency)) = (results_before, stats.last_latency) {
+			if stats.results_received > results_before {
+				self.output.write_timing(&mut out, latency)?;
+			}
+		}
+
+		Ok(outcome)
+	}
+
+	fn handle_input_command(
+		&mut self,
+		mut out: impl Write,
+		mpv: &mut MpvLink
+	) -> anyhow::Result<LineOutcome> {
+		if let Some(duration) = self.line.strip_prefix("#sleep ") {
+			let duration = write_error_and_bail!(
+				&mut out, self.output; duration.trim().parse::<u64>().context("#sleep expects a number of milliseconds")
+			);
+			std::thread::sleep(std::time::Duration::from_millis(duration));
+
+			return Ok(LineOutcome::Success)
+		}
+
+		if let Some(property_name) = self.line.strip_prefix("#observe ") {
+			let property_name = property_name.trim().to_string();
+			write_error_and_bail!(&mut out, self.output; self.validate_property(&property_name));
+
+			let observer_id = self.next_observer_id.get();
+			write_error_and_bail!(
+				&mut out, self.output; mpv.run_command(&CmdObserveProperty::new(observer_id, property_name.as_str()))
+			);
+			self.next_observer_id.set(observer_id + 1);
+			self.observers.push((observer_id, property_name));
+
+			self.output.write_result(&mut out, &observer_id)?;
+
+			return Ok(LineOutcome::Success)
+		}
+
+		if let Some(observer_id) = self.line.strip_prefix("#unobserve ") {
+			let observer_id = write_error_and_bail!(
+				&mut out, self.output; observer_id.trim().parse::<u32>().context("#unobserve expects an observer id")
+			);
+			if !self.observers.iter().any(|(id, _)| *id == observer_id) {
+				self.output
+					.write_error(&mut out, format!("No observer with id {}", observer_id))?;
+
+				return Ok(LineOutcome::Failure)
+			}
+
+			write_error_and_bail!(
+				&mut out, self.output; mpv.run_command(&CmdUnobserveProperty::new(observer_id))
+			);
+			self.observers.retain(|(id, _)| *id != observer_id);
+
+			return Ok(LineOutcome::Success)
+		}
+
+		let res = match self.line.as_str() {
+			"#events" => {
+				let events = match self.routed_events.as_mut() {
+					Some(events) => std::mem::take(events),
+					None => {
+						mpv.poll_events()?;
+
+						let mut events = Vec::new();
+						for event in mpv.drain_events() {
+							let mut formatted = Vec::new();
+							self.output.write_event(&mut formatted, &event)?;
+							events.push(formatted);
+						}
+						events
+					}
+				};
+				if self.output == OutputFormat::Text {
+					writeln!(&mut out, "Events ({}):", events.len())?;
+				}
+				for event in events {
+					out.write_all(&event)?;
+				}
+
+				LineOutcome::Success
+			}
+			"#mode raw" => {
+				self.set_mode(InputMode::Raw, &mut out)?;
+
+				LineOutcome::Success
+			}
+			"#mode string" => {
+				self.set_mode(InputMode::String, &mut out)?;
+
+				LineOutcome::Success
+			}
+			"#mode known" => {
+				self.set_mode(InputMode::Known, &mut out)?;
+
+				LineOutcome::Success
+			}
+			"#timing on" => {
+				// stats stay enabled when timing is turned off, since they may be used by others
+				mpv.set_stats_enabled(true);
+				self.timing = true;
+
+				LineOutcome::Success
+			}
+			"#timing off" => {
+				self.timing = false;
+
+				LineOutcome::Success
+			}
+			"#quit" => LineOutcome::Quit,
+			"#help" => {
+				self.write_help(&mut out)?;
+
+				LineOutcome::Success
+			}
+			_ => {
+				self.output.write_error(&mut out, "Invalid input command")?;
+
+				LineOutcome::Failure
+			}
+		};
+
+		Ok(res)
+	}
+
+	fn write_help(&self, mut out: impl Write) -> Result<(), io::Error> {
+		writeln!(&mut out, "Help:")?;
+		writeln!(
+			&mut out,
+			"\tInput commands:\n\t\t#help\n\t\t#events\n\t\t#observe <property>\n\t\t#unobserve <id>\n\t\t#mode raw|string|known\n\t\t#timing on|off\n\t\t#sleep <ms>\n\t\t#quit"
+		)?;
+
+		self.write_mode(&mut out)?;
+
+		if !self.aliases.is_empty() {
+			let mut names: Vec<&str> = self.aliases.keys().map(String::as_str).collect();
+			names.sort_unstable();
+			writeln!(&mut out, "\tAliases: {}", names.join(" "))?;
+		}
+
+		writeln!(&mut out)?;
+
+		Ok(())
+	}
+
+	/// Switches the input mode, describing the new mode only in text output so JSON output stays parseable.
+	fn set_mode(&mut self, mode: InputMode, out: impl Write) -> Result<(), io::Error> {
+		self.mode = mode;
+
+		if self.output == OutputFormat::Text {
+			self.write_mode(out)?;
+		}
+
+		Ok(())
+	}
+
+	fn write_mode(&self, mut out: impl Write) -> Result<(), io::Error> {
+		match self.mode {
+			InputMode::Raw => {
+				writeln!(
+					&mut out,
+					"\tRaw mode is on, input is directly pasted as command"
+				)?;
+			}
+			InputMode::String => {
+				writeln!(&mut out, "\tString mode is on, input is split by spaces and elements are quoted (prefix element with @ to disable quoting), then used as JSON array elements")?;
+			}
+			InputMode::Known => {
+				writeln!(&mut out, "\tKnown mode is on, only known commands are accepted and their result is properly parsed")?;
+				writeln!(
+					&mut out,
+					"\tKnown commands: get_version client_name get_property set_property cycle seek"
+				)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn run_raw_command(
+		&mut self,
+		mpv: &mut MpvLink,
+		mut out: impl Write
+	) -> anyhow::Result<LineOutcome> {
+		write_result_and_bail!(
+			out, self.output;
+			mpv.run_command_raw::<_, std::convert::Infallible>(
+				&CmdRawText::new(self.line.as_str())
+			)
+		)
+	}
+
+	fn run_string_command(
+		&mut self,
+		mpv: &mut MpvLink,
+		mut out: impl Write
+	) -> anyhow::Result<LineOutcome> {
+		self.command.clear();
+
+		for word in self.line.split(' ') {
+			if let Some(word) = word.strip_prefix("@@") {
+				write!(&mut self.command, "\"@{}\",", word)?;
+			} else if let Some(word) = word.strip_prefix('@') {
+				write!(&mut self.command, "{},", word)?;
+			} else {
+				write!(&mut self.command, "\"{}\",", word)?;
+			}
+		}
+
+		// remove the trailing comma
+		let command = &self.command[.. self.command.len().saturating_sub(1)];
+
+		write_result_and_bail!(
+			out, self.output;
+			mpv.run_command(
+				&CmdRawJsonArgs::new(command)
+			)
+		)
+	}
+
+	/// Checks that the top-level property of `name` is in the property list, if it was queried.
+	fn validate_property(&self, name: &str) -> anyhow::Result<()> {
+		let property_list = match self.property_list.as_ref() {
+			None => return Ok(()),
+			Some(list) => list
+		};
+
+		let top_level = name.split('/').next().unwrap_or(name);
+		if !property_list.iter().any(|p| p == top_level) {
+			anyhow::bail!("Unknown property {}", top_level)
+		}
+
+		Ok(())
+	}
+
+	fn run_known_command(
+		&mut self,
+		mpv: &mut MpvLink,
+		mut out: impl Write
+	) -> anyhow::Result<LineOutcome> {
+		if self.line.trim() == "get_version" {
+			write_result_and_bail!(out, self.output; mpv.run_command(&CmdGetVersion::new()))
+		}
+
+		if self.line.trim() == "client_name" {
+			write_result_and_bail!(out, self.output; mpv.run_command(&CmdClientName::new()))
+		}
+
+		if self.line.starts_with("get_property ") || self.line.starts_with("get ") {
+			let mut iter = self.line.splitn(2, ' ');
+			iter.next().unwrap(); // get_property
+			let property_name = write_error_and_bail!(
+				&mut out, self.output; iter.next().context("get_property expects an argument")
+			);
+			write_error_and_bail!(&mut out, self.output; self.validate_property(property_name));
+
+			match KnownMpvProperty::from_name(property_name) {
+				Some(known) => {
+					write_result_and_bail!(out, self.output; mpv.run_command(&CmdGetProperty::new(known)))
+				}
+				None => {
+					write_result_and_bail!(out, self.output; mpv.run_command(&CmdGetProperty::new(property_name)))
+				}
+			}
+		}
+
+		if self.line.starts_with("set_property ") || self.line.starts_with("set ") {
+			let mut iter = self.line.splitn(3, ' ');
+			iter.next().unwrap(); // set_property
+			let property_name = write_error_and_bail!(
+				&mut out, self.output; iter.next().context("set_property expects two arguments")
+			);
+			let property_value = write_error_and_bail!(
+				&mut out, self.output; iter.next().context("set_property expects two arguments")
+			);
+			write_error_and_bail!(&mut out, self.output; self.validate_property(property_name));
+
+			// values of known properties are JSON, other values are passed as strings
+			match KnownMpvProperty::from_name(property_name) {
+				Some(known) => {
+					let value = write_error_and_bail!(
+						&mut out, self.output; serde_json::from_str(property_value).context("set_property expects a JSON value")
+					);
+					let command = write_error_and_bail!(&mut out, self.output; CmdSetProperty::known(known, value));
+					write_result_and_bail!(out, self.output; mpv.run_command(&command))
+				}
+				None => {
+					let command = CmdSetProperty::new(property_name, property_value.into());
+					write_result_and_bail!(out, self.output; mpv.run_command(&command))
+				}
+			}
+		}
+
+		if self.line.starts_with("cycle ") {
+			let mut iter = self.line.splitn(2, ' ');
+			iter.next().unwrap(); // cycle
+			let property_name = write_error_and_bail!(
+				&mut out, self.output; iter.next().context("cycle expects an argument")
+			);
+			write_error_and_bail!(&mut out, self.output; self.validate_property(property_name));
+
+			match KnownMpvProperty::from_name(property_name) {
+				Some(known) => {
+					let command = write_error_and_bail!(&mut out, self.output; CmdCycleProperty::known(known, false));
+					write_result_and_bail!(out, self.output; mpv.run_command(&command))
+				}
+				None => {
+					write_result_and_bail!(out, self.output; mpv.run_command(&CmdCycleProperty::new(property_name, false)))
+				}
+			}
+		}
+
+		if self.line.starts_with("seek ") {
+			let mut iter = self.line.split_whitespace();
+			iter.next().unwrap(); // seek
+			let target = write_error_and_bail!(
+				&mut out, self.output; iter.next().context("seek expects at least one argument").and_then(|value| value.parse::<f64>().context("seek expects an f64 first argument"))
+			);
+			let mode = write_error_and_bail!(
+				&mut out, self.output; iter.next().map(str::parse::<SeekMode>).transpose().context("seek expects a seek mode second argument")
+			);
+			let precision = write_error_and_bail!(
+				&mut out, self.output; iter.next().map(str::parse::<SeekPrecision>).transpose().context("seek expects a seek precision third argument")
+			);
+
+			let command = write_error_and_bail!(
+				&mut out, self.output; CmdSeek::new(target, mode.unwrap_or_default())
+			)
+			.with_precision(precision.unwrap_or_default());
+
+			write_result_and_bail!(out, self.output; mpv.run_command(&command))
+		}
+
+		self.output.write_error(&mut out, "Unrecognized command")?;
+		Ok(LineOutcome::Failure)
+	}
+}
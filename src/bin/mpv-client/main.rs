@@ -0,0 +1,372 @@
+use std::{ffi::OsString, path::Path};
+
+use clap::{App, AppSettings, Arg, ArgGroup, ArgMatches, SubCommand};
+
+use mpvsock::link::{spawn::MpvSpawnBuilder, MpvLink, SocketAddress, SocketType};
+
+mod config;
+mod daemon;
+mod discover;
+mod events;
+mod interactive;
+#[cfg(feature = "prometheus")]
+mod metrics;
+#[cfg(feature = "mpris")]
+mod mpris;
+mod oneshot;
+mod output;
+mod prompt;
+mod script;
+mod status;
+mod watch;
+
+use config::Config;
+use interactive::InteractiveContext;
+
+/// Splits the command line at the first `--`, arguments after it are passed to the spawned mpv.
+fn split_mpv_args() -> (Vec<OsString>, Vec<OsString>) {
+	let mut args: Vec<OsString> = std::env::args_os().collect();
+
+	match args.iter().position(|arg| arg == "--") {
+		None => (args, Vec::new()),
+		Some(index) => {
+			let mpv_args = args.split_off(index + 1);
+			args.pop(); // --
+
+			(args, mpv_args)
+		}
+	}
+}
+
+fn parse_cli(args: Vec<OsString>) -> ArgMatches<'static> {
+	let daemon_app = SubCommand::with_name("daemon")
+		.about("Keeps mpv running and executes commands received on a control socket, respawning mpv when it exits")
+		.arg(
+			Arg::with_name("control")
+				.long("control")
+				.takes_value(true)
+				.required(true)
+				.value_name("socket_path")
+				.help("Path of the control socket accepting the same syntax as interactive mode")
+		);
+
+	let app = App::new(env!("CARGO_PKG_NAME"))
+		.version(env!("CARGO_PKG_VERSION"))
+		.after_help(
+			"Arguments after -- are passed to mpv spawned with --spawn-server or --spawn-client, such as -- --no-video --volume=40 file.mkv\n\nDefaults for the link, launcher, mpv arguments and output, and aliases of command sequences are read from ~/.config/mpvsock/config.toml or config.json"
+		)
+		.arg(
+			Arg::with_name("config")
+				.long("config")
+				.takes_value(true)
+				.value_name("path")
+				.help("Read the config from this file instead, parsed as JSON if it ends with .json and as TOML otherwise")
+		)
+		.arg(
+			Arg::with_name("verbosity")
+				.short("v")
+				.long("verbosity")
+				.takes_value(true)
+				.default_value("Off")
+				.possible_values(&["Off", "Error", "Warn", "Info", "Debug", "Trace"])
+				.help("Level of verbosity")
+		)
+		.arg(
+			Arg::with_name("output")
+				.long("output")
+				.takes_value(true)
+				.default_value("text")
+				.possible_values(&["text", "json"])
+				.global(true)
+				.help("Format of printed results, errors and events, json prints one object per line")
+		)
+		// spawn options
+		.arg(
+			Arg::with_name("connect")
+				.long("connect")
+				.takes_value(true)
+				.value_name("socket_path")
+				.help("Connect to an existing mpv socket, @name connects to an abstract socket on Linux")
+		)
+		.arg(
+			Arg::with_name("auto")
+				.long("auto")
+				.takes_value(false)
+				.help("Connect to a running mpv found in standard socket locations, the most recent one if there are multiple")
+		)
+		.arg(
+			Arg::with_name("spawn_server")
+				.long("spawn-server")
+				.takes_value(true)
+				.value_name("socket_path")
+				.help("Spawn a new mpv process that acts as a server opening a socket at given path")
+		)
+		.arg(
+			Arg::with_name("spawn_client")
+				.long("spawn-client")
+				.takes_value(false)
+				.help("Spawn a new mpv process that acts as a client listening on an unnamed socket")
+		)
+		.arg(
+			Arg::with_name("launcher")
+				.long("launcher")
+				.takes_value(true)
+				.value_name("command")
+				.help("Command running mpv when spawning it, flatpak and snap select the usual ones, such as \"flatpak run io.mpv.Mpv\"")
+		)
+		.group(
+			ArgGroup::with_name("mpv_link")
+				.args(&["connect", "auto", "spawn_server", "spawn_client"])
+		)
+		// interactive subcommand
+		.subcommand(
+			SubCommand::with_name("interactive")
+				.about("Opens and interactive command prompt")
+		)
+		// one-shot subcommands
+		.subcommand(
+			SubCommand::with_name("get")
+				.about("Gets a property and prints its value as JSON")
+				.arg(Arg::with_name("property").required(true))
+		)
+		.subcommand(
+			SubCommand::with_name("set")
+				.about("Sets a property, the value is parsed as JSON or used as a string if that fails")
+				.arg(Arg::with_name("property").required(true))
+				.arg(Arg::with_name("value").required(true))
+		)
+		.subcommand(
+			SubCommand::with_name("seek")
+				.about("Seeks in the current file")
+				.setting(AppSettings::AllowLeadingHyphen)
+				.arg(Arg::with_name("target").required(true))
+				.arg(
+					Arg::with_name("mode")
+						.default_value("relative")
+						.possible_values(&[
+							"relative",
+							"absolute",
+							"relative-percent",
+							"absolute-percent"
+						])
+				)
+				.arg(
+					Arg::with_name("precision")
+						.long("precision")
+						.takes_value(true)
+						.default_value("default")
+						.possible_values(&["default", "keyframes", "exact"])
+				)
+		)
+		.subcommand(
+			SubCommand::with_name("loadfile")
+				.about("Loads a file or URL")
+				.arg(Arg::with_name("url").required(true))
+				.arg(
+					Arg::with_name("append")
+						.long("append")
+						.help("Append to the playlist instead of replacing it")
+				)
+		)
+		.subcommand(
+			SubCommand::with_name("raw")
+				.about("Runs a command given as a JSON array, such as [\"get_property\", \"pause\"]")
+				.arg(Arg::with_name("command").required(true))
+		)
+		// watch subcommand
+		.subcommand(
+			SubCommand::with_name("watch")
+				.about("Observes properties and prints their changes as line-delimited JSON")
+				.arg(
+					Arg::with_name("property")
+						.required(true)
+						.multiple(true)
+				)
+		)
+		// events subcommand
+		.subcommand(
+			SubCommand::with_name("events")
+				.about("Prints events as they arrive")
+				.arg(
+					Arg::with_name("filter")
+						.long("filter")
+						.takes_value(true)
+						.use_delimiter(true)
+						.value_name("event_names")
+						.help("Comma-separated names of events to print, such as start-file,end-file")
+				)
+				.arg(
+					Arg::with_name("json")
+						.long("json")
+						.help("Print events as received JSON lines")
+				)
+		)
+		// status subcommand
+		.subcommand(
+			SubCommand::with_name("status")
+				.about("Prints a now-playing summary of title, position, pause state and volume")
+				.arg(
+					Arg::with_name("long")
+						.long("long")
+						.help("Print the summary on multiple lines")
+				)
+				.arg(
+					Arg::with_name("follow")
+						.long("follow")
+						.help("Keep printing the summary whenever it changes")
+				)
+		)
+		// run subcommand
+		.subcommand(
+			SubCommand::with_name("run")
+				.about("Runs an alias from the config file, stopping at the first failed command")
+				.arg(Arg::with_name("alias").required(true))
+		)
+		// script subcommand
+		.subcommand(
+			SubCommand::with_name("script")
+				.about("Runs commands from a file line by line, using the same syntax as interactive mode")
+				.arg(
+					Arg::with_name("file")
+						.required(true)
+						.help("Path to the script or - to read from stdin")
+				)
+				.arg(
+					Arg::with_name("keep_going")
+						.long("keep-going")
+						.help("Continue after a command fails")
+				)
+		);
+
+	#[cfg(feature = "prometheus")]
+	let app = app.subcommand(
+		daemon_app.arg(
+			Arg::with_name("metrics")
+				.long("metrics")
+				.takes_value(true)
+				.value_name("address")
+				.help("Address such as 127.0.0.1:9500 of a HTTP endpoint exporting metrics in the Prometheus text format")
+		)
+	);
+	#[cfg(not(feature = "prometheus"))]
+	let app = app.subcommand(daemon_app);
+
+	#[cfg(feature = "mpris")]
+	let app = app.subcommand(
+		SubCommand::with_name("mpris")
+			.about("Exposes mpv as a MPRIS media player on the D-Bus session bus, making it controllable by media keys")
+	);
+
+	app.get_matches_from(args)
+}
+
+fn setup_logger(level: log::Level) {
+	edwardium_logger::Logger::new(
+		edwardium_logger::targets::stderr::StderrTarget::new(level, Default::default()),
+		std::time::Instant::now()
+	)
+	.init_boxed()
+	.expect("Could not initialize logger");
+}
+
+fn spawn_builder(matches: &ArgMatches, mpv_args: &[OsString]) -> MpvSpawnBuilder {
+	let launcher = match matches.value_of("launcher") {
+		None => Vec::new(),
+		Some("flatpak") => MpvSpawnBuilder::FLATPAK_LAUNCHER.to_vec(),
+		Some("snap") => MpvSpawnBuilder::SNAP_LAUNCHER.to_vec(),
+		Some(launcher) => launcher.split_whitespace().collect()
+	};
+
+	MpvSpawnBuilder::new()
+		.launcher(launcher)
+		.args(mpv_args.iter())
+}
+
+/// Connects to or spawns mpv as selected on the command line.
+fn open_link(matches: &ArgMatches, mpv_args: &[OsString]) -> anyhow::Result<MpvLink> {
+	let link = if let Some(socket_path) = matches.value_of("connect") {
+		MpvLink::connect_address(SocketAddress::parse(socket_path), SocketType::Stream)?
+	} else if matches.is_present("auto") {
+		let socket_path = discover::find_socket()?;
+		log::info!("Connecting to {}", socket_path.display());
+
+		MpvLink::connect(&socket_path)?
+	} else if let Some(socket_path) = matches.value_of("spawn_server") {
+		spawn_builder(matches, mpv_args).spawn_server(Path::new(socket_path))?
+	} else if matches.is_present("spawn_client") {
+		spawn_builder(matches, mpv_args).spawn_client()?
+	} else {
+		unreachable!()
+	};
+
+	Ok(link)
+}
+
+fn main() -> anyhow::Result<()> {
+	let (mut args, mut mpv_args) = split_mpv_args();
+	let matches = parse_cli(args.clone());
+
+	if let Some(level) = match matches.value_of("verbosity").unwrap() {
+		"Off" => None,
+		"Error" => Some(log::Level::Error),
+		"Warn" => Some(log::Level::Warn),
+		"Info" => Some(log::Level::Info),
+		"Debug" => Some(log::Level::Debug),
+		"Trace" => Some(log::Level::Trace),
+		_ => unreachable!()
+	} {
+		setup_logger(level);
+		log::debug!("{:?}", matches);
+	}
+
+	let config = Config::load(matches.value_of("config").map(Path::new))?;
+	log::debug!("{:?}", config);
+	// the config only provides options which were not given, so the command line is parsed again with them
+	let default_args = config.default_args(&matches)?;
+	let matches = match default_args.is_empty() {
+		true => matches,
+		false => {
+			args.splice(1 .. 1, default_args);
+			parse_cli(args)
+		}
+	};
+
+	let spawns = matches.is_present("spawn_server") || matches.is_present("spawn_client");
+	if !mpv_args.is_empty() && !spawns {
+		anyhow::bail!("Arguments after -- can only be passed to a spawned mpv");
+	}
+	if !["connect", "auto", "spawn_server", "spawn_client"]
+		.iter()
+		.any(|name| matches.is_present(name))
+	{
+		anyhow::bail!(
+			"One of --connect, --auto, --spawn-server or --spawn-client is required, or link in the config file"
+		);
+	}
+	mpv_args.splice(0 .. 0, config.mpv_args.iter().map(OsString::from));
+
+	let mut mpv = open_link(&matches, &mpv_args)?;
+
+	match matches.subcommand() {
+		("interactive", Some(matches)) => {
+			let mut context = InteractiveContext::new(matches).with_aliases(config.aliases);
+			context.run(&mut mpv)?;
+		}
+		("watch", Some(matches)) => watch::run(matches, &mut mpv)?,
+		("events", Some(matches)) => events::run(matches, &mut mpv)?,
+		("script", Some(matches)) => script::run(matches, &mut mpv, config.aliases)?,
+		("run", Some(matches)) => script::run_alias(matches, &mut mpv, config.aliases)?,
+		("status", Some(matches)) => status::run(matches, &mut mpv)?,
+		("daemon", Some(sub_matches)) => {
+			daemon::run(sub_matches, &mut mpv, &config.aliases, || {
+				open_link(&matches, &mpv_args)
+			})?
+		}
+		#[cfg(feature = "mpris")]
+		("mpris", Some(matches)) => mpris::run(matches, &mut mpv)?,
+		(name, Some(matches)) => oneshot::run(name, matches, &mut mpv)?,
+		_ => ()
+	}
+
+	Ok(())
+}
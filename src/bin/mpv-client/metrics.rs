@@ -0,0 +1,172 @@
+use std::{
+	fmt::Write as FmtWrite,
+	io::{self, Read, Write},
+	net::{TcpListener, TcpStream},
+	time::Duration
+};
+
+use anyhow::Context;
+
+use mpvsock::{
+	command::{property, response::MpvResponseEvent},
+	link::MpvLink,
+	property_cache::PropertyCache
+};
+
+/// Tiny HTTP endpoint exporting link stats and observed properties in the Prometheus text format.
+///
+/// Any request on any path is answered with the metrics, the listener is polled from the daemon loop.
+pub struct MetricsExporter {
+	listener: TcpListener,
+	cache: PropertyCache
+}
+impl MetricsExporter {
+	pub fn bind(address: &str) -> anyhow::Result<Self> {
+		let listener = TcpListener::bind(address)
+			.with_context(|| format!("Could not bind metrics endpoint at {}", address))?;
+		listener.set_nonblocking(true)?;
+
+		Ok(MetricsExporter {
+			listener,
+			cache: PropertyCache::new()
+		})
+	}
+
+	/// Enables stats on `mpv` and observes the exported properties, needs to be called again when the link is reopened.
+	pub fn attach(&mut self, mpv: &mut MpvLink) -> anyhow::Result<()> {
+		mpv.set_stats_enabled(true);
+
+		self.cache = PropertyCache::new();
+		self.cache.observe(mpv, property::TimePos)?;
+		self.cache.observe(mpv, property::Pause)?;
+		self.cache.observe(mpv, property::Volume)?;
+		self.cache.observe(mpv, property::PlaylistCount)?;
+
+		Ok(())
+	}
+
+	pub fn update(&mut self, event: &MpvResponseEvent) {
+		self.cache.update(event);
+	}
+
+	/// Answers all pending requests.
+	pub fn serve(&mut self, mpv: &MpvLink) -> io::Result<()> {
+		loop {
+			match self.listener.accept() {
+				Ok((stream, _)) => {
+					if let Err(err) = self.respond(stream, mpv) {
+						log::warn!("Could not answer metrics request: {}", err);
+					}
+				}
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+				Err(err) => return Err(err)
+			}
+		}
+	}
+
+	fn respond(&self, mut stream: TcpStream, mpv: &MpvLink) -> io::Result<()> {
+		stream.set_nonblocking(false)?;
+		stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+
+		// the request itself does not matter, read its head so that the client does not get a reset
+		let mut request = [0u8; 1024];
+		let _ = stream.read(&mut request)?;
+
+		let body = self.render(mpv);
+		write!(
+			stream,
+			"HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			body.len(),
+			body
+		)
+	}
+
+	fn render(&self, mpv: &MpvLink) -> String {
+		let stats = mpv.stats();
+		let mut body = String::new();
+
+		let mut metric = |name: &str, kind: &str, help: &str, value: Option<f64>| {
+			if let Some(value) = value {
+				// writing into a String cannot fail
+				let _ = write!(
+					body,
+					"# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+				);
+			}
+		};
+
+		metric(
+			"mpv_commands_sent_total",
+			"counter",
+			"Commands sent to mpv.",
+			Some(stats.commands_sent as f64)
+		);
+		metric(
+			"mpv_results_received_total",
+			"counter",
+			"Command results received from mpv.",
+			Some(stats.results_received as f64)
+		);
+		metric(
+			"mpv_events_received_total",
+			"counter",
+			"Events received from mpv.",
+			Some(stats.events_received as f64)
+		);
+		metric(
+			"mpv_sent_bytes_total",
+			"counter",
+			"Bytes sent to mpv.",
+			Some(stats.bytes_tx as f64)
+		);
+		metric(
+			"mpv_received_bytes_total",
+			"counter",
+			"Bytes received from mpv.",
+			Some(stats.bytes_rx as f64)
+		);
+		metric(
+			"mpv_command_latency_last_seconds",
+			"gauge",
+			"Round-trip latency of the last command.",
+			stats.last_latency.map(|latency| latency.as_secs_f64())
+		);
+		metric(
+			"mpv_command_latency_avg_seconds",
+			"gauge",
+			"Average round-trip latency of commands.",
+			stats.avg_latency().map(|latency| latency.as_secs_f64())
+		);
+
+		metric(
+			"mpv_time_pos_seconds",
+			"gauge",
+			"Position in the current file.",
+			self.cache.get::<property::TimePos>().copied()
+		);
+		metric(
+			"mpv_pause",
+			"gauge",
+			"Whether playback is paused.",
+			self.cache
+				.get::<property::Pause>()
+				.map(|&pause| if pause { 1.0 } else { 0.0 })
+		);
+		metric(
+			"mpv_volume",
+			"gauge",
+			"Volume in percent.",
+			self.cache.get::<property::Volume>().copied()
+		);
+		metric(
+			"mpv_playlist_count",
+			"gauge",
+			"Number of playlist entries.",
+			self.cache
+				.get::<property::PlaylistCount>()
+				.map(|&count| f64::from(count))
+		);
+
+		body
+	}
+}
@@ -0,0 +1,425 @@
+use std::{
+	borrow::Cow,
+	collections::HashMap,
+	sync::{
+		mpsc::{self, Receiver, RecvTimeoutError, Sender},
+		Arc,
+		Mutex
+	},
+	time::Duration
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+use zbus::{
+	blocking::{connection, object_server::InterfaceRef, Connection},
+	interface,
+	object_server::SignalEmitter,
+	zvariant::{ObjectPath, OwnedValue, Value}
+};
+
+use mpvsock::{
+	command::{
+		commands::{
+			CmdCycleProperty,
+			CmdLoadfile,
+			CmdPlaylistNext,
+			CmdPlaylistPrev,
+			CmdRawJsonArgs,
+			CmdSeek,
+			CmdSetProperty,
+			CmdStop
+		},
+		property,
+		response::MpvResponseEvent
+	},
+	link::MpvLink,
+	property_cache::PropertyCache,
+	snapshot::PlaybackSnapshot
+};
+
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+/// How often events are polled while no request arrives from the bus.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const TRACK_ID: &str = "/org/mpris/MediaPlayer2/Track/current";
+
+/// Requests translated from MPRIS method calls, executed on the thread owning the link.
+#[derive(Debug)]
+enum Request {
+	Quit,
+	Next,
+	Previous,
+	Play,
+	Pause,
+	PlayPause,
+	Stop,
+	/// Relative seek in seconds.
+	Seek(f64),
+	/// Absolute seek in seconds.
+	SetPosition(f64),
+	OpenUri(String),
+	/// Volume in percent, as mpv uses it.
+	SetVolume(f64)
+}
+
+/// State shared between the link thread and the bus, the latest snapshot of the observed properties.
+type SharedState = Arc<Mutex<PlaybackSnapshot>>;
+
+struct Root {
+	requests: Sender<Request>
+}
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+	fn raise(&self) {}
+
+	fn quit(&self) {
+		let _ = self.requests.send(Request::Quit);
+	}
+
+	#[zbus(property)]
+	fn can_quit(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_raise(&self) -> bool {
+		false
+	}
+
+	#[zbus(property)]
+	fn has_track_list(&self) -> bool {
+		false
+	}
+
+	#[zbus(property)]
+	fn identity(&self) -> &str {
+		"mpv"
+	}
+
+	#[zbus(property)]
+	fn desktop_entry(&self) -> &str {
+		"mpv"
+	}
+
+	#[zbus(property)]
+	fn supported_uri_schemes(&self) -> Vec<String> {
+		["file", "http", "https"]
+			.iter()
+			.map(|s| s.to_string())
+			.collect()
+	}
+
+	#[zbus(property)]
+	fn supported_mime_types(&self) -> Vec<String> {
+		Vec::new()
+	}
+}
+
+struct Player {
+	requests: Sender<Request>,
+	state: SharedState
+}
+impl Player {
+	fn send(&self, request: Request) {
+		// fails only when the link thread exited, in which case the process is exiting as well
+		let _ = self.requests.send(request);
+	}
+
+	fn snapshot(&self) -> PlaybackSnapshot {
+		self.state.lock().unwrap().clone()
+	}
+}
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+	fn next(&self) {
+		self.send(Request::Next)
+	}
+
+	fn previous(&self) {
+		self.send(Request::Previous)
+	}
+
+	fn pause(&self) {
+		self.send(Request::Pause)
+	}
+
+	fn play_pause(&self) {
+		self.send(Request::PlayPause)
+	}
+
+	fn stop(&self) {
+		self.send(Request::Stop)
+	}
+
+	fn play(&self) {
+		self.send(Request::Play)
+	}
+
+	/// Offset is in microseconds.
+	fn seek(&self, offset: i64) {
+		self.send(Request::Seek(offset as f64 / 1e6))
+	}
+
+	fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+		if track_id.as_str() == TRACK_ID {
+			self.send(Request::SetPosition(position as f64 / 1e6))
+		}
+	}
+
+	fn open_uri(&self, uri: String) {
+		self.send(Request::OpenUri(uri))
+	}
+
+	#[zbus(signal)]
+	async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+	#[zbus(property)]
+	fn playback_status(&self) -> &str {
+		playback_status(&self.snapshot())
+	}
+
+	#[zbus(property)]
+	fn rate(&self) -> f64 {
+		1.0
+	}
+
+	#[zbus(property)]
+	fn minimum_rate(&self) -> f64 {
+		1.0
+	}
+
+	#[zbus(property)]
+	fn maximum_rate(&self) -> f64 {
+		1.0
+	}
+
+	#[zbus(property)]
+	fn metadata(&self) -> HashMap<String, OwnedValue> {
+		metadata(&self.snapshot())
+	}
+
+	#[zbus(property)]
+	fn volume(&self) -> f64 {
+		self.snapshot().volume.unwrap_or(0.0) / 100.0
+	}
+
+	#[zbus(property)]
+	fn set_volume(&mut self, volume: f64) {
+		self.send(Request::SetVolume(volume.max(0.0) * 100.0))
+	}
+
+	/// Position in microseconds, not signalled on change as per the specification.
+	#[zbus(property(emits_changed_signal = "false"))]
+	fn position(&self) -> i64 {
+		to_micros(self.snapshot().time_pos)
+	}
+
+	#[zbus(property)]
+	fn can_go_next(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_go_previous(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_play(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_pause(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_seek(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_control(&self) -> bool {
+		true
+	}
+}
+
+fn playback_status(snapshot: &PlaybackSnapshot) -> &'static str {
+	match (snapshot.time_pos, snapshot.pause) {
+		(None, _) => "Stopped",
+		(Some(_), Some(true)) => "Paused",
+		(Some(_), _) => "Playing"
+	}
+}
+
+fn metadata(snapshot: &PlaybackSnapshot) -> HashMap<String, OwnedValue> {
+	let mut metadata = HashMap::new();
+	if snapshot.time_pos.is_none() {
+		return metadata
+	}
+
+	let mut insert = |key: &str, value: Value<'_>| {
+		if let Ok(value) = value.try_to_owned() {
+			metadata.insert(key.to_string(), value);
+		}
+	};
+	insert(
+		"mpris:trackid",
+		Value::from(ObjectPath::from_static_str_unchecked(TRACK_ID))
+	);
+	if let Some(title) = snapshot.media_title.as_deref() {
+		insert("xesam:title", Value::from(title));
+	}
+	if snapshot.duration.is_some() {
+		insert("mpris:length", Value::from(to_micros(snapshot.duration)));
+	}
+
+	metadata
+}
+
+fn to_micros(seconds: Option<f64>) -> i64 {
+	(seconds.unwrap_or(0.0) * 1e6) as i64
+}
+
+/// Exposes mpv as a MPRIS player on the session bus until mpv shuts down.
+pub fn run(_matches: &ArgMatches, mpv: &mut MpvLink) -> anyhow::Result<()> {
+	let (requests, receiver) = mpsc::channel();
+	let state = SharedState::default();
+
+	let connection = connection::Builder::session()?
+		.name(format!(
+			"org.mpris.MediaPlayer2.mpvsock.instance{}",
+			std::process::id()
+		))?
+		.serve_at(
+			OBJECT_PATH,
+			Root {
+				requests: requests.clone()
+			}
+		)?
+		.serve_at(
+			OBJECT_PATH,
+			Player {
+				requests,
+				state: state.clone()
+			}
+		)?
+		.build()
+		.context("Could not connect to the session bus")?;
+
+	run_link(mpv, &connection, &receiver, &state)
+}
+
+fn run_link(
+	mpv: &mut MpvLink,
+	connection: &Connection,
+	receiver: &Receiver<Request>,
+	state: &SharedState
+) -> anyhow::Result<()> {
+	let player: InterfaceRef<Player> = connection.object_server().interface(OBJECT_PATH)?;
+
+	let mut cache = PropertyCache::new();
+	PlaybackSnapshot::observe(&mut cache, mpv)?;
+
+	loop {
+		match receiver.recv_timeout(POLL_INTERVAL) {
+			Ok(request) => {
+				if let Err(err) = run_request(mpv, request) {
+					log::warn!("MPRIS request failed: {}", err);
+				}
+			}
+			Err(RecvTimeoutError::Timeout) => (),
+			Err(RecvTimeoutError::Disconnected) => return Ok(())
+		}
+
+		mpv.poll_events()?;
+		let mut seeked = false;
+		let mut shutdown = false;
+		for event in mpv.drain_events() {
+			cache.update(&event);
+			match event {
+				MpvResponseEvent::PlaybackRestart => seeked = true,
+				MpvResponseEvent::Shutdown => shutdown = true,
+				_ => ()
+			}
+		}
+		if shutdown {
+			return Ok(())
+		}
+
+		let snapshot = PlaybackSnapshot::from_cache(&cache);
+		let previous = std::mem::replace(&mut *state.lock().unwrap(), snapshot.clone());
+		emit_changes(&player, &previous, &snapshot, seeked)?;
+	}
+}
+
+fn run_request(mpv: &mut MpvLink, request: Request) -> anyhow::Result<()> {
+	log::debug!("MPRIS request: {:?}", request);
+
+	match request {
+		Request::Quit => {
+			mpv.run_command(&CmdRawJsonArgs::new("\"quit\""))?;
+		}
+		Request::Next => {
+			mpv.run_command(&CmdPlaylistNext::new(false))?;
+		}
+		Request::Previous => {
+			mpv.run_command(&CmdPlaylistPrev::new(false))?;
+		}
+		Request::Play => {
+			mpv.run_command(&CmdSetProperty::new(property::Pause, false))?;
+		}
+		Request::Pause => {
+			mpv.run_command(&CmdSetProperty::new(property::Pause, true))?;
+		}
+		Request::PlayPause => {
+			mpv.run_command(&CmdCycleProperty::new(property::Pause, false))?;
+		}
+		Request::Stop => {
+			mpv.run_command(&CmdStop::new(true))?;
+		}
+		Request::Seek(offset) => {
+			mpv.run_command(&CmdSeek::time(offset, false)?)?;
+		}
+		Request::SetPosition(position) => {
+			mpv.run_command(&CmdSeek::time(position, true)?)?;
+		}
+		Request::OpenUri(uri) => {
+			mpv.run_command(&CmdLoadfile::new(Cow::Owned(uri)))?;
+		}
+		Request::SetVolume(volume) => {
+			mpv.run_command(&CmdSetProperty::new(property::Volume, volume))?;
+		}
+	};
+
+	Ok(())
+}
+
+/// Emits `PropertiesChanged` for properties which changed between the snapshots and `Seeked` after a seek.
+fn emit_changes(
+	player: &InterfaceRef<Player>,
+	previous: &PlaybackSnapshot,
+	current: &PlaybackSnapshot,
+	seeked: bool
+) -> zbus::Result<()> {
+	let emitter = player.signal_emitter();
+	let player = player.get();
+
+	zbus::block_on(async {
+		if playback_status(previous) != playback_status(current) {
+			player.playback_status_changed(emitter).await?;
+		}
+		if previous.media_title != current.media_title || previous.duration != current.duration {
+			player.metadata_changed(emitter).await?;
+		}
+		if previous.volume != current.volume {
+			player.volume_changed(emitter).await?;
+		}
+		if seeked {
+			Player::seeked(emitter, to_micros(current.time_pos)).await?;
+		}
+
+		Ok(())
+	})
+}
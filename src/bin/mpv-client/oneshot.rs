@@ -0,0 +1,91 @@
+use std::{
+	borrow::Cow,
+	io::{self, Write}
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+use serde::Serialize;
+
+use mpvsock::{
+	command::commands::{
+		CmdGetProperty,
+		CmdLoadfile,
+		CmdRawJsonArgs,
+		CmdSeek,
+		CmdSetProperty,
+		SeekMode,
+		SeekPrecision
+	},
+	link::MpvLink
+};
+
+/// Runs a one-shot subcommand and prints its result as JSON.
+///
+/// Errors reported by mpv are returned so that the process exits with a nonzero status.
+pub fn run(name: &str, matches: &ArgMatches, mpv: &mut MpvLink) -> anyhow::Result<()> {
+	match name {
+		"get" => {
+			let property = matches.value_of("property").unwrap();
+
+			print_json(&mpv.run_command(&CmdGetProperty::new(property))?)
+		}
+		"set" => {
+			let property = matches.value_of("property").unwrap();
+			let value = parse_value(matches.value_of("value").unwrap());
+
+			print_json(&mpv.run_command(&CmdSetProperty::new(property, value))?)
+		}
+		"seek" => {
+			let target = matches
+				.value_of("target")
+				.unwrap()
+				.parse::<f64>()
+				.context("seek expects an f64 target")?;
+
+			let mode = matches.value_of("mode").unwrap().parse::<SeekMode>()?;
+			let precision = matches
+				.value_of("precision")
+				.unwrap()
+				.parse::<SeekPrecision>()?;
+			let command = CmdSeek::new(target, mode)?.with_precision(precision);
+
+			print_json(&mpv.run_command(&command)?)
+		}
+		"loadfile" => {
+			let url = Cow::Borrowed(matches.value_of("url").unwrap());
+			let command = if matches.is_present("append") {
+				CmdLoadfile::new_append(url)
+			} else {
+				CmdLoadfile::new(url)
+			};
+
+			print_json(&mpv.run_command(&command)?)
+		}
+		"raw" => {
+			let args: Vec<serde_json::Value> =
+				serde_json::from_str(matches.value_of("command").unwrap())
+					.context("raw expects a JSON array")?;
+			// strip the enclosing brackets, CmdRawJsonArgs expects only the elements
+			let args = serde_json::to_string(&args)?;
+
+			print_json(&mpv.run_command(&CmdRawJsonArgs::new(&args[1 .. args.len() - 1]))?)
+		}
+		_ => unreachable!()
+	}
+}
+
+/// Parses `value` as JSON, falling back to a JSON string.
+pub fn parse_value(value: &str) -> serde_json::Value {
+	serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()))
+}
+
+fn print_json(value: &impl Serialize) -> anyhow::Result<()> {
+	let stdout = io::stdout();
+	let mut stdout = stdout.lock();
+
+	serde_json::to_writer(&mut stdout, value)?;
+	writeln!(stdout)?;
+
+	Ok(())
+}
@@ -0,0 +1,86 @@
+use std::{
+	fmt::{Debug, Display},
+	io::{self, Write},
+	time::Duration
+};
+
+use clap::ArgMatches;
+use serde::Serialize;
+
+use mpvsock::command::response::MpvResponseEvent;
+
+/// Format in which results, errors and events are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+	/// Human-readable debug formatting.
+	Text,
+	/// One JSON object per line.
+	Json
+}
+impl OutputFormat {
+	pub fn from_matches(matches: &ArgMatches) -> Self {
+		match matches.value_of("output") {
+			Some("json") => OutputFormat::Json,
+			_ => OutputFormat::Text
+		}
+	}
+
+	/// Writes a successful command result, as `{"result": ..}` in JSON format.
+	pub fn write_result<T: Debug + Serialize>(
+		self,
+		mut out: impl Write,
+		result: &T
+	) -> io::Result<()> {
+		match self {
+			OutputFormat::Text => writeln!(out, "Result: {:?}", result),
+			OutputFormat::Json => {
+				#[derive(Serialize)]
+				struct ResultOutput<'a, T> {
+					result: &'a T
+				}
+
+				serde_json::to_writer(&mut out, &ResultOutput { result })?;
+				writeln!(out)
+			}
+		}
+	}
+
+	/// Writes an error, as `{"error": ".."}` in JSON format.
+	pub fn write_error(self, mut out: impl Write, error: impl Display) -> io::Result<()> {
+		match self {
+			OutputFormat::Text => writeln!(out, "Error: {}", error),
+			OutputFormat::Json => {
+				serde_json::to_writer(
+					&mut out,
+					&serde_json::json!({ "error": error.to_string() })
+				)?;
+				writeln!(out)
+			}
+		}
+	}
+
+	/// Writes the round-trip time of a command, as `{"time_ms": ..}` in JSON format.
+	pub fn write_timing(self, mut out: impl Write, time: Duration) -> io::Result<()> {
+		match self {
+			OutputFormat::Text => writeln!(out, "Time: {:?}", time),
+			OutputFormat::Json => {
+				serde_json::to_writer(
+					&mut out,
+					&serde_json::json!({ "time_ms": time.as_secs_f64() * 1000.0 })
+				)?;
+				writeln!(out)
+			}
+		}
+	}
+
+	/// Writes an event, in JSON format the event is written in the same shape mpv sends it.
+	pub fn write_event(self, mut out: impl Write, event: &MpvResponseEvent) -> io::Result<()> {
+		match self {
+			OutputFormat::Text => writeln!(out, "\t{:?}", event),
+			OutputFormat::Json => {
+				serde_json::to_writer(&mut out, event)?;
+				writeln!(out)
+			}
+		}
+	}
+}
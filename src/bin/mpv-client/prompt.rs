@@ -0,0 +1,224 @@
+//! Line input for the interactive mode.
+//!
+//! With the `readline` feature this provides line editing, persistent history and tab completion,
+//! otherwise lines are read from stdin as they are.
+
+#[cfg(not(feature = "readline"))]
+use std::io::{self, BufRead, Write};
+
+#[cfg(feature = "readline")]
+mod completion {
+	use rustyline::{
+		completion::Completer,
+		highlight::Highlighter,
+		hint::Hinter,
+		validate::Validator,
+		Context,
+		Helper
+	};
+
+	use mpvsock::command::property::KnownMpvProperty;
+
+	/// Input commands handled by the interactive mode itself.
+	const INPUT_COMMANDS: &[&str] = &[
+		"#help",
+		"#events",
+		"#observe",
+		"#unobserve",
+		"#mode raw",
+		"#mode string",
+		"#mode known",
+		"#timing on",
+		"#timing off",
+		"#sleep",
+		"#quit"
+	];
+	/// Commands accepted in known mode.
+	const KNOWN_COMMANDS: &[&str] = &[
+		"get_version",
+		"client_name",
+		"get_property",
+		"get",
+		"set_property",
+		"set",
+		"cycle",
+		"seek"
+	];
+	/// Known commands which take a property name as the first argument.
+	const PROPERTY_COMMANDS: &[&str] = &["get_property", "get", "set_property", "set", "cycle"];
+
+	/// Completion candidates for the interactive mode.
+	pub struct Completions {
+		pub properties: Vec<String>
+	}
+	impl Completions {
+		pub fn new() -> Self {
+			Completions {
+				properties: KnownMpvProperty::ALL
+					.iter()
+					.map(|p| p.proeprty_name().to_string())
+					.collect()
+			}
+		}
+
+		/// Returns the start of the completed word in `line` and the candidates to replace it with.
+		pub fn complete(&self, line: &str) -> (usize, Vec<String>) {
+			fn filter<'a>(words: impl Iterator<Item = &'a str>, prefix: &str) -> Vec<String> {
+				words
+					.filter(|w| w.starts_with(prefix))
+					.map(|w| w.to_string())
+					.collect()
+			}
+
+			if line.starts_with('#') {
+				return (0, filter(INPUT_COMMANDS.iter().copied(), line))
+			}
+
+			match line.split_once(' ') {
+				None => (0, filter(KNOWN_COMMANDS.iter().copied(), line)),
+				Some((command, argument))
+					if PROPERTY_COMMANDS.contains(&command) && !argument.contains(' ') =>
+				{
+					(
+						command.len() + 1,
+						filter(self.properties.iter().map(|p| p.as_str()), argument)
+					)
+				}
+				Some(_) => (0, Vec::new())
+			}
+		}
+	}
+
+	pub struct CompletionHelper(pub Completions);
+	impl Completer for CompletionHelper {
+		type Candidate = String;
+
+		fn complete(
+			&self,
+			line: &str,
+			pos: usize,
+			_ctx: &Context<'_>
+		) -> rustyline::Result<(usize, Vec<String>)> {
+			Ok(self.0.complete(&line[.. pos]))
+		}
+	}
+	impl Hinter for CompletionHelper {
+		type Hint = String;
+	}
+	impl Highlighter for CompletionHelper {}
+	impl Validator for CompletionHelper {}
+	impl Helper for CompletionHelper {}
+}
+
+#[cfg(feature = "readline")]
+pub struct Prompt {
+	editor: rustyline::Editor<completion::CompletionHelper, rustyline::history::FileHistory>,
+	history_path: Option<std::path::PathBuf>
+}
+#[cfg(feature = "readline")]
+impl Prompt {
+	pub fn new() -> anyhow::Result<Self> {
+		let mut editor = rustyline::Editor::new()?;
+		editor.set_helper(Some(completion::CompletionHelper(
+			completion::Completions::new()
+		)));
+
+		let history_path = Self::history_path();
+		if let Some(path) = history_path.as_ref() {
+			if let Err(err) = editor.load_history(path) {
+				log::debug!("Could not load history from {}: {}", path.display(), err);
+			}
+		}
+
+		Ok(Prompt {
+			editor,
+			history_path
+		})
+	}
+
+	/// Reads a line into `line`, returns `false` on EOF.
+	pub fn read_line(&mut self, prompt: &str, line: &mut String) -> anyhow::Result<bool> {
+		use rustyline::error::ReadlineError;
+
+		line.clear();
+		match self.editor.readline(prompt) {
+			Ok(input) => {
+				if !input.trim().is_empty() {
+					self.editor.add_history_entry(input.as_str())?;
+				}
+				line.push_str(&input);
+			}
+			// cancels the current line
+			Err(ReadlineError::Interrupted) => (),
+			Err(ReadlineError::Eof) => return Ok(false),
+			Err(err) => return Err(err.into())
+		}
+
+		Ok(true)
+	}
+
+	/// Replaces the property names offered for completion.
+	pub fn set_properties(&mut self, properties: Vec<String>) {
+		if let Some(helper) = self.editor.helper_mut() {
+			helper.0.properties = properties;
+		}
+	}
+
+	/// Returns `$XDG_DATA_HOME/mpvsock/history`, defaulting to `~/.local/share/mpvsock/history`.
+	fn history_path() -> Option<std::path::PathBuf> {
+		let data_home = match std::env::var_os("XDG_DATA_HOME") {
+			Some(path) if !path.is_empty() => std::path::PathBuf::from(path),
+			_ => std::path::PathBuf::from(std::env::var_os("HOME")?).join(".local/share")
+		};
+
+		Some(data_home.join("mpvsock").join("history"))
+	}
+}
+#[cfg(feature = "readline")]
+impl Drop for Prompt {
+	fn drop(&mut self) {
+		if let Some(path) = self.history_path.as_ref() {
+			let result = path
+				.parent()
+				.map(std::fs::create_dir_all)
+				.unwrap_or(Ok(()))
+				.map_err(rustyline::error::ReadlineError::from)
+				.and_then(|_| self.editor.save_history(path));
+
+			if let Err(err) = result {
+				log::warn!("Could not save history to {}: {}", path.display(), err);
+			}
+		}
+	}
+}
+
+#[cfg(not(feature = "readline"))]
+pub struct Prompt {
+	stdin: io::Stdin
+}
+#[cfg(not(feature = "readline"))]
+impl Prompt {
+	pub fn new() -> anyhow::Result<Self> {
+		Ok(Prompt { stdin: io::stdin() })
+	}
+
+	/// Property names are only used for completion, which is not available.
+	pub fn set_properties(&mut self, _properties: Vec<String>) {}
+
+	/// Reads a line into `line`, returns `false` on EOF.
+	pub fn read_line(&mut self, prompt: &str, line: &mut String) -> anyhow::Result<bool> {
+		let mut stdout = io::stdout();
+		write!(stdout, "{}", prompt)?;
+		stdout.flush()?;
+
+		line.clear();
+		if self.stdin.lock().read_line(line)? == 0 {
+			return Ok(false)
+		}
+		if line.ends_with('\n') {
+			line.pop();
+		}
+
+		Ok(true)
+	}
+}
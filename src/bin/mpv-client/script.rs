@@ -0,0 +1,68 @@
+use std::{
+	fs::File,
+	io::{self, BufRead, BufReader}
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+
+use mpvsock::link::MpvLink;
+
+use crate::{
+	config::Aliases,
+	interactive::{InteractiveContext, LineOutcome}
+};
+
+/// Executes commands from a file or stdin line by line, using the same syntax as the interactive mode.
+///
+/// Stops on the first failed command unless `--keep-going` is given.
+pub fn run(matches: &ArgMatches, mpv: &mut MpvLink, aliases: Aliases) -> anyhow::Result<()> {
+	let input: Box<dyn BufRead> = match matches.value_of("file").unwrap() {
+		"-" => Box::new(BufReader::new(io::stdin())),
+		path => Box::new(BufReader::new(
+			File::open(path).with_context(|| format!("Could not open script {}", path))?
+		))
+	};
+	let keep_going = matches.is_present("keep_going");
+
+	let mut context = InteractiveContext::new(matches).with_aliases(aliases);
+	context.load_property_list(mpv);
+
+	let mut stdout = io::stdout();
+	let mut failures = 0;
+	for (number, line) in (1 ..).zip(input.lines()) {
+		let line = line?;
+		if line.trim().is_empty() {
+			continue
+		}
+
+		match context.execute(&line, mpv, &mut stdout)? {
+			LineOutcome::Success => (),
+			LineOutcome::Failure if keep_going => failures += 1,
+			LineOutcome::Failure => anyhow::bail!("Command on line {} failed: {}", number, line),
+			LineOutcome::Quit => break
+		}
+	}
+
+	if failures > 0 {
+		anyhow::bail!("{} commands failed", failures)
+	}
+
+	Ok(())
+}
+
+/// Executes the lines of an alias from the config file, stopping on the first failed command.
+pub fn run_alias(matches: &ArgMatches, mpv: &mut MpvLink, aliases: Aliases) -> anyhow::Result<()> {
+	let name = matches.value_of("alias").unwrap();
+	if !aliases.contains_key(name) {
+		anyhow::bail!("No alias {} in the config file", name);
+	}
+
+	let mut context = InteractiveContext::new(matches).with_aliases(aliases);
+	context.load_property_list(mpv);
+
+	match context.execute(name, mpv, io::stdout())? {
+		LineOutcome::Failure => anyhow::bail!("Alias {} failed", name),
+		LineOutcome::Success | LineOutcome::Quit => Ok(())
+	}
+}
@@ -0,0 +1,131 @@
+use std::io::{self, Write};
+
+use clap::ArgMatches;
+
+use mpvsock::{
+	command::response::MpvResponseEvent,
+	link::MpvLink,
+	property_cache::PropertyCache,
+	snapshot::PlaybackSnapshot
+};
+
+use crate::output::OutputFormat;
+
+/// Prints a now-playing summary.
+///
+/// With `--follow` the properties are observed and the summary is printed again whenever it changes,
+/// until interrupted or until mpv shuts down.
+pub fn run(matches: &ArgMatches, mpv: &mut MpvLink) -> anyhow::Result<()> {
+	let output = OutputFormat::from_matches(matches);
+	let long = matches.is_present("long");
+
+	let stdout = io::stdout();
+	let mut stdout = stdout.lock();
+
+	if !matches.is_present("follow") {
+		let snapshot = PlaybackSnapshot::fetch(mpv)?;
+		write_snapshot(&mut stdout, output, long, &snapshot)?;
+
+		return Ok(())
+	}
+
+	let mut cache = PropertyCache::new();
+	PlaybackSnapshot::observe(&mut cache, mpv)?;
+
+	// the summary is printed only when the one-line form changes, which limits position updates to one per second
+	let mut last_line = None;
+	loop {
+		mpv.wait_events(None)?;
+
+		let mut shutdown = false;
+		for event in mpv.drain_events() {
+			cache.update(&event);
+			if let MpvResponseEvent::Shutdown = event {
+				shutdown = true;
+			}
+		}
+
+		let snapshot = PlaybackSnapshot::from_cache(&cache);
+		let line = format_line(&snapshot);
+		if last_line.as_ref() != Some(&line) {
+			write_snapshot(&mut stdout, output, long, &snapshot)?;
+			stdout.flush()?;
+			last_line = Some(line);
+		}
+
+		if shutdown {
+			return Ok(())
+		}
+	}
+}
+
+fn write_snapshot(
+	mut out: impl Write,
+	output: OutputFormat,
+	long: bool,
+	snapshot: &PlaybackSnapshot
+) -> io::Result<()> {
+	match output {
+		OutputFormat::Json => {
+			serde_json::to_writer(&mut out, snapshot)?;
+			writeln!(out)
+		}
+		OutputFormat::Text if long => {
+			writeln!(
+				out,
+				"Title:    {}",
+				snapshot.media_title.as_deref().unwrap_or("-")
+			)?;
+			writeln!(
+				out,
+				"Position: {} / {}",
+				format_time(snapshot.time_pos),
+				format_time(snapshot.duration)
+			)?;
+			writeln!(out, "State:    {}", format_state(snapshot.pause))?;
+			writeln!(out, "Volume:   {}", format_volume(snapshot.volume))
+		}
+		OutputFormat::Text => writeln!(out, "{}", format_line(snapshot))
+	}
+}
+
+fn format_line(snapshot: &PlaybackSnapshot) -> String {
+	format!(
+		"[{}] {} {} / {} vol {}",
+		format_state(snapshot.pause),
+		snapshot.media_title.as_deref().unwrap_or("-"),
+		format_time(snapshot.time_pos),
+		format_time(snapshot.duration),
+		format_volume(snapshot.volume)
+	)
+}
+
+fn format_state(pause: Option<bool>) -> &'static str {
+	match pause {
+		None => "-",
+		Some(true) => "paused",
+		Some(false) => "playing"
+	}
+}
+
+fn format_volume(volume: Option<f64>) -> String {
+	match volume {
+		None => "-".to_string(),
+		Some(volume) => format!("{:.0}%", volume)
+	}
+}
+
+/// Formats seconds as `mm:ss` or `h:mm:ss`.
+fn format_time(seconds: Option<f64>) -> String {
+	let seconds = match seconds {
+		Some(seconds) if seconds.is_finite() && seconds >= 0.0 => seconds as u64,
+		_ => return "--:--".to_string()
+	};
+
+	let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+	if hours > 0 {
+		format!("{}:{:02}:{:02}", hours, minutes, seconds)
+	} else {
+		format!("{:02}:{:02}", minutes, seconds)
+	}
+}
@@ -0,0 +1,47 @@
+use std::io::{self, Write};
+
+use clap::ArgMatches;
+
+use mpvsock::{
+	command::{commands::CmdObserveProperty, response::MpvResponseEvent},
+	link::MpvLink
+};
+
+/// Observes the given properties and prints their changes as line-delimited JSON.
+///
+/// Runs until interrupted or until mpv shuts down.
+pub fn run(matches: &ArgMatches, mpv: &mut MpvLink) -> anyhow::Result<()> {
+	let properties: Vec<&str> = matches.values_of("property").unwrap().collect();
+	for (observer_id, &property) in (1 ..).zip(properties.iter()) {
+		mpv.run_command(&CmdObserveProperty::new(observer_id, property))?;
+	}
+
+	let stdout = io::stdout();
+	let mut stdout = stdout.lock();
+	loop {
+		mpv.wait_events(None)?;
+
+		for event in mpv.drain_events() {
+			match event {
+				MpvResponseEvent::PropertyChange { id, data, .. } => {
+					let name = match usize::try_from(id - 1)
+						.ok()
+						.and_then(|index| properties.get(index))
+					{
+						None => continue,
+						Some(name) => name
+					};
+
+					serde_json::to_writer(
+						&mut stdout,
+						&serde_json::json!({ "name": name, "data": data })
+					)?;
+					writeln!(stdout)?;
+				}
+				MpvResponseEvent::Shutdown => return Ok(()),
+				_ => ()
+			}
+		}
+		stdout.flush()?;
+	}
+}
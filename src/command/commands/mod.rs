@@ -1,8 +1,30 @@
 use std::borrow::Cow;
 
-use crate::model::FileloadInfo;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use super::{property::MpvProperty, MpvCommandRaw};
+use crate::{
+	model::{EditionId, EditionList, FileloadInfo, LoadScriptInfo},
+	version::{MpvVersion, VersionGate}
+};
+
+use super::{
+	parse_optional,
+	parse_value,
+	property::{
+		self,
+		KnownMpvProperty,
+		ObservableMpvProperty,
+		ReadOnlyPropertyError,
+		ReadableMpvProperty,
+		WritableKnownProperty,
+		WritableMpvProperty
+	},
+	response::MpvResponseResultError,
+	serialize_command,
+	MpvCommandNamed,
+	MpvCommandRaw,
+	ParseError
+};
 
 use super::MpvCommand;
 
@@ -20,6 +42,10 @@ impl<S: AsRef<str>> MpvCommandRaw for CmdRawText<S> {
 	) -> std::io::Result<()> {
 		write!(w, "{}", self.0.as_ref())
 	}
+
+	fn expects_result(&self) -> bool {
+		false
+	}
 }
 
 pub struct CmdRawJsonArgs<S: AsRef<str>>(S);
@@ -29,24 +55,105 @@ impl<S: AsRef<str>> CmdRawJsonArgs<S> {
 	}
 }
 impl<S: AsRef<str>> MpvCommand for CmdRawJsonArgs<S> {
-	type Data = Option<serde_json::Value>;
-	type Error = std::convert::Infallible;
+	type Error = ParseError;
 	type ParsedData = serde_json::Value;
 
 	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
 		write!(w, "{}", self.0.as_ref())
 	}
 
-	fn parse_data(&self, data: Self::Data) -> Result<Self::ParsedData, Self::Error> {
-		let value = match data {
-			None => serde_json::Value::Null,
-			Some(value) => value
+	fn parse_data(&self, data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(data)
+	}
+}
+
+/// Command assembled from JSON values, the first being the command name.
+///
+/// Unlike `CmdRawJsonArgs` the arguments are serialized by this crate, so they do not need to be escaped
+/// by the caller.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CmdJson(Vec<serde_json::Value>);
+impl CmdJson {
+	pub fn new(args: Vec<serde_json::Value>) -> Self {
+		CmdJson(args)
+	}
+}
+impl MpvCommand for CmdJson {
+	type Error = ParseError;
+	type ParsedData = serde_json::Value;
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		for (index, arg) in self.0.iter().enumerate() {
+			if index > 0 {
+				write!(w, ",")?;
+			}
+			serde_json::to_writer(&mut w, arg)?;
+		}
+
+		Ok(())
+	}
+
+	fn parse_data(&self, data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(data)
+	}
+}
+
+/// Generic command model with a name and JSON arguments.
+///
+/// Serializes as and deserializes from the command object `{ "command": ["name", "arg1", ...] }`,
+/// so commands can be loaded from configuration files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command<'a> {
+	pub name: Cow<'a, str>,
+	pub args: Vec<serde_json::Value>
+}
+impl<'a> Command<'a> {
+	pub fn new(name: impl Into<Cow<'a, str>>, args: Vec<serde_json::Value>) -> Self {
+		Command {
+			name: name.into(),
+			args
+		}
+	}
+}
+impl<'a> MpvCommand for Command<'a> {
+	type Error = ParseError;
+	type ParsedData = serde_json::Value;
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		serde_json::to_writer(&mut w, self.name.as_ref())?;
+		for arg in self.args.iter() {
+			write!(w, ",")?;
+			serde_json::to_writer(&mut w, arg)?;
+		}
+
+		Ok(())
+	}
+
+	fn parse_data(&self, data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(data)
+	}
+}
+impl<'de, 'a> Deserialize<'de> for Command<'a> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		use serde::de::Error;
+
+		#[derive(Deserialize)]
+		struct CommandRepr {
+			command: Vec<serde_json::Value>
+		}
+
+		let mut args = CommandRepr::deserialize(deserializer)?.command.into_iter();
+		let name = match args.next() {
+			Some(serde_json::Value::String(name)) => name,
+			Some(_) => return Err(D::Error::custom("expected the command name to be a string")),
+			None => return Err(D::Error::invalid_length(0, &"a command name"))
 		};
 
-		Ok(value)
+		Ok(Command::new(name, args.collect()))
 	}
 }
 
+#[derive(Default)]
 pub struct CmdGetVersion(std::marker::PhantomData<()>);
 impl CmdGetVersion {
 	pub fn new() -> Self {
@@ -54,15 +161,15 @@ impl CmdGetVersion {
 	}
 }
 impl MpvCommand for CmdGetVersion {
-	type Data = u32;
-	type Error = serde_json::Error;
+	type Error = ParseError;
 	type ParsedData = (u16, u16);
 
 	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
 		write!(w, "\"get_version\"")
 	}
 
-	fn parse_data(&self, data: Self::Data) -> Result<Self::ParsedData, Self::Error> {
+	fn parse_data(&self, data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		let data: u32 = parse_value(data)?;
 		let major = (data >> 16) & 0xFFFF;
 		let minor = data & 0xFFFF;
 
@@ -70,60 +177,186 @@ impl MpvCommand for CmdGetVersion {
 	}
 }
 
-pub struct CmdGetProperty<P: MpvProperty>(P);
-impl<P: MpvProperty> CmdGetProperty<P> {
+#[derive(Default)]
+pub struct CmdClientName(std::marker::PhantomData<()>);
+impl CmdClientName {
+	pub fn new() -> Self {
+		CmdClientName(std::marker::PhantomData)
+	}
+}
+impl MpvCommand for CmdClientName {
+	type Error = ParseError;
+	type ParsedData = String;
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"client_name\"")
+	}
+
+	fn parse_data(&self, data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		parse_value(data)
+	}
+}
+
+pub struct CmdGetProperty<P: ReadableMpvProperty>(P);
+impl<P: ReadableMpvProperty> CmdGetProperty<P> {
 	pub fn new(property: P) -> Self {
 		CmdGetProperty(property)
 	}
 }
-impl<P: MpvProperty> MpvCommand for CmdGetProperty<P> {
-	type Data = P::Value;
-	type Error = std::convert::Infallible;
-	type ParsedData = Self::Data;
+impl<P: ReadableMpvProperty> MpvCommand for CmdGetProperty<P> {
+	type Error = ParseError;
+	type ParsedData = P::Value;
+
+	fn parse_error(
+		&self,
+		error: MpvResponseResultError
+	) -> Result<Self::ParsedData, MpvResponseResultError> {
+		match error {
+			MpvResponseResultError::PropertyUnavailable => {
+				self.0.value_if_unavailable().ok_or(error)
+			}
+			error => Err(error)
+		}
+	}
 
 	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
-		write!(w, "\"get_property\",\"{}\"", self.0.name())
+		w.write_all(b"\"get_property\",")?;
+		serde_json::to_writer(w, &self.0.name())?;
+
+		Ok(())
 	}
 
-	fn parse_data(&self, data: Self::Data) -> Result<Self::ParsedData, Self::Error> {
-		Ok(data)
+	fn parse_data(&self, data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		parse_value(data)
 	}
 }
 
 
-pub struct CmdSetProperty<P: MpvProperty>(P, P::Value);
-impl<P: MpvProperty> CmdSetProperty<P> {
+pub struct CmdSetProperty<P: WritableMpvProperty>(P, P::Value);
+impl<P: WritableMpvProperty> CmdSetProperty<P> {
 	pub fn new(property: P, value: P::Value) -> Self {
 		CmdSetProperty(property, value)
 	}
 }
-impl<P: MpvProperty> MpvCommand for CmdSetProperty<P> {
-	type Data = Option<()>;
-	type Error = serde_json::Error;
-	type ParsedData = Self::Data;
+impl CmdSetProperty<WritableKnownProperty> {
+	/// Fails if `property` is read-only, which is only known at runtime.
+	pub fn known(
+		property: KnownMpvProperty,
+		value: serde_json::Value
+	) -> Result<Self, ReadOnlyPropertyError> {
+		Ok(CmdSetProperty(WritableKnownProperty::new(property)?, value))
+	}
+}
+impl<P: WritableMpvProperty> MpvCommand for CmdSetProperty<P> {
+	type Error = ParseError;
+	type ParsedData = ();
 
 	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
-		write!(w, "\"set_property\",\"{}\",", self.0.name())?;
+		w.write_all(b"\"set_property\",")?;
+		serde_json::to_writer(&mut w, &self.0.name())?;
+		w.write_all(b",")?;
 		serde_json::to_writer(w, &self.1)?;
 
 		Ok(())
 	}
 
-	fn parse_data(&self, data: Self::Data) -> Result<Self::ParsedData, Self::Error> {
-		Ok(data)
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+
+/// Error of `CmdSetChapter::new` for a chapter the file does not have.
+#[derive(Debug, thiserror::Error)]
+#[error("Chapter {index} is out of range, the file has {count} chapters")]
+pub struct ChapterOutOfRangeError {
+	pub index: u32,
+	pub count: u32
+}
+
+/// Jumps to the chapter at the zero-based `index` by setting the `chapter` property.
+///
+/// mpv clamps out of range chapters, so the index is validated against the `chapters` count instead.
+pub struct CmdSetChapter(CmdSetProperty<property::Chapter>);
+impl CmdSetChapter {
+	/// Fails if `index` is not below `chapters`, the value of the `chapters` property.
+	pub fn new(index: u32, chapters: u32) -> Result<Self, ChapterOutOfRangeError> {
+		if index >= chapters {
+			return Err(ChapterOutOfRangeError {
+				index,
+				count: chapters
+			})
+		}
+
+		Ok(CmdSetChapter(CmdSetProperty::new(
+			property::Chapter,
+			i64::from(index)
+		)))
+	}
+}
+impl MpvCommand for CmdSetChapter {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, w: impl std::io::Write) -> std::io::Result<()> {
+		self.0.write_args(w)
+	}
+
+	fn parse_data(&self, data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		self.0.parse_data(data)
+	}
+}
+
+/// Error of `CmdSetEdition::new` for an edition the file does not have.
+#[derive(Debug, thiserror::Error)]
+#[error("The file has no edition with id {0}")]
+pub struct UnknownEditionError(pub i64);
+
+/// Switches to the edition with `id` by setting the `edition` property, which reloads the file.
+pub struct CmdSetEdition(CmdSetProperty<property::Edition>);
+impl CmdSetEdition {
+	/// Fails if `editions`, the value of the `edition-list` property, has no edition with `id`.
+	pub fn new(id: i64, editions: &EditionList) -> Result<Self, UnknownEditionError> {
+		if editions.get(id).is_none() {
+			return Err(UnknownEditionError(id))
+		}
+
+		Ok(CmdSetEdition(CmdSetProperty::new(
+			property::Edition,
+			EditionId::Id(id)
+		)))
 	}
 }
+impl MpvCommand for CmdSetEdition {
+	type Error = ParseError;
+	type ParsedData = ();
 
-pub struct CmdCycleProperty<P: MpvProperty>(P, bool);
-impl<P: MpvProperty> CmdCycleProperty<P> {
+	fn write_args(&self, w: impl std::io::Write) -> std::io::Result<()> {
+		self.0.write_args(w)
+	}
+
+	fn parse_data(&self, data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		self.0.parse_data(data)
+	}
+}
+
+pub struct CmdCycleProperty<P: WritableMpvProperty>(P, bool);
+impl<P: WritableMpvProperty> CmdCycleProperty<P> {
 	pub fn new(property: P, down: bool) -> Self {
 		CmdCycleProperty(property, down)
 	}
 }
-impl<P: MpvProperty> MpvCommand for CmdCycleProperty<P> {
-	type Data = Option<()>;
-	type Error = serde_json::Error;
-	type ParsedData = Self::Data;
+impl CmdCycleProperty<WritableKnownProperty> {
+	/// Fails if `property` is read-only, which is only known at runtime.
+	pub fn known(property: KnownMpvProperty, down: bool) -> Result<Self, ReadOnlyPropertyError> {
+		Ok(CmdCycleProperty(
+			WritableKnownProperty::new(property)?,
+			down
+		))
+	}
+}
+impl<P: WritableMpvProperty> MpvCommand for CmdCycleProperty<P> {
+	type Error = ParseError;
+	type ParsedData = ();
 
 	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
 		let direction = match self.1 {
@@ -131,34 +364,93 @@ impl<P: MpvProperty> MpvCommand for CmdCycleProperty<P> {
 			true => "down"
 		};
 
-		write!(w, "\"cycle\",\"{}\",\"{}\"", self.0.name(), direction)?;
+		w.write_all(b"\"cycle\",")?;
+		serde_json::to_writer(&mut w, &self.0.name())?;
+		write!(w, ",\"{}\"", direction)?;
 
 
 		Ok(())
 	}
 
-	fn parse_data(&self, data: Self::Data) -> Result<Self::ParsedData, Self::Error> {
-		Ok(data)
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+
+/// Sets the property to the value after its current one in `values`, wrapping around.
+///
+/// If the current value is not in the list the first value is set. With `reverse` the list is cycled
+/// backwards.
+pub struct CmdCycleValues<P: WritableMpvProperty> {
+	property: P,
+	values: Vec<P::Value>,
+	reverse: bool
+}
+impl<P: WritableMpvProperty> CmdCycleValues<P> {
+	pub fn new(property: P, values: Vec<P::Value>) -> Self {
+		CmdCycleValues {
+			property,
+			values,
+			reverse: false
+		}
+	}
+
+	pub fn with_reverse(mut self, reverse: bool) -> Self {
+		self.reverse = reverse;
+		self
 	}
 }
+impl<P: WritableMpvProperty> MpvCommand for CmdCycleValues<P> {
+	type Error = ParseError;
+	type ParsedData = ();
 
-pub struct CmdObserveProperty<P: MpvProperty>(u32, P);
-impl<P: MpvProperty> CmdObserveProperty<P> {
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"cycle-values\"")?;
+		if self.reverse {
+			write!(w, ",\"!reverse\"")?;
+		}
+		w.write_all(b",")?;
+		serde_json::to_writer(&mut w, &self.property.name())?;
+
+		// the values are parsed from strings like on the command line
+		for value in self.values.iter() {
+			let value = match serde_json::to_value(value)? {
+				serde_json::Value::String(value) => value,
+				serde_json::Value::Bool(true) => "yes".to_string(),
+				serde_json::Value::Bool(false) => "no".to_string(),
+				value => value.to_string()
+			};
+			w.write_all(b",")?;
+			serde_json::to_writer(&mut w, &value)?;
+		}
+
+		Ok(())
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+
+pub struct CmdObserveProperty<P: ObservableMpvProperty>(u32, P);
+impl<P: ObservableMpvProperty> CmdObserveProperty<P> {
 	pub fn new(observer_id: u32, property: P) -> Self {
 		CmdObserveProperty(observer_id, property)
 	}
 }
-impl<P: MpvProperty> MpvCommand for CmdObserveProperty<P> {
-	type Data = Option<()>;
-	type Error = std::convert::Infallible;
-	type ParsedData = Self::Data;
+impl<P: ObservableMpvProperty> MpvCommand for CmdObserveProperty<P> {
+	type Error = ParseError;
+	type ParsedData = ();
 
 	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
-		write!(w, "\"observe_property\",{},\"{}\"", self.0, self.1.name())
+		write!(w, "\"observe_property\",{},", self.0)?;
+		serde_json::to_writer(w, &self.1.name())?;
+
+		Ok(())
 	}
 
-	fn parse_data(&self, data: Self::Data) -> Result<Self::ParsedData, Self::Error> {
-		Ok(data)
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
 	}
 }
 
@@ -169,163 +461,1462 @@ impl CmdUnobserveProperty {
 	}
 }
 impl MpvCommand for CmdUnobserveProperty {
-	type Data = Option<()>;
-	type Error = std::convert::Infallible;
-	type ParsedData = Self::Data;
+	type Error = ParseError;
+	type ParsedData = ();
 
 	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
 		write!(w, "\"unobserve_property\",{}", self.0)
 	}
 
-	fn parse_data(&self, data: Self::Data) -> Result<Self::ParsedData, Self::Error> {
-		Ok(data)
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
 	}
 }
 
 
-pub struct CmdLoadfile<'a>(Cow<'a, str>, bool);
-impl<'a> CmdLoadfile<'a> {
-	pub fn new(file_path: Cow<'a, str>) -> Self {
-		CmdLoadfile(file_path, false)
+pub struct CmdEnableEvent<'a>(Cow<'a, str>);
+impl<'a> CmdEnableEvent<'a> {
+	pub fn new(event_name: Cow<'a, str>) -> Self {
+		CmdEnableEvent(event_name)
 	}
 
-	pub fn new_append(file_path: Cow<'a, str>) -> Self {
-		CmdLoadfile(file_path, true)
+	pub fn all() -> Self {
+		CmdEnableEvent(Cow::Borrowed("all"))
 	}
 }
-impl<'a> MpvCommand for CmdLoadfile<'a> {
-	type Data = FileloadInfo;
-	type Error = std::convert::Infallible;
-	type ParsedData = Self::Data;
+impl<'a> MpvCommand for CmdEnableEvent<'a> {
+	type Error = ParseError;
+	type ParsedData = ();
 
 	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
-		if self.1 {
-			write!(w, "\"loadfile\",\"{}\",\"append\"", self.0)
-		} else {
-			write!(w, "\"loadfile\",\"{}\"", self.0)
-		}
+		w.write_all(b"\"enable_event\",")?;
+		serde_json::to_writer(w, self.0.as_ref())?;
+
+		Ok(())
 	}
 
-	fn parse_data(&self, data: Self::Data) -> Result<Self::ParsedData, Self::Error> {
-		Ok(data)
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
 	}
 }
 
-pub struct CmdStop(bool);
-impl CmdStop {
-	pub fn new(keep_playlist: bool) -> Self {
-		CmdStop(keep_playlist)
+pub struct CmdDisableEvent<'a>(Cow<'a, str>);
+impl<'a> CmdDisableEvent<'a> {
+	pub fn new(event_name: Cow<'a, str>) -> Self {
+		CmdDisableEvent(event_name)
+	}
+
+	pub fn all() -> Self {
+		CmdDisableEvent(Cow::Borrowed("all"))
 	}
 }
-impl MpvCommand for CmdStop {
-	type Data = Option<()>;
-	type Error = std::convert::Infallible;
-	type ParsedData = Self::Data;
+impl<'a> MpvCommand for CmdDisableEvent<'a> {
+	type Error = ParseError;
+	type ParsedData = ();
 
 	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
-		if self.0 {
-			write!(w, "\"stop\",\"keep-playlist\"")
-		} else {
-			write!(w, "\"stop\"")
-		}
+		w.write_all(b"\"disable_event\",")?;
+		serde_json::to_writer(w, self.0.as_ref())?;
+
+		Ok(())
 	}
 
-	fn parse_data(&self, data: Self::Data) -> Result<Self::ParsedData, Self::Error> {
-		Ok(data)
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
 	}
 }
 
-enum CmdSeekInner {
-	AbsoluteTime(f64),
-	AbsolutePercent(f64),
-	RelativeTime(f64),
-	RelativePercent(f64)
+
+pub struct CmdLoadfile<'a> {
+	file_path: Cow<'a, str>,
+	mode: LoadlistMode,
+	options: Vec<(Cow<'a, str>, Cow<'a, str>)>
 }
-pub struct CmdSeek(CmdSeekInner);
-impl CmdSeek {
-	pub fn time(time: f64, absolute: bool) -> Self {
-		if absolute {
-			CmdSeek(CmdSeekInner::AbsoluteTime(time))
-		} else {
-			CmdSeek(CmdSeekInner::RelativeTime(time))
+impl<'a> CmdLoadfile<'a> {
+	/// mpv 0.38 added the `index` argument before `options`.
+	pub const INDEX_ARGUMENT: VersionGate = VersionGate::since(0, 38, 0);
+
+	pub fn new(file_path: Cow<'a, str>) -> Self {
+		CmdLoadfile {
+			file_path,
+			mode: LoadlistMode::Replace,
+			options: Vec::new()
 		}
 	}
 
-	pub fn percent(percent: f64, absolute: bool) -> Self {
-		if absolute {
-			CmdSeek(CmdSeekInner::AbsolutePercent(percent))
-		} else {
-			CmdSeek(CmdSeekInner::RelativePercent(percent))
+	pub fn new_append(file_path: Cow<'a, str>) -> Self {
+		CmdLoadfile {
+			mode: LoadlistMode::Append,
+			..CmdLoadfile::new(file_path)
 		}
 	}
-}
-impl MpvCommand for CmdSeek {
-	type Data = Option<()>;
-	type Error = std::convert::Infallible;
-	type ParsedData = Self::Data;
 
-	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
-		match self.0 {
-			CmdSeekInner::AbsoluteTime(time) => write!(w, "\"seek\",{},\"absolute\"", time),
-			CmdSeekInner::AbsolutePercent(percent) => {
-				write!(w, "\"seek\",{},\"absolute-percent\"", percent)
+	/// Appends the file and starts playing it if nothing is playing, keeping the rest of the playlist.
+	pub fn new_append_play(file_path: Cow<'a, str>) -> Self {
+		CmdLoadfile {
+			mode: LoadlistMode::AppendPlay,
+			..CmdLoadfile::new(file_path)
+		}
+	}
+
+	/// Sets an option only while playing this file, such as `start=30`.
+	pub fn with_option(mut self, name: Cow<'a, str>, value: Cow<'a, str>) -> Self {
+		self.options.push((name, value));
+		self
+	}
+
+	fn write_options(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		let mut options = String::new();
+		for (index, (name, value)) in self.options.iter().enumerate() {
+			if index > 0 {
+				options.push(',');
 			}
-			CmdSeekInner::RelativeTime(time) => write!(w, "\"seek\",{},\"relative\"", time),
-			CmdSeekInner::RelativePercent(percent) => {
-				write!(w, "\"seek\",{},\"relative-percent\"", percent)
+			options.push_str(name);
+			options.push('=');
+			if value.contains([',', '=', '%', '"', '[', ']']) {
+				options.push_str(&format!("%{}%", value.len()));
 			}
+			options.push_str(value);
 		}
-	}
 
-	fn parse_data(&self, data: Self::Data) -> Result<Self::ParsedData, Self::Error> {
-		Ok(data)
+		serde_json::to_writer(&mut w, &options)?;
+
+		Ok(())
 	}
 }
+impl<'a> MpvCommand for CmdLoadfile<'a> {
+	type Error = ParseError;
+	/// mpv versions before 0.34 return no data.
+	type ParsedData = Option<FileloadInfo>;
 
-pub struct CmdShowProgress(std::marker::PhantomData<()>);
-impl CmdShowProgress {
-	pub fn new() -> Self {
-		CmdShowProgress(std::marker::PhantomData)
+	fn write_args(&self, w: impl std::io::Write) -> std::io::Result<()> {
+		self.write_args_for_version(w, None)
 	}
-}
-impl MpvCommandRaw for CmdShowProgress {
-	fn write(
+
+	fn write_args_for_version(
 		&self,
 		mut w: impl std::io::Write,
-		_request_id: Option<std::num::NonZeroI64>
+		version: Option<MpvVersion>
 	) -> std::io::Result<()> {
-		write!(w, "show-progress")
+		write!(w, "\"loadfile\",")?;
+		serde_json::to_writer(&mut w, self.file_path.as_ref())?;
+		if self.mode == LoadlistMode::Replace && self.options.is_empty() {
+			return Ok(())
+		}
+
+		write!(w, ",\"{}\"", self.mode.as_str())?;
+		if !self.options.is_empty() {
+			if Self::INDEX_ARGUMENT.is_open(version) {
+				// -1 is the default, ignored by the flags above
+				write!(w, ",-1")?;
+			}
+			write!(w, ",")?;
+			self.write_options(&mut w)?;
+		}
+
+		Ok(())
+	}
+
+	fn parse_data(&self, data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		parse_optional(data)
+	}
+}
+impl<'a> MpvCommandNamed for CmdLoadfile<'a> {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"name\":\"loadfile\",\"url\":")?;
+		serde_json::to_writer(&mut w, self.file_path.as_ref())?;
+		if self.mode != LoadlistMode::Replace {
+			write!(w, ",\"flags\":\"{}\"", self.mode.as_str())?;
+		}
+		if !self.options.is_empty() {
+			write!(w, ",\"options\":")?;
+			self.write_options(&mut w)?;
+		}
+
+		Ok(())
 	}
 }
 
-pub struct CmdPlaylistClear(std::marker::PhantomData<()>);
-impl CmdPlaylistClear {
+/// Saves the playback position of the current file so that it is resumed the next time the file is played.
+#[derive(Default)]
+pub struct CmdWriteWatchLaterConfig(std::marker::PhantomData<()>);
+impl CmdWriteWatchLaterConfig {
 	pub fn new() -> Self {
-		CmdPlaylistClear(std::marker::PhantomData)
+		CmdWriteWatchLaterConfig(std::marker::PhantomData)
 	}
 }
-impl MpvCommandRaw for CmdPlaylistClear {
-	fn write(
-		&self,
-		mut w: impl std::io::Write,
-		_request_id: Option<std::num::NonZeroI64>
-	) -> std::io::Result<()> {
-		write!(w, "playlist-clear")
+impl MpvCommand for CmdWriteWatchLaterConfig {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"write-watch-later-config\"")
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+impl MpvCommandNamed for CmdWriteWatchLaterConfig {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"name\":\"write-watch-later-config\"")
 	}
 }
 
-pub struct CmdPlaylistShuffle(std::marker::PhantomData<()>);
-impl CmdPlaylistShuffle {
-	pub fn new() -> Self {
-		CmdPlaylistShuffle(std::marker::PhantomData)
+/// Deletes the saved playback position of the given file, or of the current file if `None`.
+pub struct CmdDeleteWatchLaterConfig<'a>(Option<Cow<'a, str>>);
+impl<'a> CmdDeleteWatchLaterConfig<'a> {
+	pub fn new(file_path: Option<Cow<'a, str>>) -> Self {
+		CmdDeleteWatchLaterConfig(file_path)
 	}
 }
-impl MpvCommandRaw for CmdPlaylistShuffle {
-	fn write(
-		&self,
-		mut w: impl std::io::Write,
-		_request_id: Option<std::num::NonZeroI64>
-	) -> std::io::Result<()> {
-		write!(w, "playlist-shuffle")
+impl<'a> MpvCommand for CmdDeleteWatchLaterConfig<'a> {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"delete-watch-later-config\"")?;
+		if let Some(file_path) = self.0.as_ref() {
+			write!(w, ",")?;
+			serde_json::to_writer(&mut w, file_path.as_ref())?;
+		}
+
+		Ok(())
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+impl<'a> MpvCommandNamed for CmdDeleteWatchLaterConfig<'a> {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"name\":\"delete-watch-later-config\"")?;
+		if let Some(file_path) = self.0.as_ref() {
+			write!(w, ",\"filename\":")?;
+			serde_json::to_writer(&mut w, file_path.as_ref())?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Starts a process from mpv with the given arguments without waiting for it.
+///
+/// Unlike `subprocess`, the process inherits the environment of mpv and its output is not captured.
+pub struct CmdRun<'a>(Vec<Cow<'a, str>>);
+impl<'a> CmdRun<'a> {
+	pub fn new(args: impl IntoIterator<Item = Cow<'a, str>>) -> Self {
+		CmdRun(args.into_iter().collect())
+	}
+}
+impl<'a> MpvCommand for CmdRun<'a> {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"run\"")?;
+		for arg in self.0.iter() {
+			write!(w, ",")?;
+			serde_json::to_writer(&mut w, arg.as_ref())?;
+		}
+
+		Ok(())
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+
+/// Operation applied to a list option by `CmdChangeList`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeListOperation {
+	/// Append a single item, which may contain the list separator.
+	Append,
+	/// Append one or more separated items.
+	Add,
+	/// Prepend one or more separated items.
+	Pre,
+	/// Replace the whole list.
+	Set,
+	/// Clear the list, the value is ignored.
+	Clr,
+	/// Remove all items equal to the value.
+	Remove,
+	/// Append the item if it is not in the list, otherwise remove it.
+	Toggle
+}
+impl ChangeListOperation {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ChangeListOperation::Append => "append",
+			ChangeListOperation::Add => "add",
+			ChangeListOperation::Pre => "pre",
+			ChangeListOperation::Set => "set",
+			ChangeListOperation::Clr => "clr",
+			ChangeListOperation::Remove => "remove",
+			ChangeListOperation::Toggle => "toggle"
+		}
+	}
+}
+
+/// Edits a list option such as `glsl-shaders` or `sub-file-paths` without replacing it as a whole.
+///
+/// There is no named form, since its `name` argument collides with the command name.
+pub struct CmdChangeList<'a>(Cow<'a, str>, ChangeListOperation, Cow<'a, str>);
+impl<'a> CmdChangeList<'a> {
+	pub fn new(option: Cow<'a, str>, operation: ChangeListOperation, value: Cow<'a, str>) -> Self {
+		CmdChangeList(option, operation, value)
+	}
+
+	pub fn clear(option: Cow<'a, str>) -> Self {
+		CmdChangeList(option, ChangeListOperation::Clr, Cow::Borrowed(""))
+	}
+}
+impl<'a> MpvCommand for CmdChangeList<'a> {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"change-list\",")?;
+		serde_json::to_writer(&mut w, self.0.as_ref())?;
+		write!(w, ",\"{}\",", self.1.as_str())?;
+		serde_json::to_writer(&mut w, self.2.as_ref())?;
+
+		Ok(())
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+
+/// Loads a Lua or JavaScript script (or a C plugin) into the running mpv.
+pub struct CmdLoadScript<'a>(Cow<'a, str>);
+impl<'a> CmdLoadScript<'a> {
+	pub fn new(path: Cow<'a, str>) -> Self {
+		CmdLoadScript(path)
+	}
+}
+impl<'a> MpvCommand for CmdLoadScript<'a> {
+	type Error = ParseError;
+	/// mpv versions before 0.34 return no data.
+	type ParsedData = Option<LoadScriptInfo>;
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"load-script\",")?;
+		serde_json::to_writer(&mut w, self.0.as_ref())?;
+
+		Ok(())
+	}
+
+	fn parse_data(&self, data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		parse_optional(data)
+	}
+}
+impl<'a> MpvCommandNamed for CmdLoadScript<'a> {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"name\":\"load-script\",\"filename\":")?;
+		serde_json::to_writer(&mut w, self.0.as_ref())?;
+
+		Ok(())
+	}
+}
+
+/// Binds `key` to `command` in the syntax of `input.conf`, replacing the current binding of the key.
+pub struct CmdKeybind<'a>(Cow<'a, str>, Cow<'a, str>);
+impl<'a> CmdKeybind<'a> {
+	pub fn new(key: Cow<'a, str>, command: Cow<'a, str>) -> Self {
+		CmdKeybind(key, command)
+	}
+}
+impl<'a> MpvCommand for CmdKeybind<'a> {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"keybind\",")?;
+		serde_json::to_writer(&mut w, self.0.as_ref())?;
+		write!(w, ",")?;
+		serde_json::to_writer(&mut w, self.1.as_ref())?;
+
+		Ok(())
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+
+/// Priority of the bindings of a section defined by `CmdDefineSection`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionPriority {
+	/// Bindings of the user, such as those in `input.conf`, take precedence.
+	Default,
+	/// The section takes precedence over the bindings of the user.
+	Force
+}
+impl SectionPriority {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			SectionPriority::Default => "default",
+			SectionPriority::Force => "force"
+		}
+	}
+}
+
+/// Defines the input section `name` with `contents` in the syntax of `input.conf`, replacing it if it exists.
+///
+/// The section has no effect until it is enabled with `CmdEnableSection`.
+pub struct CmdDefineSection<'a>(Cow<'a, str>, Cow<'a, str>, SectionPriority);
+impl<'a> CmdDefineSection<'a> {
+	pub fn new(name: Cow<'a, str>, contents: Cow<'a, str>, priority: SectionPriority) -> Self {
+		CmdDefineSection(name, contents, priority)
+	}
+}
+impl<'a> MpvCommand for CmdDefineSection<'a> {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"define-section\",")?;
+		serde_json::to_writer(&mut w, self.0.as_ref())?;
+		write!(w, ",")?;
+		serde_json::to_writer(&mut w, self.1.as_ref())?;
+		write!(w, ",\"{}\"", self.2.as_str())?;
+
+		Ok(())
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+
+/// Enables the input section `name`, enabling it again moves it to the top.
+pub struct CmdEnableSection<'a>(Cow<'a, str>);
+impl<'a> CmdEnableSection<'a> {
+	pub fn new(name: Cow<'a, str>) -> Self {
+		CmdEnableSection(name)
+	}
+}
+impl<'a> MpvCommand for CmdEnableSection<'a> {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"enable-section\",")?;
+		serde_json::to_writer(&mut w, self.0.as_ref())?;
+
+		Ok(())
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+
+/// Presses and releases `key` as if it was typed, running its binding.
+///
+/// Sent as a text command, so mpv does not reply to it.
+pub struct CmdKeypress<'a>(Cow<'a, str>);
+impl<'a> CmdKeypress<'a> {
+	pub fn new(key: Cow<'a, str>) -> Self {
+		CmdKeypress(key)
+	}
+}
+impl<'a> MpvCommandRaw for CmdKeypress<'a> {
+	fn write(
+		&self,
+		mut w: impl std::io::Write,
+		_request_id: Option<std::num::NonZeroI64>
+	) -> std::io::Result<()> {
+		// JSON escapes are understood in quoted arguments of text commands
+		write!(w, "keypress ")?;
+		serde_json::to_writer(&mut w, self.0.as_ref())?;
+
+		Ok(())
+	}
+
+	fn expects_result(&self) -> bool {
+		false
+	}
+}
+
+/// Bound of the range dumped by `CmdDumpCache`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DumpCacheBound {
+	/// Timestamp in seconds.
+	Time(f64),
+	/// Start or end of the cache.
+	Unbounded
+}
+
+/// Error of `CmdDumpCache::new` for a bound which is not a finite timestamp.
+#[derive(Debug, thiserror::Error)]
+#[error("Dump cache bound {0} is not a finite timestamp")]
+pub struct InvalidDumpCacheBoundError(pub f64);
+
+/// Dumps the demuxer cache between `start` and `end` into a file.
+///
+/// mpv replies only once the dump is finished, so `run_command` returns on completion and a failed dump
+/// is reported as `ErrorRunningCommand`. With an unbounded end mpv keeps appending newly cached data
+/// until the command is aborted, so the reply does not arrive before that. Send the command wrapped in
+/// `Async` to keep using the link meanwhile, its completion is then the result of the raw command.
+pub struct CmdDumpCache<'a>(DumpCacheBound, DumpCacheBound, Cow<'a, str>);
+impl<'a> CmdDumpCache<'a> {
+	/// Fails if a bound is a timestamp which is not finite, since it cannot be written as JSON.
+	pub fn new(
+		start: DumpCacheBound,
+		end: DumpCacheBound,
+		filename: Cow<'a, str>
+	) -> Result<Self, InvalidDumpCacheBoundError> {
+		for bound in [start, end] {
+			if let DumpCacheBound::Time(time) = bound {
+				if !time.is_finite() {
+					return Err(InvalidDumpCacheBoundError(time))
+				}
+			}
+		}
+
+		Ok(CmdDumpCache(start, end, filename))
+	}
+}
+impl<'a> MpvCommand for CmdDumpCache<'a> {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"dump-cache\"")?;
+		for bound in [self.0, self.1] {
+			match bound {
+				DumpCacheBound::Time(time) => {
+					w.write_all(b",")?;
+					serde_json::to_writer(&mut w, &time)?;
+				}
+				DumpCacheBound::Unbounded => write!(w, ",\"no\"")?
+			}
+		}
+		write!(w, ",")?;
+		serde_json::to_writer(&mut w, self.2.as_ref())?;
+
+		Ok(())
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+impl<'a> MpvCommandNamed for CmdDumpCache<'a> {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"name\":\"dump-cache\"")?;
+		for (name, bound) in [("start", self.0), ("end", self.1)] {
+			match bound {
+				DumpCacheBound::Time(time) => {
+					write!(w, ",\"{}\":", name)?;
+					serde_json::to_writer(&mut w, &time)?;
+				}
+				DumpCacheBound::Unbounded => write!(w, ",\"{}\":\"no\"", name)?
+			}
+		}
+		write!(w, ",\"filename\":")?;
+		serde_json::to_writer(&mut w, self.2.as_ref())?;
+
+		Ok(())
+	}
+}
+
+/// How `CmdLoadlist` adds the playlist entries, or `CmdLoadfile` the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoadlistMode {
+	/// Replace the playlist and start playing the first entry.
+	#[default]
+	Replace,
+	/// Append to the end of the playlist.
+	Append,
+	/// Append to the end of the playlist and start playing if nothing is playing.
+	AppendPlay
+}
+impl LoadlistMode {
+	pub const fn as_str(&self) -> &'static str {
+		match self {
+			LoadlistMode::Replace => "replace",
+			LoadlistMode::Append => "append",
+			LoadlistMode::AppendPlay => "append-play"
+		}
+	}
+}
+
+/// Loads a playlist file, such as an m3u file, see `crate::playlist::load_paths` for loading a list of paths.
+pub struct CmdLoadlist<'a>(Cow<'a, str>, LoadlistMode);
+impl<'a> CmdLoadlist<'a> {
+	pub fn new(playlist_path: Cow<'a, str>, mode: LoadlistMode) -> Self {
+		CmdLoadlist(playlist_path, mode)
+	}
+}
+impl<'a> MpvCommand for CmdLoadlist<'a> {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"loadlist\",")?;
+		serde_json::to_writer(&mut w, self.0.as_ref())?;
+		write!(w, ",\"{}\"", self.1.as_str())
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+impl<'a> MpvCommandNamed for CmdLoadlist<'a> {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"name\":\"loadlist\",\"url\":")?;
+		serde_json::to_writer(&mut w, self.0.as_ref())?;
+		write!(w, ",\"flags\":\"{}\"", self.1.as_str())
+	}
+}
+
+pub struct CmdStop(bool);
+impl CmdStop {
+	pub fn new(keep_playlist: bool) -> Self {
+		CmdStop(keep_playlist)
+	}
+}
+impl MpvCommand for CmdStop {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		if self.0 {
+			write!(w, "\"stop\",\"keep-playlist\"")
+		} else {
+			write!(w, "\"stop\"")
+		}
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+impl MpvCommandNamed for CmdStop {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		if self.0 {
+			write!(w, "\"name\":\"stop\",\"flags\":\"keep-playlist\"")
+		} else {
+			write!(w, "\"name\":\"stop\"")
+		}
+	}
+}
+
+/// Plays the next playlist entry, with `force` also stopping playback at the end of the playlist.
+pub struct CmdPlaylistNext(bool);
+impl CmdPlaylistNext {
+	pub fn new(force: bool) -> Self {
+		CmdPlaylistNext(force)
+	}
+}
+impl MpvCommand for CmdPlaylistNext {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		if self.0 {
+			write!(w, "\"playlist-next\",\"force\"")
+		} else {
+			write!(w, "\"playlist-next\",\"weak\"")
+		}
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+impl MpvCommandNamed for CmdPlaylistNext {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		if self.0 {
+			write!(w, "\"name\":\"playlist-next\",\"flags\":\"force\"")
+		} else {
+			write!(w, "\"name\":\"playlist-next\",\"flags\":\"weak\"")
+		}
+	}
+}
+
+/// Plays the previous playlist entry, with `force` also stopping playback at the start of the playlist.
+pub struct CmdPlaylistPrev(bool);
+impl CmdPlaylistPrev {
+	pub fn new(force: bool) -> Self {
+		CmdPlaylistPrev(force)
+	}
+}
+impl MpvCommand for CmdPlaylistPrev {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		if self.0 {
+			write!(w, "\"playlist-prev\",\"force\"")
+		} else {
+			write!(w, "\"playlist-prev\",\"weak\"")
+		}
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+impl MpvCommandNamed for CmdPlaylistPrev {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		if self.0 {
+			write!(w, "\"name\":\"playlist-prev\",\"flags\":\"force\"")
+		} else {
+			write!(w, "\"name\":\"playlist-prev\",\"flags\":\"weak\"")
+		}
+	}
+}
+
+/// Plays the playlist entry at the zero-based `index`.
+pub struct CmdPlaylistPlayIndex(u32);
+impl CmdPlaylistPlayIndex {
+	pub fn new(index: u32) -> Self {
+		CmdPlaylistPlayIndex(index)
+	}
+}
+impl MpvCommand for CmdPlaylistPlayIndex {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"playlist-play-index\",{}", self.0)
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+impl MpvCommandNamed for CmdPlaylistPlayIndex {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"name\":\"playlist-play-index\",\"index\":{}", self.0)
+	}
+}
+
+/// How the target of `CmdSeek` is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SeekMode {
+	/// Offset from the current position in seconds.
+	#[default]
+	Relative,
+	/// Position in seconds.
+	Absolute,
+	/// Offset from the current position in percent of the file.
+	RelativePercent,
+	/// Position in percent of the file.
+	AbsolutePercent
+}
+impl SeekMode {
+	pub const ALL: &'static [SeekMode] = &[
+		SeekMode::Relative,
+		SeekMode::Absolute,
+		SeekMode::RelativePercent,
+		SeekMode::AbsolutePercent
+	];
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			SeekMode::Relative => "relative",
+			SeekMode::Absolute => "absolute",
+			SeekMode::RelativePercent => "relative-percent",
+			SeekMode::AbsolutePercent => "absolute-percent"
+		}
+	}
+}
+impl std::str::FromStr for SeekMode {
+	type Err = UnknownFlagError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		SeekMode::ALL
+			.iter()
+			.copied()
+			.find(|mode| mode.as_str() == s)
+			.ok_or_else(|| UnknownFlagError(s.to_string()))
+	}
+}
+
+/// How precisely `CmdSeek` seeks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SeekPrecision {
+	/// Use the `hr-seek` option.
+	#[default]
+	Default,
+	/// Seek to the nearest keyframe.
+	Keyframes,
+	/// Seek to the exact frame, which is slower.
+	Exact
+}
+impl SeekPrecision {
+	pub const ALL: &'static [SeekPrecision] = &[
+		SeekPrecision::Default,
+		SeekPrecision::Keyframes,
+		SeekPrecision::Exact
+	];
+
+	/// The flag, `None` for `Default`.
+	pub fn as_str(&self) -> Option<&'static str> {
+		match self {
+			SeekPrecision::Default => None,
+			SeekPrecision::Keyframes => Some("keyframes"),
+			SeekPrecision::Exact => Some("exact")
+		}
+	}
+}
+impl std::str::FromStr for SeekPrecision {
+	type Err = UnknownFlagError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"default" => Ok(SeekPrecision::Default),
+			"keyframes" => Ok(SeekPrecision::Keyframes),
+			"exact" => Ok(SeekPrecision::Exact),
+			_ => Err(UnknownFlagError(s.to_string()))
+		}
+	}
+}
+
+/// Error of parsing command flags from strings.
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown flag {0:?}")]
+pub struct UnknownFlagError(pub String);
+
+/// Error of the `CmdSeek` constructors for a target which is not finite.
+#[derive(Debug, thiserror::Error)]
+#[error("Seek target {0} is not finite")]
+pub struct InvalidSeekTargetError(pub f64);
+
+pub struct CmdSeek {
+	target: f64,
+	mode: SeekMode,
+	precision: SeekPrecision
+}
+impl CmdSeek {
+	/// Fails if `target` is not finite, since it cannot be written as JSON.
+	pub fn new(target: f64, mode: SeekMode) -> Result<Self, InvalidSeekTargetError> {
+		if !target.is_finite() {
+			return Err(InvalidSeekTargetError(target))
+		}
+
+		Ok(CmdSeek {
+			target,
+			mode,
+			precision: SeekPrecision::Default
+		})
+	}
+
+	pub fn time(time: f64, absolute: bool) -> Result<Self, InvalidSeekTargetError> {
+		if absolute {
+			CmdSeek::new(time, SeekMode::Absolute)
+		} else {
+			CmdSeek::new(time, SeekMode::Relative)
+		}
+	}
+
+	pub fn percent(percent: f64, absolute: bool) -> Result<Self, InvalidSeekTargetError> {
+		if absolute {
+			CmdSeek::new(percent, SeekMode::AbsolutePercent)
+		} else {
+			CmdSeek::new(percent, SeekMode::RelativePercent)
+		}
+	}
+
+	pub fn with_precision(mut self, precision: SeekPrecision) -> Self {
+		self.precision = precision;
+		self
+	}
+
+	fn flags(&self) -> String {
+		match self.precision.as_str() {
+			None => self.mode.as_str().to_string(),
+			Some(precision) => format!("{}+{}", self.mode.as_str(), precision)
+		}
+	}
+}
+impl MpvCommand for CmdSeek {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"seek\",{},\"{}\"", self.target, self.flags())
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+impl MpvCommandNamed for CmdSeek {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(
+			w,
+			"\"name\":\"seek\",\"target\":{},\"flags\":\"{}\"",
+			self.target,
+			self.flags()
+		)
+	}
+}
+
+/// How `CmdRevertSeek` treats the mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RevertSeekMark {
+	/// Undo the last seek, or return to the mark if one is set.
+	#[default]
+	Revert,
+	/// Set the mark to the current position for a later revert.
+	Mark,
+	/// Like `Mark`, but keeps an already set mark.
+	MarkPermanent
+}
+
+/// Undoes the last seek or manipulates the position it returns to.
+pub struct CmdRevertSeek(RevertSeekMark);
+impl CmdRevertSeek {
+	pub fn new(mark: RevertSeekMark) -> Self {
+		CmdRevertSeek(mark)
+	}
+}
+impl MpvCommand for CmdRevertSeek {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		match self.0 {
+			RevertSeekMark::Revert => write!(w, "\"revert-seek\""),
+			RevertSeekMark::Mark => write!(w, "\"revert-seek\",\"mark\""),
+			RevertSeekMark::MarkPermanent => write!(w, "\"revert-seek\",\"mark-permanent\"")
+		}
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+impl MpvCommandNamed for CmdRevertSeek {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		match self.0 {
+			RevertSeekMark::Revert => write!(w, "\"name\":\"revert-seek\""),
+			RevertSeekMark::Mark => write!(w, "\"name\":\"revert-seek\",\"flags\":\"mark\""),
+			RevertSeekMark::MarkPermanent => {
+				write!(w, "\"name\":\"revert-seek\",\"flags\":\"mark-permanent\"")
+			}
+		}
+	}
+}
+
+/// Adds, updates or removes the OSD overlay `id` of this client, drawn from ASS events.
+///
+/// mpv documents only the named form as stable, so it is best sent with `MpvLink::run_command_named`.
+/// The positional form leaves out `compute_bounds`, which is not supported.
+pub struct CmdOsdOverlay<'a> {
+	id: u32,
+	/// `None` removes the overlay.
+	data: Option<Cow<'a, str>>,
+	res_x: u32,
+	res_y: u32,
+	z: i32,
+	hidden: bool
+}
+impl<'a> CmdOsdOverlay<'a> {
+	/// Shows the ASS events `data`, one event per line, in a canvas 720 units high and as wide as the
+	/// aspect ratio of the window.
+	pub fn new(id: u32, data: Cow<'a, str>) -> Self {
+		CmdOsdOverlay {
+			id,
+			data: Some(data),
+			res_x: 0,
+			res_y: 720,
+			z: 0,
+			hidden: false
+		}
+	}
+
+	pub fn remove(id: u32) -> Self {
+		CmdOsdOverlay {
+			data: None,
+			..CmdOsdOverlay::new(id, Cow::Borrowed(""))
+		}
+	}
+
+	/// Sets the size of the canvas, `res_x` of `0` derives the width from the aspect ratio of the window.
+	pub fn with_resolution(mut self, res_x: u32, res_y: u32) -> Self {
+		self.res_x = res_x;
+		self.res_y = res_y;
+		self
+	}
+
+	/// Sets the order of overlapping overlays, higher values are drawn on top.
+	pub fn with_z(mut self, z: i32) -> Self {
+		self.z = z;
+		self
+	}
+
+	pub fn with_hidden(mut self, hidden: bool) -> Self {
+		self.hidden = hidden;
+		self
+	}
+
+	fn format(&self) -> &'static str {
+		match self.data {
+			Some(_) => "ass-events",
+			None => "none"
+		}
+	}
+}
+impl<'a> MpvCommand for CmdOsdOverlay<'a> {
+	type Error = ParseError;
+	type ParsedData = ();
+
+	fn write_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(w, "\"osd-overlay\",{},\"{}\",", self.id, self.format())?;
+		serde_json::to_writer(&mut w, self.data.as_deref().unwrap_or(""))?;
+		write!(
+			w,
+			",{},{},{},{}",
+			self.res_x, self.res_y, self.z, self.hidden
+		)
+	}
+
+	fn parse_data(&self, _data: serde_json::Value) -> Result<Self::ParsedData, Self::Error> {
+		Ok(())
+	}
+}
+impl<'a> MpvCommandNamed for CmdOsdOverlay<'a> {
+	fn write_named_args(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+		write!(
+			w,
+			"\"name\":\"osd-overlay\",\"id\":{},\"format\":\"{}\",\"data\":",
+			self.id,
+			self.format()
+		)?;
+		serde_json::to_writer(&mut w, self.data.as_deref().unwrap_or(""))?;
+		write!(
+			w,
+			",\"res_x\":{},\"res_y\":{},\"z\":{},\"hidden\":{}",
+			self.res_x, self.res_y, self.z, self.hidden
+		)
+	}
+}
+
+#[derive(Default)]
+pub struct CmdShowProgress(std::marker::PhantomData<()>);
+impl CmdShowProgress {
+	pub fn new() -> Self {
+		CmdShowProgress(std::marker::PhantomData)
+	}
+}
+impl MpvCommandRaw for CmdShowProgress {
+	fn write(
+		&self,
+		mut w: impl std::io::Write,
+		_request_id: Option<std::num::NonZeroI64>
+	) -> std::io::Result<()> {
+		write!(w, "show-progress")
+	}
+
+	fn expects_result(&self) -> bool {
+		false
+	}
+}
+
+#[derive(Default)]
+pub struct CmdPlaylistClear(std::marker::PhantomData<()>);
+impl CmdPlaylistClear {
+	pub fn new() -> Self {
+		CmdPlaylistClear(std::marker::PhantomData)
+	}
+}
+impl MpvCommandRaw for CmdPlaylistClear {
+	fn write(
+		&self,
+		mut w: impl std::io::Write,
+		_request_id: Option<std::num::NonZeroI64>
+	) -> std::io::Result<()> {
+		write!(w, "playlist-clear")
+	}
+
+	fn expects_result(&self) -> bool {
+		false
+	}
+}
+
+#[derive(Default)]
+pub struct CmdPlaylistShuffle(std::marker::PhantomData<()>);
+impl CmdPlaylistShuffle {
+	pub fn new() -> Self {
+		CmdPlaylistShuffle(std::marker::PhantomData)
+	}
+}
+impl MpvCommandRaw for CmdPlaylistShuffle {
+	fn write(
+		&self,
+		mut w: impl std::io::Write,
+		_request_id: Option<std::num::NonZeroI64>
+	) -> std::io::Result<()> {
+		write!(w, "playlist-shuffle")
+	}
+
+	fn expects_result(&self) -> bool {
+		false
+	}
+}
+
+/// Implements `Serialize` producing the command object, see `serialize_command`.
+///
+/// Text commands cannot be represented as command objects and do not implement it.
+macro_rules! impl_serialize_command {
+	(
+		$(
+			[$($generics: tt)*] $command: ty
+		),+ $(,)?
+	) => {
+		$(
+			impl<$($generics)*> Serialize for $command {
+				fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+					serialize_command(self, serializer)
+				}
+			}
+		)+
+	};
+}
+impl_serialize_command! {
+	[S: AsRef<str>] CmdRawJsonArgs<S>,
+	[] CmdJson,
+	['a] Command<'a>,
+	[] CmdGetVersion,
+	[] CmdClientName,
+	[P: ReadableMpvProperty] CmdGetProperty<P>,
+	[P: WritableMpvProperty] CmdSetProperty<P>,
+	[] CmdSetChapter,
+	[] CmdSetEdition,
+	[P: WritableMpvProperty] CmdCycleProperty<P>,
+	[P: WritableMpvProperty] CmdCycleValues<P>,
+	[P: ObservableMpvProperty] CmdObserveProperty<P>,
+	[] CmdUnobserveProperty,
+	['a] CmdEnableEvent<'a>,
+	['a] CmdDisableEvent<'a>,
+	['a] CmdLoadfile<'a>,
+	[] CmdWriteWatchLaterConfig,
+	['a] CmdDeleteWatchLaterConfig<'a>,
+	['a] CmdRun<'a>,
+	['a] CmdChangeList<'a>,
+	['a] CmdLoadScript<'a>,
+	['a] CmdKeybind<'a>,
+	['a] CmdDefineSection<'a>,
+	['a] CmdEnableSection<'a>,
+	['a] CmdDumpCache<'a>,
+	['a] CmdLoadlist<'a>,
+	[] CmdStop,
+	[] CmdPlaylistNext,
+	[] CmdPlaylistPrev,
+	[] CmdPlaylistPlayIndex,
+	[] CmdSeek,
+	[] CmdRevertSeek,
+	['a] CmdOsdOverlay<'a>,
+}
+
+#[cfg(test)]
+mod test {
+	use std::{borrow::Cow, num::NonZeroI64};
+
+	use super::{
+		CmdCycleProperty,
+		CmdCycleValues,
+		CmdDisableEvent,
+		CmdDumpCache,
+		CmdEnableEvent,
+		CmdGetProperty,
+		CmdJson,
+		CmdLoadfile,
+		CmdObserveProperty,
+		CmdOsdOverlay,
+		CmdSeek,
+		CmdSetChapter,
+		CmdSetEdition,
+		CmdSetProperty,
+		Command,
+		DumpCacheBound,
+		SeekMode,
+		SeekPrecision
+	};
+	use crate::{
+		command::{
+			property::{self, KnownMpvProperty, Maybe, OptionProperty},
+			response::MpvResponseResultError,
+			Async,
+			MpvCommand,
+			MpvCommandRaw,
+			Named
+		},
+		model::{Edition, EditionList},
+		version::MpvVersion
+	};
+
+	fn args_for_version(command: &impl MpvCommand, version: Option<MpvVersion>) -> String {
+		let mut out = Vec::new();
+		command.write_args_for_version(&mut out, version).unwrap();
+
+		String::from_utf8(out).unwrap()
+	}
+
+	#[test]
+	fn test_loadfile_options_version() {
+		let command = CmdLoadfile::new(Cow::Borrowed("/a \"b\".mkv"))
+			.with_option(Cow::Borrowed("start"), Cow::Borrowed("30"));
+
+		assert_eq!(
+			args_for_version(&command, Some(MpvVersion::new(0, 37, 0))),
+			r#""loadfile","/a \"b\".mkv","replace","start=30""#
+		);
+		assert_eq!(
+			args_for_version(&command, Some(MpvVersion::new(0, 38, 0))),
+			r#""loadfile","/a \"b\".mkv","replace",-1,"start=30""#
+		);
+		assert_eq!(
+			args_for_version(&CmdLoadfile::new(Cow::Borrowed("/a")), None),
+			r#""loadfile","/a""#
+		);
+		assert_eq!(
+			args_for_version(&CmdLoadfile::new_append_play(Cow::Borrowed("/a")), None),
+			r#""loadfile","/a","append-play""#
+		);
+	}
+
+	#[test]
+	fn test_named_form() {
+		let mut out = Vec::new();
+		let command = CmdSeek::new(10.5, SeekMode::Absolute)
+			.unwrap()
+			.with_precision(SeekPrecision::Exact);
+		Named(&command).write(&mut out, None).unwrap();
+
+		let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+		assert_eq!(
+			value,
+			serde_json::json!({
+				"request_id": 0,
+				"command": { "name": "seek", "target": 10.5, "flags": "absolute+exact" }
+			})
+		);
+	}
+
+	#[test]
+	fn test_seek_rejects_non_finite() {
+		assert!(CmdSeek::new(f64::NAN, SeekMode::Relative).is_err());
+		assert!(CmdSeek::time(f64::INFINITY, true).is_err());
+		assert!(CmdSeek::percent(f64::NEG_INFINITY, false).is_err());
+	}
+
+	#[test]
+	fn test_osd_overlay() {
+		let mut out = Vec::new();
+		let command = CmdOsdOverlay::new(3, Cow::Borrowed("{\\an7}Menu")).with_z(1);
+		Named(&command).write(&mut out, None).unwrap();
+
+		let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+		assert_eq!(
+			value["command"],
+			serde_json::json!({
+				"name": "osd-overlay",
+				"id": 3,
+				"format": "ass-events",
+				"data": "{\\an7}Menu",
+				"res_x": 0,
+				"res_y": 720,
+				"z": 1,
+				"hidden": false
+			})
+		);
+
+		assert_eq!(
+			args_for_version(&CmdOsdOverlay::remove(3), None),
+			r#""osd-overlay",3,"none","",0,720,0,false"#
+		);
+	}
+
+	#[test]
+	fn test_serialize_command() {
+		let command = CmdSeek::new(-5.5, SeekMode::Relative).unwrap();
+
+		assert_eq!(
+			serde_json::to_value(&command).unwrap(),
+			serde_json::json!({ "command": ["seek", -5.5, "relative"] })
+		);
+	}
+
+	#[test]
+	fn test_command_roundtrip() {
+		let value = serde_json::json!({ "command": ["loadfile", "/a.mkv", "append"] });
+		let command: Command = serde_json::from_value(value.clone()).unwrap();
+
+		assert_eq!(command.name, "loadfile");
+		assert_eq!(serde_json::to_value(&command).unwrap(), value);
+	}
+
+	#[test]
+	fn test_set_chapter_and_edition() {
+		assert_eq!(
+			args_for_version(&CmdSetChapter::new(2, 3).unwrap(), None),
+			r#""set_property","chapter",2"#
+		);
+		assert!(CmdSetChapter::new(3, 3).is_err());
+
+		let editions = EditionList(vec![Edition {
+			id: 1,
+			default: true,
+			title: None
+		}]);
+		assert_eq!(
+			args_for_version(&CmdSetEdition::new(1, &editions).unwrap(), None),
+			r#""set_property","edition",1"#
+		);
+		assert!(CmdSetEdition::new(0, &editions).is_err());
+	}
+
+	#[test]
+	fn test_dump_cache() {
+		let command = CmdDumpCache::new(
+			DumpCacheBound::Time(1.5),
+			DumpCacheBound::Unbounded,
+			Cow::Borrowed("/a.ts")
+		)
+		.unwrap();
+		let mut out = Vec::new();
+		Async(&command).write(&mut out, NonZeroI64::new(4)).unwrap();
+
+		let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+		assert_eq!(
+			value,
+			serde_json::json!({
+				"request_id": 4,
+				"async": true,
+				"command": ["dump-cache", 1.5, "no", "/a.ts"]
+			})
+		);
+
+		for time in [f64::NAN, f64::INFINITY] {
+			assert!(CmdDumpCache::new(
+				DumpCacheBound::Unbounded,
+				DumpCacheBound::Time(time),
+				Cow::Borrowed("/a.ts")
+			)
+			.is_err());
+		}
+	}
+
+	#[test]
+	fn test_get_maybe_property() {
+		let command = CmdGetProperty::new(Maybe(property::Duration));
+		assert_eq!(
+			command.parse_error(MpvResponseResultError::PropertyUnavailable),
+			Ok(None)
+		);
+		assert_eq!(
+			command.parse_error(MpvResponseResultError::PropertyNotFound),
+			Err(MpvResponseResultError::PropertyNotFound)
+		);
+		assert_eq!(
+			command.parse_data(serde_json::json!(5.0)).unwrap(),
+			Some(5.0)
+		);
+
+		let command = CmdGetProperty::new(property::Duration);
+		assert_eq!(
+			command.parse_error(MpvResponseResultError::PropertyUnavailable),
+			Err(MpvResponseResultError::PropertyUnavailable)
+		);
+	}
+
+	#[test]
+	fn test_cycle_values() {
+		assert_eq!(
+			args_for_version(
+				&CmdCycleValues::new(property::Speed, vec![1.0, 1.5, 2.0]),
+				None
+			),
+			r#""cycle-values","speed","1.0","1.5","2.0""#
+		);
+		assert_eq!(
+			args_for_version(
+				&CmdCycleValues::new(property::Pause, vec![true, false]).with_reverse(true),
+				None
+			),
+			r#""cycle-values","!reverse","pause","yes","no""#
+		);
+	}
+
+	#[test]
+	fn test_set_known_property() {
+		assert_eq!(
+			args_for_version(
+				&CmdSetProperty::known(KnownMpvProperty::Volume, serde_json::json!(50)).unwrap(),
+				None
+			),
+			r#""set_property","volume",50"#
+		);
+		assert!(CmdSetProperty::known(KnownMpvProperty::Duration, serde_json::json!(1)).is_err());
+		assert!(CmdCycleProperty::known(KnownMpvProperty::Duration, false).is_err());
+	}
+
+	#[test]
+	fn test_property_name_escapes() {
+		let name = "a\",\"b\\";
+		assert_eq!(
+			args_for_version(&CmdGetProperty::new(name), None),
+			r#""get_property","a\",\"b\\""#
+		);
+		assert_eq!(
+			args_for_version(&CmdSetProperty::new(name, serde_json::json!(1)), None),
+			r#""set_property","a\",\"b\\",1"#
+		);
+		assert_eq!(
+			args_for_version(&CmdObserveProperty::new(7, name), None),
+			r#""observe_property",7,"a\",\"b\\""#
+		);
+	}
+
+	#[test]
+	fn test_option_property_escapes() {
+		let option = OptionProperty::file_local("x\"y").typed::<String>();
+		assert_eq!(
+			args_for_version(&CmdGetProperty::new(option.clone()), None),
+			r#""get_property","file-local-options/x\"y""#
+		);
+		assert_eq!(
+			args_for_version(&CmdSetProperty::new(option, "a\"b".to_string()), None),
+			r#""set_property","file-local-options/x\"y","a\"b""#
+		);
+	}
+
+	#[test]
+	fn test_event_name_escapes() {
+		assert_eq!(
+			args_for_version(&CmdEnableEvent::new(Cow::Borrowed("x\",\"y")), None),
+			r#""enable_event","x\",\"y""#
+		);
+		assert_eq!(
+			args_for_version(&CmdDisableEvent::new(Cow::Borrowed("a\\b")), None),
+			r#""disable_event","a\\b""#
+		);
+	}
+
+	#[test]
+	fn test_json_command_escapes() {
+		let command = CmdJson::new(vec![
+			serde_json::json!("show-text"),
+			serde_json::json!("\"quoted\"\nline"),
+			serde_json::json!(2000),
+		]);
+
+		let mut out = Vec::new();
+		command.write_args(&mut out).unwrap();
+		assert_eq!(
+			String::from_utf8(out).unwrap(),
+			r#""show-text","\"quoted\"\nline",2000"#
+		);
 	}
 }
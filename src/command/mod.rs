@@ -1,6 +1,9 @@
 use std::{io, num::NonZeroI64};
 
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serializer};
+use thiserror::Error;
+
+use crate::{command::response::MpvResponseResultError, version::MpvVersion};
 
 pub mod commands;
 pub mod property;
@@ -12,6 +15,25 @@ pub mod response;
 pub trait MpvCommandRaw {
 	/// Formats command into a stream.
 	fn write(&self, w: impl io::Write, request_id: Option<NonZeroI64>) -> io::Result<()>;
+
+	/// Whether mpv replies to the command with a result, text commands get no reply.
+	fn expects_result(&self) -> bool {
+		true
+	}
+
+	/// Formats command into a stream for a server of `version`, `None` if the version is unknown.
+	///
+	/// This is what `MpvLink` calls, commands whose syntax changed between mpv versions override it
+	/// and check the version with a `VersionGate`. Defaults to `write`.
+	fn write_for_version(
+		&self,
+		w: impl io::Write,
+		request_id: Option<NonZeroI64>,
+		version: Option<MpvVersion>
+	) -> io::Result<()> {
+		let _ = version;
+		self.write(w, request_id)
+	}
 }
 
 /// Trait for mpv JSON command definition.
@@ -22,13 +44,11 @@ pub trait MpvCommandRaw {
 /// { "command": ["name", "arg1", "arg2"], "request_id"?: 123 }
 /// ```
 pub trait MpvCommand: MpvCommandRaw {
-	/// The response `data` field type.
-	///
-	/// Usually `serde_json::Value`.
-	type Data: DeserializeOwned;
 	/// The output of `parse_data`.
 	type ParsedData;
-	/// The error produced while parsing `Self::Data`.
+	/// The error produced while parsing the response data.
+	///
+	/// Usually `ParseError`.
 	type Error: std::error::Error;
 
 	/// Formats command arguments into a stream.
@@ -41,20 +61,222 @@ pub trait MpvCommand: MpvCommandRaw {
 	/// This method is only called from the default implementation of `write_raw`.
 	fn write_args(&self, w: impl io::Write) -> io::Result<()>;
 
-	/// Parses data from a response "data" field. The data is guaranteed to be a valid JSON value.
-	fn parse_data(&self, data: Self::Data) -> Result<Self::ParsedData, Self::Error>;
+	/// Parses and validates data from a response "data" field.
+	///
+	/// The field is `Value::Null` when the response has none, mpv omits it for many commands and some
+	/// mpv versions omit it where newer ones return data.
+	fn parse_data(&self, data: serde_json::Value) -> Result<Self::ParsedData, Self::Error>;
+
+	/// Handles an error response, called by `MpvLink` instead of `parse_data` when the command failed.
+	///
+	/// Returning `Ok` turns the error into a successful result, `CmdGetProperty` of a `Maybe` property turns
+	/// `property unavailable` into `None` this way. Defaults to returning the error.
+	fn parse_error(
+		&self,
+		error: MpvResponseResultError
+	) -> Result<Self::ParsedData, MpvResponseResultError> {
+		Err(error)
+	}
+
+	/// Version-aware counterpart of `write_args`, see `MpvCommandRaw::write_for_version`.
+	///
+	/// Defaults to `write_args`.
+	fn write_args_for_version(
+		&self,
+		w: impl io::Write,
+		version: Option<MpvVersion>
+	) -> io::Result<()> {
+		let _ = version;
+		self.write_args(w)
+	}
+
+	/// Version-aware counterpart of `parse_data`, called by `MpvLink`.
+	///
+	/// Defaults to `parse_data`.
+	fn parse_data_for_version(
+		&self,
+		data: serde_json::Value,
+		version: Option<MpvVersion>
+	) -> Result<Self::ParsedData, Self::Error> {
+		let _ = version;
+		self.parse_data(data)
+	}
 }
 
-impl<T: MpvCommand> MpvCommandRaw for T {
+/// Trait for mpv JSON commands which can also be sent with named arguments.
+///
+/// Command model:
+///
+/// ```json
+/// { "command": { "name": "name", "arg1": "value1" }, "request_id"?: 123 }
+/// ```
+///
+/// Optional arguments can be left out regardless of their position, so the named form is not affected by
+/// arguments inserted in newer mpv versions. Only input commands accept it, the IPC commands such as
+/// `get_property` do not.
+pub trait MpvCommandNamed: MpvCommand {
+	/// Formats the command name and arguments as members of a JSON object with the enclosing braces removed.
+	///
+	/// For example to send `{ "command": { "name": "seek", "target": 10 } }` this method should write
+	/// into the stream `"name": "seek", "target": 10`.
+	fn write_named_args(&self, w: impl io::Write) -> io::Result<()>;
+}
+
+/// Adapter sending the wrapped command in the named form, see `MpvLink::run_command_named`.
+pub struct Named<'c, C: MpvCommandNamed + ?Sized>(pub &'c C);
+impl<'c, C: MpvCommandNamed + ?Sized> MpvCommandRaw for Named<'c, C> {
 	fn write(&self, mut w: impl io::Write, request_id: Option<NonZeroI64>) -> io::Result<()> {
 		write!(
 			w,
-			"{{\"request_id\":{},\"command\":[",
+			"{{\"request_id\":{},\"command\":{{",
 			request_id.map(|n| n.get()).unwrap_or(0)
 		)?;
-		self.write_args(&mut w)?;
-		write!(w, "]}}",)?;
+		self.0.write_named_args(&mut w)?;
+		write!(w, "}}}}")?;
 
 		Ok(())
 	}
 }
+
+/// Adapter sending the wrapped command to be run asynchronously by mpv.
+///
+/// mpv replies once the command finished without blocking other commands meanwhile, so the command is meant to
+/// be sent with `MpvLink::run_command_raw` and its result taken with `MpvLink::take_raw_result` later.
+pub struct Async<'c, C: MpvCommand + ?Sized>(pub &'c C);
+impl<'c, C: MpvCommand + ?Sized> MpvCommandRaw for Async<'c, C> {
+	fn write(&self, w: impl io::Write, request_id: Option<NonZeroI64>) -> io::Result<()> {
+		self.write_for_version(w, request_id, None)
+	}
+
+	fn write_for_version(
+		&self,
+		mut w: impl io::Write,
+		request_id: Option<NonZeroI64>,
+		version: Option<MpvVersion>
+	) -> io::Result<()> {
+		write!(
+			w,
+			"{{\"request_id\":{},\"async\":true,\"command\":[",
+			request_id.map(|n| n.get()).unwrap_or(0)
+		)?;
+		self.0.write_args_for_version(&mut w, version)?;
+		write!(w, "]}}")?;
+
+		Ok(())
+	}
+}
+
+/// Error of validating response data, shared by the bundled commands.
+#[derive(Debug, Error)]
+pub enum ParseError {
+	#[error("Expected data but the response has none")]
+	MissingData,
+	#[error("Could not deserialize data: {0}")]
+	Deserialize(#[from] serde_json::Error),
+	#[error("Invalid data: {0}")]
+	Invalid(String)
+}
+
+/// Deserializes required response data, reporting `MissingData` when it is absent and not accepted by `T`.
+pub fn parse_value<T: DeserializeOwned>(data: serde_json::Value) -> Result<T, ParseError> {
+	let missing = data.is_null();
+
+	serde_json::from_value(data).map_err(|err| match missing {
+		true => ParseError::MissingData,
+		false => ParseError::Deserialize(err)
+	})
+}
+
+/// Deserializes optional response data, absent data is `None`.
+pub fn parse_optional<T: DeserializeOwned>(
+	data: serde_json::Value
+) -> Result<Option<T>, ParseError> {
+	match data {
+		serde_json::Value::Null => Ok(None),
+		data => Ok(Some(serde_json::from_value(data)?))
+	}
+}
+
+impl<T: MpvCommand> MpvCommandRaw for T {
+	fn write(&self, w: impl io::Write, request_id: Option<NonZeroI64>) -> io::Result<()> {
+		write_command(w, request_id, |w| self.write_args(w))
+	}
+
+	fn write_for_version(
+		&self,
+		w: impl io::Write,
+		request_id: Option<NonZeroI64>,
+		version: Option<MpvVersion>
+	) -> io::Result<()> {
+		write_command(w, request_id, |w| self.write_args_for_version(w, version))
+	}
+}
+
+/// Serializes `command` as the command object `{ "command": [...] }` without a request id.
+///
+/// Used by the `Serialize` implementations of the bundled commands. Works with any serializer,
+/// the arguments are converted through `serde_json::Value`.
+pub fn serialize_command<C: MpvCommand + ?Sized, S: Serializer>(
+	command: &C,
+	serializer: S
+) -> Result<S::Ok, S::Error> {
+	use serde::ser::{Error, SerializeStruct};
+
+	let mut args = vec![b'['];
+	command.write_args(&mut args).map_err(S::Error::custom)?;
+	args.push(b']');
+	let args: serde_json::Value = serde_json::from_slice(&args).map_err(S::Error::custom)?;
+
+	let mut state = serializer.serialize_struct("Command", 1)?;
+	state.serialize_field("command", &args)?;
+	state.end()
+}
+
+/// Writes the command object around the arguments written by `write_args`.
+fn write_command<W: io::Write>(
+	mut w: W,
+	request_id: Option<NonZeroI64>,
+	write_args: impl FnOnce(&mut W) -> io::Result<()>
+) -> io::Result<()> {
+	write!(
+		w,
+		"{{\"request_id\":{},\"command\":[",
+		request_id.map(|n| n.get()).unwrap_or(0)
+	)?;
+	write_args(&mut w)?;
+	write!(w, "]}}",)?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use serde_json::json;
+
+	use super::{parse_optional, parse_value, ParseError};
+
+	#[test]
+	fn test_parse_value_missing() {
+		assert!(matches!(
+			parse_value::<u32>(serde_json::Value::Null),
+			Err(ParseError::MissingData)
+		));
+		assert!(matches!(
+			parse_value::<u32>(json!("x")),
+			Err(ParseError::Deserialize(_))
+		));
+		assert_eq!(
+			parse_value::<serde_json::Value>(serde_json::Value::Null).unwrap(),
+			serde_json::Value::Null
+		);
+	}
+
+	#[test]
+	fn test_parse_optional() {
+		assert_eq!(
+			parse_optional::<u32>(serde_json::Value::Null).unwrap(),
+			None
+		);
+		assert_eq!(parse_optional::<u32>(json!(1)).unwrap(), Some(1));
+	}
+}
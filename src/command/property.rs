@@ -1,14 +1,49 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, marker::PhantomData};
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::model::TrackId;
+use crate::model::{EditionId, Filter, PlaylistEntry, ReplayGain, Track, TrackId};
 
 pub trait MpvProperty {
 	type Value: Serialize + DeserializeOwned;
 
 	fn name(&self) -> Cow<'_, str>;
+
+	/// Value `get_property` returns instead of failing when mpv reports the property unavailable.
+	///
+	/// Defaults to `None`, which keeps the error. Overridden by `Maybe`.
+	fn value_if_unavailable(&self) -> Option<Self::Value> {
+		None
+	}
+}
+
+/// Property whose value is `None` when it is unavailable, rather than an error.
+///
+/// Reading `duration` before a file is loaded fails, while reading `Maybe(Duration)` returns `None`:
+///
+/// ```
+/// use mpvsock::command::{commands::CmdGetProperty, property};
+///
+/// let command = CmdGetProperty::new(property::Maybe(property::Duration));
+/// ```
+///
+/// Unavailable properties are observed as `null`, which is `None` as well. Properties which do not exist
+/// still fail with `property not found`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Maybe<P>(pub P);
+impl<P: MpvProperty> MpvProperty for Maybe<P> {
+	type Value = Option<P::Value>;
+
+	fn name(&self) -> Cow<'_, str> {
+		self.0.name()
+	}
+
+	fn value_if_unavailable(&self) -> Option<Self::Value> {
+		Some(None)
+	}
 }
+impl<P: ReadableMpvProperty> ReadableMpvProperty for Maybe<P> {}
+impl<P: ObservableMpvProperty> ObservableMpvProperty for Maybe<P> {}
 
 impl<'a> MpvProperty for &'a str {
 	type Value = serde_json::Value;
@@ -28,15 +63,141 @@ impl<'a> MpvProperty for Cow<'a, str> {
 	}
 }
 
+/// Namespace of an `OptionProperty`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionScope {
+	/// `options/<name>`, the global value of the option.
+	Global,
+	/// `file-local-options/<name>`, the value reset when the current file ends.
+	FileLocal
+}
+impl OptionScope {
+	pub fn prefix(&self) -> &'static str {
+		match self {
+			OptionScope::Global => "options/",
+			OptionScope::FileLocal => "file-local-options/"
+		}
+	}
+}
+
+/// Runtime option accessed through the `options/` or `file-local-options/` property namespace.
+///
+/// The value is untyped JSON unless a type is given with `typed`, for example
+/// `OptionProperty::new("ytdl-format").typed::<String>()`.
+#[derive(Debug, Clone)]
+pub struct OptionProperty<'a, T = serde_json::Value> {
+	name: Cow<'a, str>,
+	scope: OptionScope,
+	value: PhantomData<fn() -> T>
+}
+impl<'a> OptionProperty<'a> {
+	pub fn new(name: impl Into<Cow<'a, str>>) -> Self {
+		OptionProperty {
+			name: name.into(),
+			scope: OptionScope::Global,
+			value: PhantomData
+		}
+	}
+
+	pub fn file_local(name: impl Into<Cow<'a, str>>) -> Self {
+		OptionProperty::new(name).with_scope(OptionScope::FileLocal)
+	}
+}
+impl<'a, T: Serialize + DeserializeOwned> OptionProperty<'a, T> {
+	pub fn with_scope(self, scope: OptionScope) -> Self {
+		OptionProperty { scope, ..self }
+	}
+
+	/// Changes the type the option value is (de)serialized as.
+	pub fn typed<U: Serialize + DeserializeOwned>(self) -> OptionProperty<'a, U> {
+		OptionProperty {
+			name: self.name,
+			scope: self.scope,
+			value: PhantomData
+		}
+	}
+
+	/// Name of the option without the namespace.
+	pub fn option_name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn scope(&self) -> OptionScope {
+		self.scope
+	}
+}
+impl<'a, T: Serialize + DeserializeOwned> MpvProperty for OptionProperty<'a, T> {
+	type Value = T;
+
+	fn name(&self) -> Cow<'_, str> {
+		Cow::Owned(format!("{}{}", self.scope.prefix(), self.name))
+	}
+}
+impl<'a, T: Serialize + DeserializeOwned> ReadableMpvProperty for OptionProperty<'a, T> {}
+impl<'a, T: Serialize + DeserializeOwned> WritableMpvProperty for OptionProperty<'a, T> {}
+impl<'a, T: Serialize + DeserializeOwned> ObservableMpvProperty for OptionProperty<'a, T> {}
+
+/// Property which can be read with `get_property`.
+pub trait ReadableMpvProperty: MpvProperty {}
+/// Property which can be written with `set_property` or `cycle`.
+///
+/// Properties named by strings implement this as well, since their capabilities are not known up front.
+/// Writing a known read-only property does not compile:
+///
+/// ```compile_fail
+/// use mpvsock::command::{commands::CmdSetProperty, property};
+///
+/// CmdSetProperty::new(property::Duration, 1.0);
+/// ```
+pub trait WritableMpvProperty: MpvProperty {}
+/// Property which can be observed with `observe_property`.
+pub trait ObservableMpvProperty: MpvProperty {}
+
+impl ReadableMpvProperty for &str {}
+impl WritableMpvProperty for &str {}
+impl ObservableMpvProperty for &str {}
+impl ReadableMpvProperty for Cow<'_, str> {}
+impl WritableMpvProperty for Cow<'_, str> {}
+impl ObservableMpvProperty for Cow<'_, str> {}
+
+macro_rules! impl_known_property_capability {
+	(@impl $name: ident readable) => {
+		impl ReadableMpvProperty for $name {}
+	};
+	(@impl $name: ident writable) => {
+		impl WritableMpvProperty for $name {}
+	};
+	(@impl $name: ident observable) => {
+		impl ObservableMpvProperty for $name {}
+	};
+
+	(@has $capability: ident;) => {
+		false
+	};
+	(@has readable; readable $($rest: ident)*) => {
+		true
+	};
+	(@has writable; writable $($rest: ident)*) => {
+		true
+	};
+	(@has observable; observable $($rest: ident)*) => {
+		true
+	};
+	(@has $capability: ident; $other: ident $($rest: ident)*) => {
+		impl_known_property_capability!(@has $capability; $($rest)*)
+	};
+}
+
 macro_rules! impl_known_property {
 	(
 		pub enum $known_enum_name: ident;
 
 		$(
-			$name: ident: $property_name: literal, $value_type: ty
+			$name: ident: $property_name: tt, $value_type: ty, [$($capability: ident),*]
 		),+ $(,)?
 	) => {
 		$(
+			#[derive(Debug, Clone, Copy, Default)]
 			pub struct $name;
 			impl MpvProperty for $name {
 				type Value = $value_type;
@@ -45,6 +206,9 @@ macro_rules! impl_known_property {
 					Cow::Borrowed($property_name)
 				}
 			}
+			$(
+				impl_known_property_capability!(@impl $name $capability);
+			)*
 		)+
 
 		#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -55,6 +219,23 @@ macro_rules! impl_known_property {
 			),+
 		}
 		impl $known_enum_name {
+			/// All known properties.
+			pub const ALL: &'static [$known_enum_name] = &[
+				$(
+					$known_enum_name::$name
+				),+
+			];
+
+			/// Looks up the known property named `name`.
+			pub fn from_name(name: &str) -> Option<Self> {
+				match name {
+					$(
+						$property_name => Some($known_enum_name::$name),
+					)+
+					_ => None
+				}
+			}
+
 			pub const fn proeprty_name(&self) -> &'static str {
 				match self {
 					$(
@@ -62,6 +243,55 @@ macro_rules! impl_known_property {
 					),+
 				}
 			}
+
+			pub const fn is_readable(&self) -> bool {
+				match self {
+					$(
+						$known_enum_name::$name => impl_known_property_capability!(@has readable; $($capability)*)
+					),+
+				}
+			}
+
+			pub const fn is_writable(&self) -> bool {
+				match self {
+					$(
+						$known_enum_name::$name => impl_known_property_capability!(@has writable; $($capability)*)
+					),+
+				}
+			}
+
+			pub const fn is_observable(&self) -> bool {
+				match self {
+					$(
+						$known_enum_name::$name => impl_known_property_capability!(@has observable; $($capability)*)
+					),+
+				}
+			}
+		}
+
+		/// Resolves a property name to its known property struct at compile time.
+		///
+		/// Names which are not known fail to compile, use the name as `&str` for other properties.
+		///
+		/// ```
+		/// use mpvsock::{command::{commands::CmdGetProperty, property}, property};
+		///
+		/// let command: CmdGetProperty<property::TimePos> = CmdGetProperty::new(property!("time-pos"));
+		/// ```
+		///
+		/// ```compile_fail
+		/// let property = mpvsock::property!("time_pos");
+		/// ```
+		#[macro_export]
+		macro_rules! property {
+			$(
+				($property_name) => {
+					$crate::command::property::$name
+				};
+			)+
+			($other: literal) => {
+				compile_error!(concat!("Unknown mpv property ", $other))
+			};
 		}
 	};
 }
@@ -70,23 +300,181 @@ impl_known_property! {
 	pub enum KnownMpvProperty;
 
 	// f64
-	Volume: "volume", f64,
-	PercentPos: "percent-pos", f64,
-	TimePos: "time-pos", f64,
+	Volume: "volume", f64, [readable, writable, observable],
+	VolumeMax: "volume-max", f64, [readable, writable, observable],
+	VolumeGain: "volume-gain", f64, [readable, writable, observable],
+	ReplaygainPreamp: "replaygain-preamp", f64, [readable, writable, observable],
+	ReplaygainFallback: "replaygain-fallback", f64, [readable, writable, observable],
+	PercentPos: "percent-pos", f64, [readable, writable, observable],
+	TimePos: "time-pos", f64, [readable, writable, observable],
+	Duration: "duration", f64, [readable, observable],
+	Speed: "speed", f64, [readable, writable, observable],
+	EstimatedVfFps: "estimated-vf-fps", f64, [readable, observable],
+	Avsync: "avsync", f64, [readable, observable],
+	CacheSpeed: "cache-speed", f64, [readable, observable],
+	DemuxerCacheDuration: "demuxer-cache-duration", f64, [readable, observable],
+	SubDelay: "sub-delay", f64, [readable, writable, observable],
+	SubScale: "sub-scale", f64, [readable, writable, observable],
+	WindowScale: "window-scale", f64, [readable, writable, observable],
+	CurrentWindowScale: "current-window-scale", f64, [readable, writable, observable],
+	VideoZoom: "video-zoom", f64, [readable, writable, observable],
+	VideoPanX: "video-pan-x", f64, [readable, writable, observable],
+	VideoPanY: "video-pan-y", f64, [readable, writable, observable],
+	VideoAlignX: "video-align-x", f64, [readable, writable, observable],
+	VideoAlignY: "video-align-y", f64, [readable, writable, observable],
+	Panscan: "panscan", f64, [readable, writable, observable],
 	// u32
-	ImageDisplayDuration: "image-display-duration", u32,
+	ImageDisplayDuration: "image-display-duration", u32, [readable, writable, observable],
+	PlaylistCount: "playlist-count", u32, [readable, observable],
+	Chapters: "chapters", u32, [readable, observable],
+	Editions: "editions", u32, [readable, observable],
+	FrameDropCount: "frame-drop-count", u32, [readable, observable],
+	DecoderFrameDropCount: "decoder-frame-drop-count", u32, [readable, observable],
+	VoDelayedFrameCount: "vo-delayed-frame-count", u32, [readable, observable],
+	VideoRotate: "video-rotate", u32, [readable, writable, observable],
+	// i64
+	PlaylistPos: "playlist-pos", i64, [readable, writable, observable],
+	PlaylistPos1: "playlist-pos-1", i64, [readable, writable, observable],
+	PlaylistCurrentPos: "playlist-current-pos", i64, [readable, writable, observable],
+	PlaylistPlayingPos: "playlist-playing-pos", i64, [readable, observable],
+	Chapter: "chapter", i64, [readable, writable, observable],
+	CurrentEdition: "current-edition", i64, [readable, observable],
 	// String
-	Path: "path", String,
-	WorkingDirectory: "working-directory", String,
-	MediaTitle: "media-title", String,
-	Filename: "filename", String,
+	Path: "path", String, [readable, observable],
+	WorkingDirectory: "working-directory", String, [readable],
+	AudioDevice: "audio-device", String, [readable, writable, observable],
+	AudioChannels: "audio-channels", String, [readable, writable, observable],
+	YtdlFormat: "ytdl-format", String, [readable, writable, observable],
+	StreamOpenFilename: "stream-open-filename", String, [readable, writable],
+	StreamPath: "stream-path", String, [readable, observable],
+	MediaTitle: "media-title", String, [readable, observable],
+	Filename: "filename", String, [readable, observable],
+	// EditionId, `auto` unless an edition was selected, the playing edition is `current-edition`
+	Edition: "edition", EditionId, [readable, writable, observable],
 	// Track id
-	Aid: "aid", TrackId,
-	Vid: "vid", TrackId,
-	Sid: "sid", TrackId,
+	Aid: "aid", TrackId, [readable, writable, observable],
+	Vid: "vid", TrackId, [readable, writable, observable],
+	Sid: "sid", TrackId, [readable, writable, observable],
 	// bool
-	Fullscreen: "fullscreen", bool,
-	Pause: "pause", bool,
-	KeepOpen: "keep-open", bool,
-	LoopPlaylist: "loop-playlist", bool,
+	Fullscreen: "fullscreen", bool, [readable, writable, observable],
+	WindowMinimized: "window-minimized", bool, [readable, observable],
+	WindowMaximized: "window-maximized", bool, [readable, writable, observable],
+	Ontop: "ontop", bool, [readable, writable, observable],
+	Border: "border", bool, [readable, writable, observable],
+	Keepaspect: "keepaspect", bool, [readable, writable, observable],
+	Pause: "pause", bool, [readable, writable, observable],
+	Mute: "mute", bool, [readable, writable, observable],
+	SubVisibility: "sub-visibility", bool, [readable, writable, observable],
+	AudioNormalizeDownmix: "audio-normalize-downmix", bool, [readable, writable, observable],
+	ReplaygainClip: "replaygain-clip", bool, [readable, writable, observable],
+	KeepOpen: "keep-open", bool, [readable, writable, observable],
+	IdleActive: "idle-active", bool, [readable, observable],
+	EofReached: "eof-reached", bool, [readable, observable],
+	CoreIdle: "core-idle", bool, [readable, observable],
+	PausedForCache: "paused-for-cache", bool, [readable, observable],
+	Seeking: "seeking", bool, [readable, observable],
+	LoopPlaylist: "loop-playlist", bool, [readable, writable, observable],
+	AudioPitchCorrection: "audio-pitch-correction", bool, [readable, writable, observable],
+	// Vec<String>
+	PropertyList: "property-list", Vec<String>, [readable],
+	// ReplayGain
+	Replaygain: "replaygain", ReplayGain, [readable, writable, observable],
+	// Vec<Track>
+	TrackList: "track-list", Vec<Track>, [readable, observable],
+	// Vec<PlaylistEntry>
+	Playlist: "playlist", Vec<PlaylistEntry>, [readable, observable],
+	// Vec<Filter>
+	Af: "af", Vec<Filter>, [readable, writable, observable],
+	Vf: "vf", Vec<Filter>, [readable, writable, observable],
+	// models named like their property are qualified
+	EditionList: "edition-list", crate::model::EditionList, [readable, observable],
+	AudioDeviceList: "audio-device-list", crate::model::AudioDeviceList, [readable, observable],
+}
+
+/// The value is untyped, since which property it is is only known at runtime.
+impl MpvProperty for KnownMpvProperty {
+	type Value = serde_json::Value;
+
+	fn name(&self) -> Cow<'_, str> {
+		Cow::Borrowed(self.proeprty_name())
+	}
+}
+// like names, capabilities are checked at runtime with `is_readable` and friends
+impl ReadableMpvProperty for KnownMpvProperty {}
+impl ObservableMpvProperty for KnownMpvProperty {}
+
+/// Error of `WritableKnownProperty::new` for a property which is read-only.
+#[derive(Debug, thiserror::Error)]
+#[error("Property {} is read-only", .0.proeprty_name())]
+pub struct ReadOnlyPropertyError(pub KnownMpvProperty);
+
+/// `KnownMpvProperty` checked to be writable, so that it can be set or cycled.
+#[derive(Debug, Clone, Copy)]
+pub struct WritableKnownProperty(KnownMpvProperty);
+impl WritableKnownProperty {
+	pub fn new(property: KnownMpvProperty) -> Result<Self, ReadOnlyPropertyError> {
+		if !property.is_writable() {
+			return Err(ReadOnlyPropertyError(property))
+		}
+
+		Ok(WritableKnownProperty(property))
+	}
+
+	pub fn property(&self) -> KnownMpvProperty {
+		self.0
+	}
+}
+impl MpvProperty for WritableKnownProperty {
+	type Value = serde_json::Value;
+
+	fn name(&self) -> Cow<'_, str> {
+		self.0.name()
+	}
+}
+impl WritableMpvProperty for WritableKnownProperty {}
+
+#[cfg(test)]
+mod test {
+	use super::{KnownMpvProperty, MpvProperty, OptionProperty, WritableKnownProperty};
+
+	#[test]
+	fn test_known_property_capabilities() {
+		assert!(KnownMpvProperty::Volume.is_readable());
+		assert!(KnownMpvProperty::Volume.is_writable());
+		assert!(KnownMpvProperty::Volume.is_observable());
+
+		assert!(!KnownMpvProperty::Duration.is_writable());
+		assert!(KnownMpvProperty::Duration.is_observable());
+
+		assert!(!KnownMpvProperty::PropertyList.is_writable());
+		assert!(!KnownMpvProperty::PropertyList.is_observable());
+
+		assert!(WritableKnownProperty::new(KnownMpvProperty::Volume).is_ok());
+		assert!(WritableKnownProperty::new(KnownMpvProperty::Duration).is_err());
+	}
+
+	#[test]
+	fn test_known_property_from_name() {
+		let known = KnownMpvProperty::from_name("time-pos").unwrap();
+		assert!(matches!(known, KnownMpvProperty::TimePos));
+		assert_eq!(known.name(), "time-pos");
+
+		assert!(KnownMpvProperty::from_name("time_pos").is_none());
+	}
+
+	#[test]
+	fn test_property_macro() {
+		let time_pos: super::TimePos = property!("time-pos");
+		assert_eq!(time_pos.name(), "time-pos");
+	}
+
+	#[test]
+	fn test_option_property_name() {
+		let global = OptionProperty::new("ytdl-format").typed::<String>();
+		assert_eq!(global.name(), "options/ytdl-format");
+
+		let file_local = OptionProperty::file_local("sub-delay").typed::<f64>();
+		assert_eq!(file_local.name(), "file-local-options/sub-delay");
+		assert_eq!(file_local.option_name(), "sub-delay");
+	}
 }
@@ -1,6 +1,13 @@
-use serde::{de::DeserializeOwned, Deserialize};
+use std::{borrow::Cow, convert::Infallible, fmt, process::ExitStatus, str::FromStr};
 
-use crate::{command::property::KnownMpvProperty, model::FileloadInfo};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
+use thiserror::Error;
+
+use crate::{
+	command::property::KnownMpvProperty,
+	model::{EndFileReason, FileloadInfo}
+};
 
 /// Event model:
 ///
@@ -10,7 +17,11 @@ use crate::{command::property::KnownMpvProperty, model::FileloadInfo};
 /// ```
 ///
 /// See https://mpv.io/manual/stable/#list-of-events.
-#[derive(Debug, Deserialize)]
+///
+/// Events not known to this crate are deserialized as `Unknown` with their name and remaining fields preserved.
+/// Serializing an event produces the same model.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 #[serde(tag = "event")]
 pub enum MpvResponseEvent {
 	#[serde(rename = "property-change")]
@@ -27,7 +38,16 @@ pub enum MpvResponseEvent {
 	#[serde(rename = "start-file")]
 	StartFile(FileloadInfo),
 	#[serde(rename = "end-file")]
-	EndFile {}, // TODO
+	EndFile {
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		reason: Option<EndFileReason>,
+		/// Missing before mpv 0.34.
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		playlist_entry_id: Option<i64>,
+		/// Description of the error if `reason` is `Error`.
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		file_error: Option<String>
+	},
 	#[serde(rename = "file-loaded")]
 	FileLoaded,
 	#[serde(rename = "seek")]
@@ -60,11 +80,224 @@ pub enum MpvResponseEvent {
 	// ChapterChange
 
 	// unknown
-	#[serde(other)]
-	Unknown
+	#[serde(skip)]
+	Unknown {
+		// renamed only to avoid the conflict with the tag, the variant is constructed manually
+		#[serde(rename = "unknown_event")]
+		event: String,
+		/// All fields of the event except `event`.
+		rest: serde_json::Map<String, serde_json::Value>
+	},
+	/// A line which could not be deserialized, only produced by a link with `MalformedLinePolicy::Emit`.
+	#[serde(skip)]
+	Malformed {
+		/// The line, converted to UTF-8 lossily.
+		raw: String
+	},
+	/// The mpv child process exited, produced by the link and not by mpv.
+	#[serde(skip)]
+	ProcessExited { status: ExitStatus },
+	/// mpv was respawned by `MpvSupervisor` and the link now talks to the new process.
+	#[serde(skip)]
+	Restarted {
+		/// Number of restarts so far, including this one.
+		restarts: u32
+	}
 }
+impl MpvResponseEvent {
+	/// Names of events which deserialize into a variant other than `Unknown`.
+	const KNOWN_EVENTS: &'static [&'static str] = &[
+		"property-change",
+		"log-message",
+		"start-file",
+		"end-file",
+		"file-loaded",
+		"seek",
+		"playback-restart",
+		"shutdown",
+		"audio-reconfig",
+		"video-reconfig"
+	];
 
-#[derive(Debug, Deserialize)]
+	/// Name of the event as mpv sends it, or the name it serializes with for events produced by this crate.
+	pub fn name(&self) -> &str {
+		match self {
+			MpvResponseEvent::Unknown { event, .. } => event,
+			// only `Unknown` is of the `Other` kind
+			_ => self.kind().static_name().unwrap_or_default()
+		}
+	}
+
+	pub fn kind(&self) -> MpvEventKind {
+		match self {
+			MpvResponseEvent::PropertyChange { .. } => MpvEventKind::PropertyChange,
+			MpvResponseEvent::LogMessage {} => MpvEventKind::LogMessage,
+			MpvResponseEvent::StartFile(_) => MpvEventKind::StartFile,
+			MpvResponseEvent::EndFile { .. } => MpvEventKind::EndFile,
+			MpvResponseEvent::FileLoaded => MpvEventKind::FileLoaded,
+			MpvResponseEvent::Seek => MpvEventKind::Seek,
+			MpvResponseEvent::PlaybackRestart => MpvEventKind::PlaybackRestart,
+			MpvResponseEvent::Shutdown => MpvEventKind::Shutdown,
+			MpvResponseEvent::AudioReconfig => MpvEventKind::AudioReconfig,
+			MpvResponseEvent::VideoReconfig => MpvEventKind::VideoReconfig,
+			MpvResponseEvent::Unknown { event, .. } => MpvEventKind::Other(event.clone()),
+			MpvResponseEvent::Malformed { .. } => MpvEventKind::Malformed,
+			MpvResponseEvent::ProcessExited { .. } => MpvEventKind::ProcessExited,
+			MpvResponseEvent::Restarted { .. } => MpvEventKind::Restarted
+		}
+	}
+}
+
+/// Kind of an event without its data, convertible from and to the event name.
+///
+/// Used to select events by name, for example from command line flags. Names of events which have no variant
+/// of their own parse as `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MpvEventKind {
+	PropertyChange,
+	LogMessage,
+	StartFile,
+	EndFile,
+	FileLoaded,
+	Seek,
+	PlaybackRestart,
+	Shutdown,
+	AudioReconfig,
+	VideoReconfig,
+	Malformed,
+	ProcessExited,
+	Restarted,
+	Other(String)
+}
+impl MpvEventKind {
+	pub fn as_str(&self) -> &str {
+		match self {
+			MpvEventKind::Other(name) => name,
+			kind => kind.static_name().unwrap_or_default()
+		}
+	}
+
+	/// Name of the kind, `None` for `Other`.
+	fn static_name(&self) -> Option<&'static str> {
+		Some(match self {
+			MpvEventKind::PropertyChange => "property-change",
+			MpvEventKind::LogMessage => "log-message",
+			MpvEventKind::StartFile => "start-file",
+			MpvEventKind::EndFile => "end-file",
+			MpvEventKind::FileLoaded => "file-loaded",
+			MpvEventKind::Seek => "seek",
+			MpvEventKind::PlaybackRestart => "playback-restart",
+			MpvEventKind::Shutdown => "shutdown",
+			MpvEventKind::AudioReconfig => "audio-reconfig",
+			MpvEventKind::VideoReconfig => "video-reconfig",
+			MpvEventKind::Malformed => "malformed",
+			MpvEventKind::ProcessExited => "process-exited",
+			MpvEventKind::Restarted => "restarted",
+			MpvEventKind::Other(_) => return None
+		})
+	}
+
+	/// Whether `event` is of this kind.
+	pub fn matches(&self, event: &MpvResponseEvent) -> bool {
+		self.as_str() == event.name()
+	}
+}
+impl FromStr for MpvEventKind {
+	type Err = Infallible;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(match s {
+			"property-change" => MpvEventKind::PropertyChange,
+			"log-message" => MpvEventKind::LogMessage,
+			"start-file" => MpvEventKind::StartFile,
+			"end-file" => MpvEventKind::EndFile,
+			"file-loaded" => MpvEventKind::FileLoaded,
+			"seek" => MpvEventKind::Seek,
+			"playback-restart" => MpvEventKind::PlaybackRestart,
+			"shutdown" => MpvEventKind::Shutdown,
+			"audio-reconfig" => MpvEventKind::AudioReconfig,
+			"video-reconfig" => MpvEventKind::VideoReconfig,
+			"malformed" => MpvEventKind::Malformed,
+			"process-exited" => MpvEventKind::ProcessExited,
+			"restarted" => MpvEventKind::Restarted,
+			other => MpvEventKind::Other(other.to_string())
+		})
+	}
+}
+impl fmt::Display for MpvEventKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+impl<'de> Deserialize<'de> for MpvResponseEvent {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		use serde::de::Error;
+
+		let mut rest = serde_json::Map::<String, serde_json::Value>::deserialize(deserializer)?;
+
+		let known = match rest.get("event") {
+			None => return Err(D::Error::missing_field("event")),
+			Some(serde_json::Value::String(event)) => Self::KNOWN_EVENTS.contains(&event.as_str()),
+			Some(_) => return Err(D::Error::custom("expected field `event` to be a string"))
+		};
+
+		if known {
+			// calls the derived implementation
+			return MpvResponseEvent::deserialize(serde_json::Value::Object(rest))
+				.map_err(D::Error::custom)
+		}
+
+		let event = match rest.remove("event") {
+			Some(serde_json::Value::String(event)) => event,
+			_ => unreachable!()
+		};
+
+		Ok(MpvResponseEvent::Unknown { event, rest })
+	}
+}
+impl Serialize for MpvResponseEvent {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		use serde::ser::SerializeMap;
+
+		match self {
+			MpvResponseEvent::Unknown { event, rest } => {
+				let mut map = serializer.serialize_map(Some(rest.len() + 1))?;
+				map.serialize_entry("event", event)?;
+				for (key, value) in rest.iter().filter(|(key, _)| *key != "event") {
+					map.serialize_entry(key, value)?;
+				}
+				map.end()
+			}
+			MpvResponseEvent::Malformed { raw } => {
+				let mut map = serializer.serialize_map(Some(2))?;
+				map.serialize_entry("event", "malformed")?;
+				map.serialize_entry("raw", raw)?;
+				map.end()
+			}
+			MpvResponseEvent::ProcessExited { status } => {
+				let mut map = serializer.serialize_map(None)?;
+				map.serialize_entry("event", "process-exited")?;
+				map.serialize_entry("code", &status.code())?;
+				#[cfg(unix)]
+				map.serialize_entry(
+					"signal",
+					&std::os::unix::process::ExitStatusExt::signal(status)
+				)?;
+				map.end()
+			}
+			MpvResponseEvent::Restarted { restarts } => {
+				let mut map = serializer.serialize_map(Some(2))?;
+				map.serialize_entry("event", "restarted")?;
+				map.serialize_entry("restarts", restarts)?;
+				map.end()
+			}
+			// calls the derived implementation
+			_ => MpvResponseEvent::serialize(self, serializer)
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 #[serde(untagged)]
 pub enum MpvResponseEventPropertyName {
@@ -77,12 +310,13 @@ pub enum MpvResponseEventPropertyName {
 /// ```json
 /// { "error": "success" | "invalid paramter" | "...", "data"?: "value" | 123 | true | null, "request_id"?: 123 }
 /// ```
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
+///
+/// Many commands succeed without `data`, which is deserialized the same as `null`, so `Option` or
+/// `serde_json::Value` data accepts both shapes.
+#[derive(Debug)]
 pub enum MpvResponseResult<Data: DeserializeOwned = serde_json::Value> {
 	Success {
 		error: MpvResponseResultSuccess,
-		#[serde(bound = "")]
 		data: Data,
 		request_id: Option<i64>
 	},
@@ -98,22 +332,122 @@ impl<D: DeserializeOwned> MpvResponseResult<D> {
 			MpvResponseResult::Error { request_id, .. } => *request_id
 		}
 	}
+
+	/// Converts into a plain `Result` of the data, dropping the request id.
+	pub fn into_result(self) -> MpvResult<D> {
+		match self {
+			MpvResponseResult::Success { data, .. } => Ok(data),
+			MpvResponseResult::Error { error, .. } => Err(MpvError::new(error))
+		}
+	}
+}
+impl<D: DeserializeOwned> From<MpvResponseResult<D>> for MpvResult<D> {
+	fn from(result: MpvResponseResult<D>) -> Self {
+		result.into_result()
+	}
+}
+impl<'de, Data: DeserializeOwned> Deserialize<'de> for MpvResponseResult<Data> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		use serde::de::{Error, IntoDeserializer};
+
+		/// The type of `data` depends on `error`, so it is kept as a value first.
+		#[derive(Deserialize)]
+		struct Repr {
+			error: String,
+			#[serde(default)]
+			data: serde_json::Value,
+			#[serde(default)]
+			request_id: Option<i64>
+		}
+
+		let Repr {
+			error,
+			data,
+			request_id
+		} = Repr::deserialize(deserializer)?;
+
+		if error == "success" {
+			let data = Data::deserialize(data).map_err(D::Error::custom)?;
+
+			Ok(MpvResponseResult::Success {
+				error: MpvResponseResultSuccess::Success,
+				data,
+				request_id
+			})
+		} else {
+			let error = MpvResponseResultError::deserialize(error.into_deserializer())?;
+
+			Ok(MpvResponseResult::Error { error, request_id })
+		}
+	}
 }
 #[derive(Debug, Deserialize)]
 pub enum MpvResponseResultSuccess {
 	#[serde(rename = "success")]
 	Success
 }
-#[derive(Debug, Deserialize)]
+/// Data of a successful result or the error of a failed one, see `MpvResponseResult::into_result`.
+pub type MpvResult<T> = Result<T, MpvError>;
+
+/// Error of a failed result.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{raw}")]
+pub struct MpvError {
+	pub kind: MpvResponseResultError,
+	/// The error string as mpv sent it.
+	pub raw: String
+}
+impl MpvError {
+	pub fn new(kind: MpvResponseResultError) -> Self {
+		let raw = kind.as_str().to_string();
+
+		MpvError { kind, raw }
+	}
+}
+
+/// Error string of a failed result.
+///
+/// Strings without a variant of their own are kept in `Other`, `as_str` returns the string as mpv sent it.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MpvResponseResultError {
-	#[serde(rename = "invalid parameter")]
 	InvalidParameter,
-	#[serde(rename = "property unavailable")]
 	PropertyUnavailable,
-	#[serde(rename = "property not found")]
 	PropertyNotFound,
-	#[serde(rename = "error running command")]
-	ErrorRunningCommand
+	ErrorRunningCommand,
+	Other(String)
+}
+impl MpvResponseResultError {
+	pub fn as_str(&self) -> &str {
+		match self {
+			MpvResponseResultError::InvalidParameter => "invalid parameter",
+			MpvResponseResultError::PropertyUnavailable => "property unavailable",
+			MpvResponseResultError::PropertyNotFound => "property not found",
+			MpvResponseResultError::ErrorRunningCommand => "error running command",
+			MpvResponseResultError::Other(error) => error
+		}
+	}
+}
+impl fmt::Display for MpvResponseResultError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+impl<'de> Deserialize<'de> for MpvResponseResultError {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		use serde::de::Error;
+
+		let error = Cow::<str>::deserialize(deserializer)?;
+
+		Ok(match error.as_ref() {
+			// otherwise a success whose data failed to deserialize would be taken for an error
+			"success" => return Err(D::Error::custom("expected an error but found success")),
+			"invalid parameter" => MpvResponseResultError::InvalidParameter,
+			"property unavailable" => MpvResponseResultError::PropertyUnavailable,
+			"property not found" => MpvResponseResultError::PropertyNotFound,
+			"error running command" => MpvResponseResultError::ErrorRunningCommand,
+			_ => MpvResponseResultError::Other(error.into_owned())
+		})
+	}
 }
 
 /// Either a mpv event or a mpv result.
@@ -125,9 +459,290 @@ pub enum MpvResponse<ResponseData: DeserializeOwned = serde_json::Value> {
 	Result(MpvResponseResult<ResponseData>)
 }
 
+/// Borrowed counterpart of `MpvResponse`.
+///
+/// Strings and data are borrowed from the response line where possible, so no allocation is needed
+/// to inspect a response. Fields not captured here can be parsed from `raw`.
+#[derive(Debug)]
+pub enum MpvResponseRef<'a> {
+	Event(MpvResponseEventRef<'a>),
+	Result(MpvResponseResultRef<'a>)
+}
+impl<'a> MpvResponseRef<'a> {
+	pub fn from_slice(line: &'a [u8]) -> Result<Self, serde_json::Error> {
+		use serde::de::Error;
+
+		let repr: MpvResponseRefRepr<'a> = serde_json::from_slice(line)?;
+
+		let response = match repr.event {
+			Some(event) => MpvResponseRef::Event(MpvResponseEventRef {
+				event,
+				id: repr.id,
+				name: repr.name,
+				data: repr.data,
+				raw: line
+			}),
+			// only results have `error`, a line with neither is malformed
+			None => MpvResponseRef::Result(MpvResponseResultRef {
+				error: repr
+					.error
+					.ok_or_else(|| serde_json::Error::custom("neither an event nor a result"))?,
+				data: repr.data,
+				request_id: repr.request_id,
+				raw: line
+			})
+		};
+
+		Ok(response)
+	}
+}
+
+/// Borrowed counterpart of `MpvResponseEvent`.
+#[derive(Debug)]
+pub struct MpvResponseEventRef<'a> {
+	/// Name of the event, such as `property-change`.
+	pub event: Cow<'a, str>,
+	/// Id of the observer for `property-change` events.
+	pub id: Option<i64>,
+	/// Name of the property for `property-change` events.
+	pub name: Option<Cow<'a, str>>,
+	pub data: Option<&'a RawValue>,
+	/// The whole response line.
+	pub raw: &'a [u8]
+}
+impl<'a> MpvResponseEventRef<'a> {
+	/// Deserializes `data`, if present.
+	pub fn parse_data<T: Deserialize<'a>>(&self) -> Result<Option<T>, serde_json::Error> {
+		self.data
+			.map(|data| serde_json::from_str(data.get()))
+			.transpose()
+	}
+
+	/// Deserializes `data` as a string borrowed from the line unless it contains escapes.
+	///
+	/// Returns `None` if `data` is missing or null.
+	pub fn parse_data_str(&self) -> Result<Option<Cow<'a, str>>, serde_json::Error> {
+		match self.data {
+			None => Ok(None),
+			Some(data) => deserialize_cow_str(&mut serde_json::Deserializer::from_str(data.get()))
+		}
+	}
+
+	/// Deserializes the whole event into its owned counterpart.
+	pub fn to_owned_event(&self) -> Result<MpvResponseEvent, serde_json::Error> {
+		serde_json::from_slice(self.raw)
+	}
+}
+
+/// Borrowed counterpart of `MpvResponseResult`.
+#[derive(Debug)]
+pub struct MpvResponseResultRef<'a> {
+	/// The `error` field, `success` on success.
+	pub error: Cow<'a, str>,
+	pub data: Option<&'a RawValue>,
+	pub request_id: Option<i64>,
+	/// The whole response line.
+	pub raw: &'a [u8]
+}
+impl<'a> MpvResponseResultRef<'a> {
+	/// Deserializes the whole result into its owned counterpart.
+	pub fn to_owned_result<D: DeserializeOwned>(
+		&self
+	) -> Result<MpvResponseResult<D>, serde_json::Error> {
+		serde_json::from_slice(self.raw)
+	}
+}
+
+/// Flat representation of both events and results so that `RawValue` can be borrowed.
+#[derive(Deserialize)]
+struct MpvResponseRefRepr<'a> {
+	#[serde(borrow, default, deserialize_with = "deserialize_cow_str")]
+	event: Option<Cow<'a, str>>,
+	#[serde(default)]
+	id: Option<i64>,
+	#[serde(borrow, default, deserialize_with = "deserialize_cow_str")]
+	name: Option<Cow<'a, str>>,
+	#[serde(borrow, default)]
+	data: Option<&'a RawValue>,
+	#[serde(borrow, default, deserialize_with = "deserialize_cow_str")]
+	error: Option<Cow<'a, str>>,
+	#[serde(default)]
+	request_id: Option<i64>
+}
+
+/// Deserializes an optional string, borrowing it unless it contains escapes.
+///
+/// The `Deserialize` impl of `Cow` always allocates, even with `#[serde(borrow)]` when inside an `Option`.
+fn deserialize_cow_str<'de, D: Deserializer<'de>>(
+	deserializer: D
+) -> Result<Option<Cow<'de, str>>, D::Error> {
+	struct CowStrVisitor;
+	impl<'de> serde::de::Visitor<'de> for CowStrVisitor {
+		type Value = Option<Cow<'de, str>>;
+
+		fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+			write!(f, "a string or null")
+		}
+
+		fn visit_borrowed_str<E: serde::de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+			Ok(Some(Cow::Borrowed(v)))
+		}
+
+		fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+			Ok(Some(Cow::Owned(v.to_string())))
+		}
+
+		fn visit_string<E: serde::de::Error>(self, v: String) -> Result<Self::Value, E> {
+			Ok(Some(Cow::Owned(v)))
+		}
+
+		fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+			Ok(None)
+		}
+
+		fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+			Ok(None)
+		}
+
+		fn visit_some<D: Deserializer<'de>>(
+			self,
+			deserializer: D
+		) -> Result<Self::Value, D::Error> {
+			deserializer.deserialize_str(self)
+		}
+	}
+
+	deserializer.deserialize_option(CowStrVisitor)
+}
+
 #[cfg(test)]
 mod test {
-	use super::{KnownMpvProperty, MpvResponseEvent, MpvResponseEventPropertyName};
+	use std::borrow::Cow;
+
+	use super::{
+		EndFileReason,
+		KnownMpvProperty,
+		MpvEventKind,
+		MpvResponse,
+		MpvResponseEvent,
+		MpvResponseEventPropertyName,
+		MpvResponseRef,
+		MpvResponseResult,
+		MpvResponseResultError,
+		MpvResult
+	};
+
+	#[test]
+	fn test_result_error_string() {
+		let result: MpvResponseResult = serde_json::from_value(serde_json::json!(
+			{ "error": "unsupported format", "request_id": 3 }
+		))
+		.unwrap();
+		match result {
+			MpvResponseResult::Error { error, .. } => {
+				assert_eq!(
+					error,
+					MpvResponseResultError::Other("unsupported format".to_string())
+				);
+				assert_eq!(error.as_str(), "unsupported format");
+			}
+			result => panic!("Expected MpvResponseResult::Error but found {:?}", result)
+		}
+
+		// a success whose data does not deserialize is not an error result
+		assert!(
+			serde_json::from_value::<MpvResponseResult<u32>>(serde_json::json!(
+				{ "error": "success", "data": "text" }
+			))
+			.is_err()
+		);
+	}
+
+	#[test]
+	fn test_into_result() {
+		let result: MpvResult<u32> = serde_json::from_value::<MpvResponseResult<u32>>(
+			serde_json::json!({ "error": "success", "data": 3, "request_id": 1 })
+		)
+		.unwrap()
+		.into();
+		assert_eq!(result, Ok(3));
+
+		let err = serde_json::from_value::<MpvResponseResult<u32>>(
+			serde_json::json!({ "error": "property unavailable", "request_id": 1 })
+		)
+		.unwrap()
+		.into_result()
+		.unwrap_err();
+		assert_eq!(err.kind, MpvResponseResultError::PropertyUnavailable);
+		assert_eq!(err.raw, "property unavailable");
+		assert_eq!(err.to_string(), "property unavailable");
+	}
+
+	#[test]
+	fn test_result_missing_data() {
+		let result: MpvResponseResult = serde_json::from_value(serde_json::json!(
+			{ "error": "success", "request_id": 2 }
+		))
+		.unwrap();
+		match result {
+			MpvResponseResult::Success {
+				data, request_id, ..
+			} => {
+				assert_eq!(data, serde_json::Value::Null);
+				assert_eq!(request_id, Some(2));
+			}
+			result => panic!("Expected MpvResponseResult::Success but found {:?}", result)
+		}
+
+		let result: MpvResponseResult<Option<u32>> = serde_json::from_value(serde_json::json!(
+			{ "error": "success", "data": 5, "request_id": 2 }
+		))
+		.unwrap();
+		assert!(matches!(
+			result,
+			MpvResponseResult::Success { data: Some(5), .. }
+		));
+
+		let result: MpvResponseResult<Option<u32>> =
+			serde_json::from_value(serde_json::json!({ "error": "success" })).unwrap();
+		assert!(matches!(
+			result,
+			MpvResponseResult::Success {
+				data: None,
+				request_id: None,
+				..
+			}
+		));
+
+		let result: MpvResponseResult<()> =
+			serde_json::from_slice(br#"{"error":"success","request_id":7}"#).unwrap();
+		assert_eq!(result.request_id(), Some(7));
+	}
+
+	#[test]
+	fn test_event_kind_names() {
+		let seek: MpvResponseEvent =
+			serde_json::from_value(serde_json::json!({ "event": "seek" })).unwrap();
+		assert_eq!(seek.name(), "seek");
+		assert_eq!(seek.kind(), MpvEventKind::Seek);
+
+		let idle: MpvResponseEvent =
+			serde_json::from_value(serde_json::json!({ "event": "idle" })).unwrap();
+		assert_eq!(idle.name(), "idle");
+
+		let kind: MpvEventKind = "idle".parse().unwrap();
+		assert_eq!(kind, MpvEventKind::Other("idle".to_string()));
+		assert!(kind.matches(&idle));
+		assert!(!kind.matches(&seek));
+
+		assert_eq!(
+			"playback-restart"
+				.parse::<MpvEventKind>()
+				.unwrap()
+				.to_string(),
+			"playback-restart"
+		);
+	}
 
 	#[test]
 	fn test_mpv_response_event_property_change() {
@@ -183,15 +798,146 @@ mod test {
 
 		let response: MpvResponseEvent = serde_json::from_value(json).unwrap();
 
-		// TODO: Is this feasible?
-		// match response {
-		// 	MpvResponseEvent::Unknown(ref tag) if tag == "idle" => (),
-		// 	me => panic!("Expected MpvResponseEvent::Unknown(\"idle\") but found {:?}", me)
-		// }
+		match response {
+			MpvResponseEvent::Unknown { ref event, ref rest } if event == "idle" && rest.is_empty() => (),
+			me => panic!("Expected MpvResponseEvent::Unknown {{ event: \"idle\", rest: {{}} }} but found {:?}", me)
+		}
+	}
+
+	#[test]
+	fn test_mpv_response_event_unknown_preserves_fields() {
+		let json = serde_json::json!(
+			{
+				"event": "client-message",
+				"args": ["key", "a"]
+			}
+		);
+
+		let response: MpvResponseEvent = serde_json::from_value(json).unwrap();
+
+		match response {
+			MpvResponseEvent::Unknown { ref event, ref rest }
+				if event == "client-message"
+					&& rest.len() == 1 && rest["args"] == serde_json::json!(["key", "a"]) => {}
+			me => panic!("Expected MpvResponseEvent::Unknown {{ event: \"client-message\", rest: {{ args }} }} but found {:?}", me)
+		}
+	}
+
+	#[test]
+	fn test_mpv_response_event_end_file() {
+		let json = serde_json::json!(
+			{
+				"event": "end-file",
+				"reason": "eof",
+				"playlist_entry_id": 1,
+				"playlist_insert_id": 3
+			}
+		);
+
+		let response: MpvResponseEvent = serde_json::from_value(json).unwrap();
 
 		match response {
-			MpvResponseEvent::Unknown => (),
-			me => panic!("Expected MpvResponseEvent::Unknown() but found {:?}", me)
+			MpvResponseEvent::EndFile {
+				reason: Some(EndFileReason::Eof),
+				playlist_entry_id: Some(1),
+				file_error: None
+			} => (),
+			me => panic!(
+				"Expected MpvResponseEvent::EndFile {{ reason: Eof, .. }} but found {:?}",
+				me
+			)
+		}
+
+		// mpv before 0.34 only sends the reason
+		let response: MpvResponseEvent =
+			serde_json::from_value(serde_json::json!({ "event": "end-file", "reason": "stop" }))
+				.unwrap();
+		assert!(matches!(
+			response,
+			MpvResponseEvent::EndFile {
+				reason: Some(EndFileReason::Stop),
+				playlist_entry_id: None,
+				..
+			}
+		));
+	}
+
+	#[test]
+	fn test_mpv_response_event_serialize_roundtrip() {
+		let known = serde_json::json!(
+			{
+				"event": "property-change",
+				"id": 1,
+				"name": "volume",
+				"data": 50.0
+			}
+		);
+		let end_file = serde_json::json!(
+			{
+				"event": "end-file",
+				"reason": "error",
+				"playlist_entry_id": 2,
+				"file_error": "loading failed"
+			}
+		);
+		let unknown = serde_json::json!(
+			{
+				"event": "client-message",
+				"args": ["key", "a"]
+			}
+		);
+
+		for json in [known, end_file, unknown] {
+			let response: MpvResponseEvent = serde_json::from_value(json.clone()).unwrap();
+			assert_eq!(serde_json::to_value(&response).unwrap(), json);
+		}
+	}
+
+	#[test]
+	fn test_mpv_response_result_is_not_event() {
+		let json = serde_json::json!(
+			{
+				"error": "success",
+				"data": null,
+				"request_id": 1
+			}
+		);
+
+		let response: MpvResponse = serde_json::from_value(json).unwrap();
+
+		assert!(matches!(response, MpvResponse::Result(_)));
+	}
+
+	#[test]
+	fn test_mpv_response_ref_event_borrows() {
+		let line = br#"{"event":"property-change","id":1,"name":"time-pos","data":12.5}"#;
+
+		let response = MpvResponseRef::from_slice(line).unwrap();
+
+		match response {
+			MpvResponseRef::Event(event) => {
+				assert!(matches!(event.event, Cow::Borrowed("property-change")));
+				assert!(matches!(event.name, Some(Cow::Borrowed("time-pos"))));
+				assert_eq!(event.id, Some(1));
+				assert_eq!(event.parse_data::<f64>().unwrap(), Some(12.5));
+			}
+			me => panic!("Expected MpvResponseRef::Event but found {:?}", me)
+		}
+	}
+
+	#[test]
+	fn test_mpv_response_ref_result() {
+		let line = br#"{"error":"success","data":"a \"quoted\" title","request_id":7}"#;
+
+		let response = MpvResponseRef::from_slice(line).unwrap();
+
+		match response {
+			MpvResponseRef::Result(result) => {
+				assert_eq!(result.error, "success");
+				assert_eq!(result.request_id, Some(7));
+				assert_eq!(result.data.unwrap().get(), r#""a \"quoted\" title""#);
+			}
+			me => panic!("Expected MpvResponseRef::Result but found {:?}", me)
 		}
 	}
 }
@@ -0,0 +1,276 @@
+use std::{
+	io::{self, Write},
+	time::{Duration, SystemTime, UNIX_EPOCH}
+};
+
+use serde::{Serialize, Serializer};
+
+use crate::{
+	command::{
+		property::{self, KnownMpvProperty},
+		response::{MpvResponseEvent, MpvResponseEventPropertyName},
+		ParseError
+	},
+	link::{CommandError, MpvLink},
+	model::EndFileReason,
+	property_cache::PropertyCache,
+	session::{PlaybackSession, SessionEvent}
+};
+
+/// Largest forward step of `time-pos` counted as watched, longer steps are seeks.
+const MAX_WATCHED_STEP: f64 = 2.0;
+
+/// Playback session recorded by `HistoryRecorder`, written as one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryRecord {
+	/// Path or URL of the file, `None` if it was not in the playlist known to the recorder.
+	pub path: Option<String>,
+	/// Last `media-title` of the file.
+	pub title: Option<String>,
+	/// When the file started, written as seconds since the Unix epoch.
+	#[serde(serialize_with = "serialize_unix_time")]
+	pub started_at: SystemTime,
+	/// Playback time actually watched, without pauses and seeked over parts, written in seconds.
+	#[serde(serialize_with = "serialize_seconds")]
+	pub watched: Duration,
+	/// Playback reached the end of the file.
+	pub finished: bool
+}
+
+struct CurrentRecord {
+	record: HistoryRecord,
+	last_time_pos: Option<f64>
+}
+
+/// Records a `HistoryRecord` per playback session, such as for scrobbling or a watch history.
+///
+/// Sessions are tracked with `PlaybackSession`, the watched duration is summed up from changes of
+/// `time-pos`. Call `observe` once, then pass all events to `update`.
+///
+/// Finished records are collected and can be read with `records` or drained with `take_records`. With
+/// `with_writer` they are written as JSON lines instead and not collected.
+pub struct HistoryRecorder {
+	session: PlaybackSession,
+	current: Option<CurrentRecord>,
+	records: Vec<HistoryRecord>,
+	writer: Option<Box<dyn Write + Send>>
+}
+impl HistoryRecorder {
+	pub fn new() -> Self {
+		HistoryRecorder {
+			session: PlaybackSession::new(),
+			current: None,
+			records: Vec::new(),
+			writer: None
+		}
+	}
+
+	/// Writes the finished records to `writer` as JSON lines instead of collecting them.
+	pub fn with_writer(mut self, writer: impl Write + Send + 'static) -> Self {
+		self.writer = Some(Box::new(writer));
+		self
+	}
+
+	/// Starts observing the properties the recorder needs in `cache`.
+	pub fn observe(
+		cache: &mut PropertyCache,
+		link: &mut MpvLink
+	) -> Result<(), CommandError<ParseError>> {
+		PlaybackSession::observe(cache, link)?;
+		cache.observe(link, property::TimePos)?;
+		cache.observe(link, property::MediaTitle)?;
+
+		Ok(())
+	}
+
+	/// Updates the current record from `event`, returning the record if the session ended.
+	///
+	/// Fails only if writing the finished record fails, the record is lost then.
+	pub fn update(&mut self, event: &MpvResponseEvent) -> io::Result<Option<&HistoryRecord>> {
+		if let MpvResponseEvent::PropertyChange {
+			name: MpvResponseEventPropertyName::Known(name),
+			data,
+			..
+		} = event
+		{
+			if let Some(current) = self.current.as_mut() {
+				match name {
+					KnownMpvProperty::TimePos => current.update_time_pos(data.as_f64()),
+					KnownMpvProperty::MediaTitle => {
+						if let Some(title) = data.as_str() {
+							current.record.title = Some(title.to_string());
+						}
+					}
+					_ => ()
+				}
+			}
+		}
+
+		match self.session.update(event) {
+			Some(SessionEvent::Started(start)) => {
+				self.current = Some(CurrentRecord {
+					record: HistoryRecord {
+						path: start.filename,
+						title: None,
+						started_at: SystemTime::now(),
+						watched: Duration::ZERO,
+						finished: false
+					},
+					last_time_pos: None
+				});
+				Ok(None)
+			}
+			Some(SessionEvent::Ended(end)) => {
+				let mut record = match self.current.take() {
+					Some(current) => current.record,
+					None => return Ok(None)
+				};
+				if record.path.is_none() {
+					record.path = end.filename;
+				}
+				record.finished = end.reason == Some(EndFileReason::Eof);
+
+				match self.writer.as_mut() {
+					Some(writer) => {
+						serde_json::to_writer(&mut *writer, &record)?;
+						writeln!(writer)?;
+						writer.flush()?;
+
+						self.records.clear();
+						self.records.push(record);
+					}
+					None => self.records.push(record)
+				}
+				Ok(self.records.last())
+			}
+			None => Ok(None)
+		}
+	}
+
+	/// Records finished so far, oldest first.
+	///
+	/// With a writer only the last written record is kept.
+	pub fn records(&self) -> impl Iterator<Item = &HistoryRecord> {
+		self.records.iter()
+	}
+
+	pub fn take_records(&mut self) -> Vec<HistoryRecord> {
+		std::mem::take(&mut self.records)
+	}
+}
+impl Default for HistoryRecorder {
+	fn default() -> Self {
+		HistoryRecorder::new()
+	}
+}
+impl std::fmt::Debug for HistoryRecorder {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("HistoryRecorder")
+			.field("session", &self.session)
+			.field(
+				"current",
+				&self.current.as_ref().map(|current| &current.record)
+			)
+			.field("records", &self.records)
+			.finish()
+	}
+}
+
+impl CurrentRecord {
+	fn update_time_pos(&mut self, time_pos: Option<f64>) {
+		if let (Some(last), Some(time_pos)) = (self.last_time_pos, time_pos) {
+			let step = time_pos - last;
+			if step > 0.0 && step <= MAX_WATCHED_STEP {
+				self.record.watched += Duration::from_secs_f64(step);
+			}
+		}
+		self.last_time_pos = time_pos;
+	}
+}
+
+fn serialize_unix_time<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+	let seconds = time
+		.duration_since(UNIX_EPOCH)
+		.map(|since| since.as_secs_f64())
+		.unwrap_or(0.0);
+	serializer.serialize_f64(seconds)
+}
+
+fn serialize_seconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.serialize_f64(duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod test {
+	use std::{
+		io::{self, Write},
+		sync::{Arc, Mutex},
+		time::Duration
+	};
+
+	use super::HistoryRecorder;
+	use crate::command::response::MpvResponseEvent;
+
+	const EVENTS: [&str; 9] = [
+		r#"{"event":"property-change","id":1,"name":"playlist","data":[{"filename":"/music/a.flac","id":1}]}"#,
+		r#"{"event":"start-file","playlist_entry_id":1}"#,
+		r#"{"event":"property-change","id":2,"name":"media-title","data":"Song A"}"#,
+		r#"{"event":"property-change","id":3,"name":"time-pos","data":0.0}"#,
+		r#"{"event":"property-change","id":3,"name":"time-pos","data":1.5}"#,
+		// seeking forward is not watching
+		r#"{"event":"property-change","id":3,"name":"time-pos","data":60.0}"#,
+		r#"{"event":"property-change","id":3,"name":"time-pos","data":61.0}"#,
+		r#"{"event":"property-change","id":3,"name":"time-pos","data":null}"#,
+		r#"{"event":"end-file","reason":"eof","playlist_entry_id":1}"#
+	];
+
+	#[derive(Clone, Default)]
+	struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+	impl Write for SharedBuffer {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			self.0.lock().unwrap().write(buf)
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+
+	fn feed(recorder: &mut HistoryRecorder) {
+		for line in EVENTS {
+			let event: MpvResponseEvent = serde_json::from_str(line).unwrap();
+			recorder.update(&event).unwrap();
+		}
+	}
+
+	#[test]
+	fn test_records() {
+		let mut recorder = HistoryRecorder::new();
+		feed(&mut recorder);
+
+		let records = recorder.take_records();
+		assert_eq!(records.len(), 1);
+		assert_eq!(records[0].path.as_deref(), Some("/music/a.flac"));
+		assert_eq!(records[0].title.as_deref(), Some("Song A"));
+		assert_eq!(records[0].watched, Duration::from_secs_f64(2.5));
+		assert!(records[0].finished);
+		assert_eq!(recorder.records().count(), 0);
+	}
+
+	#[test]
+	fn test_jsonl() {
+		let buffer = SharedBuffer::default();
+		let mut recorder = HistoryRecorder::new().with_writer(buffer.clone());
+		feed(&mut recorder);
+
+		let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+		let lines: Vec<_> = output.lines().collect();
+		assert_eq!(lines.len(), 1);
+
+		let record: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+		assert_eq!(record["path"], "/music/a.flac");
+		assert_eq!(record["watched"], 2.5);
+		assert_eq!(record["finished"], true);
+		assert!(record["started_at"].as_f64().unwrap() > 0.0);
+	}
+}
@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+
+use crate::{
+	command::{
+		commands::{CmdClientName, CmdKeybind},
+		response::MpvResponseEvent,
+		ParseError
+	},
+	link::{CommandError, MpvLink}
+};
+
+/// Named keys of mpv bound by `InputEventRecorder::new`, besides the printable characters.
+const NAMED_KEYS: &[&str] = &[
+	"SPACE",
+	"SHARP",
+	"ENTER",
+	"TAB",
+	"BS",
+	"DEL",
+	"INS",
+	"ESC",
+	"HOME",
+	"END",
+	"PGUP",
+	"PGDWN",
+	"UP",
+	"DOWN",
+	"LEFT",
+	"RIGHT",
+	"F1",
+	"F2",
+	"F3",
+	"F4",
+	"F5",
+	"F6",
+	"F7",
+	"F8",
+	"F9",
+	"F10",
+	"F11",
+	"F12",
+	"KP0",
+	"KP1",
+	"KP2",
+	"KP3",
+	"KP4",
+	"KP5",
+	"KP6",
+	"KP7",
+	"KP8",
+	"KP9",
+	"KP_DEC",
+	"KP_ENTER",
+	"PLAY",
+	"PAUSE",
+	"PLAYPAUSE",
+	"STOP",
+	"NEXT",
+	"PREV",
+	"FORWARD",
+	"REWIND",
+	"VOLUME_UP",
+	"VOLUME_DOWN",
+	"MUTE"
+];
+
+/// Key forwarded to this client by the bindings of `InputEventRecorder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPressed {
+	/// Name of the key as in `input.conf`, such as `a`, `SPACE` or `Ctrl+s`.
+	pub key: String
+}
+
+/// Rebinds keys in mpv so that pressing them sends a message to this client instead of running a command.
+///
+/// This lets the controller implement its own key handling. Each key is bound with `keybind` to
+/// `script-message-to <client> key <name>`, the messages arrive as `client-message` events which
+/// `key_pressed` turns into `KeyPressed`. The previous bindings of the keys are replaced for the rest
+/// of the mpv session.
+#[derive(Debug, Clone)]
+pub struct InputEventRecorder {
+	keys: Vec<String>
+}
+impl InputEventRecorder {
+	/// First argument of the forwarded `client-message` events.
+	pub const MESSAGE: &'static str = "key";
+
+	/// Creates a recorder of all printable ASCII characters and the named keys, without modifiers.
+	pub fn new() -> Self {
+		let printable = ('!' ..= '~').filter(|&c| c != '#').map(String::from);
+		let named = NAMED_KEYS.iter().map(|&key| String::from(key));
+
+		InputEventRecorder {
+			keys: printable.chain(named).collect()
+		}
+	}
+
+	/// Creates a recorder of `keys` only, which can include modifiers such as `Ctrl+s`.
+	pub fn with_keys(keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+		InputEventRecorder {
+			keys: keys.into_iter().map(Into::into).collect()
+		}
+	}
+
+	pub fn keys(&self) -> &[String] {
+		&self.keys
+	}
+
+	/// Binds the keys on `link` to messages sent to the client of the link.
+	pub fn register(&self, link: &mut MpvLink) -> Result<(), CommandError<ParseError>> {
+		let client_name = match link.client_name() {
+			Some(client_name) => client_name.to_string(),
+			None => link.run_command(&CmdClientName::new())?
+		};
+
+		let commands: Vec<_> = self
+			.keys
+			.iter()
+			.map(|key| {
+				CmdKeybind::new(
+					Cow::Borrowed(key.as_str()),
+					Cow::Owned(Self::binding_command(&client_name, key))
+				)
+			})
+			.collect();
+		for result in link.run_commands_batched(&commands)? {
+			result?;
+		}
+
+		Ok(())
+	}
+
+	/// Returns the key if `event` is a message sent by one of the bindings.
+	pub fn key_pressed(event: &MpvResponseEvent) -> Option<KeyPressed> {
+		let args = match event {
+			MpvResponseEvent::Unknown { event, rest } if event == "client-message" => {
+				rest.get("args")?.as_array()?
+			}
+			_ => return None
+		};
+
+		match args.as_slice() {
+			[message, key] if message.as_str() == Some(Self::MESSAGE) => Some(KeyPressed {
+				key: key.as_str()?.to_string()
+			}),
+			_ => None
+		}
+	}
+
+	/// Command the key is bound to, quoting the arguments as JSON strings which mpv unescapes.
+	fn binding_command(client_name: &str, key: &str) -> String {
+		format!(
+			"script-message-to {} {} {}",
+			serde_json::Value::from(client_name),
+			Self::MESSAGE,
+			serde_json::Value::from(key)
+		)
+	}
+}
+impl Default for InputEventRecorder {
+	fn default() -> Self {
+		InputEventRecorder::new()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{InputEventRecorder, KeyPressed};
+	use crate::command::response::MpvResponseEvent;
+
+	#[test]
+	fn test_binding_command() {
+		assert_eq!(
+			InputEventRecorder::binding_command("ipc_3", "\""),
+			r#"script-message-to "ipc_3" key "\"""#
+		);
+
+		let keys = InputEventRecorder::new();
+		assert!(keys.keys().iter().any(|key| key == "SHARP"));
+		assert!(!keys.keys().iter().any(|key| key == "#"));
+	}
+
+	#[test]
+	fn test_key_pressed() {
+		let event: MpvResponseEvent =
+			serde_json::from_str(r#"{"event":"client-message","args":["key","Ctrl+s"]}"#).unwrap();
+		assert_eq!(
+			InputEventRecorder::key_pressed(&event),
+			Some(KeyPressed {
+				key: "Ctrl+s".to_string()
+			})
+		);
+
+		let event: MpvResponseEvent =
+			serde_json::from_str(r#"{"event":"client-message","args":["other","a"]}"#).unwrap();
+		assert_eq!(InputEventRecorder::key_pressed(&event), None);
+	}
+}
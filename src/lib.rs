@@ -1,4 +1,23 @@
+pub mod audio;
 pub mod command;
+#[cfg(feature = "history")]
+pub mod history;
+pub mod input;
 pub mod link;
 pub mod model;
+pub mod osd;
+pub mod player;
+pub mod playlist;
+pub mod property_cache;
+pub mod queue;
 pub mod response_buffer;
+pub mod resume;
+pub mod session;
+pub mod snapshot;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod transitions;
+#[cfg(feature = "ui-bridge")]
+pub mod ui_bridge;
+pub mod version;
+pub mod video;
@@ -0,0 +1,458 @@
+use std::{
+	collections::{HashSet, VecDeque},
+	io::{self, Write},
+	num::NonZeroI64,
+	os::unix::net::UnixStream,
+	path::Path,
+	pin::Pin,
+	task::{ready, Context, Poll}
+};
+
+use ::async_io::Async;
+use futures_core::Stream;
+
+use super::{
+	request_id::RequestIdAllocator,
+	server_version_from,
+	CommandError,
+	HandshakeError,
+	MpvLinkInitError,
+	ReceiveError,
+	SendError
+};
+use crate::{
+	command::{
+		commands::{CmdGetProperty, CmdGetVersion},
+		response::{MpvResponse, MpvResponseEvent, MpvResponseResult},
+		MpvCommand,
+		MpvCommandRaw
+	},
+	response_buffer::ResponseBuffer,
+	version::MpvVersion
+};
+
+/// Asynchronous counterpart of `MpvLink` over a socket registered with the `async-io` reactor.
+///
+/// Works with smol, async-std or any other executor, since the reactor runs on its own thread. Events received
+/// while waiting for a result are queued and returned by `next_event` first.
+///
+/// The link is also a `Stream` of events which wakes the task when the socket becomes readable, so it can be
+/// combined with other streams. The stream ends after the connection is closed.
+///
+/// The futures can be cancelled, for example by `select!`. A command whose result is no longer awaited is still
+/// sent completely, the rest of it is written while waiting for other results or events, and its result is
+/// dropped when it arrives.
+pub struct AsyncMpvLink {
+	socket: Async<UnixStream>,
+	/// Ids of commands whose results were not received yet, including cancelled ones.
+	request_ids: RequestIdAllocator,
+	/// Ids of commands whose results are no longer awaited because waiting for them failed, see `MpvLink`.
+	abandoned: HashSet<i64>,
+	response_buffer: ResponseBuffer,
+	/// Commands which were not written completely yet, the front might be partially written.
+	command_buffer: Vec<u8>,
+	event_queue: VecDeque<MpvResponseEvent>,
+	/// mpv version cached by `handshake`.
+	server_version: Option<MpvVersion>,
+	/// Set once reading reached the end of the connection, ending the event stream.
+	closed: bool
+}
+impl AsyncMpvLink {
+	/// Connects to mpv started with the `input-ipc-server` option.
+	pub async fn connect(socket_path: &Path) -> Result<Self, MpvLinkInitError> {
+		let socket = Async::<UnixStream>::connect(socket_path)
+			.await
+			.map_err(MpvLinkInitError::Connect)?;
+
+		Self::new(socket).await
+	}
+
+	/// Wraps a connected socket, such as one end of a socket pair passed to mpv with `input-ipc-client`.
+	pub async fn from_stream(socket: UnixStream) -> Result<Self, MpvLinkInitError> {
+		let socket = Async::new(socket).map_err(MpvLinkInitError::Nonblocking)?;
+
+		Self::new(socket).await
+	}
+
+	async fn new(socket: Async<UnixStream>) -> Result<Self, MpvLinkInitError> {
+		let mut me = AsyncMpvLink {
+			socket,
+			request_ids: RequestIdAllocator::new(),
+			abandoned: HashSet::new(),
+			response_buffer: ResponseBuffer::new(),
+			command_buffer: Vec::new(),
+			event_queue: VecDeque::new(),
+			server_version: None,
+			closed: false
+		};
+		me.handshake().await?;
+
+		Ok(me)
+	}
+
+	/// Checks the IPC protocol version and caches the mpv version, like `MpvLink::handshake`.
+	async fn handshake(&mut self) -> Result<(), HandshakeError> {
+		let version = self
+			.send(&CmdGetVersion::new())
+			.await
+			.map_err(HandshakeError::Version)?;
+		log::info!("Handshake: IPC version {}.{}", version.0, version.1);
+
+		self.server_version =
+			server_version_from(self.send(&CmdGetProperty::new("mpv-version")).await);
+		log::info!("Handshake: mpv version {:?}", self.server_version);
+
+		Ok(())
+	}
+
+	/// Returns the mpv version cached by the handshake, `None` if it could not be determined.
+	pub fn server_version(&self) -> Option<MpvVersion> {
+		self.server_version
+	}
+
+	/// Sends `command` and waits for its result, the asynchronous counterpart of `MpvLink::run_command`.
+	pub async fn send<C: MpvCommand + ?Sized>(
+		&mut self,
+		command: &C
+	) -> Result<C::ParsedData, CommandError<C::Error>> {
+		let current_id = self.request_ids.allocate();
+		let result = match self.send_command(command, current_id).await {
+			Ok(()) => self
+				.next_result(current_id)
+				.await
+				.map_err(CommandError::from),
+			Err(err) => Err(err.into())
+		};
+		let result = match result {
+			Ok(result) => result,
+			Err(err) => {
+				// the result might still arrive, it is dropped then
+				if self.request_ids.is_in_flight(current_id.get()) {
+					self.abandoned.insert(current_id.get());
+				}
+				return Err(err)
+			}
+		};
+
+		let data = match result.into_result() {
+			Ok(data) => data,
+			Err(err) => {
+				return command
+					.parse_error(err.kind)
+					.map_err(CommandError::ResultError)
+			}
+		};
+
+		command
+			.parse_data_for_version(data, self.server_version)
+			.map_err(CommandError::DataParseError)
+	}
+
+	/// Waits for the next event, returning queued events first.
+	pub async fn next_event(&mut self) -> Result<MpvResponseEvent, ReceiveError> {
+		std::future::poll_fn(|cx| self.poll_next_event(cx)).await
+	}
+
+	fn poll_next_event(
+		&mut self,
+		cx: &mut Context
+	) -> Poll<Result<MpvResponseEvent, ReceiveError>> {
+		if let Some(event) = self.event_queue.pop_front() {
+			return Poll::Ready(Ok(event))
+		}
+
+		loop {
+			match ready!(self.poll_next_response(cx))? {
+				MpvResponse::Event(event) => return Poll::Ready(Ok(event)),
+				MpvResponse::Result(result) => self.drop_result(result)
+			}
+		}
+	}
+
+	/// Drains the queued events without waiting, returning the iterator.
+	pub fn drain_events(&mut self) -> impl Iterator<Item = MpvResponseEvent> + '_ {
+		self.event_queue.drain(..)
+	}
+
+	/// Number of commands whose results were not received yet, see `MpvLink::in_flight`.
+	///
+	/// Results of cancelled commands are counted until they arrive and are dropped.
+	pub fn in_flight(&self) -> usize {
+		self.request_ids.in_flight()
+	}
+
+	/// Drops a result which is not awaited, belonging to a cancelled or abandoned command.
+	fn drop_result(&mut self, result: MpvResponseResult) {
+		match result.request_id() {
+			Some(request_id) if self.abandoned.remove(&request_id) => {
+				log::debug!("Dropping result of abandoned command: {:?}", result)
+			}
+			_ => log::debug!("Dropping result nobody waits for: {:?}", result)
+		}
+		if let Some(request_id) = result.request_id() {
+			self.request_ids.release(request_id);
+		}
+	}
+
+	async fn send_command<C: MpvCommandRaw + ?Sized>(
+		&mut self,
+		command: &C,
+		current_id: NonZeroI64
+	) -> Result<(), SendError> {
+		let start = self.command_buffer.len();
+		let result = command.write_for_version(
+			&mut self.command_buffer,
+			Some(current_id),
+			self.server_version
+		);
+		let result = match result {
+			Err(err) => Err(err.into()),
+			// the line delimiter would split the command into two
+			Ok(()) if self.command_buffer[start ..].contains(&b'\n') => {
+				Err(SendError::EmbeddedNewline)
+			}
+			Ok(()) => Ok(())
+		};
+		if result.is_err() {
+			self.command_buffer.truncate(start);
+			self.request_ids.release(current_id.get());
+			return result
+		}
+		self.command_buffer.push(b'\n');
+
+		if log::log_enabled!(log::Level::Debug) {
+			let line = &self.command_buffer[start .. self.command_buffer.len() - 1];
+			match std::str::from_utf8(line) {
+				Ok(command) => log::debug!("Sending command: {}", command),
+				Err(_) => log::debug!("Sending command: {:?}", line)
+			};
+		}
+
+		self.flush_commands().await
+	}
+
+	/// Writes the command buffer, removing what was written so that a cancelled write continues with the next command.
+	async fn flush_commands(&mut self) -> Result<(), SendError> {
+		while !self.command_buffer.is_empty() {
+			let command_buffer = &self.command_buffer;
+			let written = self
+				.socket
+				.write_with(|mut socket| socket.write(command_buffer))
+				.await?;
+			if written == 0 {
+				return Err(io::Error::from(io::ErrorKind::WriteZero).into())
+			}
+
+			self.command_buffer.drain(.. written);
+		}
+
+		Ok(())
+	}
+
+	/// Waits for the result of the command with `request_id`, queueing events and dropping other results.
+	///
+	/// Other results belong to commands which were cancelled or sent without a request id.
+	async fn next_result(
+		&mut self,
+		request_id: NonZeroI64
+	) -> Result<MpvResponseResult, ReceiveError> {
+		loop {
+			match self.next_response().await? {
+				MpvResponse::Event(event) => {
+					log::trace!("Queued event: {:?}", event);
+					self.event_queue.push_back(event);
+				}
+				MpvResponse::Result(result) if result.request_id() == Some(request_id.get()) => {
+					self.request_ids.release(request_id.get());
+					return Ok(result)
+				}
+				MpvResponse::Result(result) => self.drop_result(result)
+			}
+		}
+	}
+
+	async fn next_response(&mut self) -> Result<MpvResponse, ReceiveError> {
+		std::future::poll_fn(|cx| self.poll_next_response(cx)).await
+	}
+
+	/// Writes what is left of cancelled commands without waiting, registering for a wakeup when the socket is
+	/// not writable.
+	fn poll_flush_pending(&mut self, cx: &mut Context) -> io::Result<()> {
+		while !self.command_buffer.is_empty() {
+			let mut socket = self.socket.get_ref();
+			match socket.write(&self.command_buffer) {
+				Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+				Ok(written) => {
+					self.command_buffer.drain(.. written);
+				}
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+					match self.socket.poll_writable(cx) {
+						Poll::Ready(result) => result?,
+						Poll::Pending => return Ok(())
+					}
+				}
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+				Err(err) => return Err(err)
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Reads until a response line is buffered, registering for a wakeup when the socket is not readable.
+	///
+	/// Pending commands are flushed first, mpv would not answer them otherwise.
+	fn poll_next_response(&mut self, cx: &mut Context) -> Poll<Result<MpvResponse, ReceiveError>> {
+		self.poll_flush_pending(cx)?;
+
+		loop {
+			if let Some(line) = self.response_buffer.consume_line() {
+				return Poll::Ready(
+					serde_json::from_slice(line)
+						.map_err(|err| ReceiveError::deserialize(err, line))
+				)
+			}
+
+			match self.response_buffer.read_from(self.socket.get_ref()) {
+				Ok(0) => {
+					self.closed = true;
+					return Poll::Ready(Err(ReceiveError::Io(io::ErrorKind::UnexpectedEof.into())))
+				}
+				Ok(_) => (),
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+					ready!(self.socket.poll_readable(cx))?;
+				}
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+				Err(err) => return Poll::Ready(Err(err.into()))
+			}
+		}
+	}
+}
+impl Stream for AsyncMpvLink {
+	type Item = Result<MpvResponseEvent, ReceiveError>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let me = self.get_mut();
+		if me.closed && me.event_queue.is_empty() {
+			return Poll::Ready(None)
+		}
+
+		match ready!(me.poll_next_event(cx)) {
+			// the end of the connection ends the stream instead of being an error
+			Err(ReceiveError::Io(err))
+				if me.closed && err.kind() == io::ErrorKind::UnexpectedEof =>
+			{
+				Poll::Ready(None)
+			}
+			result => Poll::Ready(Some(result))
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{io::Write, os::unix::net::UnixStream, pin::Pin, thread::JoinHandle};
+
+	use futures_core::Stream;
+
+	use super::AsyncMpvLink;
+	use crate::{
+		command::{commands::CmdGetProperty, property, response::MpvResponseEvent},
+		link::fake_mpv::{self, Requests},
+		version::MpvVersion
+	};
+
+	/// Answers of the handshake of `AsyncMpvLink`: the IPC version and the mpv version.
+	const HANDSHAKE: &[&str] = &["131073", "\"mpv 0.38.0\""];
+
+	/// Answers the handshake, passes the requests to `serve` and sends a `shutdown` event.
+	///
+	/// The connection is closed once the returned socket is dropped after joining.
+	fn fake_mpv(
+		socket: UnixStream,
+		serve: impl FnOnce(&mut Requests, &mut UnixStream) + Send + 'static
+	) -> JoinHandle<UnixStream> {
+		fake_mpv::serve(socket, |requests, socket| {
+			fake_mpv::answer(requests, &mut *socket, HANDSHAKE);
+			serve(requests, &mut *socket);
+			socket.write_all(b"{\"event\":\"shutdown\"}\n").unwrap();
+		})
+	}
+
+	/// Answers one command with the volume, sending an event before the answer.
+	fn seek_then_volume(requests: &mut Requests, socket: &mut UnixStream) {
+		socket.write_all(b"{\"event\":\"seek\"}\n").unwrap();
+		fake_mpv::answer(requests, socket, &["50.5"]);
+	}
+
+	#[test]
+	fn test_send() {
+		let (socket, peer) = UnixStream::pair().unwrap();
+		let server = fake_mpv(peer, seek_then_volume);
+
+		::async_io::block_on(async {
+			let mut link = AsyncMpvLink::from_stream(socket).await.unwrap();
+			assert_eq!(link.server_version(), Some(MpvVersion::new(0, 38, 0)));
+
+			let volume = link
+				.send(&CmdGetProperty::new(property::Volume))
+				.await
+				.unwrap();
+			assert_eq!(volume, 50.5);
+			assert_eq!(link.in_flight(), 0);
+
+			let event = link.next_event().await.unwrap();
+			assert!(matches!(event, MpvResponseEvent::Seek));
+			// keeps the link open until the server wrote everything
+			let event = link.next_event().await.unwrap();
+			assert!(matches!(event, MpvResponseEvent::Shutdown));
+		});
+		server.join().unwrap();
+	}
+
+	#[test]
+	fn test_flush_cancelled_command() {
+		let (socket, peer) = UnixStream::pair().unwrap();
+		// only answers once the rest of the command arrived
+		let server = fake_mpv(peer, |requests, _| {
+			assert_eq!(requests.next().unwrap().unwrap(), r#"{"command":["stop"]}"#);
+		});
+
+		::async_io::block_on(async {
+			let mut link = AsyncMpvLink::from_stream(socket).await.unwrap();
+			// as left by a cancelled `send`
+			link.command_buffer.extend_from_slice(b"[\"stop\"]}\n");
+			link.socket.get_ref().write_all(b"{\"command\":").unwrap();
+
+			let event = link.next_event().await.unwrap();
+			assert!(matches!(event, MpvResponseEvent::Shutdown));
+			assert!(link.command_buffer.is_empty());
+		});
+		server.join().unwrap();
+	}
+
+	#[test]
+	fn test_event_stream() {
+		let (socket, peer) = UnixStream::pair().unwrap();
+		let server = fake_mpv(peer, seek_then_volume);
+
+		::async_io::block_on(async {
+			let mut link = AsyncMpvLink::from_stream(socket).await.unwrap();
+			link.send(&CmdGetProperty::new(property::Volume))
+				.await
+				.unwrap();
+			server.join().unwrap();
+
+			let mut events = Vec::new();
+			while let Some(event) =
+				std::future::poll_fn(|cx| Pin::new(&mut link).poll_next(cx)).await
+			{
+				events.push(event.unwrap());
+			}
+			assert!(matches!(
+				events.as_slice(),
+				[MpvResponseEvent::Seek, MpvResponseEvent::Shutdown]
+			));
+		});
+	}
+}
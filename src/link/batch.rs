@@ -0,0 +1,77 @@
+use serde::de::DeserializeOwned;
+
+use crate::command::{parse_optional, parse_value, ParseError};
+
+use super::CommandError;
+
+/// Values of `CmdGetProperty` commands run with `MpvLink::run_commands_batched`, taken in the order of the
+/// commands.
+pub(crate) struct BatchedValues {
+	values: std::vec::IntoIter<Result<serde_json::Value, CommandError<ParseError>>>
+}
+impl BatchedValues {
+	pub fn new(values: Vec<Result<serde_json::Value, CommandError<ParseError>>>) -> Self {
+		BatchedValues {
+			values: values.into_iter()
+		}
+	}
+
+	/// Takes the next value, `None` if the property failed, such as when it is unavailable, or is null.
+	///
+	/// Values which cannot be parsed are logged and `None` as well.
+	pub fn next_optional<T: DeserializeOwned>(&mut self) -> Option<T> {
+		let value = self.values.next()?.ok()?;
+
+		match parse_optional(value) {
+			Ok(value) => value,
+			Err(err) => {
+				log::warn!("Could not parse batched property: {}", err);
+				None
+			}
+		}
+	}
+
+	/// Takes the next value, failing if the property failed or its value is missing or cannot be parsed.
+	pub fn next_required<T: DeserializeOwned>(&mut self) -> Result<T, CommandError<ParseError>> {
+		let value = self
+			.values
+			.next()
+			.unwrap_or(Err(CommandError::DataParseError(ParseError::MissingData)))?;
+
+		parse_value(value).map_err(CommandError::DataParseError)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use serde_json::json;
+
+	use super::BatchedValues;
+	use crate::{
+		command::{response::MpvResponseResultError, ParseError},
+		link::CommandError
+	};
+
+	#[test]
+	fn test_next_optional() {
+		let mut values = BatchedValues::new(vec![
+			Ok(json!(1.5)),
+			Err(CommandError::ResultError(
+				MpvResponseResultError::PropertyUnavailable
+			)),
+			Ok(json!(null)),
+			Ok(json!("not a number"))
+		]);
+
+		assert_eq!(values.next_optional::<f64>(), Some(1.5));
+		assert_eq!(values.next_optional::<f64>(), None);
+		assert_eq!(values.next_optional::<f64>(), None);
+		assert_eq!(values.next_optional::<f64>(), None);
+		// past the last command
+		assert_eq!(values.next_optional::<f64>(), None);
+		assert!(matches!(
+			values.next_required::<f64>(),
+			Err(CommandError::DataParseError(ParseError::MissingData))
+		));
+	}
+}
@@ -0,0 +1,403 @@
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex}
+};
+
+use serde::Deserialize;
+
+use crate::command::{
+	commands::SectionPriority,
+	property::MpvProperty,
+	response::{MpvResponseEvent, MpvResponseEventRef}
+};
+
+type BindingCallback = Box<dyn FnMut(&serde_json::Value) + Send>;
+type ScriptBindingCallback = Box<dyn FnMut(&KeyBindingEvent) + Send>;
+
+/// Observer ids of dropped bindings which were not unobserved yet.
+type UnboundIds = Arc<Mutex<Vec<u32>>>;
+
+/// Guard of a property bound with `MpvLink::bind_property`.
+///
+/// Dropping the guard unbinds the property, it is unobserved the next time the link polls events. Use
+/// `MpvLink::unbind_property` to unobserve it immediately and handle errors.
+#[must_use = "the property is unbound when the binding is dropped"]
+pub struct PropertyBinding {
+	observer_id: u32,
+	unbound: UnboundIds
+}
+impl PropertyBinding {
+	/// Observer id the property is observed with.
+	pub fn observer_id(&self) -> u32 {
+		self.observer_id
+	}
+}
+impl Drop for PropertyBinding {
+	fn drop(&mut self) {
+		if let Ok(mut unbound) = self.unbound.lock() {
+			unbound.push(self.observer_id);
+		}
+	}
+}
+impl std::fmt::Debug for PropertyBinding {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("PropertyBinding")
+			.field("observer_id", &self.observer_id)
+			.finish()
+	}
+}
+
+/// Transition of the key which triggered a script binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyBindingState {
+	Down,
+	Up,
+	/// The key is held down and repeats.
+	Repeat,
+	/// The key was pressed and released at once, such as a mouse wheel step.
+	Press
+}
+
+/// Trigger of a binding added with `MpvLink::add_binding`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindingEvent {
+	/// Name of the binding.
+	pub name: String,
+	pub state: KeyBindingState,
+	/// Name of the key, if mpv sent it.
+	pub key: Option<String>
+}
+impl KeyBindingEvent {
+	/// Parses the arguments of a `client-message` sent by `script-binding`.
+	///
+	/// They are `key-binding`, the name, the state such as `d-` and optionally the key.
+	fn from_args(args: &[serde_json::Value]) -> Option<Self> {
+		let (name, state, rest) = match args {
+			[message, name, state, rest @ ..] if message.as_str() == Some("key-binding") => {
+				(name.as_str()?, state.as_str()?, rest)
+			}
+			_ => return None
+		};
+		let state = match state.chars().next()? {
+			'd' => KeyBindingState::Down,
+			'u' => KeyBindingState::Up,
+			'r' => KeyBindingState::Repeat,
+			'p' => KeyBindingState::Press,
+			_ => return None
+		};
+
+		Some(KeyBindingEvent {
+			name: name.to_string(),
+			state,
+			key: rest.first().and_then(|key| key.as_str()).map(String::from)
+		})
+	}
+
+	/// Parses a `client-message` event sent by `script-binding`.
+	pub fn from_event(event: &MpvResponseEvent) -> Option<Self> {
+		match event {
+			MpvResponseEvent::Unknown { event, rest } if event == "client-message" => {
+				Self::from_args(rest.get("args")?.as_array()?)
+			}
+			_ => None
+		}
+	}
+
+	/// Returns `true` for the transitions which trigger a simple binding, that is down and press.
+	pub fn is_pressed(&self) -> bool {
+		matches!(self.state, KeyBindingState::Down | KeyBindingState::Press)
+	}
+}
+
+struct BindingEntry {
+	name: String,
+	callback: BindingCallback
+}
+
+/// Callbacks of bound properties by observer id and of script bindings by name.
+///
+/// `Default` is only a placeholder while the bindings are temporarily taken out of the link.
+#[derive(Default)]
+pub(super) struct Bindings {
+	entries: HashMap<u32, BindingEntry>,
+	next_observer_id: u32,
+	unbound: UnboundIds,
+	scripts: HashMap<String, ScriptBindingCallback>,
+	/// Keys bound to script bindings as `(key, name, priority)`, see `section_contents`.
+	script_keys: Vec<(String, String, SectionPriority)>
+}
+impl Bindings {
+	/// First observer id of bindings, above the ids of `PropertyCache::new` so that they do not collide.
+	const FIRST_OBSERVER_ID: u32 = 1 << 25;
+
+	pub fn new() -> Self {
+		Bindings {
+			entries: HashMap::new(),
+			next_observer_id: Self::FIRST_OBSERVER_ID,
+			unbound: Arc::new(Mutex::new(Vec::new())),
+			scripts: HashMap::new(),
+			script_keys: Vec::new()
+		}
+	}
+
+	/// Registers `callback` under a new observer id, which the caller observes `property` with.
+	pub fn register<P: MpvProperty>(
+		&mut self,
+		property: &P,
+		mut callback: impl FnMut(P::Value) + Send + 'static
+	) -> PropertyBinding {
+		let observer_id = self.next_observer_id;
+		self.next_observer_id = self.next_observer_id.wrapping_add(1);
+
+		let name = property.name().into_owned();
+		let callback_name = name.clone();
+		let callback = move |data: &serde_json::Value| match P::Value::deserialize(data) {
+			Ok(value) => callback(value),
+			// values of unavailable properties are null
+			Err(err) if data.is_null() => {
+				log::debug!("Skipping unavailable value of {}: {}", callback_name, err)
+			}
+			Err(err) => log::warn!("Could not parse value of {}: {}", callback_name, err)
+		};
+		self.entries.insert(
+			observer_id,
+			BindingEntry {
+				name,
+				callback: Box::new(callback)
+			}
+		);
+
+		PropertyBinding {
+			observer_id,
+			unbound: self.unbound.clone()
+		}
+	}
+
+	/// Calls the callback bound to `observer_id`, returns `false` if there is none.
+	pub fn dispatch(&mut self, observer_id: i64, data: &serde_json::Value) -> bool {
+		let entry = match u32::try_from(observer_id)
+			.ok()
+			.and_then(|id| self.entries.get_mut(&id))
+		{
+			None => return false,
+			Some(entry) => entry
+		};
+		log::trace!("Calling binding of {}", entry.name);
+		(entry.callback)(data);
+
+		true
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty() && self.scripts.is_empty()
+	}
+
+	pub fn is_bound(&self, observer_id: i64) -> bool {
+		u32::try_from(observer_id).is_ok_and(|id| self.entries.contains_key(&id))
+	}
+
+	/// Calls the callback bound to the property or script binding `event` belongs to.
+	pub fn dispatch_event(&mut self, event: &MpvResponseEvent) {
+		match event {
+			MpvResponseEvent::PropertyChange { id, data, .. } => {
+				self.dispatch(*id, data);
+			}
+			MpvResponseEvent::Unknown { event, rest } if event == "client-message" => {
+				if let Some(args) = rest.get("args").and_then(|args| args.as_array()) {
+					self.dispatch_script(args);
+				}
+			}
+			_ => ()
+		}
+	}
+
+	/// Borrowed counterpart of `dispatch_event`, the data is only parsed for bound events.
+	pub fn dispatch_event_ref(&mut self, event: &MpvResponseEventRef) {
+		let bound = match event.event.as_ref() {
+			"property-change" => event.id.is_some_and(|id| self.is_bound(id)),
+			"client-message" => !self.scripts.is_empty(),
+			_ => false
+		};
+		if !bound {
+			return
+		}
+
+		match event.to_owned_event() {
+			Ok(event) => self.dispatch_event(&event),
+			Err(err) => log::warn!("Could not parse bound event: {}", err)
+		}
+	}
+
+	fn dispatch_script(&mut self, args: &[serde_json::Value]) {
+		let event = match KeyBindingEvent::from_args(args) {
+			None => return,
+			Some(event) => event
+		};
+		if let Some(callback) = self.scripts.get_mut(&event.name) {
+			log::trace!("Calling script binding {}", event.name);
+			callback(&event);
+		}
+	}
+
+	pub fn add_script(
+		&mut self,
+		name: &str,
+		callback: impl FnMut(&KeyBindingEvent) + Send + 'static
+	) {
+		self.scripts.insert(name.to_string(), Box::new(callback));
+	}
+
+	/// Removes the script binding `name` and its keys, returns the priorities of the removed keys.
+	pub fn remove_script(&mut self, name: &str) -> Vec<SectionPriority> {
+		self.scripts.remove(name);
+
+		let mut removed = Vec::new();
+		self.script_keys.retain(|(_, bound_name, priority)| {
+			if bound_name != name {
+				return true
+			}
+			if !removed.contains(priority) {
+				removed.push(*priority);
+			}
+			false
+		});
+		removed
+	}
+
+	/// Binds `key` to the script binding `name`, replacing the previous binding of the key.
+	///
+	/// Returns the priority of the replaced binding if it differs from `priority`.
+	pub fn add_script_key(
+		&mut self,
+		key: &str,
+		name: &str,
+		priority: SectionPriority
+	) -> Option<SectionPriority> {
+		let replaced = self
+			.script_keys
+			.iter()
+			.position(|(bound_key, _, _)| bound_key == key)
+			.map(|index| self.script_keys.remove(index).2)
+			.filter(|replaced| *replaced != priority);
+		self.script_keys.push((key.to_string(), name.to_string(), priority));
+
+		replaced
+	}
+
+	/// Contents of the input section with the keys of script bindings of `client_name` bound with
+	/// `priority`.
+	pub fn section_contents(&self, client_name: &str, priority: SectionPriority) -> String {
+		self.script_keys
+			.iter()
+			.filter(|(_, _, bound_priority)| *bound_priority == priority)
+			.map(|(key, name, _)| format!("{} script-binding {}/{}\n", key, client_name, name))
+			.collect()
+	}
+
+	/// Removes the callbacks of dropped bindings, returning their observer ids.
+	pub fn take_unbound(&mut self) -> Vec<u32> {
+		let ids = match self.unbound.lock() {
+			Ok(mut unbound) => std::mem::take(&mut *unbound),
+			Err(_) => return Vec::new()
+		};
+		for id in ids.iter() {
+			self.entries.remove(id);
+		}
+
+		ids
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::{Arc, Mutex};
+
+	use super::{Bindings, KeyBindingEvent, KeyBindingState};
+	use crate::command::{commands::SectionPriority, property, response::MpvResponseEvent};
+
+	#[test]
+	fn test_dispatch_and_unbind() {
+		let mut bindings = Bindings::new();
+		let values = Arc::new(Mutex::new(Vec::new()));
+
+		let binding = {
+			let values = values.clone();
+			bindings.register(&property::Volume, move |value| {
+				values.lock().unwrap().push(value)
+			})
+		};
+		let observer_id = binding.observer_id();
+		let id = i64::from(observer_id);
+
+		assert!(bindings.dispatch(id, &serde_json::json!(50.0)));
+		// unavailable and malformed values are skipped
+		assert!(bindings.dispatch(id, &serde_json::Value::Null));
+		assert!(bindings.dispatch(id, &serde_json::json!("loud")));
+		assert!(!bindings.dispatch(id + 1, &serde_json::json!(10.0)));
+		assert_eq!(*values.lock().unwrap(), [50.0]);
+
+		assert!(bindings.take_unbound().is_empty());
+		std::mem::drop(binding);
+		assert_eq!(bindings.take_unbound(), [observer_id]);
+		assert!(!bindings.is_bound(id));
+		assert!(!bindings.dispatch(id, &serde_json::json!(60.0)));
+	}
+
+	#[test]
+	fn test_script_binding() {
+		let mut bindings = Bindings::new();
+		let triggered = Arc::new(Mutex::new(Vec::new()));
+		{
+			let triggered = triggered.clone();
+			bindings.add_script("my-action", move |event: &KeyBindingEvent| {
+				triggered.lock().unwrap().push(event.clone())
+			});
+		}
+		bindings.add_script_key("x", "my-action", SectionPriority::Default);
+		bindings.add_script_key("Ctrl+x", "my-action", SectionPriority::Default);
+		bindings.add_script_key("ESC", "my-action", SectionPriority::Force);
+		assert_eq!(
+			bindings.section_contents("ipc_1", SectionPriority::Default),
+			"x script-binding ipc_1/my-action\nCtrl+x script-binding ipc_1/my-action\n"
+		);
+		assert_eq!(
+			bindings.section_contents("ipc_1", SectionPriority::Force),
+			"ESC script-binding ipc_1/my-action\n"
+		);
+
+		// rebinding a key with another priority moves it to the other section
+		assert_eq!(
+			bindings.add_script_key("x", "my-action", SectionPriority::Force),
+			Some(SectionPriority::Default)
+		);
+		assert_eq!(
+			bindings.section_contents("ipc_1", SectionPriority::Default),
+			"Ctrl+x script-binding ipc_1/my-action\n"
+		);
+
+		for line in [
+			r#"{"event":"client-message","args":["key-binding","my-action","d-","x","x"]}"#,
+			r#"{"event":"client-message","args":["key-binding","other","d-","y","y"]}"#,
+			r#"{"event":"client-message","args":["my-action"]}"#
+		] {
+			let event: MpvResponseEvent = serde_json::from_str(line).unwrap();
+			bindings.dispatch_event(&event);
+		}
+		assert_eq!(
+			*triggered.lock().unwrap(),
+			[KeyBindingEvent {
+				name: "my-action".to_string(),
+				state: KeyBindingState::Down,
+				key: Some("x".to_string())
+			}]
+		);
+
+		assert_eq!(
+			bindings.remove_script("my-action"),
+			[SectionPriority::Default, SectionPriority::Force]
+		);
+		assert_eq!(bindings.section_contents("ipc_1", SectionPriority::Default), "");
+		assert_eq!(bindings.section_contents("ipc_1", SectionPriority::Force), "");
+		assert!(bindings.is_empty());
+	}
+}
@@ -0,0 +1,68 @@
+//! Fake mpv answering over a socket, for tests of the links and of what is built on them.
+
+use std::{
+	io::{BufRead, BufReader, Lines, Write},
+	os::unix::net::{UnixListener, UnixStream},
+	path::Path,
+	thread::JoinHandle
+};
+
+/// Requests as read by the fake mpv.
+pub(crate) type Requests = Lines<BufReader<UnixStream>>;
+
+/// Answers of the handshake of `MpvLink`: the IPC version, the client name and the mpv version.
+pub(crate) const HANDSHAKE: &[&str] = &["131073", "\"mpvsock\"", "\"mpv 0.38.0\""];
+
+/// Answers the request on `line` with a successful result carrying `data`.
+pub(crate) fn write_result(mut writer: impl Write, line: &str, data: &str) {
+	let request: serde_json::Value = serde_json::from_str(line).unwrap();
+	writeln!(
+		writer,
+		"{{\"error\":\"success\",\"data\":{},\"request_id\":{}}}",
+		data, request["request_id"]
+	)
+	.unwrap();
+}
+
+/// Answers the next requests with `answers`, in order.
+pub(crate) fn answer(requests: &mut Requests, mut writer: impl Write, answers: &[&str]) {
+	for data in answers {
+		write_result(&mut writer, &requests.next().unwrap().unwrap(), data);
+	}
+}
+
+/// Serves `socket` in a thread, passing the requests to `serve`.
+///
+/// The socket is returned to keep the connection open.
+pub(crate) fn serve(
+	socket: UnixStream,
+	serve: impl FnOnce(&mut Requests, &mut UnixStream) + Send + 'static
+) -> JoinHandle<UnixStream> {
+	std::thread::spawn(move || {
+		let mut socket = socket;
+		let mut requests = BufReader::new(socket.try_clone().unwrap()).lines();
+		serve(&mut requests, &mut socket);
+
+		socket
+	})
+}
+
+/// Accepts a connection on `path` and answers the handshake of `MpvLink`, then passes the requests to `serve`.
+pub(crate) fn listen(
+	path: &Path,
+	serve: impl FnOnce(&mut Requests, &mut UnixStream) + Send + 'static
+) -> JoinHandle<UnixStream> {
+	let _ = std::fs::remove_file(path);
+	let listener = UnixListener::bind(path).unwrap();
+
+	std::thread::spawn(move || {
+		let (socket, _) = listener.accept().unwrap();
+
+		self::serve(socket, |requests, socket| {
+			answer(requests, &mut *socket, HANDSHAKE);
+			serve(requests, socket);
+		})
+		.join()
+		.unwrap()
+	})
+}
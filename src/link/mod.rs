@@ -1,14 +1,36 @@
 use std::{
+	borrow::Cow,
+	collections::{HashSet, VecDeque},
 	io::{self, Write},
 	num::NonZeroI64,
-	path::Path
+	path::Path,
+	process::ExitStatus,
+	time::{Duration, Instant}
 };
 
 use serde::de::DeserializeOwned;
 use thiserror::Error;
 
-use crate::{command::MpvCommandRaw, response_buffer::ResponseBuffer};
+use crate::{
+	command::{MpvCommandNamed, MpvCommandRaw, Named},
+	response_buffer::ResponseBuffer,
+	version::MpvVersion
+};
 
+#[cfg(all(unix, feature = "async-io"))]
+pub mod async_io;
+pub(crate) mod batch;
+pub mod binding;
+#[cfg(all(test, unix))]
+pub(crate) mod fake_mpv;
+pub mod request_id;
+pub mod retry;
+#[cfg(feature = "spawn")]
+pub mod spawn;
+pub mod stats;
+#[cfg(feature = "spawn")]
+pub mod supervisor;
+pub mod trace;
 #[cfg(unix)]
 pub mod unix;
 
@@ -16,38 +38,149 @@ pub mod unix;
 type InnerLink = unix::MpvLink;
 
 use crate::command::{
-	response::{MpvResponse, MpvResponseEvent, MpvResponseResult, MpvResponseResultError},
-	MpvCommand
+	commands::{
+		CmdClientName,
+		CmdDefineSection,
+		CmdDisableEvent,
+		CmdEnableEvent,
+		CmdEnableSection,
+		CmdGetProperty,
+		CmdGetVersion,
+		CmdObserveProperty,
+		CmdUnobserveProperty,
+		SectionPriority
+	},
+	property::{ObservableMpvProperty, ReadableMpvProperty},
+	response::{
+		MpvResponse,
+		MpvResponseEvent,
+		MpvResponseEventRef,
+		MpvResponseRef,
+		MpvResponseResult,
+		MpvResponseResultError
+	},
+	MpvCommand,
+	ParseError
 };
 
+/// Result of running the command `C`.
+pub type CommandResult<C> =
+	Result<<C as MpvCommand>::ParsedData, CommandError<<C as MpvCommand>::Error>>;
+
 #[derive(Debug, Error)]
 pub enum CommandError<E: std::error::Error> {
 	#[error(transparent)]
 	SendError(#[from] SendError),
 	#[error(transparent)]
 	ReceiveError(#[from] ReceiveError),
-	#[error("Received error response: {0:?}")]
+	#[error("Received error response: {0}")]
 	ResultError(MpvResponseResultError),
 	#[error("Error while parsing response data: {0}")]
 	DataParseError(E)
 }
+impl<E: std::error::Error> CommandError<E> {
+	/// The error mpv responded with, if the command failed in mpv.
+	pub fn mpv_error(&self) -> Option<&MpvResponseResultError> {
+		match self {
+			CommandError::ResultError(err) => Some(err),
+			_ => None
+		}
+	}
+
+	/// Whether the connection to mpv was lost, in which case the link has to be reopened.
+	pub fn is_disconnect(&self) -> bool {
+		match self {
+			CommandError::SendError(err) => err.is_disconnect(),
+			CommandError::ReceiveError(err) => err.is_disconnect(),
+			_ => false
+		}
+	}
+
+	/// Whether running the same command again on the same link may succeed.
+	///
+	/// This is the case for timeouts and for properties which are unavailable, for example because no file
+	/// is loaded yet.
+	pub fn is_retryable(&self) -> bool {
+		match self {
+			CommandError::SendError(err) => err.is_retryable(),
+			CommandError::ReceiveError(err) => err.is_retryable(),
+			CommandError::ResultError(err) => {
+				matches!(err, MpvResponseResultError::PropertyUnavailable)
+			}
+			CommandError::DataParseError(_) => false
+		}
+	}
+}
+
+/// Whether `err` means that the other side closed the socket.
+fn is_disconnect_io(err: &io::Error) -> bool {
+	matches!(
+		err.kind(),
+		io::ErrorKind::UnexpectedEof
+			| io::ErrorKind::BrokenPipe
+			| io::ErrorKind::ConnectionReset
+			| io::ErrorKind::ConnectionAborted
+			| io::ErrorKind::NotConnected
+	)
+}
+
+/// Whether the operation failing with `err` may succeed when repeated.
+fn is_retryable_io(err: &io::Error) -> bool {
+	matches!(
+		err.kind(),
+		io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+	)
+}
 
 #[derive(Debug, Error)]
 pub enum SendError {
 	#[error("Could not write into the stream: {0}")]
-	Io(#[from] std::io::Error)
+	Io(#[from] std::io::Error),
+	#[error("Serialized command contains a line delimiter")]
+	EmbeddedNewline
+}
+impl SendError {
+	pub fn is_disconnect(&self) -> bool {
+		matches!(self, SendError::Io(err) if is_disconnect_io(err))
+	}
+
+	pub fn is_retryable(&self) -> bool {
+		matches!(self, SendError::Io(err) if is_retryable_io(err))
+	}
 }
 
 #[derive(Debug, Error)]
 pub enum ReceiveError {
 	#[error("Could not read from the stream: {0}")]
 	Io(#[from] std::io::Error),
-	#[error("Could not deserialize response: {0}")]
-	Deserialize(#[from] serde_json::Error),
+	#[error("Could not deserialize response {raw}: {source}")]
+	Deserialize {
+		source: serde_json::Error,
+		/// The offending line, converted to UTF-8 lossily.
+		raw: String
+	},
 	#[error("Expected request_id = {expected} but found request_id = {found}")]
 	RequestIdMismatch { expected: i64, found: i64 },
 	#[error("Expected only events but found a result response")]
-	UnexpectedResponseResult(MpvResponseResult)
+	UnexpectedResponseResult(MpvResponseResult),
+	#[error("Dropped a line longer than {max} bytes")]
+	LineTooLong { max: usize }
+}
+impl ReceiveError {
+	pub fn is_disconnect(&self) -> bool {
+		matches!(self, ReceiveError::Io(err) if is_disconnect_io(err))
+	}
+
+	pub fn is_retryable(&self) -> bool {
+		matches!(self, ReceiveError::Io(err) if is_retryable_io(err))
+	}
+
+	fn deserialize(source: serde_json::Error, line: &[u8]) -> Self {
+		ReceiveError::Deserialize {
+			source,
+			raw: String::from_utf8_lossy(line).into_owned()
+		}
+	}
 }
 
 #[derive(Debug, Error)]
@@ -65,8 +198,28 @@ pub enum MpvLinkInitError {
 	Spawn(io::Error),
 	#[error("Failed to connect to server socket")]
 	Connect(io::Error),
+	#[cfg(feature = "spawn")]
+	#[error("mpv exited before creating the server socket: {0}")]
+	ExitedBeforeSocket(std::process::ExitStatus),
+	#[cfg(feature = "spawn")]
+	#[error("Timed out waiting for mpv to create the server socket")]
+	SocketTimeout,
 	#[error("Failed to remove previous socket")]
-	RemovePrevious(io::Error)
+	RemovePrevious(io::Error),
+	#[error("Failed to set socket permissions")]
+	Permissions(io::Error),
+	#[error("XDG_RUNTIME_DIR is not set")]
+	NoRuntimeDir,
+	#[error("Failed to perform handshake: {0}")]
+	Handshake(#[from] HandshakeError)
+}
+
+#[derive(Debug, Error)]
+pub enum HandshakeError {
+	#[error("Failed to get IPC protocol version: {0}")]
+	Version(CommandError<ParseError>),
+	#[error("Failed to get client name: {0}")]
+	ClientName(CommandError<ParseError>)
 }
 
 #[derive(Debug, Error)]
@@ -74,29 +227,204 @@ pub enum MpvLinkDeinitError {
 	#[error("Failed to shutdown socket")]
 	Shutdown(io::Error),
 	#[error("Failed to wait for the child process")]
-	Wait(io::Error)
+	Wait(io::Error),
+	#[error("Failed to kill the child process")]
+	Kill(io::Error)
+}
+
+/// Address of a socket to connect to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketAddress<'a> {
+	Path(&'a Path),
+	/// Name in the Linux abstract socket namespace, without the leading NUL byte.
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	Abstract(&'a [u8])
+}
+impl<'a> SocketAddress<'a> {
+	/// Parses `@name` as an abstract address where supported and anything else as a path.
+	pub fn parse(address: &'a str) -> Self {
+		#[cfg(any(target_os = "linux", target_os = "android"))]
+		if let Some(name) = address.strip_prefix('@') {
+			return SocketAddress::Abstract(name.as_bytes())
+		}
+
+		SocketAddress::Path(Path::new(address))
+	}
+}
+
+/// Type of the socket to connect with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SocketType {
+	/// What mpv itself listens on.
+	#[default]
+	Stream,
+	/// Message oriented socket, where each message has to fit into the response buffer.
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	SeqPacket
+}
+
+/// State of the mpv process as seen by the link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildStatus {
+	Running,
+	Exited(ExitStatus),
+	/// mpv is not a child of this process, so its state is unknown.
+	NotChild
+}
+
+/// What happens to the link, and to mpv if it is a child of this process, when the link is shut down.
+///
+/// Links to an mpv process which is not a child only close the socket with any policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownPolicy {
+	/// Sends `quit` and waits for the child to exit.
+	///
+	/// If `timeout` is not `None` and the child is still running once it elapses, the child is killed.
+	/// This is what happens when the link is dropped, with no timeout.
+	QuitChild { timeout: Option<Duration> },
+	/// Closes the socket and leaves the child running without waiting for it.
+	DetachChild,
+	/// Closes the socket without sending `quit` and waits for the child to exit on its own.
+	CloseSocketOnly
+}
+
+/// How lines received from mpv which cannot be deserialized are handled.
+///
+/// Lenient policies apply only to lines which are not results, since a result is awaited by a command
+/// and skipping it would leave the command waiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MalformedLinePolicy {
+	/// Return `ReceiveError::Deserialize`.
+	#[default]
+	Error,
+	/// Log and skip the line.
+	Skip,
+	/// Log the line and queue it as `MpvResponseEvent::Malformed`.
+	Emit
+}
+
+/// Returns `true` if `line` is an object with the `error` field, which only results have.
+fn is_result_line(line: &[u8]) -> bool {
+	#[derive(serde::Deserialize)]
+	struct ResultProbe {
+		#[allow(dead_code)]
+		error: serde::de::IgnoredAny
+	}
+
+	serde_json::from_slice::<ResultProbe>(line).is_ok()
+}
+
+/// Parses the result of getting the `mpv-version` property, logging why it could not be determined.
+fn server_version_from(
+	result: Result<serde_json::Value, CommandError<ParseError>>
+) -> Option<MpvVersion> {
+	match result {
+		Ok(serde_json::Value::String(value)) => {
+			let server_version = MpvVersion::parse(&value);
+			if server_version.is_none() {
+				log::warn!("Could not parse mpv version {:?}", value);
+			}
+			server_version
+		}
+		Ok(value) => {
+			log::warn!("Unexpected mpv version {}", value);
+			None
+		}
+		Err(err) => {
+			log::warn!("Could not get mpv version: {}", err);
+			None
+		}
+	}
 }
 
+type LineTap = Box<dyn FnMut(&[u8]) + Send>;
+
 pub struct MpvLink {
 	inner: InnerLink,
-	current_id: NonZeroI64,
+	/// Ids of commands whose results were not received yet.
+	request_ids: request_id::RequestIdAllocator,
 	response_buffer: ResponseBuffer,
-	event_queue: Vec<MpvResponseEvent>
+	/// Reusable buffer commands are serialized into before being sent.
+	command_buffer: Vec<u8>,
+	event_queue: Vec<MpvResponseEvent>,
+	/// IPC protocol version cached by `handshake`.
+	ipc_version: Option<(u16, u16)>,
+	/// Client name cached by `handshake`.
+	client_name: Option<String>,
+	/// mpv version cached by `handshake`.
+	server_version: Option<MpvVersion>,
+	malformed_line_policy: MalformedLinePolicy,
+	/// Called with every line received, before it is parsed.
+	response_tap: Option<LineTap>,
+	/// Called with every command sent, without the line delimiter.
+	command_tap: Option<LineTap>,
+	/// Collected only when enabled.
+	stats: Option<stats::LinkStats>,
+	/// Last commands and lines, kept only when enabled, see `set_trace_capacity`.
+	trace: Option<trace::TraceBuffer>,
+	/// Ids of commands sent with `run_command_raw` whose results were not received yet.
+	raw_pending: HashSet<i64>,
+	/// Results of commands sent with `run_command_raw`, oldest first, see `take_raw_result`.
+	raw_results: VecDeque<MpvResponseResult>,
+	/// Ids of commands whose results are no longer awaited, because waiting for them failed.
+	///
+	/// Their results are dropped when they arrive instead of being returned for another command.
+	abandoned: HashSet<i64>,
+	/// Results without a request id, oldest first, see `drain_unsolicited_results`.
+	unsolicited_results: VecDeque<MpvResponseResult>,
+	/// Callbacks of properties bound with `bind_property`.
+	bindings: binding::Bindings,
+	/// Whether `MpvResponseEvent::ProcessExited` was queued already.
+	child_exit_reported: bool,
+	/// Set when the socket is blocking, see `set_read_timeout`.
+	read_timeout: Option<Duration>
 }
 impl MpvLink {
-	const NONZERO_ONE: NonZeroI64 = unsafe { NonZeroI64::new_unchecked(1) };
+	/// How long constructors wait for each answer of the handshake, so that a peer which accepts connections but
+	/// never answers, such as a socket not belonging to mpv, fails the construction instead of blocking it.
+	pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+	/// How many results of raw commands, or unsolicited results, are kept before the oldest are discarded.
+	const RAW_RESULT_CAPACITY: usize = 64;
 
-	fn new(mut inner: InnerLink) -> Result<Self, MpvLinkInitError> {
+	fn new(inner: InnerLink) -> Result<Self, MpvLinkInitError> {
+		Self::with_handshake_timeout(inner, Self::HANDSHAKE_TIMEOUT)
+	}
+
+	fn with_handshake_timeout(
+		mut inner: InnerLink,
+		handshake_timeout: Duration
+	) -> Result<Self, MpvLinkInitError> {
 		inner
 			.set_nonblocking(true)
 			.map_err(MpvLinkInitError::Nonblocking)?;
 
-		let me = MpvLink {
+		let mut me = MpvLink {
 			inner,
-			current_id: Self::NONZERO_ONE,
+			request_ids: request_id::RequestIdAllocator::new(),
 			response_buffer: ResponseBuffer::new(),
-			event_queue: Vec::new()
+			command_buffer: Vec::new(),
+			event_queue: Vec::new(),
+			ipc_version: None,
+			client_name: None,
+			server_version: None,
+			malformed_line_policy: MalformedLinePolicy::Error,
+			response_tap: None,
+			command_tap: None,
+			stats: None,
+			trace: None,
+			raw_pending: HashSet::new(),
+			raw_results: VecDeque::new(),
+			abandoned: HashSet::new(),
+			unsolicited_results: VecDeque::new(),
+			bindings: binding::Bindings::new(),
+			child_exit_reported: false,
+			read_timeout: None
 		};
+		me.set_read_timeout(Some(handshake_timeout))
+			.map_err(MpvLinkInitError::Nonblocking)?;
+		me.handshake()?;
+		me.set_read_timeout(None)
+			.map_err(MpvLinkInitError::Nonblocking)?;
 
 		Ok(me)
 	}
@@ -107,130 +435,967 @@ impl MpvLink {
 		Self::new(inner)
 	}
 
-	pub fn spawn_server(socket_path: &Path) -> Result<Self, MpvLinkInitError> {
-		let inner = InnerLink::spawn_server(socket_path)?;
+	/// Connects to a socket which is not necessarily a stream socket on the filesystem.
+	///
+	/// mpv only creates stream sockets with a path, other addresses and types are exposed by wrappers forwarding its IPC.
+	#[cfg(unix)]
+	pub fn connect_address(
+		address: SocketAddress,
+		socket_type: SocketType
+	) -> Result<Self, MpvLinkInitError> {
+		let inner = InnerLink::connect_address(address, socket_type)?;
 
 		Self::new(inner)
 	}
 
-	#[cfg(unix)]
+	/// Spawns mpv with default options, use `MpvSpawnBuilder` to pass additional arguments.
+	#[cfg(feature = "spawn")]
+	pub fn spawn_server(socket_path: &Path) -> Result<Self, MpvLinkInitError> {
+		spawn::MpvSpawnBuilder::new().spawn_server(socket_path)
+	}
+
+	/// Spawns mpv with default options and a socket with a unique name in `XDG_RUNTIME_DIR`,
+	/// returning the path of the socket.
+	#[cfg(all(unix, feature = "spawn"))]
+	pub fn spawn_server_auto() -> Result<(Self, std::path::PathBuf), MpvLinkInitError> {
+		spawn::MpvSpawnBuilder::new().spawn_server_auto()
+	}
+
+	/// Spawns mpv with default options, use `MpvSpawnBuilder` to pass additional arguments.
+	#[cfg(all(unix, feature = "spawn"))]
 	pub fn spawn_client() -> Result<Self, MpvLinkInitError> {
-		let inner = InnerLink::spawn_client()?;
+		spawn::MpvSpawnBuilder::new().spawn_client()
+	}
 
-		Self::new(inner)
+	/// Switches the socket to blocking reads with `timeout`, or back to nonblocking reads if `None`.
+	///
+	/// Nonblocking is the default, in which case `poll_events` only takes responses which already arrived and
+	/// waiting for a result blocks until it arrives. With a timeout every read waits for the timeout at most,
+	/// so `poll_events` returns once no response arrives for the timeout and waiting for a result fails with
+	/// `TimedOut` instead of blocking indefinitely.
+	pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<(), io::Error> {
+		self.inner.set_read_timeout(timeout)?;
+		self.read_timeout = timeout;
+
+		Ok(())
+	}
+
+	pub fn read_timeout(&self) -> Option<Duration> {
+		self.read_timeout
+	}
+
+	/// Checks whether the mpv child process is still running without blocking.
+	pub fn child_status(&mut self) -> Result<ChildStatus, io::Error> {
+		self.inner.child_status()
+	}
+
+	/// Takes the stdout of the mpv child spawned with `SpawnStdio::Piped`, `None` if it was taken already.
+	///
+	/// The pipe has to be drained, otherwise mpv blocks once it fills up.
+	#[cfg(feature = "spawn")]
+	pub fn take_child_stdout(&mut self) -> Option<std::process::ChildStdout> {
+		self.inner.take_stdout()
+	}
+
+	/// Takes the stderr of the mpv child spawned with `SpawnStdio::Piped`, `None` if it was taken already.
+	///
+	/// The pipe has to be drained, otherwise mpv blocks once it fills up.
+	#[cfg(feature = "spawn")]
+	pub fn take_child_stderr(&mut self) -> Option<std::process::ChildStderr> {
+		self.inner.take_stderr()
+	}
+
+	/// Closes the link according to `policy`.
+	///
+	/// Dropping the link is equivalent to `ShutdownPolicy::QuitChild` without timeout, but panics on error.
+	pub fn shutdown(mut self, policy: ShutdownPolicy) -> Result<(), MpvLinkDeinitError> {
+		self.close(policy)
+	}
+
+	/// Like `shutdown` but leaves the link closed in place, any further use of it panics.
+	pub(crate) fn close(&mut self, policy: ShutdownPolicy) -> Result<(), MpvLinkDeinitError> {
+		self.inner.shutdown(policy)
+	}
+
+	/// Fetches the IPC protocol version, the client name and the mpv version and caches them on the link.
+	///
+	/// This is done automatically when the link is created. Failing to get the mpv version is not an error,
+	/// commands then assume a recent version.
+	pub fn handshake(&mut self) -> Result<(), HandshakeError> {
+		let version = self
+			.run_command(&CmdGetVersion::new())
+			.map_err(HandshakeError::Version)?;
+		let client_name = self
+			.run_command(&CmdClientName::new())
+			.map_err(HandshakeError::ClientName)?;
+
+		log::info!(
+			"Handshake: IPC version {}.{}, client name {}",
+			version.0,
+			version.1,
+			client_name
+		);
+		self.ipc_version = Some(version);
+		self.client_name = Some(client_name);
+
+		self.server_version =
+			server_version_from(self.run_command(&CmdGetProperty::new("mpv-version")));
+		log::info!("Handshake: mpv version {:?}", self.server_version);
+
+		Ok(())
+	}
+
+	/// Returns the IPC protocol version as `(major, minor)` cached by `handshake`.
+	pub fn ipc_version(&self) -> Option<(u16, u16)> {
+		self.ipc_version
+	}
+
+	/// Returns the mpv version cached by `handshake`, `None` if it could not be determined.
+	pub fn server_version(&self) -> Option<MpvVersion> {
+		self.server_version
+	}
+
+	/// Returns the name of this client as assigned by mpv, cached by `handshake`.
+	pub fn client_name(&self) -> Option<&str> {
+		self.client_name.as_deref()
+	}
+
+	/// Sets how lines which cannot be deserialized are handled, `MalformedLinePolicy::Error` by default.
+	pub fn set_malformed_line_policy(&mut self, policy: MalformedLinePolicy) {
+		self.malformed_line_policy = policy;
+	}
+
+	pub fn malformed_line_policy(&self) -> MalformedLinePolicy {
+		self.malformed_line_policy
+	}
+
+	/// Sets the maximum length of a received line, unlimited (`None`) by default.
+	///
+	/// A misbehaving peer could otherwise exhaust memory by sending a line without end. Longer lines are
+	/// dropped and reading them fails with `ReceiveError::LineTooLong` once, after which reading resumes at
+	/// the next line. A dropped result is never received, so the command waiting for it fails with the error.
+	pub fn set_max_line_length(&mut self, max_line_length: Option<usize>) {
+		self.response_buffer.set_max_line_length(max_line_length);
+	}
+
+	pub fn max_line_length(&self) -> Option<usize> {
+		self.response_buffer.max_line_length()
+	}
+
+	/// Sets a function called with every raw line received from mpv, before it is parsed.
+	///
+	/// Useful for protocol logging, metrics or persistence. Replaces the previously set tap.
+	pub fn set_response_tap(&mut self, tap: impl FnMut(&[u8]) + Send + 'static) {
+		self.response_tap = Some(Box::new(tap));
+	}
+
+	pub fn remove_response_tap(&mut self) {
+		self.response_tap = None;
+	}
+
+	/// Sets a function called with every raw command line sent to mpv, without the line delimiter.
+	///
+	/// Replaces the previously set tap.
+	pub fn set_command_tap(&mut self, tap: impl FnMut(&[u8]) + Send + 'static) {
+		self.command_tap = Some(Box::new(tap));
+	}
+
+	pub fn remove_command_tap(&mut self) {
+		self.command_tap = None;
+	}
+
+	/// Enables or disables collecting `LinkStats`, disabling also resets them.
+	pub fn set_stats_enabled(&mut self, enabled: bool) {
+		match (enabled, self.stats.is_some()) {
+			(true, false) => self.stats = Some(Default::default()),
+			(false, true) => self.stats = None,
+			_ => ()
+		}
+	}
+
+	/// Returns the collected stats, all zero if collecting is not enabled.
+	pub fn stats(&self) -> stats::LinkStats {
+		self.stats.unwrap_or_default()
+	}
+
+	/// Keeps the last `capacity` commands sent and lines received with their times, `None` disables it.
+	///
+	/// The trace costs a copy of every line, but explains what happened before mpv stopped responding even
+	/// when logging was not enabled, see `dump_trace`. Changing the capacity clears the trace.
+	pub fn set_trace_capacity(&mut self, capacity: Option<usize>) {
+		if self.trace.as_ref().map(trace::TraceBuffer::capacity) != capacity {
+			self.trace = capacity.map(trace::TraceBuffer::new);
+		}
+	}
+
+	pub fn trace(&self) -> Option<&trace::TraceBuffer> {
+		self.trace.as_ref()
+	}
+
+	/// Writes the trace to `w` oldest first, see `TraceBuffer::write_to`. Writes nothing if tracing is disabled.
+	pub fn dump_trace(&self, w: impl io::Write) -> io::Result<()> {
+		match self.trace.as_ref() {
+			Some(trace) => trace.write_to(w),
+			None => Ok(())
+		}
 	}
 
 	pub fn run_command<C: MpvCommand + ?Sized>(
 		&mut self,
 		command: &C
 	) -> Result<C::ParsedData, CommandError<C::Error>> {
-		let current_id = self.run_command_raw(command)?;
+		self.run_command_as(command, command)
+	}
 
-		let result = loop {
-			let result = self.next_result::<C::Data>()?;
-			match result.request_id() {
-				Some(request_id) if request_id == current_id.get() => break result,
-				request_id => {
-					return Err(ReceiveError::RequestIdMismatch {
-						expected: current_id.get(),
-						found: request_id.unwrap_or(0)
-					}
-					.into())
-				}
+	/// Reads `property`, returning `None` if it is unavailable or does not exist.
+	///
+	/// Both are routine: `duration` is unavailable before a file is loaded and properties added in newer mpv
+	/// versions do not exist in older ones. A `null` value is treated as unavailable as well.
+	pub fn try_get_property<P: ReadableMpvProperty>(
+		&mut self,
+		property: P
+	) -> Result<Option<P::Value>, CommandError<ParseError>> {
+		match self.run_command(&CmdGetProperty::new(property)) {
+			Ok(value) => Ok(Some(value)),
+			Err(CommandError::ResultError(
+				MpvResponseResultError::PropertyUnavailable
+				| MpvResponseResultError::PropertyNotFound
+			)) => Ok(None),
+			Err(CommandError::DataParseError(ParseError::MissingData)) => Ok(None),
+			Err(err) => Err(err)
+		}
+	}
+
+	/// Reads `property` like `try_get_property`, returning `default` if it is unavailable or does not exist.
+	pub fn get_property_or<P: ReadableMpvProperty>(
+		&mut self,
+		property: P,
+		default: P::Value
+	) -> Result<P::Value, CommandError<ParseError>> {
+		Ok(self.try_get_property(property)?.unwrap_or(default))
+	}
+
+	/// Observes `property` and calls `callback` with its value whenever it changes.
+	///
+	/// The callback is called while events are polled, or received while waiting for a result, with the
+	/// value deserialized as `P::Value`. Values which do not deserialize, such as `null` of unavailable
+	/// properties, are skipped. The `property-change` events stay in the internal queue as well.
+	///
+	/// The property is unbound when the returned binding is dropped.
+	pub fn bind_property<P: ObservableMpvProperty>(
+		&mut self,
+		property: P,
+		callback: impl FnMut(P::Value) + Send + 'static
+	) -> Result<binding::PropertyBinding, CommandError<ParseError>>
+	where
+		P::Value: 'static
+	{
+		let binding = self.bindings.register(&property, callback);
+		self.run_command(&CmdObserveProperty::new(binding.observer_id(), property))?;
+
+		Ok(binding)
+	}
+
+	/// Unbinds the property of `binding` and unobserves it right away.
+	pub fn unbind_property(
+		&mut self,
+		binding: binding::PropertyBinding
+	) -> Result<(), CommandError<ParseError>> {
+		std::mem::drop(binding);
+
+		self.unobserve_unbound()
+	}
+
+	/// Calls `callback` whenever the binding `name` is triggered with `script-binding <client>/<name>`.
+	///
+	/// Keys are bound to it with `bind_key`, or in `input.conf` using the client name. Like property
+	/// bindings, the callback is called while events are polled and the `client-message` events stay
+	/// in the internal queue. Adding a binding with the same name replaces its callback.
+	pub fn add_binding(
+		&mut self,
+		name: &str,
+		callback: impl FnMut(&binding::KeyBindingEvent) + Send + 'static
+	) {
+		self.bindings.add_script(name, callback);
+	}
+
+	/// Binds `key` to the binding `name` in an input section of this client with `priority`.
+	///
+	/// With `SectionPriority::Default` keys bound by the user in `input.conf` take precedence, with
+	/// `SectionPriority::Force` the key overrides them.
+	pub fn bind_key(
+		&mut self,
+		key: &str,
+		name: &str,
+		priority: SectionPriority
+	) -> Result<(), CommandError<ParseError>> {
+		if let Some(replaced) = self.bindings.add_script_key(key, name, priority) {
+			self.update_binding_section(replaced)?;
+		}
+
+		self.update_binding_section(priority)
+	}
+
+	/// Removes the binding `name` along with its keys.
+	pub fn remove_binding(&mut self, name: &str) -> Result<(), CommandError<ParseError>> {
+		for priority in self.bindings.remove_script(name) {
+			self.update_binding_section(priority)?;
+		}
+
+		Ok(())
+	}
+
+	/// Defines and enables the input section with the keys of script bindings bound with `priority`.
+	fn update_binding_section(
+		&mut self,
+		priority: SectionPriority
+	) -> Result<(), CommandError<ParseError>> {
+		let client_name = match self.client_name.clone() {
+			Some(client_name) => client_name,
+			None => self.run_command(&CmdClientName::new())?
+		};
+		let section = match priority {
+			SectionPriority::Default => format!("{}-bindings", client_name),
+			SectionPriority::Force => format!("{}-bindings-force", client_name)
+		};
+
+		self.run_command(&CmdDefineSection::new(
+			Cow::Borrowed(&section),
+			Cow::Owned(self.bindings.section_contents(&client_name, priority)),
+			priority
+		))?;
+		self.run_command(&CmdEnableSection::new(Cow::Borrowed(&section)))
+	}
+
+	/// Unobserves the properties of dropped bindings.
+	fn unobserve_unbound(&mut self) -> Result<(), CommandError<ParseError>> {
+		let mut result = Ok(());
+		for observer_id in self.bindings.take_unbound() {
+			if let Err(err) = self.run_command(&CmdUnobserveProperty::new(observer_id)) {
+				result = Err(err);
+			}
+		}
+
+		result
+	}
+
+	/// Runs `command` sending it with named arguments, see `MpvCommandNamed`.
+	pub fn run_command_named<C: MpvCommandNamed + ?Sized>(
+		&mut self,
+		command: &C
+	) -> Result<C::ParsedData, CommandError<C::Error>> {
+		self.run_command_as(command, &Named(command))
+	}
+
+	/// Sends `raw` and parses the result with `command`.
+	fn run_command_as<C: MpvCommand + ?Sized, R: MpvCommandRaw + ?Sized>(
+		&mut self,
+		command: &C,
+		raw: &R
+	) -> Result<C::ParsedData, CommandError<C::Error>> {
+		let sent_at = self.stats.is_some().then(Instant::now);
+		let current_id = self.send_with_new_id(raw)?;
+
+		let result = self.next_result_for(current_id)?;
+		self.record_latency(sent_at);
+
+		let data = match result.into_result() {
+			Ok(data) => data,
+			Err(err) => {
+				return command
+					.parse_error(err.kind)
+					.map_err(CommandError::ResultError)
 			}
 		};
 
-		match result {
-			MpvResponseResult::Error { error, .. } => Err(CommandError::ResultError(error)),
-			MpvResponseResult::Success { data, .. } => {
-				let data = command
-					.parse_data(data)
-					.map_err(CommandError::DataParseError)?;
+		command
+			.parse_data_for_version(data, self.server_version)
+			.map_err(CommandError::DataParseError)
+	}
 
-				Ok(data)
+	/// Runs `commands` pipelined, sending all of them before reading any result.
+	///
+	/// This takes a single round trip instead of one per command, and the commands are sent with a single write
+	/// when possible. The outer error is returned when the link fails, errors of individual commands are returned
+	/// in place of their results, in order.
+	pub fn run_commands_batched<C: MpvCommand>(
+		&mut self,
+		commands: &[C]
+	) -> Result<Vec<CommandResult<C>>, CommandError<C::Error>> {
+		let mut ids: Vec<NonZeroI64> = Vec::with_capacity(commands.len());
+		for command in commands {
+			let current_id = self.request_ids.allocate();
+			if let Err(err) = self.queue_command(command, current_id) {
+				// none of the commands were sent
+				self.command_buffer.clear();
+				for id in ids {
+					self.request_ids.release(id.get());
+				}
+				return Err(err.into())
 			}
+			ids.push(current_id);
+		}
+		let sent_at = self.stats.is_some().then(Instant::now);
+		if let Err(err) = self.flush_commands() {
+			// some of the commands might have been sent
+			self.abandon_request_ids(&ids);
+			return Err(err.into())
 		}
+
+		let mut results = Vec::with_capacity(commands.len());
+		for (index, (command, &current_id)) in commands.iter().zip(ids.iter()).enumerate() {
+			let result = match self.next_result_for(current_id) {
+				Ok(result) => result,
+				Err(err) => {
+					self.abandon_request_ids(&ids[index + 1 ..]);
+					return Err(err.into())
+				}
+			};
+			self.record_latency(sent_at);
+
+			results.push(match result.into_result() {
+				Err(err) => command
+					.parse_error(err.kind)
+					.map_err(CommandError::ResultError),
+				Ok(data) => command
+					.parse_data_for_version(data, self.server_version)
+					.map_err(CommandError::DataParseError)
+			});
+		}
+
+		Ok(results)
+	}
+
+	/// Disables all events and then enables only the events named in `event_names`.
+	///
+	/// Note that `property-change` is an event as well and must be listed to keep receiving property changes.
+	pub fn set_enabled_events<S: AsRef<str>>(
+		&mut self,
+		event_names: &[S]
+	) -> Result<(), CommandError<ParseError>> {
+		self.run_command(&CmdDisableEvent::all())?;
+		for name in event_names {
+			self.run_command(&CmdEnableEvent::new(Cow::Borrowed(name.as_ref())))?;
+		}
+
+		Ok(())
 	}
 
 	/// Runs a `MpvCommandRaw` and does not wait for the result.
+	///
+	/// The result is set aside when it arrives, so that it does not get mistaken for the result of another
+	/// command, and can be retrieved with `take_raw_result`.
 	pub fn run_command_raw<C: MpvCommandRaw + ?Sized, E: std::error::Error>(
 		&mut self,
 		command: &C
 	) -> Result<NonZeroI64, CommandError<E>> {
-		let current_id = {
-			let current = self.current_id;
-			self.current_id =
-				NonZeroI64::new(self.current_id.get().wrapping_add(1)).unwrap_or(Self::NONZERO_ONE);
-			current
-		};
+		let current_id = self.send_with_new_id(command)?;
+		if command.expects_result() {
+			self.raw_pending.insert(current_id.get());
+		} else {
+			self.request_ids.release(current_id.get());
+		}
+
+		Ok(current_id)
+	}
+
+	/// Takes the result of a command sent with `run_command_raw` if it was already received.
+	///
+	/// Results arrive while waiting for other results or polling events, use `poll_events` to check for new ones.
+	/// Only the last few results are kept, older ones are discarded.
+	pub fn take_raw_result(&mut self, request_id: NonZeroI64) -> Option<MpvResponseResult> {
+		let index = self
+			.raw_results
+			.iter()
+			.position(|result| result.request_id() == Some(request_id.get()))?;
 
+		self.raw_results.remove(index)
+	}
+
+	/// Whether the result of a command sent with `run_command_raw` has not been received yet.
+	pub fn is_raw_pending(&self, request_id: NonZeroI64) -> bool {
+		self.raw_pending.contains(&request_id.get())
+	}
+
+	/// Number of commands whose results were not received yet.
+	///
+	/// Results of commands sent with `run_command_raw` are counted until they are received, even if they are not
+	/// taken, and so are results of commands whose wait failed, for example because it timed out. A number which
+	/// keeps growing points to commands mpv never answered.
+	pub fn in_flight(&self) -> usize {
+		self.request_ids.in_flight()
+	}
+
+	/// Drains results which were not requested by this link, returning the iterator.
+	///
+	/// mpv answers commands sent without a request id with `request_id` 0, for example commands written
+	/// to the socket by other means. Such results are set aside instead of being mistaken for the result of
+	/// a command of this link. Only the last few results are kept, older ones are discarded.
+	pub fn drain_unsolicited_results(&mut self) -> impl Iterator<Item = MpvResponseResult> + '_ {
+		self.unsolicited_results.drain(..)
+	}
+
+	/// Whether a result with `request_id` is set aside by `stash_result` instead of being returned as the result
+	/// of a command.
+	fn is_stashed_request_id(&self, request_id: Option<i64>) -> bool {
+		match request_id {
+			None | Some(0) => true,
+			Some(request_id) => {
+				self.raw_pending.contains(&request_id) || self.abandoned.contains(&request_id)
+			}
+		}
+	}
+
+	/// Marks sent commands as no longer awaited, so that their results are dropped when they arrive.
+	fn abandon_request_ids(&mut self, request_ids: &[NonZeroI64]) {
+		self.abandoned
+			.extend(request_ids.iter().map(|request_id| request_id.get()));
+	}
+
+	fn send_with_new_id<C: MpvCommandRaw + ?Sized>(
+		&mut self,
+		command: &C
+	) -> Result<NonZeroI64, SendError> {
+		let current_id = self.request_ids.allocate();
 		self.send_command(command, current_id)?;
 
 		Ok(current_id)
 	}
 
+	fn release_request_id(&mut self, result: &MpvResponseResult) {
+		if let Some(request_id) = result.request_id() {
+			self.request_ids.release(request_id);
+		}
+	}
+
+	/// Sets `result` aside if it belongs to a raw command or has no request id, otherwise returns it back.
+	fn stash_result(&mut self, result: MpvResponseResult) -> Option<MpvResponseResult> {
+		match result.request_id() {
+			None | Some(0) => {
+				log::debug!("Set aside unsolicited result: {:?}", result);
+				if self.unsolicited_results.len() == Self::RAW_RESULT_CAPACITY {
+					self.unsolicited_results.pop_front();
+				}
+				self.unsolicited_results.push_back(result);
+
+				None
+			}
+			Some(request_id) if self.abandoned.remove(&request_id) => {
+				log::debug!("Dropping result of abandoned command: {:?}", result);
+
+				None
+			}
+			Some(request_id) if self.raw_pending.remove(&request_id) => {
+				log::trace!("Set aside raw result: {:?}", result);
+				if self.raw_results.len() == Self::RAW_RESULT_CAPACITY {
+					self.raw_results.pop_front();
+				}
+				self.raw_results.push_back(result);
+
+				None
+			}
+			_ => Some(result)
+		}
+	}
+
 	/// Polls for events which are added to the internal queue.
+	///
+	/// When the mpv child process exits `MpvResponseEvent::ProcessExited` is queued once. If reading fails
+	/// because of the exit, the error is only returned by the next call so that the event is seen first.
 	pub fn poll_events(&mut self) -> Result<(), ReceiveError> {
+		self.unobserve_unbound_logged();
+		let result = self.poll_responses();
+
+		if self.report_child_exit() {
+			// the socket is closed by the exit
+			if let Err(ReceiveError::Io(err)) = result {
+				log::debug!("Deferring read error after the child exited: {}", err);
+				return Ok(())
+			}
+		}
+
+		result
+	}
+
+	fn poll_responses(&mut self) -> Result<(), ReceiveError> {
 		loop {
 			match self.next_response()? {
 				None => break,
-				Some(MpvResponse::Event(event)) => {
-					log::trace!("Queued event: {:?}", event);
-					self.event_queue.push(event);
-				}
+				Some(MpvResponse::Event(event)) => self.push_event(event),
 				Some(MpvResponse::Result(result)) => {
-					return Err(ReceiveError::UnexpectedResponseResult(result))
+					self.release_request_id(&result);
+					if let Some(result) = self.stash_result(result) {
+						return Err(ReceiveError::UnexpectedResponseResult(result))
+					}
 				}
 			};
 		}
-		self.response_buffer.shift();
 
 		Ok(())
 	}
 
+	/// Polls for events like `poll_events` and returns only those received during this call.
+	///
+	/// The events stay in the internal queue as well.
+	pub fn poll_new_events(&mut self) -> Result<&[MpvResponseEvent], ReceiveError> {
+		let start = self.event_queue.len();
+		self.poll_events()?;
+
+		Ok(&self.event_queue[start ..])
+	}
+
+	/// Returns the internal queue of events without draining it.
+	pub fn peek_events(&self) -> &[MpvResponseEvent] {
+		&self.event_queue
+	}
+
+	/// Queues `MpvResponseEvent::ProcessExited` if the child exited and it was not queued yet.
+	///
+	/// Returns whether it was queued.
+	fn report_child_exit(&mut self) -> bool {
+		if self.child_exit_reported {
+			return false
+		}
+
+		match self.inner.child_status() {
+			Ok(ChildStatus::Exited(status)) => {
+				log::info!("mpv child exited: {}", status);
+				self.child_exit_reported = true;
+				self.event_queue
+					.push(MpvResponseEvent::ProcessExited { status });
+
+				true
+			}
+			Ok(_) => false,
+			Err(err) => {
+				log::warn!("Could not check the child process: {}", err);
+				false
+			}
+		}
+	}
+
+	/// Blocks until there is at least one event in the internal queue.
+	///
+	/// If timeout is not `None` then returns `Err(TimedOut)` on timeout.
+	pub fn wait_events(&mut self, timeout: Option<Duration>) -> Result<(), ReceiveError> {
+		loop {
+			self.poll_events()?;
+			if !self.event_queue.is_empty() {
+				return Ok(())
+			}
+
+			self.inner.wait_read(timeout)?;
+		}
+	}
+
+	/// Blocks until an event matching `predicate` is received and removes it from the internal queue.
+	///
+	/// Other events, including those received before, stay in the queue.
+	///
+	/// If timeout is not `None` then returns `Err(TimedOut)` once it elapses.
+	pub fn wait_for_event(
+		&mut self,
+		predicate: impl FnMut(&MpvResponseEvent) -> bool,
+		timeout: Option<Duration>
+	) -> Result<MpvResponseEvent, ReceiveError> {
+		self.wait_for_event_after(0, predicate, timeout)
+	}
+
+	/// Like `wait_for_event`, but skips the first `start` events of the internal queue.
+	///
+	/// Taking the length of `peek_events` before sending a command makes the wait ignore events queued before it,
+	/// such as events of a previously loaded file.
+	pub fn wait_for_event_after(
+		&mut self,
+		start: usize,
+		mut predicate: impl FnMut(&MpvResponseEvent) -> bool,
+		timeout: Option<Duration>
+	) -> Result<MpvResponseEvent, ReceiveError> {
+		let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+		let mut checked = start.min(self.event_queue.len());
+		loop {
+			self.poll_events()?;
+			if let Some(index) = self.event_queue[checked ..].iter().position(&mut predicate) {
+				return Ok(self.event_queue.remove(checked + index))
+			}
+			checked = self.event_queue.len();
+
+			let remaining = match deadline {
+				None => None,
+				Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+					Some(remaining) if !remaining.is_zero() => Some(remaining),
+					_ => return Err(ReceiveError::Io(io::ErrorKind::TimedOut.into()))
+				}
+			};
+			match self.inner.wait_read(remaining) {
+				// the deadline is checked above
+				Err(err) if err.kind() == io::ErrorKind::TimedOut => (),
+				result => result?
+			}
+		}
+	}
+
+	/// Blocks until reading becomes available on the underlying stream.
+	///
+	/// Responses which are already buffered are not taken into account, so this is meant to be called after
+	/// `poll_events` or `poll_events_with`.
+	///
+	/// If timeout is not `None` then returns `Err(TimedOut)` on timeout.
+	pub fn wait_read(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+		self.inner.wait_read(timeout)
+	}
+
+	/// Polls for events and passes them to `callback` borrowed from the response buffer.
+	///
+	/// Unlike `poll_events` the events are neither copied nor added to the internal queue.
+	/// Malformed lines are skipped with both lenient policies, as they have no borrowed representation.
+	pub fn poll_events_with(
+		&mut self,
+		mut callback: impl FnMut(MpvResponseEventRef<'_>)
+	) -> Result<(), ReceiveError> {
+		self.unobserve_unbound_logged();
+
+		// taken out as the events borrow the link, only when needed so that nothing is allocated otherwise
+		let mut bindings = (!self.bindings.is_empty()).then(|| std::mem::take(&mut self.bindings));
+		let result = self.poll_events_with_bindings(bindings.as_mut(), &mut callback);
+		if let Some(bindings) = bindings {
+			self.bindings = bindings;
+		}
+
+		result
+	}
+
+	fn poll_events_with_bindings(
+		&mut self,
+		mut bindings: Option<&mut binding::Bindings>,
+		mut callback: impl FnMut(MpvResponseEventRef<'_>)
+	) -> Result<(), ReceiveError> {
+		let policy = self.malformed_line_policy;
+		while let Some(line) = self.next_line()? {
+			let response = match MpvResponseRef::from_slice(line) {
+				Ok(response) => response,
+				Err(err) => {
+					let err = ReceiveError::deserialize(err, line);
+					if policy == MalformedLinePolicy::Error || is_result_line(line) {
+						return Err(err)
+					}
+
+					log::warn!("Skipping malformed line: {}", err);
+					continue
+				}
+			};
+			match response {
+				MpvResponseRef::Event(event) => {
+					if let Some(bindings) = bindings.as_deref_mut() {
+						bindings.dispatch_event_ref(&event);
+					}
+					callback(event);
+					if let Some(stats) = self.stats.as_mut() {
+						stats.events_received += 1;
+					}
+				}
+				MpvResponseRef::Result(result) => {
+					let result = result
+						.to_owned_result()
+						.map_err(|err| ReceiveError::deserialize(err, line))?;
+					self.release_request_id(&result);
+					if let Some(result) = self.stash_result(result) {
+						return Err(ReceiveError::UnexpectedResponseResult(result))
+					}
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Adds an event which did not come from mpv to the internal queue.
+	#[cfg(feature = "spawn")]
+	pub(crate) fn queue_event(&mut self, event: MpvResponseEvent) {
+		self.event_queue.push(event);
+	}
+
+	/// Calls the callback of a bound property or script binding and adds `event` to the internal queue.
+	fn push_event(&mut self, event: MpvResponseEvent) {
+		self.bindings.dispatch_event(&event);
+		log::trace!("Queued event: {:?}", event);
+		self.event_queue.push(event);
+	}
+
+	/// Unobserves the properties of dropped bindings while polling, where errors are only logged.
+	fn unobserve_unbound_logged(&mut self) {
+		if let Err(err) = self.unobserve_unbound() {
+			log::warn!("Could not unobserve unbound property: {}", err);
+		}
+	}
+
+	/// Keeps only the events in the internal queue for which `predicate` returns `true`.
+	pub fn retain_events(&mut self, predicate: impl FnMut(&MpvResponseEvent) -> bool) {
+		self.event_queue.retain(predicate);
+	}
+
 	/// Drains the internal queue of events, returning the iterator.
 	pub fn drain_events(&mut self) -> impl Iterator<Item = MpvResponseEvent> + '_ {
 		self.event_queue.drain(..)
 	}
 
+	fn record_latency(&mut self, sent_at: Option<Instant>) {
+		if let (Some(stats), Some(sent_at)) = (self.stats.as_mut(), sent_at) {
+			stats.record_latency(sent_at.elapsed());
+		}
+	}
+
 	fn send_command<C: MpvCommandRaw + ?Sized>(
 		&mut self,
 		command: &C,
 		current_id: NonZeroI64
 	) -> Result<(), SendError> {
-		if log::log_enabled!(log::Level::Debug) {
-			let mut buffer = Vec::new();
-			command.write(&mut buffer, Some(current_id))?;
+		self.queue_command(command, current_id)?;
+
+		self.flush_commands()
+	}
+
+	/// Serializes `command` as a line appended to the command buffer, which is sent by `flush_commands`.
+	///
+	/// The buffer is left unchanged on error.
+	fn queue_command<C: MpvCommandRaw + ?Sized>(
+		&mut self,
+		command: &C,
+		current_id: NonZeroI64
+	) -> Result<(), SendError> {
+		let start = self.command_buffer.len();
+		let result = command.write_for_version(
+			&mut self.command_buffer,
+			Some(current_id),
+			self.server_version
+		);
+		let line = &self.command_buffer[start ..];
 
-			match std::str::from_utf8(&buffer) {
+		let result = match result {
+			Err(err) => Err(err.into()),
+			// the line delimiter would split the command into two
+			Ok(()) if line.contains(&b'\n') => Err(SendError::EmbeddedNewline),
+			Ok(()) => Ok(())
+		};
+		if result.is_err() {
+			self.command_buffer.truncate(start);
+			// the command was not sent, so its id is free again
+			self.request_ids.release(current_id.get());
+			return result
+		}
+
+		if log::log_enabled!(log::Level::Debug) {
+			match std::str::from_utf8(line) {
 				Ok(command) => log::debug!("Sending command: {}", command),
-				Err(_) => log::debug!("Sending command: {:?}", buffer)
+				Err(_) => log::debug!("Sending command: {:?}", line)
 			};
 		}
+		self.command_buffer.push(b'\n');
+
+		Ok(())
+	}
 
-		let mut stream = self.inner.stream();
-		command.write(&mut stream, Some(current_id))?;
-		writeln!(stream)?;
+	/// Writes all queued commands in as few writes as possible.
+	///
+	/// The socket is nonblocking, so the write is retried after short writes and waits until the socket
+	/// is writable when it would block. The buffer is cleared even on error, since it is not known which part
+	/// of it was received.
+	fn flush_commands(&mut self) -> Result<(), SendError> {
+		// traced before writing, so that a command whose write blocks forever is traced as well
+		if let (Some(trace), Some((b'\n', commands))) =
+			(self.trace.as_mut(), self.command_buffer.split_last())
+		{
+			for line in commands.split(|&b| b == b'\n') {
+				trace.push(trace::TraceDirection::Sent, line);
+			}
+		}
+		let result = self.write_command_buffer();
+		if let (Ok(()), Some((b'\n', commands))) = (&result, self.command_buffer.split_last()) {
+			// every command is a single line
+			for line in commands.split(|&b| b == b'\n') {
+				if let Some(tap) = self.command_tap.as_mut() {
+					tap(line);
+				}
+				if let Some(stats) = self.stats.as_mut() {
+					stats.commands_sent += 1;
+					stats.bytes_tx += line.len() as u64 + 1;
+				}
+			}
+		}
+		self.command_buffer.clear();
+
+		result
+	}
+
+	fn write_command_buffer(&mut self) -> Result<(), SendError> {
+		let mut written = 0;
+		while written < self.command_buffer.len() {
+			let result = self.inner.stream().write(&self.command_buffer[written ..]);
+
+			match result {
+				Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero).into()),
+				Ok(count) => written += count,
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+					self.inner.wait_write(None)?
+				}
+				Err(err) => return Err(err.into())
+			}
+		}
 
 		Ok(())
 	}
 
-	fn next_result<Data: DeserializeOwned>(
-		&mut self
-	) -> Result<MpvResponseResult<Data>, ReceiveError> {
+	/// Waits for the result of the command with `request_id`.
+	///
+	/// The command is abandoned if waiting fails, since its result might still arrive later.
+	fn next_result_for(
+		&mut self,
+		request_id: NonZeroI64
+	) -> Result<MpvResponseResult, ReceiveError> {
+		let result = match self.next_result() {
+			Ok(result) => result,
+			Err(err) => {
+				self.abandon_request_ids(&[request_id]);
+				return Err(err)
+			}
+		};
+
+		match result.request_id() {
+			Some(found) if found == request_id.get() => Ok(result),
+			found => {
+				self.abandon_request_ids(&[request_id]);
+				Err(ReceiveError::RequestIdMismatch {
+					expected: request_id.get(),
+					found: found.unwrap_or(0)
+				})
+			}
+		}
+	}
+
+	/// Waits for the next result which does not belong to a raw or abandoned command.
+	fn next_result(&mut self) -> Result<MpvResponseResult, ReceiveError> {
 		log::trace!("Waiting for next result");
 		let result = loop {
-			match self.next_response()? {
+			match self.next_response::<serde_json::Value>()? {
 				// TODO: Handle deadlock from issuing a non-result command through non-raw interface throuw timeout?
-				None => self.inner.wait_read(None)?,
-				Some(MpvResponse::Event(event)) => {
-					log::trace!("Queued event: {:?}", event);
-					self.event_queue.push(event);
+				None if self.read_timeout.is_none() => self.inner.wait_read(None)?,
+				// the read already waited for the timeout
+				None => return Err(ReceiveError::Io(io::ErrorKind::TimedOut.into())),
+				Some(MpvResponse::Event(event)) => self.push_event(event),
+				Some(MpvResponse::Result(result)) => {
+					self.release_request_id(&result);
+					if !self.is_stashed_request_id(result.request_id()) {
+						break result
+					}
+					self.stash_result(result);
 				}
-				Some(MpvResponse::Result(result))  => break result
 			};
 		};
-		self.response_buffer.shift();
+		if let Some(stats) = self.stats.as_mut() {
+			stats.results_received += 1;
+		}
 
 		Ok(result)
 	}
@@ -239,19 +1404,237 @@ impl MpvLink {
 		&mut self
 	) -> Result<Option<MpvResponse<ResponseData>>, ReceiveError> {
 		log::trace!("Waiting for next response");
-		let line = match self.response_buffer.consume_line() {
-			Some(line) => line,
-			None => {
-				self.response_buffer.read_nonblocking(self.inner.stream())?;
-				match self.response_buffer.consume_line() {
-					Some(line) => line,
-					None => return Ok(None)
+		let policy = self.malformed_line_policy;
+
+		loop {
+			let line = match self.next_line()? {
+				Some(line) => line,
+				None => return Ok(None)
+			};
+
+			let err = match serde_json::from_slice(line) {
+				Ok(response) => {
+					if let (Some(stats), MpvResponse::Event(_)) = (self.stats.as_mut(), &response) {
+						stats.events_received += 1;
+					}
+					return Ok(Some(response))
 				}
+				Err(err) => ReceiveError::deserialize(err, line)
+			};
+
+			// a result which fails to deserialize is still awaited by someone
+			if is_result_line(line) {
+				return Err(err)
 			}
-		};
 
-		let response: MpvResponse<ResponseData> = serde_json::from_slice(line)?;
+			match (policy, err) {
+				(MalformedLinePolicy::Error, err) => return Err(err),
+				(MalformedLinePolicy::Skip, err) => {
+					log::warn!("Skipping malformed line: {}", err);
+				}
+				(MalformedLinePolicy::Emit, ReceiveError::Deserialize { source, raw }) => {
+					log::warn!("Received malformed line {}: {}", raw, source);
+					return Ok(Some(MpvResponse::Event(MpvResponseEvent::Malformed {
+						raw
+					})))
+				}
+				(MalformedLinePolicy::Emit, err) => return Err(err)
+			}
+		}
+	}
+
+	/// Returns the next line from the response buffer, reading from the stream if there isn't one buffered.
+	fn next_line(&mut self) -> Result<Option<&[u8]>, ReceiveError> {
+		if !self.response_buffer.has_line() {
+			let read = match self.read_timeout {
+				None => self.response_buffer.read_nonblocking(self.inner.stream())?,
+				Some(_) => self.response_buffer.read_blocking(self.inner.stream())?
+			};
+			if let Some(stats) = self.stats.as_mut() {
+				stats.bytes_rx += read as u64;
+			}
+		}
+
+		// drops lines which are too long before reporting them
+		self.response_buffer.has_line();
+		if self.response_buffer.take_dropped_lines() > 0 {
+			return Err(ReceiveError::LineTooLong {
+				max: self.response_buffer.max_line_length().unwrap_or_default()
+			})
+		}
+
+		let line = self.response_buffer.consume_line();
+		if let (Some(tap), Some(line)) = (self.response_tap.as_mut(), line) {
+			tap(line);
+		}
+		if let (Some(trace), Some(line)) = (self.trace.as_mut(), line) {
+			trace.push(trace::TraceDirection::Received, line);
+		}
+
+		Ok(line)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{
+		io::{self, Write},
+		os::unix::net::UnixListener,
+		path::Path,
+		time::Duration
+	};
+
+	use super::{
+		fake_mpv,
+		is_result_line,
+		CommandError,
+		HandshakeError,
+		InnerLink,
+		MalformedLinePolicy,
+		MpvLink,
+		MpvLinkInitError,
+		ReceiveError,
+		SocketAddress
+	};
+	use crate::command::{commands::CmdGetProperty, property, ParseError};
+
+	#[test]
+	fn test_handshake_timeout() {
+		let path = std::env::temp_dir().join(format!("mpvsock-silent-{}.sock", std::process::id()));
+		let _ = std::fs::remove_file(&path);
+		// accepts connections into the backlog, but never answers
+		let _listener = UnixListener::bind(&path).unwrap();
+
+		let inner = InnerLink::connect(&path).unwrap();
+		let result = MpvLink::with_handshake_timeout(inner, Duration::from_millis(50));
+		std::fs::remove_file(&path).unwrap();
+
+		match result {
+			Err(MpvLinkInitError::Handshake(HandshakeError::Version(
+				CommandError::ReceiveError(ReceiveError::Io(err))
+			))) => assert_eq!(err.kind(), io::ErrorKind::TimedOut),
+			result => panic!(
+				"Expected a timed out handshake but found {:?}",
+				result.err()
+			)
+		}
+	}
+
+	#[test]
+	fn test_poll_events_with_releases_raw_result() {
+		let path = std::env::temp_dir().join(format!("mpvsock-raw-{}.sock", std::process::id()));
+		let server = fake_mpv::listen(&path, |requests, socket| {
+			fake_mpv::answer(requests, socket, &["50.5"]);
+		});
+
+		let mut link = MpvLink::connect(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		let request_id = link
+			.run_command_raw::<_, ParseError>(&CmdGetProperty::new(property::Volume))
+			.unwrap();
+		assert_eq!(link.in_flight(), 1);
+
+		let _socket = server.join().unwrap();
+		link.poll_events_with(|_| ()).unwrap();
+		assert_eq!(link.in_flight(), 0);
+		assert!(link.take_raw_result(request_id).is_some());
+	}
+
+	#[test]
+	fn test_poll_events_with_skips_malformed_line() {
+		let path = std::env::temp_dir().join(format!("mpvsock-skip-{}.sock", std::process::id()));
+		let server = fake_mpv::listen(&path, |requests, socket| {
+			let line = requests.next().unwrap().unwrap();
+			let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+			// neither an event nor a result, even though it carries the request id
+			writeln!(
+				&mut *socket,
+				"{{\"data\":\"garbage\",\"request_id\":{}}}",
+				request["request_id"]
+			)
+			.unwrap();
+			writeln!(socket, "not json").unwrap();
+			fake_mpv::write_result(socket, &line, "50.5");
+		});
+
+		let mut link = MpvLink::connect(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		link.set_malformed_line_policy(MalformedLinePolicy::Skip);
+		let request_id = link
+			.run_command_raw::<_, ParseError>(&CmdGetProperty::new(property::Volume))
+			.unwrap();
+
+		let _socket = server.join().unwrap();
+		link.poll_events_with(|_| ()).unwrap();
+		let result = link.take_raw_result(request_id).unwrap();
+		assert_eq!(result.into_result().unwrap(), 50.5);
+		assert_eq!(link.drain_unsolicited_results().count(), 0);
+	}
+
+	#[test]
+	fn test_timed_out_result_is_dropped() {
+		let path = std::env::temp_dir().join(format!("mpvsock-late-{}.sock", std::process::id()));
+		// answers the first command only once the second one arrived
+		let server = fake_mpv::listen(&path, |requests, socket| {
+			let late = requests.next().unwrap().unwrap();
+			let next = requests.next().unwrap().unwrap();
+			fake_mpv::write_result(&mut *socket, &late, "1.0");
+			fake_mpv::write_result(socket, &next, "2.0");
+		});
+
+		let mut link = MpvLink::connect(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		link.set_read_timeout(Some(Duration::from_millis(50)))
+			.unwrap();
+
+		match link.run_command(&CmdGetProperty::new(property::Volume)) {
+			Err(CommandError::ReceiveError(ReceiveError::Io(err))) => {
+				assert_eq!(err.kind(), io::ErrorKind::TimedOut)
+			}
+			result => panic!("Expected a timed out command but found {:?}", result)
+		}
+		assert_eq!(link.in_flight(), 1);
+
+		let volume = link
+			.run_command(&CmdGetProperty::new(property::Volume))
+			.unwrap();
+		assert_eq!(volume, 2.0);
+		assert_eq!(link.in_flight(), 0);
+		let _socket = server.join().unwrap();
+	}
+
+	#[test]
+	fn test_deserialize_error_keeps_line() {
+		let line = b"{\"event\": \"seek\", \xff";
+		let source = serde_json::from_slice::<serde_json::Value>(line).unwrap_err();
+
+		match ReceiveError::deserialize(source, line) {
+			ReceiveError::Deserialize { raw, .. } => {
+				assert_eq!(raw, "{\"event\": \"seek\", \u{fffd}")
+			}
+			err => panic!("Expected ReceiveError::Deserialize but found {:?}", err)
+		}
+	}
+
+	#[test]
+	fn test_is_result_line() {
+		assert!(is_result_line(
+			b"{\"error\":\"success\",\"data\":{\"a\":[]}}"
+		));
+		assert!(!is_result_line(b"{\"event\":\"seek\",\"data\":1}"));
+		assert!(!is_result_line(b"{\"event\":"));
+	}
 
-		Ok(Some(response))
+	#[test]
+	fn test_parse_socket_address() {
+		assert_eq!(
+			SocketAddress::parse("/tmp/mpv.sock"),
+			SocketAddress::Path(Path::new("/tmp/mpv.sock"))
+		);
+		#[cfg(any(target_os = "linux", target_os = "android"))]
+		assert_eq!(
+			SocketAddress::parse("@mpv"),
+			SocketAddress::Abstract(b"mpv")
+		);
 	}
 }
@@ -0,0 +1,104 @@
+use std::{collections::HashSet, num::NonZeroI64};
+
+/// Allocates request ids for commands and tracks the ids whose results were not received yet.
+///
+/// Ids count up from `1` and wrap around to `1` after `i64::MAX`. An id is skipped while it is still in
+/// flight, so that a late result of an old command is never mistaken for the result of a new one.
+#[derive(Debug, Clone)]
+pub struct RequestIdAllocator {
+	next: NonZeroI64,
+	watermark: Option<NonZeroI64>,
+	in_flight: HashSet<i64>
+}
+impl RequestIdAllocator {
+	const NONZERO_ONE: NonZeroI64 = NonZeroI64::new(1).unwrap();
+
+	pub fn new() -> Self {
+		RequestIdAllocator {
+			next: Self::NONZERO_ONE,
+			watermark: None,
+			in_flight: HashSet::new()
+		}
+	}
+
+	/// Returns the next id which is not in flight and marks it as in flight.
+	pub fn allocate(&mut self) -> NonZeroI64 {
+		loop {
+			let id = self.next;
+			self.next = NonZeroI64::new(id.get().wrapping_add(1))
+				.filter(|next| next.get() > 0)
+				.unwrap_or(Self::NONZERO_ONE);
+
+			if self.in_flight.insert(id.get()) {
+				self.watermark = Some(id);
+				return id
+			}
+			log::warn!("Skipping request id {} which is still in flight", id);
+		}
+	}
+
+	/// Marks `id` as no longer in flight, returns whether it was.
+	pub fn release(&mut self, id: i64) -> bool {
+		self.in_flight.remove(&id)
+	}
+
+	pub fn is_in_flight(&self, id: i64) -> bool {
+		self.in_flight.contains(&id)
+	}
+
+	/// Number of ids whose results were not received yet.
+	///
+	/// Ids of commands which failed while being written stay in flight, since mpv may have received them.
+	pub fn in_flight(&self) -> usize {
+		self.in_flight.len()
+	}
+
+	/// Last allocated id, `None` before the first one.
+	pub fn watermark(&self) -> Option<NonZeroI64> {
+		self.watermark
+	}
+}
+impl Default for RequestIdAllocator {
+	fn default() -> Self {
+		RequestIdAllocator::new()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::num::NonZeroI64;
+
+	use super::RequestIdAllocator;
+
+	#[test]
+	fn test_allocate_and_release() {
+		let mut ids = RequestIdAllocator::new();
+		assert_eq!(ids.watermark(), None);
+
+		assert_eq!(ids.allocate().get(), 1);
+		assert_eq!(ids.allocate().get(), 2);
+		assert_eq!(ids.in_flight(), 2);
+		assert_eq!(ids.watermark().map(NonZeroI64::get), Some(2));
+
+		assert!(ids.release(1));
+		assert!(!ids.release(1));
+		assert!(ids.is_in_flight(2));
+		assert_eq!(ids.in_flight(), 1);
+	}
+
+	#[test]
+	fn test_wraparound_skips_in_flight() {
+		let mut ids = RequestIdAllocator::new();
+		let first = ids.allocate();
+		assert_eq!(first.get(), 1);
+
+		ids.next = NonZeroI64::new(i64::MAX).unwrap();
+		assert_eq!(ids.allocate().get(), i64::MAX);
+		// wraps to positive ids only and skips 1, whose result is still awaited
+		assert_eq!(ids.allocate().get(), 2);
+
+		ids.release(first.get());
+		ids.next = first;
+		assert_eq!(ids.allocate(), first);
+	}
+}
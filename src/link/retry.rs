@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use super::CommandError;
+use crate::command::{response::MpvResponseResultError, ParseError};
+
+/// When and how often `with_retry` runs an operation again after it failed.
+///
+/// The delay before each retry grows by `backoff` times, up to `max_delay`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy<E: std::error::Error = ParseError> {
+	attempts: u32,
+	delay: Duration,
+	backoff: f64,
+	max_delay: Duration,
+	should_retry: fn(&CommandError<E>) -> bool
+}
+impl<E: std::error::Error> RetryPolicy<E> {
+	/// Creates a policy retrying errors for which `should_retry` returns `true`, three attempts in total
+	/// with a delay starting at 50 milliseconds.
+	pub fn new(should_retry: fn(&CommandError<E>) -> bool) -> Self {
+		RetryPolicy {
+			attempts: 3,
+			delay: Duration::from_millis(50),
+			backoff: 2.0,
+			max_delay: Duration::from_secs(1),
+			should_retry
+		}
+	}
+
+	/// Retries errors which `CommandError::is_retryable` considers transient, such as timeouts.
+	pub fn transient() -> Self {
+		RetryPolicy::new(CommandError::is_retryable)
+	}
+
+	/// Retries `property unavailable` for up to about seven and a half seconds in total, which is longer than
+	/// properties such as `duration` usually take to become available while a file loads.
+	pub fn during_load() -> Self {
+		RetryPolicy::new(|err| {
+			err.mpv_error() == Some(&MpvResponseResultError::PropertyUnavailable)
+		})
+		.with_attempts(20)
+		.with_backoff(1.5)
+		.with_max_delay(Duration::from_millis(500))
+	}
+
+	/// Sets how many times the operation runs at most, including the first attempt.
+	pub fn with_attempts(mut self, attempts: u32) -> Self {
+		self.attempts = attempts.max(1);
+		self
+	}
+
+	/// Sets the delay before the first retry.
+	pub fn with_delay(mut self, delay: Duration) -> Self {
+		self.delay = delay;
+		self
+	}
+
+	/// Sets the factor the delay is multiplied by after each retry, `1.0` keeps it constant.
+	pub fn with_backoff(mut self, backoff: f64) -> Self {
+		self.backoff = backoff;
+		self
+	}
+
+	pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+		self.max_delay = max_delay;
+		self
+	}
+
+	pub fn attempts(&self) -> u32 {
+		self.attempts
+	}
+
+	pub fn should_retry(&self, err: &CommandError<E>) -> bool {
+		(self.should_retry)(err)
+	}
+
+	/// Multiplies `delay` by the backoff, saturating at `max_delay` instead of overflowing.
+	fn next_delay(&self, delay: Duration) -> Duration {
+		Duration::try_from_secs_f64(delay.as_secs_f64() * self.backoff.max(0.0))
+			.unwrap_or(self.max_delay)
+			.min(self.max_delay)
+	}
+}
+
+/// Runs `operation` until it succeeds, fails with an error `policy` does not retry or runs out of attempts.
+///
+/// Returns the last error. The thread sleeps between attempts. For example reading `duration` right after
+/// `loadfile` is `with_retry(&RetryPolicy::during_load(), || link.run_command(&command))`.
+pub fn with_retry<T, E: std::error::Error>(
+	policy: &RetryPolicy<E>,
+	mut operation: impl FnMut() -> Result<T, CommandError<E>>
+) -> Result<T, CommandError<E>> {
+	let mut delay = policy.delay;
+	let mut attempt = 1;
+	loop {
+		match operation() {
+			Ok(value) => return Ok(value),
+			Err(err) if attempt < policy.attempts && policy.should_retry(&err) => {
+				log::debug!(
+					"Attempt {} of {} failed, retrying in {:?}: {}",
+					attempt,
+					policy.attempts,
+					delay,
+					err
+				);
+			}
+			Err(err) => return Err(err)
+		}
+
+		std::thread::sleep(delay);
+		delay = policy.next_delay(delay);
+		attempt += 1;
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use super::{with_retry, RetryPolicy};
+	use crate::{command::response::MpvResponseResultError, link::CommandError};
+
+	#[test]
+	fn test_with_retry() {
+		let policy: RetryPolicy = RetryPolicy::during_load()
+			.with_attempts(3)
+			.with_delay(Duration::ZERO);
+
+		let mut calls = 0;
+		let result = with_retry(&policy, || {
+			calls += 1;
+			match calls {
+				3 => Ok(calls),
+				_ => Err(CommandError::ResultError(
+					MpvResponseResultError::PropertyUnavailable
+				))
+			}
+		});
+		assert_eq!(result.unwrap(), 3);
+
+		// errors the policy does not retry are returned right away
+		let mut calls = 0;
+		let result: Result<(), _> = with_retry(&policy, || {
+			calls += 1;
+			Err(CommandError::ResultError(
+				MpvResponseResultError::PropertyNotFound
+			))
+		});
+		assert!(result.is_err());
+		assert_eq!(calls, 1);
+
+		// the last error is returned once the attempts run out
+		let mut calls = 0;
+		let result: Result<(), _> = with_retry(&policy, || {
+			calls += 1;
+			Err(CommandError::ResultError(
+				MpvResponseResultError::PropertyUnavailable
+			))
+		});
+		assert!(result.is_err());
+		assert_eq!(calls, 3);
+	}
+
+	#[test]
+	fn test_backoff() {
+		let policy: RetryPolicy = RetryPolicy::transient()
+			.with_delay(Duration::from_millis(100))
+			.with_backoff(3.0)
+			.with_max_delay(Duration::from_millis(500));
+
+		assert_eq!(
+			policy.next_delay(Duration::from_millis(100)),
+			Duration::from_millis(300)
+		);
+		assert_eq!(
+			policy.next_delay(Duration::from_millis(300)),
+			Duration::from_millis(500)
+		);
+
+		let policy = policy.with_backoff(f64::INFINITY);
+		assert_eq!(
+			policy.next_delay(Duration::from_millis(100)),
+			Duration::from_millis(500)
+		);
+		let policy = policy.with_backoff(2.0).with_max_delay(Duration::MAX);
+		assert_eq!(policy.next_delay(Duration::MAX), Duration::MAX);
+	}
+}
@@ -0,0 +1,263 @@
+use std::{
+	ffi::{OsStr, OsString},
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+	sync::atomic::{AtomicU32, Ordering}
+};
+
+use super::{InnerLink, MpvLink, MpvLinkInitError};
+
+/// What the stdout or stderr of a spawned mpv is connected to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpawnStdio {
+	#[default]
+	Null,
+	/// Shares the stream of this process, for example to show the mpv status line in the terminal.
+	Inherit,
+	/// Creates a pipe, the reading end is taken from the link with `MpvLink::take_child_stdout`
+	/// or `MpvLink::take_child_stderr`.
+	Piped
+}
+impl SpawnStdio {
+	fn to_stdio(self) -> Stdio {
+		match self {
+			SpawnStdio::Null => Stdio::null(),
+			SpawnStdio::Inherit => Stdio::inherit(),
+			SpawnStdio::Piped => Stdio::piped()
+		}
+	}
+}
+
+/// Builder for spawning a child mpv process.
+///
+/// The process is always started with `--idle` and the option passing it the socket, additional arguments
+/// follow them so that they can end with `--` and a list of files. `--no-terminal` is passed as well unless
+/// stdout or stderr is connected to something.
+#[derive(Debug, Clone)]
+pub struct MpvSpawnBuilder {
+	program: OsString,
+	/// Command running mpv in place of `program`, such as `flatpak run io.mpv.Mpv`.
+	launcher: Vec<OsString>,
+	args: Vec<OsString>,
+	stdout: SpawnStdio,
+	stderr: SpawnStdio,
+	/// Permissions of the socket created by `spawn_server`.
+	pub(crate) socket_mode: Option<u32>,
+	/// User and group owning the socket created by `spawn_server`.
+	pub(crate) socket_owner: (Option<u32>, Option<u32>)
+}
+impl MpvSpawnBuilder {
+	/// Launcher running mpv installed from Flathub.
+	pub const FLATPAK_LAUNCHER: &'static [&'static str] = &["flatpak", "run", "io.mpv.Mpv"];
+	/// Launcher running mpv installed as a snap.
+	pub const SNAP_LAUNCHER: &'static [&'static str] = &["snap", "run", "mpv"];
+
+	pub fn new() -> Self {
+		MpvSpawnBuilder {
+			program: "mpv".into(),
+			launcher: Vec::new(),
+			args: Vec::new(),
+			stdout: SpawnStdio::Null,
+			stderr: SpawnStdio::Null,
+			socket_mode: None,
+			socket_owner: (None, None)
+		}
+	}
+
+	/// Sets the executable to spawn, `mpv` found in `PATH` by default.
+	pub fn program(mut self, program: impl AsRef<OsStr>) -> Self {
+		self.program = program.as_ref().to_owned();
+		self
+	}
+
+	/// Sets a command which runs mpv, used instead of `program` when not empty.
+	///
+	/// The mpv arguments are appended to it, for example `FLATPAK_LAUNCHER` spawns
+	/// `flatpak run io.mpv.Mpv --idle ...`. Sandboxed mpv only sees the paths shared with the sandbox,
+	/// so the socket path of `spawn_server` has to be one of them, and `spawn_client` needs the launcher
+	/// to pass the socket to mpv.
+	pub fn launcher<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(mut self, launcher: I) -> Self {
+		self.launcher = launcher
+			.into_iter()
+			.map(|arg| arg.as_ref().to_owned())
+			.collect();
+		self
+	}
+
+	/// Appends an argument passed to mpv, such as `--no-video` or a file to play.
+	pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+		self.args.push(arg.as_ref().to_owned());
+		self
+	}
+
+	/// Appends arguments passed to mpv.
+	pub fn args<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(mut self, args: I) -> Self {
+		self.args
+			.extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+		self
+	}
+
+	/// Connects the stdout of mpv, which is discarded by default.
+	pub fn stdout(mut self, stdout: SpawnStdio) -> Self {
+		self.stdout = stdout;
+		self
+	}
+
+	/// Connects the stderr of mpv, where it writes the terminal status line, which is discarded by default.
+	pub fn stderr(mut self, stderr: SpawnStdio) -> Self {
+		self.stderr = stderr;
+		self
+	}
+
+	/// Sets the permissions of the socket created by `spawn_server`, such as `0o600`.
+	///
+	/// mpv creates the socket according to its umask, the permissions are changed once the socket exists,
+	/// so it should be placed in a directory which only the intended users can access, see `spawn_server_auto`.
+	#[cfg(unix)]
+	pub fn socket_mode(mut self, mode: u32) -> Self {
+		self.socket_mode = Some(mode);
+		self
+	}
+
+	/// Changes the owning user and group of the socket created by `spawn_server`, `None` keeps the current one.
+	///
+	/// Changing the user requires privileges, changing the group requires being a member of it.
+	#[cfg(unix)]
+	pub fn socket_owner(mut self, uid: Option<u32>, gid: Option<u32>) -> Self {
+		self.socket_owner = (uid, gid);
+		self
+	}
+
+	/// Spawns mpv acting as a client of a socket pair, see `MpvLink::spawn_client`.
+	#[cfg(unix)]
+	pub fn spawn_client(&self) -> Result<MpvLink, MpvLinkInitError> {
+		let inner = InnerLink::spawn_client(self)?;
+
+		MpvLink::new(inner)
+	}
+
+	/// Spawns mpv acting as a server creating a socket at `socket_path`, see `MpvLink::spawn_server`.
+	pub fn spawn_server(&self, socket_path: &Path) -> Result<MpvLink, MpvLinkInitError> {
+		let inner = InnerLink::spawn_server(socket_path, self)?;
+
+		MpvLink::new(inner)
+	}
+
+	/// Spawns mpv acting as a server creating a socket with a unique name in `XDG_RUNTIME_DIR`.
+	///
+	/// The runtime directory is only accessible by its user, unlike the shared temporary directory.
+	/// Returns the link together with the path of the socket.
+	#[cfg(unix)]
+	pub fn spawn_server_auto(&self) -> Result<(MpvLink, PathBuf), MpvLinkInitError> {
+		let socket_path = runtime_socket_path()?;
+		let link = self.spawn_server(&socket_path)?;
+
+		Ok((link, socket_path))
+	}
+
+	/// Creates the command, passing `socket_arg` before the additional arguments.
+	pub(crate) fn command(&self, socket_arg: impl AsRef<OsStr>) -> Command {
+		let mut command = match self.launcher.split_first() {
+			None => Command::new(&self.program),
+			Some((launcher, launcher_args)) => {
+				let mut command = Command::new(launcher);
+				command.args(launcher_args);
+				command
+			}
+		};
+		command.arg("--idle");
+		// mpv writes nothing to the terminal with it
+		if self.stdout == SpawnStdio::Null && self.stderr == SpawnStdio::Null {
+			command.arg("--no-terminal");
+		}
+		command
+			.arg(socket_arg)
+			.args(&self.args)
+			.stdin(Stdio::null())
+			.stdout(self.stdout.to_stdio())
+			.stderr(self.stderr.to_stdio());
+
+		command
+	}
+}
+impl Default for MpvSpawnBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Generates a socket path unique within this process in `XDG_RUNTIME_DIR`.
+#[cfg(unix)]
+fn runtime_socket_path() -> Result<PathBuf, MpvLinkInitError> {
+	static SOCKET_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+	let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+		.filter(|dir| !dir.is_empty())
+		.ok_or(MpvLinkInitError::NoRuntimeDir)?;
+
+	Ok(PathBuf::from(runtime_dir).join(format!(
+		"mpvsock-{}-{}.sock",
+		std::process::id(),
+		SOCKET_COUNTER.fetch_add(1, Ordering::Relaxed)
+	)))
+}
+
+#[cfg(test)]
+mod test {
+	use super::{MpvSpawnBuilder, SpawnStdio};
+
+	#[test]
+	fn test_command_args() {
+		let command = MpvSpawnBuilder::new()
+			.program("/usr/local/bin/mpv")
+			.arg("--no-video")
+			.args(["--", "file.mkv"])
+			.command("--input-ipc-server=/tmp/socket");
+
+		assert_eq!(command.get_program(), "/usr/local/bin/mpv");
+		assert_eq!(
+			command.get_args().collect::<Vec<_>>(),
+			[
+				"--idle",
+				"--no-terminal",
+				"--input-ipc-server=/tmp/socket",
+				"--no-video",
+				"--",
+				"file.mkv"
+			]
+		);
+	}
+
+	#[test]
+	fn test_terminal_output_args() {
+		let command = MpvSpawnBuilder::new()
+			.stderr(SpawnStdio::Inherit)
+			.command("--input-ipc-server=/tmp/socket");
+
+		assert_eq!(
+			command.get_args().collect::<Vec<_>>(),
+			["--idle", "--input-ipc-server=/tmp/socket"]
+		);
+	}
+
+	#[test]
+	fn test_launcher_args() {
+		let command = MpvSpawnBuilder::new()
+			.launcher(MpvSpawnBuilder::FLATPAK_LAUNCHER)
+			.arg("file.mkv")
+			.command("--input-ipc-server=/tmp/socket");
+
+		assert_eq!(command.get_program(), "flatpak");
+		assert_eq!(
+			command.get_args().collect::<Vec<_>>(),
+			[
+				"run",
+				"io.mpv.Mpv",
+				"--idle",
+				"--no-terminal",
+				"--input-ipc-server=/tmp/socket",
+				"file.mkv"
+			]
+		);
+	}
+}
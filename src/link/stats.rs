@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+/// Counters and command latencies of a link, see `MpvLink::set_stats_enabled`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkStats {
+	pub commands_sent: u64,
+	pub results_received: u64,
+	pub events_received: u64,
+	/// Bytes written to the stream, including line delimiters.
+	pub bytes_tx: u64,
+	/// Bytes read from the stream.
+	pub bytes_rx: u64,
+	/// Round-trip latency of the last command whose result was awaited.
+	pub last_latency: Option<Duration>,
+	total_latency: Duration,
+	latency_samples: u64
+}
+impl LinkStats {
+	/// Average round-trip latency of commands whose result was awaited.
+	pub fn avg_latency(&self) -> Option<Duration> {
+		if self.latency_samples == 0 {
+			return None
+		}
+
+		let average = self.total_latency.as_nanos() / u128::from(self.latency_samples);
+		Some(Duration::from_nanos(
+			u64::try_from(average).unwrap_or(u64::MAX)
+		))
+	}
+
+	pub(crate) fn record_latency(&mut self, latency: Duration) {
+		self.last_latency = Some(latency);
+		self.total_latency += latency;
+		self.latency_samples += 1;
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use super::LinkStats;
+
+	#[test]
+	fn test_avg_latency() {
+		let mut stats = LinkStats::default();
+		assert_eq!(stats.avg_latency(), None);
+
+		stats.record_latency(Duration::from_millis(1));
+		stats.record_latency(Duration::from_millis(3));
+
+		assert_eq!(stats.last_latency, Some(Duration::from_millis(3)));
+		assert_eq!(stats.avg_latency(), Some(Duration::from_millis(2)));
+	}
+}
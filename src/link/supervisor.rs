@@ -0,0 +1,379 @@
+use std::{
+	path::{Path, PathBuf},
+	time::Duration
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::command::{
+	commands::{CmdObserveProperty, CmdSetProperty, CmdUnobserveProperty},
+	property::{MpvProperty, ObservableMpvProperty, OptionProperty},
+	response::MpvResponseEvent,
+	ParseError
+};
+
+use super::{
+	spawn::MpvSpawnBuilder,
+	CommandError,
+	MpvLink,
+	MpvLinkInitError,
+	ReceiveError,
+	ShutdownPolicy
+};
+
+#[derive(Debug, Error)]
+pub enum SupervisorError {
+	#[error(transparent)]
+	Command(#[from] CommandError<ParseError>),
+	#[error(transparent)]
+	Receive(#[from] ReceiveError),
+	#[error("Failed to restart mpv: {0}")]
+	Restart(#[from] MpvLinkInitError),
+	#[error("Could not serialize option value: {0}")]
+	Serialize(#[from] serde_json::Error),
+	/// Observers or options which could not be applied after a restart, the rest of them was applied.
+	#[error("Failed to apply {} observers or options after the restart", .0.len())]
+	Reapply(Vec<CommandError<ParseError>>),
+	#[error("mpv is not running, a failed restart is pending")]
+	NotRunning
+}
+
+/// How the supervised mpv is spawned.
+#[derive(Debug, Clone)]
+pub enum SpawnTarget {
+	/// See `MpvSpawnBuilder::spawn_server`.
+	Server(PathBuf),
+	/// See `MpvSpawnBuilder::spawn_client`.
+	#[cfg(unix)]
+	Client
+}
+impl SpawnTarget {
+	fn spawn(&self, builder: &MpvSpawnBuilder) -> Result<MpvLink, MpvLinkInitError> {
+		match self {
+			SpawnTarget::Server(socket_path) => builder.spawn_server(socket_path),
+			#[cfg(unix)]
+			SpawnTarget::Client => builder.spawn_client()
+		}
+	}
+}
+
+/// Keeps a spawned mpv running by respawning it when it exits.
+///
+/// After a restart the observers registered with `observe` and the options set with `set_option` are applied
+/// to the new process, events not drained from the old link are moved to the new one and
+/// `MpvResponseEvent::Restarted` is queued after them.
+///
+/// Restarts happen in `poll_events`, which has to be called regularly instead of `MpvLink::poll_events`.
+pub struct MpvSupervisor {
+	builder: MpvSpawnBuilder,
+	target: SpawnTarget,
+	/// `None` when restarting failed, until it is attempted again by the next `poll_events`.
+	link: Option<MpvLink>,
+	restart_on_shutdown: bool,
+	/// Whether the current mpv sent `shutdown`, so that its exit is expected.
+	shutdown_announced: bool,
+	/// Number of events at the front of the queue of the link which were already checked by `poll_events`.
+	checked_events: usize,
+	/// Observer ids and names of observed properties.
+	observed: Vec<(u32, String)>,
+	/// Names of option properties with their values, in the order they were set.
+	options: Vec<(String, serde_json::Value)>,
+	restarts: u32,
+	/// Events taken from the previous link, queued on the new link once it is spawned.
+	carried_events: Vec<MpvResponseEvent>
+}
+impl MpvSupervisor {
+	/// How long an mpv which announced `shutdown` is given to exit before it is killed.
+	const QUIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+	pub fn spawn(builder: MpvSpawnBuilder, target: SpawnTarget) -> Result<Self, MpvLinkInitError> {
+		let link = target.spawn(&builder)?;
+
+		Ok(MpvSupervisor {
+			builder,
+			target,
+			link: Some(link),
+			restart_on_shutdown: true,
+			shutdown_announced: false,
+			checked_events: 0,
+			observed: Vec::new(),
+			options: Vec::new(),
+			restarts: 0,
+			carried_events: Vec::new()
+		})
+	}
+
+	pub fn spawn_server(
+		builder: MpvSpawnBuilder,
+		socket_path: &Path
+	) -> Result<Self, MpvLinkInitError> {
+		Self::spawn(builder, SpawnTarget::Server(socket_path.to_path_buf()))
+	}
+
+	#[cfg(unix)]
+	pub fn spawn_client(builder: MpvSpawnBuilder) -> Result<Self, MpvLinkInitError> {
+		Self::spawn(builder, SpawnTarget::Client)
+	}
+
+	/// Whether mpv is restarted on the `shutdown` event as well, which it sends when it is asked to quit.
+	///
+	/// Enabled by default. When disabled mpv is only restarted when it exits without announcing it, for example
+	/// when it crashes.
+	pub fn set_restart_on_shutdown(&mut self, restart_on_shutdown: bool) {
+		self.restart_on_shutdown = restart_on_shutdown;
+	}
+
+	/// The link to the current mpv process.
+	///
+	/// The link is replaced on restart. If restarting failed there is no link until `poll_events` restarts mpv
+	/// successfully.
+	pub fn link(&self) -> Option<&MpvLink> {
+		self.link.as_ref()
+	}
+
+	pub fn link_mut(&mut self) -> Option<&mut MpvLink> {
+		self.link.as_mut()
+	}
+
+	fn running_link(&mut self) -> Result<&mut MpvLink, SupervisorError> {
+		self.link.as_mut().ok_or(SupervisorError::NotRunning)
+	}
+
+	/// Number of restarts so far.
+	pub fn restarts(&self) -> u32 {
+		self.restarts
+	}
+
+	/// Observes `property` with `observer_id`, observing it again after each restart.
+	pub fn observe<P: ObservableMpvProperty>(
+		&mut self,
+		observer_id: u32,
+		property: P
+	) -> Result<(), SupervisorError> {
+		let name = property.name().into_owned();
+		self.running_link()?
+			.run_command(&CmdObserveProperty::new(observer_id, property))?;
+
+		self.observed.retain(|(id, _)| *id != observer_id);
+		self.observed.push((observer_id, name));
+
+		Ok(())
+	}
+
+	pub fn unobserve(&mut self, observer_id: u32) -> Result<(), SupervisorError> {
+		self.running_link()?
+			.run_command(&CmdUnobserveProperty::new(observer_id))?;
+		self.observed.retain(|(id, _)| *id != observer_id);
+
+		Ok(())
+	}
+
+	/// Sets `option` to `value`, setting it again after each restart.
+	pub fn set_option<T: Serialize + DeserializeOwned>(
+		&mut self,
+		option: OptionProperty<'_, T>,
+		value: T
+	) -> Result<(), SupervisorError> {
+		let name = option.name().into_owned();
+		let json_value = serde_json::to_value(&value)?;
+		self.running_link()?
+			.run_command(&CmdSetProperty::new(option, value))?;
+
+		self.options.retain(|(option_name, _)| *option_name != name);
+		self.options.push((name, json_value));
+
+		Ok(())
+	}
+
+	/// Polls for events on the link and restarts mpv if it exited or announced `shutdown`.
+	///
+	/// Events queued since the last call are checked as well, such as those received while running commands,
+	/// so they should not be drained from the link before this is called.
+	pub fn poll_events(&mut self) -> Result<(), SupervisorError> {
+		let link = match self.link.as_mut() {
+			Some(link) => link,
+			None => return self.restart()
+		};
+		link.poll_events()?;
+
+		// the events before were checked already or carried over from the previous process
+		let events = link.peek_events();
+		let mut exited = false;
+		for event in events[self.checked_events.min(events.len()) ..].iter() {
+			match event {
+				MpvResponseEvent::Shutdown => {
+					self.shutdown_announced = true;
+					exited |= self.restart_on_shutdown;
+				}
+				MpvResponseEvent::ProcessExited { .. } => {
+					exited |= self.restart_on_shutdown || !self.shutdown_announced;
+				}
+				_ => ()
+			}
+		}
+		self.checked_events = events.len();
+
+		if exited {
+			self.restart()?;
+		}
+
+		Ok(())
+	}
+
+	/// Closes the current link, if a failed restart did not take it already, and spawns mpv again.
+	///
+	/// Observers and options which fail to apply do not stop the others from being applied, their errors are
+	/// returned together.
+	fn restart(&mut self) -> Result<(), SupervisorError> {
+		if let Some(mut link) = self.link.take() {
+			self.carried_events.extend(link.drain_events());
+			if let Err(err) = link.close(ShutdownPolicy::QuitChild {
+				timeout: Some(Self::QUIT_TIMEOUT)
+			}) {
+				log::warn!("Could not shut down the previous mpv: {}", err);
+			}
+		}
+
+		log::info!("Restarting mpv");
+		let link = self.link.insert(self.target.spawn(&self.builder)?);
+		self.restarts += 1;
+
+		for event in self.carried_events.drain(..) {
+			link.queue_event(event);
+		}
+		// before the events caused by observing the properties again
+		link.queue_event(MpvResponseEvent::Restarted {
+			restarts: self.restarts
+		});
+		self.shutdown_announced = false;
+		self.checked_events = link.peek_events().len();
+
+		let mut errors = Vec::new();
+		for (observer_id, name) in self.observed.iter() {
+			if let Err(err) =
+				link.run_command(&CmdObserveProperty::new(*observer_id, name.as_str()))
+			{
+				log::warn!("Could not observe {} again: {}", name, err);
+				errors.push(err);
+			}
+		}
+		for (name, value) in self.options.iter() {
+			if let Err(err) = link.run_command(&CmdSetProperty::new(name.as_str(), value.clone())) {
+				log::warn!("Could not set {} again: {}", name, err);
+				errors.push(err);
+			}
+		}
+
+		if !errors.is_empty() {
+			return Err(SupervisorError::Reapply(errors))
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{
+		io::Write,
+		os::unix::{net::UnixStream, process::ExitStatusExt},
+		process::ExitStatus,
+		thread::JoinHandle
+	};
+
+	use super::{MpvSupervisor, SpawnTarget, SupervisorError};
+	use crate::{
+		command::{commands::CmdGetProperty, property, response::MpvResponseEvent},
+		link::{fake_mpv, spawn::MpvSpawnBuilder, MpvLink, MpvLinkInitError}
+	};
+
+	/// Supervisor of a fake mpv whose restarts fail right away, so that attempted restarts show as errors.
+	fn fake_supervisor(
+		name: &str,
+		serve: impl FnOnce(&mut fake_mpv::Requests, &mut UnixStream) + Send + 'static
+	) -> (MpvSupervisor, JoinHandle<UnixStream>) {
+		let path = std::env::temp_dir().join(format!(
+			"mpvsock-supervisor-{}-{}.sock",
+			name,
+			std::process::id()
+		));
+		let server = fake_mpv::listen(&path, serve);
+		let link = MpvLink::connect(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+
+		let supervisor = MpvSupervisor {
+			builder: MpvSpawnBuilder::new().program("false"),
+			target: SpawnTarget::Server(path),
+			link: Some(link),
+			restart_on_shutdown: true,
+			shutdown_announced: false,
+			checked_events: 0,
+			observed: Vec::new(),
+			options: Vec::new(),
+			restarts: 0,
+			carried_events: Vec::new()
+		};
+
+		(supervisor, server)
+	}
+
+	fn is_restart_error(result: Result<(), SupervisorError>) -> bool {
+		matches!(
+			result,
+			Err(SupervisorError::Restart(MpvLinkInitError::ExitedBeforeSocket(_)))
+		)
+	}
+
+	#[test]
+	fn test_restart_on_shutdown() {
+		for restart_on_shutdown in [true, false] {
+			let (mut supervisor, server) = fake_supervisor("shutdown", |_, _| ());
+			supervisor.set_restart_on_shutdown(restart_on_shutdown);
+
+			let link = supervisor.link_mut().unwrap();
+			link.queue_event(MpvResponseEvent::Shutdown);
+			link.queue_event(MpvResponseEvent::ProcessExited {
+				status: ExitStatus::from_raw(0)
+			});
+			assert_eq!(
+				is_restart_error(supervisor.poll_events()),
+				restart_on_shutdown
+			);
+			assert_eq!(supervisor.link().is_none(), restart_on_shutdown);
+			let _socket = server.join().unwrap();
+		}
+	}
+
+	#[test]
+	fn test_restart_on_unannounced_exit() {
+		let (mut supervisor, server) = fake_supervisor("exit", |_, _| ());
+		supervisor.set_restart_on_shutdown(false);
+
+		supervisor
+			.link_mut()
+			.unwrap()
+			.queue_event(MpvResponseEvent::ProcessExited {
+				status: ExitStatus::from_raw(libc::SIGSEGV)
+			});
+		assert!(is_restart_error(supervisor.poll_events()));
+		let _socket = server.join().unwrap();
+	}
+
+	#[test]
+	fn test_restart_on_event_queued_by_command() {
+		let (mut supervisor, server) = fake_supervisor("command", |requests, socket| {
+			socket.write_all(b"{\"event\":\"shutdown\"}\n").unwrap();
+			fake_mpv::answer(requests, &mut *socket, &["50.5"]);
+		});
+
+		supervisor
+			.link_mut()
+			.unwrap()
+			.run_command(&CmdGetProperty::new(property::Volume))
+			.unwrap();
+		assert_eq!(supervisor.link().unwrap().peek_events().len(), 1);
+		assert!(is_restart_error(supervisor.poll_events()));
+		let _socket = server.join().unwrap();
+	}
+}
@@ -0,0 +1,124 @@
+use std::{
+	collections::VecDeque,
+	io,
+	time::{SystemTime, UNIX_EPOCH}
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceDirection {
+	Sent,
+	Received
+}
+
+/// Command sent or line received by a link, without the line delimiter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+	pub time: SystemTime,
+	pub direction: TraceDirection,
+	pub line: Vec<u8>
+}
+
+/// Ring buffer of the last traced lines of a link, see `MpvLink::set_trace_capacity`.
+#[derive(Debug, Clone)]
+pub struct TraceBuffer {
+	capacity: usize,
+	entries: VecDeque<TraceEntry>
+}
+impl TraceBuffer {
+	pub fn new(capacity: usize) -> Self {
+		TraceBuffer {
+			capacity,
+			entries: VecDeque::with_capacity(capacity)
+		}
+	}
+
+	pub fn capacity(&self) -> usize {
+		self.capacity
+	}
+
+	/// Appends `line`, discarding the oldest entry when the buffer is full.
+	pub fn push(&mut self, direction: TraceDirection, line: &[u8]) {
+		if self.capacity == 0 {
+			return
+		}
+		if self.entries.len() == self.capacity {
+			self.entries.pop_front();
+		}
+		self.entries.push_back(TraceEntry {
+			time: SystemTime::now(),
+			direction,
+			line: line.to_vec()
+		});
+	}
+
+	/// Traced entries, oldest first.
+	pub fn entries(&self) -> impl Iterator<Item = &TraceEntry> {
+		self.entries.iter()
+	}
+
+	/// Writes the entries oldest first, one per line as `<unix time> > <command>` or `<unix time> < <line>`.
+	pub fn write_to(&self, mut w: impl io::Write) -> io::Result<()> {
+		for entry in self.entries.iter() {
+			let time = entry
+				.time
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or_default()
+				.as_secs_f64();
+			let direction = match entry.direction {
+				TraceDirection::Sent => '>',
+				TraceDirection::Received => '<'
+			};
+			writeln!(
+				w,
+				"{:.3} {} {}",
+				time,
+				direction,
+				String::from_utf8_lossy(&entry.line)
+			)?;
+		}
+
+		w.flush()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::{Duration, UNIX_EPOCH};
+
+	use super::{TraceBuffer, TraceDirection};
+
+	#[test]
+	fn test_ring_buffer() {
+		let mut trace = TraceBuffer::new(2);
+		trace.push(TraceDirection::Sent, b"{\"command\":[\"a\"]}");
+		trace.push(TraceDirection::Received, b"{\"error\":\"success\"}");
+		trace.push(TraceDirection::Sent, b"{\"command\":[\"b\"]}");
+
+		let lines: Vec<_> = trace.entries().map(|entry| entry.line.as_slice()).collect();
+		assert_eq!(
+			lines,
+			[&b"{\"error\":\"success\"}"[..], b"{\"command\":[\"b\"]}"]
+		);
+
+		let mut disabled = TraceBuffer::new(0);
+		disabled.push(TraceDirection::Sent, b"{}");
+		assert_eq!(disabled.entries().count(), 0);
+	}
+
+	#[test]
+	fn test_write_to() {
+		let mut trace = TraceBuffer::new(4);
+		trace.push(TraceDirection::Sent, b"{\"command\":[\"stop\"]}");
+		trace.push(TraceDirection::Received, b"{\"event\":\"idle\"}");
+		for entry in trace.entries.iter_mut() {
+			entry.time = UNIX_EPOCH + Duration::from_millis(1500);
+		}
+
+		let mut output = Vec::new();
+		trace.write_to(&mut output).unwrap();
+		assert_eq!(
+			String::from_utf8(output).unwrap(),
+			"1.500 > {\"command\":[\"stop\"]}\n1.500 < {\"event\":\"idle\"}\n"
+		);
+	}
+}
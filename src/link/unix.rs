@@ -1,14 +1,28 @@
 use std::{
 	convert::TryFrom,
-	fs,
 	io::{self, Read, Write},
 	path::Path,
-	process::{Child, Command, Stdio}
+	time::{Duration, Instant}
+};
+#[cfg(feature = "spawn")]
+use std::{
+	fs,
+	os::unix::fs::FileTypeExt,
+	process::{Child, ChildStderr, ChildStdout}
 };
 
-use std::os::unix::{fs::FileTypeExt, net::UnixStream, prelude::AsRawFd};
-
-use super::{MpvLinkDeinitError, MpvLinkInitError};
+use std::os::unix::{net::UnixStream, prelude::AsRawFd};
+
+#[cfg(feature = "spawn")]
+use super::spawn::MpvSpawnBuilder;
+use super::{
+	ChildStatus,
+	MpvLinkDeinitError,
+	MpvLinkInitError,
+	ShutdownPolicy,
+	SocketAddress,
+	SocketType
+};
 
 enum MpvLinkInner {
 	/// Link has been closed.
@@ -16,6 +30,7 @@ enum MpvLinkInner {
 	/// The mpv process is separate.
 	Socket { socket: UnixStream },
 	/// The mpv process is a child of this process.
+	#[cfg(feature = "spawn")]
 	Child { child: Child, socket: UnixStream }
 }
 pub struct MpvLink {
@@ -23,7 +38,8 @@ pub struct MpvLink {
 }
 impl MpvLink {
 	/// Spawns a new child process and uses the `input-ipc-client` option to pass it a socket.
-	pub fn spawn_client() -> Result<Self, MpvLinkInitError> {
+	#[cfg(feature = "spawn")]
+	pub fn spawn_client(builder: &MpvSpawnBuilder) -> Result<Self, MpvLinkInitError> {
 		let (socket, mpv_socket) = UnixStream::pair().map_err(MpvLinkInitError::SocketPair)?;
 
 		// unset cloexec so the child inherits the socket
@@ -37,13 +53,8 @@ impl MpvLink {
 		let child = {
 			let socket_arg = format!("--input-ipc-client=fd://{}", mpv_socket.as_raw_fd());
 
-			Command::new("mpv")
-				.arg("--idle")
-				.arg("--no-terminal")
-				.arg(&socket_arg)
-				.stdin(Stdio::null())
-				.stdout(Stdio::null())
-				.stderr(Stdio::null())
+			builder
+				.command(&socket_arg)
 				.spawn()
 				.map_err(MpvLinkInitError::Spawn)?
 		};
@@ -59,7 +70,16 @@ impl MpvLink {
 	}
 
 	/// Spawns a new child process and uses the `input-ipc-server` option to pass it a path where to create a socket.
-	pub fn spawn_server(path: &Path) -> Result<Self, MpvLinkInitError> {
+	///
+	/// Fails if mpv exits before creating the socket, for example because of invalid arguments, or if it does
+	/// not create it within `SOCKET_TIMEOUT`.
+	#[cfg(feature = "spawn")]
+	pub fn spawn_server(path: &Path, builder: &MpvSpawnBuilder) -> Result<Self, MpvLinkInitError> {
+		/// How long mpv is given to create the socket.
+		const SOCKET_TIMEOUT: Duration = Duration::from_secs(10);
+		/// How often connecting is retried while the socket does not exist.
+		const CONNECT_INTERVAL: Duration = Duration::from_millis(10);
+
 		if fs::metadata(path)
 			.map(|m| m.file_type().is_socket())
 			.unwrap_or(false)
@@ -68,42 +88,90 @@ impl MpvLink {
 			fs::remove_file(path).map_err(MpvLinkInitError::RemovePrevious)?;
 		}
 
-		let child = {
+		let mut child = {
 			let socket_arg = format!("--input-ipc-server={}", path.display());
 
-			Command::new("mpv")
-				.arg("--idle")
-				.arg("--no-terminal")
-				.arg(&socket_arg)
-				.stdin(Stdio::null())
-				.stdout(Stdio::null())
-				.stderr(Stdio::null())
+			builder
+				.command(&socket_arg)
 				.spawn()
 				.map_err(MpvLinkInitError::Spawn)?
 		};
 
 		log::info!("Spawned mpv with pid: {}", child.id());
 
+		let deadline = Instant::now() + SOCKET_TIMEOUT;
 		let socket = loop {
 			match UnixStream::connect(path) {
 				Ok(socket) => break socket,
-				Err(err) if err.kind() == io::ErrorKind::NotFound => {
-					std::thread::yield_now();
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+				Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+				Err(err) => {
+					kill_child(child);
+					return Err(MpvLinkInitError::Connect(err))
 				}
-				Err(err) => return Err(MpvLinkInitError::Connect(err))
 			}
+
+			// reaps the child if it exited
+			match child.try_wait() {
+				Ok(Some(status)) => return Err(MpvLinkInitError::ExitedBeforeSocket(status)),
+				Ok(None) => (),
+				Err(err) => {
+					kill_child(child);
+					return Err(MpvLinkInitError::Spawn(err))
+				}
+			}
+			if Instant::now() >= deadline {
+				kill_child(child);
+				return Err(MpvLinkInitError::SocketTimeout)
+			}
+			std::thread::sleep(CONNECT_INTERVAL);
 		};
 
-		let me = MpvLink {
+		let mut me = MpvLink {
 			inner: MpvLinkInner::Child { child, socket }
 		};
 
+		if let Err(err) = set_socket_access(path, builder) {
+			let _ = me.deinit();
+			return Err(MpvLinkInitError::Permissions(err))
+		}
+
 		Ok(me)
 	}
 
 	/// Connects to an existing process spawned with `input-ipc-server` option by opening the socket.
 	pub fn connect(path: &Path) -> Result<Self, MpvLinkInitError> {
-		let socket = UnixStream::connect(path).map_err(MpvLinkInitError::Connect)?;
+		let socket =
+			retry_interrupted(|| UnixStream::connect(path)).map_err(MpvLinkInitError::Connect)?;
+
+		let me = MpvLink {
+			inner: MpvLinkInner::Socket { socket }
+		};
+
+		Ok(me)
+	}
+
+	/// Connects to `address` with a socket of `socket_type`.
+	pub fn connect_address(
+		address: SocketAddress,
+		socket_type: SocketType
+	) -> Result<Self, MpvLinkInitError> {
+		let socket = retry_interrupted(|| match (socket_type, address) {
+			(SocketType::Stream, SocketAddress::Path(path)) => UnixStream::connect(path),
+			#[cfg(any(target_os = "linux", target_os = "android"))]
+			(SocketType::Stream, SocketAddress::Abstract(name)) => {
+				#[cfg(target_os = "android")]
+				use std::os::android::net::SocketAddrExt;
+				#[cfg(target_os = "linux")]
+				use std::os::linux::net::SocketAddrExt;
+
+				std::os::unix::net::SocketAddr::from_abstract_name(name)
+					.and_then(|address| UnixStream::connect_addr(&address))
+			}
+			#[cfg(any(target_os = "linux", target_os = "android"))]
+			(SocketType::SeqPacket, address) => connect_seqpacket(address)
+		})
+		.map_err(MpvLinkInitError::Connect)?;
 
 		let me = MpvLink {
 			inner: MpvLinkInner::Socket { socket }
@@ -115,6 +183,7 @@ impl MpvLink {
 	pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<(), io::Error> {
 		match &mut self.inner {
 			MpvLinkInner::Closed => panic!("Mpv link closed"),
+			#[cfg(feature = "spawn")]
 			MpvLinkInner::Child { socket, .. } => {
 				socket.set_nonblocking(nonblocking)?;
 			}
@@ -126,34 +195,70 @@ impl MpvLink {
 		Ok(())
 	}
 
+	/// Makes the socket blocking with a read timeout, or nonblocking if `timeout` is `None`.
+	pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<(), io::Error> {
+		let socket = match &mut self.inner {
+			MpvLinkInner::Closed => panic!("Mpv link closed"),
+			#[cfg(feature = "spawn")]
+			MpvLinkInner::Child { socket, .. } => socket,
+			MpvLinkInner::Socket { socket } => socket
+		};
+		socket.set_read_timeout(timeout)?;
+		socket.set_nonblocking(timeout.is_none())?;
+
+		Ok(())
+	}
+
 	/// Blocks until reading becomes available on `self.stream()`.
 	///
-	/// If timeout is not `None` then returns `Err(TimedOut)` on timeout.
-	pub fn wait_read(&self, timeout: Option<std::time::Duration>) -> Result<(), io::Error> {
+	/// If timeout is not `None` then returns `Err(TimedOut)` on timeout. The timeout has millisecond precision,
+	/// rounded up so that short timeouts still wait.
+	pub fn wait_read(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
 		log::trace!("Waiting on stream (timeout = {:?})", timeout);
 
+		self.poll(libc::POLLIN, timeout)
+	}
+
+	/// Waits until the stream can be written to without blocking.
+	pub fn wait_write(&self, timeout: Option<Duration>) -> Result<(), io::Error> {
+		log::trace!(
+			"Waiting for stream to be writable (timeout = {:?})",
+			timeout
+		);
+
+		self.poll(libc::POLLOUT, timeout)
+	}
+
+	fn poll(&self, events: libc::c_short, timeout: Option<Duration>) -> Result<(), io::Error> {
 		let fd = match &self.inner {
 			MpvLinkInner::Closed => panic!("Mpv link closed"),
+			#[cfg(feature = "spawn")]
 			MpvLinkInner::Child { socket, .. } => socket.as_raw_fd(),
 			MpvLinkInner::Socket { socket } => socket.as_raw_fd()
 		};
 
 		let mut info = libc::pollfd {
 			fd,
-			events: libc::POLLIN,
+			events,
 			revents: 0
 		};
 
-		let timeout = match timeout {
-			None => -1,
-			Some(timeout) => libc::c_int::try_from(timeout.as_secs()).unwrap_or(libc::c_int::MAX)
-		};
+		// a timeout too long to represent waits indefinitely
+		let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
 
-		let result = unsafe { libc::poll(&mut info, 1, timeout) };
+		let result = retry_interrupted(|| {
+			// the time spent before an interruption counts towards the timeout
+			let timeout = match deadline {
+				None => -1,
+				Some(deadline) => poll_timeout(deadline.saturating_duration_since(Instant::now()))
+			};
 
-		if result < 0 {
-			return Err(io::Error::last_os_error())
-		} else if result == 0 {
+			match unsafe { libc::poll(&mut info, 1, timeout) } {
+				result if result < 0 => Err(io::Error::last_os_error()),
+				result => Ok(result)
+			}
+		})?;
+		if result == 0 {
 			return Err(io::ErrorKind::TimedOut.into())
 		}
 
@@ -176,54 +281,70 @@ impl MpvLink {
 	pub fn stream(&mut self) -> impl Read + Write + '_ {
 		match &mut self.inner {
 			MpvLinkInner::Closed => panic!("Mpv link closed"),
+			#[cfg(feature = "spawn")]
 			MpvLinkInner::Child { socket, .. } => socket,
 			MpvLinkInner::Socket { socket } => socket
 		}
 	}
 
+	/// Checks whether the child process exited without blocking.
+	pub fn child_status(&mut self) -> Result<ChildStatus, io::Error> {
+		match &mut self.inner {
+			MpvLinkInner::Closed | MpvLinkInner::Socket { .. } => Ok(ChildStatus::NotChild),
+			#[cfg(feature = "spawn")]
+			MpvLinkInner::Child { child, .. } => Ok(match child.try_wait()? {
+				None => ChildStatus::Running,
+				Some(status) => ChildStatus::Exited(status)
+			})
+		}
+	}
+
+	/// Takes the reading end of the piped stdout of the child.
+	#[cfg(feature = "spawn")]
+	pub fn take_stdout(&mut self) -> Option<ChildStdout> {
+		match &mut self.inner {
+			MpvLinkInner::Child { child, .. } => child.stdout.take(),
+			_ => None
+		}
+	}
+
+	/// Takes the reading end of the piped stderr of the child.
+	#[cfg(feature = "spawn")]
+	pub fn take_stderr(&mut self) -> Option<ChildStderr> {
+		match &mut self.inner {
+			MpvLinkInner::Child { child, .. } => child.stderr.take(),
+			_ => None
+		}
+	}
+
 	/// Returns `true` if `self.deinit()` has been called.
 	pub fn is_deinit(&self) -> bool {
-		match self.inner {
-			MpvLinkInner::Closed => true,
-			_ => false
-		}
+		matches!(self.inner, MpvLinkInner::Closed)
 	}
 
-	/// Deinitializes `self`.
+	/// Deinitializes `self`, asking a child to quit and waiting for it.
 	///
 	/// If `self` has been deinitialized returns `Ok(())`.
 	pub fn deinit(&mut self) -> Result<(), MpvLinkDeinitError> {
-		let inner = std::mem::replace(&mut self.inner, MpvLinkInner::Closed);
-
-		fn deinit_socket(socket: UnixStream) -> Result<(), MpvLinkDeinitError> {
-			log::info!("Shutting down and closing socket");
-			let _ = socket
-				.shutdown(std::net::Shutdown::Both)
-				.map_err(MpvLinkDeinitError::Shutdown)?;
-			std::mem::drop(socket);
+		self.shutdown(ShutdownPolicy::QuitChild { timeout: None })
+	}
 
-			Ok(())
-		}
+	/// Deinitializes `self` according to `policy`.
+	///
+	/// If `self` has been deinitialized returns `Ok(())`.
+	pub fn shutdown(&mut self, policy: ShutdownPolicy) -> Result<(), MpvLinkDeinitError> {
+		let inner = std::mem::replace(&mut self.inner, MpvLinkInner::Closed);
 
 		match inner {
 			MpvLinkInner::Closed => Ok(()),
-			MpvLinkInner::Socket { socket } => deinit_socket(socket),
-			MpvLinkInner::Child {
-				mut socket,
-				mut child
-			} => {
-				// write quit command to make sure mpv quits
-				let quit_result = socket.write(b"quit\n");
-				log::info!("Wrote quit command: {:?}", quit_result);
-
-				let _ = deinit_socket(socket);
-
-				log::info!("Waiting for mpv child to exit");
-				child.wait().map_err(MpvLinkDeinitError::Wait)?;
-				std::mem::drop(child);
-
-				Ok(())
+			MpvLinkInner::Socket { socket } => {
+				// only affects children
+				let _ = policy;
+
+				deinit_socket(socket)
 			}
+			#[cfg(feature = "spawn")]
+			MpvLinkInner::Child { socket, child } => shutdown_child(socket, child, policy)
 		}
 	}
 }
@@ -232,3 +353,279 @@ impl Drop for MpvLink {
 		self.deinit().expect("Failed to deinit MpvLink in drop")
 	}
 }
+
+fn deinit_socket(socket: UnixStream) -> Result<(), MpvLinkDeinitError> {
+	log::info!("Shutting down and closing socket");
+	socket
+		.shutdown(std::net::Shutdown::Both)
+		.map_err(MpvLinkDeinitError::Shutdown)?;
+	std::mem::drop(socket);
+
+	Ok(())
+}
+
+#[cfg(feature = "spawn")]
+fn shutdown_child(
+	mut socket: UnixStream,
+	mut child: Child,
+	policy: ShutdownPolicy
+) -> Result<(), MpvLinkDeinitError> {
+	match policy {
+		ShutdownPolicy::QuitChild { timeout } => {
+			// write quit command to make sure mpv quits
+			let quit_result = socket.write(b"quit\n");
+			log::info!("Wrote quit command: {:?}", quit_result);
+
+			let _ = deinit_socket(socket);
+
+			log::info!("Waiting for mpv child to exit");
+			match timeout {
+				None => {
+					child.wait().map_err(MpvLinkDeinitError::Wait)?;
+				}
+				Some(timeout) => wait_child(child, timeout)?
+			}
+
+			Ok(())
+		}
+		ShutdownPolicy::DetachChild => {
+			log::info!("Detaching mpv child with pid: {}", child.id());
+			deinit_socket(socket)
+		}
+		ShutdownPolicy::CloseSocketOnly => {
+			deinit_socket(socket)?;
+
+			log::info!("Waiting for mpv child to exit");
+			child.wait().map_err(MpvLinkDeinitError::Wait)?;
+
+			Ok(())
+		}
+	}
+}
+
+/// Kills `child` and reaps it, for when it is abandoned before a link is created.
+#[cfg(feature = "spawn")]
+fn kill_child(mut child: Child) {
+	if let Err(err) = child.kill() {
+		log::warn!("Could not kill the child process: {}", err);
+	}
+	let _ = child.wait();
+}
+
+/// Waits for `child` to exit, killing it once `timeout` elapses.
+#[cfg(feature = "spawn")]
+fn wait_child(mut child: Child, timeout: Duration) -> Result<(), MpvLinkDeinitError> {
+	/// How often the child is checked.
+	const WAIT_INTERVAL: Duration = Duration::from_millis(10);
+
+	let deadline = Instant::now() + timeout;
+	loop {
+		if child
+			.try_wait()
+			.map_err(MpvLinkDeinitError::Wait)?
+			.is_some()
+		{
+			return Ok(())
+		}
+		if Instant::now() >= deadline {
+			break
+		}
+		std::thread::sleep(WAIT_INTERVAL);
+	}
+
+	log::warn!("mpv child did not exit in {:?}, killing it", timeout);
+	child.kill().map_err(MpvLinkDeinitError::Kill)?;
+	child.wait().map_err(MpvLinkDeinitError::Wait)?;
+
+	Ok(())
+}
+
+/// Applies the permissions and owner configured in `builder` to the socket at `path`.
+#[cfg(feature = "spawn")]
+fn set_socket_access(path: &Path, builder: &MpvSpawnBuilder) -> io::Result<()> {
+	use std::os::unix::fs::PermissionsExt;
+
+	if let Some(mode) = builder.socket_mode {
+		fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+	}
+	let (uid, gid) = builder.socket_owner;
+	if uid.is_some() || gid.is_some() {
+		std::os::unix::fs::chown(path, uid, gid)?;
+	}
+
+	Ok(())
+}
+
+/// Converts `timeout` to the milliseconds `poll` takes, rounding up and saturating.
+fn poll_timeout(timeout: Duration) -> libc::c_int {
+	let mut millis = timeout.as_millis();
+	if !timeout.subsec_nanos().is_multiple_of(1_000_000) {
+		millis += 1;
+	}
+
+	libc::c_int::try_from(millis).unwrap_or(libc::c_int::MAX)
+}
+
+/// Calls `f` again while it fails with `Interrupted`.
+///
+/// Syscalls fail with `EINTR` when a signal handler runs while they block, such as a `SIGCHLD` handler of
+/// the application, which is not a failure of the link.
+fn retry_interrupted<T>(mut f: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+	loop {
+		match f() {
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => {
+				log::trace!("Retrying interrupted syscall");
+			}
+			result => return result
+		}
+	}
+}
+
+/// Connects a `SOCK_SEQPACKET` socket to `address`.
+///
+/// The socket is wrapped in `UnixStream` since reading and writing it works the same, only message boundaries
+/// are preserved.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn connect_seqpacket(address: SocketAddress) -> Result<UnixStream, io::Error> {
+	use std::os::unix::{
+		ffi::OsStrExt,
+		io::{FromRawFd, OwnedFd}
+	};
+
+	let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+	addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+
+	// abstract names start with a NUL byte, paths end with one
+	let (bytes, offset, terminator) = match address {
+		SocketAddress::Path(path) => (path.as_os_str().as_bytes(), 0, 1),
+		SocketAddress::Abstract(name) => (name, 1, 0)
+	};
+	if offset + bytes.len() + terminator > addr.sun_path.len() {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			"socket address is too long"
+		))
+	}
+	if terminator == 1 && bytes.contains(&0) {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			"socket path contains a NUL byte"
+		))
+	}
+	for (dst, &src) in addr.sun_path[offset ..].iter_mut().zip(bytes) {
+		*dst = src as libc::c_char;
+	}
+	let len = std::mem::size_of::<libc::sa_family_t>() + offset + bytes.len() + terminator;
+
+	let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC, 0) };
+	if fd < 0 {
+		return Err(io::Error::last_os_error())
+	}
+	// closes the socket on error
+	let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+	let res = unsafe {
+		libc::connect(
+			fd.as_raw_fd(),
+			&addr as *const libc::sockaddr_un as *const libc::sockaddr,
+			len as libc::socklen_t
+		)
+	};
+	if res < 0 {
+		return Err(io::Error::last_os_error())
+	}
+
+	Ok(UnixStream::from(fd))
+}
+
+#[cfg(test)]
+mod test {
+	use std::{
+		io::{self, Write},
+		os::unix::net::UnixStream,
+		time::{Duration, Instant}
+	};
+
+	use super::{poll_timeout, MpvLink, MpvLinkInner};
+
+	extern "C" fn ignore_signal(_: libc::c_int) {}
+
+	#[test]
+	fn test_wait_read_retries_interrupted() {
+		let (socket, mut peer) = UnixStream::pair().unwrap();
+		let link = MpvLink {
+			inner: MpvLinkInner::Socket { socket }
+		};
+
+		// without SA_RESTART, so that the signal interrupts poll
+		unsafe {
+			let mut action: libc::sigaction = std::mem::zeroed();
+			action.sa_sigaction = ignore_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+			libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut());
+		}
+
+		let thread = unsafe { libc::pthread_self() };
+		let signaller = std::thread::spawn(move || {
+			std::thread::sleep(Duration::from_millis(50));
+			unsafe { libc::pthread_kill(thread, libc::SIGUSR1) };
+			std::thread::sleep(Duration::from_millis(50));
+			peer.write_all(b"{}\n").unwrap();
+			peer
+		});
+
+		link.wait_read(Some(Duration::from_secs(5))).unwrap();
+		signaller.join().unwrap();
+	}
+
+	#[test]
+	fn test_poll_timeout() {
+		assert_eq!(poll_timeout(Duration::ZERO), 0);
+		assert_eq!(poll_timeout(Duration::from_micros(1)), 1);
+		assert_eq!(poll_timeout(Duration::from_millis(250)), 250);
+		assert_eq!(poll_timeout(Duration::from_micros(1500)), 2);
+		assert_eq!(poll_timeout(Duration::from_secs(3)), 3000);
+		assert_eq!(poll_timeout(Duration::MAX), libc::c_int::MAX);
+	}
+
+	#[test]
+	fn test_wait_read_timeout() {
+		let (socket, _peer) = UnixStream::pair().unwrap();
+		let link = MpvLink {
+			inner: MpvLinkInner::Socket { socket }
+		};
+
+		let start = Instant::now();
+		let err = link
+			.wait_read(Some(Duration::from_millis(100)))
+			.unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+		assert!(start.elapsed() >= Duration::from_millis(100));
+		assert!(start.elapsed() < Duration::from_secs(1));
+
+		// too long to represent, waits indefinitely instead of overflowing
+		let (socket, mut peer) = UnixStream::pair().unwrap();
+		let link = MpvLink {
+			inner: MpvLinkInner::Socket { socket }
+		};
+		peer.write_all(b"{}\n").unwrap();
+		link.wait_read(Some(Duration::MAX)).unwrap();
+	}
+
+	#[test]
+	#[cfg(feature = "spawn")]
+	fn test_spawn_server_child_exits() {
+		use crate::link::{spawn::MpvSpawnBuilder, MpvLinkInitError};
+
+		let path = std::env::temp_dir().join(format!(
+			"mpvsock-spawn-exit-{}.sock",
+			std::process::id()
+		));
+		// exits right away without creating the socket
+		let builder = MpvSpawnBuilder::new().program("false");
+
+		let start = Instant::now();
+		let err = MpvLink::spawn_server(&path, &builder).err().unwrap();
+		assert!(matches!(err, MpvLinkInitError::ExitedBeforeSocket(status) if !status.success()));
+		assert!(start.elapsed() < Duration::from_secs(5));
+	}
+}
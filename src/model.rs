@@ -1,10 +1,125 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct FileloadInfo {
 	pub playlist_entry_id: i64
 }
 
+/// Data returned by `load-script`, mpv versions before 0.34 return none.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LoadScriptInfo {
+	pub client_id: i64
+}
+
+/// Entry of the `edition-list` property.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edition {
+	/// Id the `edition` property is set to.
+	pub id: i64,
+	#[serde(default)]
+	pub default: bool,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub title: Option<String>
+}
+
+/// Editions of the current file, empty if it has none.
+///
+/// Editions are alternative cuts of a Matroska file, each with its own chapters.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EditionList(pub Vec<Edition>);
+impl EditionList {
+	pub fn get(&self, id: i64) -> Option<&Edition> {
+		self.0.iter().find(|edition| edition.id == id)
+	}
+
+	/// The edition played unless another one is selected.
+	pub fn default_edition(&self) -> Option<&Edition> {
+		self.0.iter().find(|edition| edition.default)
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum EditionIdRepr {
+	Id(i64),
+	Str(EditionIdReprStr)
+}
+#[derive(Debug, Serialize, Deserialize)]
+enum EditionIdReprStr {
+	#[serde(rename = "auto")]
+	Auto
+}
+
+/// Value of the `edition` property, the edition selected to play.
+///
+/// It is `Auto` unless an edition was selected, the edition actually playing is `current-edition`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "EditionIdRepr")]
+#[serde(into = "EditionIdRepr")]
+pub enum EditionId {
+	Id(i64),
+	#[default]
+	Auto
+}
+impl From<EditionId> for EditionIdRepr {
+	fn from(id: EditionId) -> Self {
+		match id {
+			EditionId::Id(id) => EditionIdRepr::Id(id),
+			EditionId::Auto => EditionIdRepr::Str(EditionIdReprStr::Auto)
+		}
+	}
+}
+impl From<EditionIdRepr> for EditionId {
+	fn from(repr: EditionIdRepr) -> Self {
+		match repr {
+			EditionIdRepr::Id(id) => EditionId::Id(id),
+			EditionIdRepr::Str(EditionIdReprStr::Auto) => EditionId::Auto
+		}
+	}
+}
+
+/// Entry of the `audio-device-list` property.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioDevice {
+	/// Name the `audio-device` property is set to, such as `pulse/alsa_output.pci-0000_00_1f.3.analog-stereo`.
+	pub name: String,
+	/// Human readable name of the device.
+	#[serde(default)]
+	pub description: String
+}
+
+/// Audio output devices, always starting with `auto` which lets the audio output choose.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AudioDeviceList(pub Vec<AudioDevice>);
+impl AudioDeviceList {
+	pub fn get(&self, name: &str) -> Option<&AudioDevice> {
+		self.0.iter().find(|device| device.name == name)
+	}
+
+	pub fn iter(&self) -> std::slice::Iter<'_, AudioDevice> {
+		self.0.iter()
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -21,20 +136,16 @@ enum TrackIdReprStr {
 	No
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 #[serde(untagged)]
 #[serde(from = "TrackIdRepr")]
 #[serde(into = "TrackIdRepr")]
 pub enum TrackId {
 	Index(u32),
+	#[default]
 	Auto,
 	None
 }
-impl Default for TrackId {
-	fn default() -> Self {
-		TrackId::Auto
-	}
-}
 impl From<TrackId> for TrackIdRepr {
 	fn from(id: TrackId) -> Self {
 		match id {
@@ -54,12 +165,251 @@ impl From<TrackIdRepr> for TrackId {
 	}
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackType {
+	Audio,
+	Video,
+	Sub,
+	/// Types added by newer mpv versions.
+	#[serde(other)]
+	Other
+}
+
+/// Entry of the `track-list` property.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Track {
+	/// Id within the tracks of the same type, which `aid`, `vid` and `sid` are set to.
+	pub id: u32,
+	#[serde(rename = "type")]
+	pub track_type: TrackType,
+	/// Language tag as stored in the file, such as `en` or `eng`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub lang: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub title: Option<String>,
+	#[serde(default)]
+	pub selected: bool,
+	#[serde(default)]
+	pub default: bool,
+	/// The track was loaded from a separate file, such as with `sub-add`.
+	#[serde(default)]
+	pub external: bool
+}
+
+/// Entry of the `playlist` property.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+	pub filename: String,
+	/// Id which `start-file` and `end-file` refer to as `playlist_entry_id`, missing before mpv 0.33.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub id: Option<i64>,
+	/// Title from the playlist file the entry was loaded from, if it had any.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub title: Option<String>,
+	#[serde(default)]
+	pub current: bool,
+	#[serde(default)]
+	pub playing: bool
+}
+
+/// Why playback of a file ended, the `reason` of the `end-file` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndFileReason {
+	/// The end of the file was reached.
+	Eof,
+	/// Playback was stopped, by `stop`, by playing another entry or by removing the entry.
+	Stop,
+	Quit,
+	/// The file could not be opened or played.
+	Error,
+	/// The file was a playlist which replaced its entry.
+	Redirect,
+	/// Reasons added by newer mpv versions.
+	#[serde(other)]
+	Unknown
+}
+
+/// Quality of a stream opened through youtube-dl or yt-dlp, converted to a `ytdl-format` selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamQuality {
+	/// The best video and audio, what youtube-dl picks by default.
+	Best,
+	/// The best video up to the height in pixels, such as `720`.
+	MaxHeight(u32),
+	/// The best audio without video.
+	AudioOnly,
+	/// A custom format selector passed as it is.
+	Format(String)
+}
+impl StreamQuality {
+	pub fn ytdl_format(&self) -> Cow<'_, str> {
+		match self {
+			StreamQuality::Best => Cow::Borrowed("bestvideo+bestaudio/best"),
+			StreamQuality::MaxHeight(height) => Cow::Owned(format!(
+				"bestvideo[height<=?{0}]+bestaudio/best[height<=?{0}]/best",
+				height
+			)),
+			StreamQuality::AudioOnly => Cow::Borrowed("bestaudio/best"),
+			StreamQuality::Format(format) => Cow::Borrowed(format)
+		}
+	}
+}
+
+/// What the player is doing, derived from the `idle-active`, `seeking`, `paused-for-cache`, `pause` and
+/// `core-idle` flags.
+///
+/// `core-idle` alone is true whenever playback does not advance, for any of the reasons below, so the flags
+/// are checked in the order of the variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IdleState {
+	/// No file is loaded and mpv waits for one.
+	Idle,
+	Seeking,
+	/// Playback waits for the cache to fill, also called buffering.
+	PausedForCache,
+	/// Paused by the user.
+	Paused,
+	/// The core is idle for another reason, such as while the file opens.
+	Loading,
+	Playing
+}
+impl IdleState {
+	pub fn from_flags(
+		idle_active: bool,
+		seeking: bool,
+		paused_for_cache: bool,
+		pause: bool,
+		core_idle: bool
+	) -> Self {
+		if idle_active {
+			IdleState::Idle
+		} else if seeking {
+			IdleState::Seeking
+		} else if paused_for_cache {
+			IdleState::PausedForCache
+		} else if pause {
+			IdleState::Paused
+		} else if core_idle {
+			IdleState::Loading
+		} else {
+			IdleState::Playing
+		}
+	}
+
+	/// Returns `true` if playback advances.
+	pub fn is_playing(&self) -> bool {
+		matches!(self, IdleState::Playing)
+	}
+}
+
+/// Value of the `replaygain` option, which ReplayGain tags adjust the volume with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGain {
+	#[default]
+	No,
+	Track,
+	Album
+}
+
+/// Filter chain property edited by the filter helpers of `MpvPlayer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterChain {
+	Audio,
+	Video
+}
+impl FilterChain {
+	/// Name of the property, `af` or `vf`.
+	pub fn property_name(&self) -> &'static str {
+		match self {
+			FilterChain::Audio => "af",
+			FilterChain::Video => "vf"
+		}
+	}
+}
+
+/// Entry of the `af` or `vf` filter list.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Filter {
+	pub name: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub label: Option<String>,
+	#[serde(default = "Filter::default_enabled")]
+	pub enabled: bool,
+	#[serde(default)]
+	pub params: BTreeMap<String, String>
+}
+impl Filter {
+	pub fn new(name: impl Into<String>) -> Self {
+		Filter {
+			name: name.into(),
+			label: None,
+			enabled: true,
+			params: BTreeMap::new()
+		}
+	}
+
+	pub fn with_label(mut self, label: impl Into<String>) -> Self {
+		self.label = Some(label.into());
+		self
+	}
+
+	pub fn with_param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+		self.params.insert(key.into(), value.into());
+		self
+	}
+
+	fn default_enabled() -> bool {
+		true
+	}
+
+	/// Formats the filter in the option syntax, e.g. `@label:name=key=value`.
+	///
+	/// Parameter values containing syntax characters are quoted as `%length%value`.
+	pub fn to_option_string(&self) -> String {
+		let mut result = String::new();
+		if let Some(label) = self.label.as_ref() {
+			result.push('@');
+			result.push_str(label);
+			result.push(':');
+		}
+		if !self.enabled {
+			result.push('!');
+		}
+		result.push_str(&self.name);
+
+		for (index, (key, value)) in self.params.iter().enumerate() {
+			result.push(if index == 0 { '=' } else { ':' });
+			result.push_str(key);
+			result.push('=');
+			if value.contains([':', ',', '=', '[', ']', '%', '"', '\\']) {
+				result.push_str(&format!("%{}%", value.len()));
+			}
+			result.push_str(value);
+		}
+
+		result
+	}
+}
+
 #[cfg(test)]
 mod test {
-	//! Test `TrackId`s as seen in the wild.
+	//! Test `TrackId`s and filter lists as seen in the wild.
 	use serde_json::json;
 
-	use super::TrackId;
+	use super::{
+		AudioDeviceList,
+		EditionId,
+		EditionList,
+		Filter,
+		StreamQuality,
+		Track,
+		TrackId,
+		TrackType
+	};
 
 	#[test]
 	fn parse_track_id_index() {
@@ -92,4 +442,125 @@ mod test {
 
 		assert!(matches!(track, TrackId::Auto));
 	}
+
+	#[test]
+	fn parse_edition_id() {
+		assert_eq!(
+			serde_json::from_value::<EditionId>(json!("auto")).unwrap(),
+			EditionId::Auto
+		);
+		assert_eq!(
+			serde_json::from_value::<EditionId>(json!(1)).unwrap(),
+			EditionId::Id(1)
+		);
+		assert_eq!(
+			serde_json::to_value(EditionId::Auto).unwrap(),
+			json!("auto")
+		);
+	}
+
+	#[test]
+	fn parse_filter_list() {
+		let value = json!([
+			{ "name": "lavfi", "label": "eq", "enabled": false, "params": { "graph": "eq=gamma=1.2" } },
+			{ "name": "scale", "enabled": true, "params": {} }
+		]);
+		let filters = serde_json::from_value::<Vec<Filter>>(value).unwrap();
+
+		assert_eq!(
+			filters,
+			vec![
+				Filter {
+					enabled: false,
+					..Filter::new("lavfi")
+						.with_label("eq")
+						.with_param("graph", "eq=gamma=1.2")
+				},
+				Filter::new("scale")
+			]
+		);
+	}
+
+	#[test]
+	fn parse_track_list() {
+		let value = json!([
+			{ "id": 1, "type": "video", "src-id": 0, "selected": true, "codec": "h264" },
+			{ "id": 1, "type": "sub", "lang": "eng", "title": "Full", "default": true },
+			{ "id": 2, "type": "sub", "external": true },
+			{ "id": 1, "type": "hologram" }
+		]);
+		let tracks = serde_json::from_value::<Vec<Track>>(value).unwrap();
+
+		assert_eq!(tracks[0].track_type, TrackType::Video);
+		assert!(tracks[0].selected);
+		assert_eq!(tracks[1].lang.as_deref(), Some("eng"));
+		assert!(tracks[1].default && !tracks[1].selected);
+		assert!(tracks[2].external);
+		assert_eq!(tracks[3].track_type, TrackType::Other);
+	}
+
+	#[test]
+	fn parse_audio_device_list() {
+		let value = json!([
+			{ "name": "auto", "description": "Autoselect device" },
+			{ "name": "alsa/default", "description": "Default (alsa)" }
+		]);
+		let devices = serde_json::from_value::<AudioDeviceList>(value).unwrap();
+
+		assert_eq!(devices.len(), 2);
+		assert_eq!(
+			devices
+				.get("alsa/default")
+				.map(|device| device.description.as_str()),
+			Some("Default (alsa)")
+		);
+		assert!(devices.get("pulse").is_none());
+	}
+
+	#[test]
+	fn format_stream_quality() {
+		assert_eq!(
+			StreamQuality::MaxHeight(720).ytdl_format(),
+			"bestvideo[height<=?720]+bestaudio/best[height<=?720]/best"
+		);
+		assert_eq!(
+			StreamQuality::Format("worst".to_string()).ytdl_format(),
+			"worst"
+		);
+	}
+
+	#[test]
+	fn parse_edition_list() {
+		let value = json!([
+			{ "id": 0, "default": true, "title": "Theatrical" },
+			{ "id": 1 }
+		]);
+		let editions = serde_json::from_value::<EditionList>(value).unwrap();
+
+		assert_eq!(editions.len(), 2);
+		assert_eq!(
+			editions.default_edition().map(|edition| edition.id),
+			Some(0)
+		);
+		assert_eq!(
+			editions.get(0).and_then(|edition| edition.title.as_deref()),
+			Some("Theatrical")
+		);
+		assert!(!editions.get(1).unwrap().default);
+		assert!(editions.get(2).is_none());
+	}
+
+	#[test]
+	fn format_filter_option_string() {
+		let filter = Filter::new("lavfi")
+			.with_label("eq")
+			.with_param("graph", "eq=gamma=1.2")
+			.with_param("o", "x");
+
+		assert_eq!(
+			filter.to_option_string(),
+			"@eq:lavfi=graph=%12%eq=gamma=1.2:o=x"
+		);
+		assert_eq!(Filter::new("scale").to_option_string(), "scale");
+	}
 }
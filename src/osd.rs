@@ -0,0 +1,567 @@
+use std::{
+	borrow::Cow,
+	fmt::Write,
+	sync::mpsc,
+	time::{Duration, Instant}
+};
+
+use crate::{
+	command::{
+		commands::{CmdOsdOverlay, SectionPriority},
+		property::{self, KnownMpvProperty},
+		response::{MpvResponseEvent, MpvResponseEventPropertyName},
+		ParseError
+	},
+	link::{
+		binding::{KeyBindingEvent, KeyBindingState},
+		CommandError,
+		MpvLink,
+		ReceiveError
+	},
+	property_cache::PropertyCache
+};
+
+/// Escapes `text` so that libass shows it as it is, the same way as the scripts bundled with mpv.
+pub fn ass_escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			// a zero width no-break space keeps the backslash from starting an escape sequence
+			'\\' => escaped.push_str("\\\u{feff}"),
+			'{' => escaped.push_str("\\{"),
+			'}' => escaped.push_str("\\}"),
+			'\n' => escaped.push_str("\\N"),
+			c => escaped.push(c)
+		}
+	}
+
+	escaped
+}
+
+/// Key action of an open `Menu`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuAction {
+	Up,
+	Down,
+	Select,
+	Cancel
+}
+impl MenuAction {
+	const ALL: [(MenuAction, &'static str, &'static [&'static str]); 4] = [
+		(MenuAction::Up, "mpvsock-menu-up", &["UP", "WHEEL_UP"]),
+		(
+			MenuAction::Down,
+			"mpvsock-menu-down",
+			&["DOWN", "WHEEL_DOWN"]
+		),
+		(
+			MenuAction::Select,
+			"mpvsock-menu-select",
+			&["ENTER", "KP_ENTER"]
+		),
+		(MenuAction::Cancel, "mpvsock-menu-cancel", &["ESC"])
+	];
+}
+
+/// State of a `Menu` returned by `Menu::poll`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuStatus {
+	/// The menu is shown and waits for keys.
+	Open,
+	/// The entry at the index was selected and the menu closed.
+	Selected(usize),
+	/// The menu was closed with `ESC` or `close`.
+	Cancelled
+}
+
+/// Navigable text menu drawn on the video with `osd-overlay`, such as for picking a track or a playlist
+/// entry.
+///
+/// While the menu is open, the arrow keys move the selection, `ENTER` selects and `ESC` cancels. The keys
+/// are bound to script bindings of this client with `SectionPriority::Force`, see `MpvLink::bind_key`,
+/// so they override the bindings of the user only while the menu is open, and only one menu can be open
+/// at a time.
+#[derive(Debug)]
+pub struct Menu {
+	title: Option<String>,
+	entries: Vec<String>,
+	selected: usize,
+	max_visible: usize,
+	overlay_id: u32,
+	actions: Option<mpsc::Receiver<MenuAction>>
+}
+impl Menu {
+	pub const DEFAULT_OVERLAY_ID: u32 = 1;
+
+	pub fn new(entries: impl IntoIterator<Item = impl Into<String>>) -> Self {
+		Menu {
+			title: None,
+			entries: entries.into_iter().map(Into::into).collect(),
+			selected: 0,
+			max_visible: 10,
+			overlay_id: Self::DEFAULT_OVERLAY_ID,
+			actions: None
+		}
+	}
+
+	pub fn with_title(mut self, title: impl Into<String>) -> Self {
+		self.title = Some(title.into());
+		self
+	}
+
+	/// Sets the initially selected entry, clamped to the entries.
+	pub fn with_selected(mut self, selected: usize) -> Self {
+		self.selected = selected.min(self.entries.len().saturating_sub(1));
+		self
+	}
+
+	/// Sets how many entries are shown at once, the list scrolls to keep the selection visible.
+	pub fn with_max_visible(mut self, max_visible: usize) -> Self {
+		self.max_visible = max_visible.max(1);
+		self
+	}
+
+	/// Sets the id of the overlay, so that it does not replace another overlay of this client.
+	pub fn with_overlay_id(mut self, overlay_id: u32) -> Self {
+		self.overlay_id = overlay_id;
+		self
+	}
+
+	pub fn entries(&self) -> &[String] {
+		&self.entries
+	}
+
+	pub fn selected(&self) -> usize {
+		self.selected
+	}
+
+	pub fn is_open(&self) -> bool {
+		self.actions.is_some()
+	}
+
+	/// Shows the menu and binds its keys.
+	pub fn open(&mut self, link: &mut MpvLink) -> Result<(), CommandError<ParseError>> {
+		let (sender, receiver) = mpsc::channel();
+		for (action, name, keys) in MenuAction::ALL {
+			let sender = sender.clone();
+			link.add_binding(name, move |event: &KeyBindingEvent| {
+				let repeats = matches!(action, MenuAction::Up | MenuAction::Down);
+				if event.is_pressed() || (repeats && event.state == KeyBindingState::Repeat) {
+					// the menu may be dropped without closing it
+					let _ = sender.send(action);
+				}
+			});
+			for key in keys {
+				link.bind_key(key, name, SectionPriority::Force)?;
+			}
+		}
+		self.actions = Some(receiver);
+
+		self.draw(link)
+	}
+
+	/// Hides the menu and unbinds its keys.
+	pub fn close(&mut self, link: &mut MpvLink) -> Result<(), CommandError<ParseError>> {
+		self.actions = None;
+		for (_, name, _) in MenuAction::ALL {
+			link.remove_binding(name)?;
+		}
+
+		link.run_command_named(&CmdOsdOverlay::remove(self.overlay_id))
+	}
+
+	/// Polls events from `link` and applies the pressed keys, redrawing the menu if the selection moved.
+	///
+	/// The menu closes once an entry is selected or it is cancelled.
+	pub fn poll(&mut self, link: &mut MpvLink) -> Result<MenuStatus, CommandError<ParseError>> {
+		// keys may be pressed while redrawing, so poll until none are left
+		loop {
+			link.poll_events()?;
+			// the bindings already handled the messages
+			link.retain_events(|event| {
+				KeyBindingEvent::from_event(event).is_none_or(|event| {
+					!MenuAction::ALL
+						.iter()
+						.any(|(_, name, _)| event.name == *name)
+				})
+			});
+
+			let actions: Vec<_> = match self.actions.as_ref() {
+				None => return Ok(MenuStatus::Cancelled),
+				Some(actions) => actions.try_iter().collect()
+			};
+			if actions.is_empty() {
+				return Ok(MenuStatus::Open)
+			}
+
+			let selected = self.selected;
+			for action in actions {
+				match action {
+					MenuAction::Up => self.select_previous(),
+					MenuAction::Down => self.select_next(),
+					MenuAction::Select if !self.entries.is_empty() => {
+						self.close(link)?;
+						return Ok(MenuStatus::Selected(self.selected))
+					}
+					MenuAction::Select => (),
+					MenuAction::Cancel => {
+						self.close(link)?;
+						return Ok(MenuStatus::Cancelled)
+					}
+				}
+			}
+			if self.selected != selected {
+				self.draw(link)?;
+			}
+		}
+	}
+
+	/// Opens the menu and blocks until an entry is selected, returning its index.
+	///
+	/// Returns `None` if the menu is cancelled or `timeout` elapses, the menu is closed in both cases.
+	pub fn run(
+		&mut self,
+		link: &mut MpvLink,
+		timeout: Option<Duration>
+	) -> Result<Option<usize>, CommandError<ParseError>> {
+		let deadline = timeout.map(|timeout| Instant::now() + timeout);
+		self.open(link)?;
+
+		loop {
+			match self.poll(link)? {
+				MenuStatus::Open => (),
+				MenuStatus::Selected(index) => return Ok(Some(index)),
+				MenuStatus::Cancelled => return Ok(None)
+			}
+
+			let remaining = match deadline {
+				None => None,
+				Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+					Some(remaining) if !remaining.is_zero() => Some(remaining),
+					_ => {
+						self.close(link)?;
+						return Ok(None)
+					}
+				}
+			};
+			match link.wait_read(remaining) {
+				// the deadline is checked above
+				Err(err) if err.kind() == std::io::ErrorKind::TimedOut => (),
+				result => result.map_err(ReceiveError::Io)?
+			}
+		}
+	}
+
+	/// Moves the selection up, wrapping around to the last entry.
+	pub fn select_previous(&mut self) {
+		self.selected = match self.selected {
+			0 => self.entries.len().saturating_sub(1),
+			selected => selected - 1
+		};
+	}
+
+	/// Moves the selection down, wrapping around to the first entry.
+	pub fn select_next(&mut self) {
+		self.selected = match self.selected + 1 {
+			next if next >= self.entries.len() => 0,
+			next => next
+		};
+	}
+
+	/// Renders the menu as an ASS event, with the visible entries and the selected one highlighted.
+	pub fn render(&self) -> String {
+		let mut ass = String::from("{\\an7\\pos(30,30)\\fs28\\bord2}");
+		if let Some(title) = self.title.as_deref() {
+			let _ = write!(ass, "{{\\b1}}{}{{\\b0}}\\N", ass_escape(title));
+		}
+
+		// keeps the selection in the middle of the window where possible
+		let first = self
+			.selected
+			.saturating_sub(self.max_visible / 2)
+			.min(self.entries.len().saturating_sub(self.max_visible));
+		let last = (first + self.max_visible).min(self.entries.len());
+		if first > 0 {
+			ass.push_str("…\\N");
+		}
+		for (index, entry) in self.entries[first .. last].iter().enumerate() {
+			match first + index == self.selected {
+				true => {
+					let _ = write!(ass, "{{\\c&H00FFFF&}}› {}{{\\c}}\\N", ass_escape(entry));
+				}
+				false => {
+					let _ = write!(ass, "  {}\\N", ass_escape(entry));
+				}
+			}
+		}
+		if last < self.entries.len() {
+			ass.push_str("…\\N");
+		}
+
+		ass
+	}
+
+	fn draw(&self, link: &mut MpvLink) -> Result<(), CommandError<ParseError>> {
+		link.run_command_named(&CmdOsdOverlay::new(
+			self.overlay_id,
+			Cow::Owned(self.render())
+		))
+	}
+}
+
+/// Seek bar drawn along the bottom of the video with `osd-overlay`, for frontends without their own
+/// window that still want visual feedback.
+///
+/// The bar follows `time-pos` and `duration`, but is redrawn at most once per `interval` to limit the
+/// traffic. It is hidden while no file with a known duration plays. Call `observe` once, then pass all
+/// events to `update`.
+///
+/// The bar is drawn on a 1280×720 canvas which is stretched to the window, so its sizes are in those
+/// units.
+#[derive(Debug, Clone)]
+pub struct ProgressBar {
+	overlay_id: u32,
+	interval: Duration,
+	height: u32,
+	margin: u32,
+	/// Colors as `0xRRGGBB`.
+	fill_color: u32,
+	background_color: u32,
+	time_pos: Option<f64>,
+	duration: Option<f64>,
+	last_drawn: Option<Instant>,
+	shown: bool
+}
+impl ProgressBar {
+	const CANVAS_HEIGHT: u32 = 720;
+	const CANVAS_WIDTH: u32 = 1280;
+	pub const DEFAULT_OVERLAY_ID: u32 = 2;
+
+	pub fn new() -> Self {
+		ProgressBar {
+			overlay_id: Self::DEFAULT_OVERLAY_ID,
+			interval: Duration::from_millis(250),
+			height: 8,
+			margin: 24,
+			fill_color: 0xFFFFFF,
+			background_color: 0x404040,
+			time_pos: None,
+			duration: None,
+			last_drawn: None,
+			shown: false
+		}
+	}
+
+	/// Sets the shortest time between redraws.
+	pub fn with_interval(mut self, interval: Duration) -> Self {
+		self.interval = interval;
+		self
+	}
+
+	/// Sets the height of the bar and its distance from the edges of the canvas.
+	pub fn with_size(mut self, height: u32, margin: u32) -> Self {
+		self.height = height;
+		self.margin = margin;
+		self
+	}
+
+	/// Sets the colors of the played part and of the rest as `0xRRGGBB`.
+	pub fn with_colors(mut self, fill_color: u32, background_color: u32) -> Self {
+		self.fill_color = fill_color;
+		self.background_color = background_color;
+		self
+	}
+
+	/// Sets the id of the overlay, so that it does not replace another overlay of this client.
+	pub fn with_overlay_id(mut self, overlay_id: u32) -> Self {
+		self.overlay_id = overlay_id;
+		self
+	}
+
+	/// Played part of the file from `0` to `1`, `None` while it is unknown.
+	pub fn progress(&self) -> Option<f64> {
+		match (self.time_pos, self.duration) {
+			(Some(time_pos), Some(duration)) if duration > 0.0 => {
+				Some((time_pos / duration).clamp(0.0, 1.0))
+			}
+			_ => None
+		}
+	}
+
+	/// Starts observing the properties the bar follows in `cache`.
+	pub fn observe(
+		cache: &mut PropertyCache,
+		link: &mut MpvLink
+	) -> Result<(), CommandError<ParseError>> {
+		cache.observe(link, property::TimePos)?;
+		cache.observe(link, property::Duration)?;
+
+		Ok(())
+	}
+
+	/// Updates the bar from `event`, redrawing it if `interval` elapsed since the last draw.
+	pub fn update(
+		&mut self,
+		link: &mut MpvLink,
+		event: &MpvResponseEvent
+	) -> Result<(), CommandError<ParseError>> {
+		let (property, data) = match event {
+			MpvResponseEvent::PropertyChange {
+				name: MpvResponseEventPropertyName::Known(property),
+				data,
+				..
+			} => (property, data),
+			_ => return Ok(())
+		};
+		let force = match property {
+			KnownMpvProperty::TimePos => {
+				self.time_pos = data.as_f64();
+				false
+			}
+			KnownMpvProperty::Duration => {
+				self.duration = data.as_f64();
+				true
+			}
+			_ => return Ok(())
+		};
+
+		let due = self
+			.last_drawn
+			.is_none_or(|last_drawn| last_drawn.elapsed() >= self.interval);
+		if force || due || self.progress().is_none() {
+			self.draw(link)?;
+		}
+
+		Ok(())
+	}
+
+	/// Draws the bar now, or hides it if the progress is unknown.
+	pub fn draw(&mut self, link: &mut MpvLink) -> Result<(), CommandError<ParseError>> {
+		match self.render() {
+			Some(ass) => {
+				link.run_command_named(
+					&CmdOsdOverlay::new(self.overlay_id, Cow::Owned(ass))
+						.with_resolution(Self::CANVAS_WIDTH, Self::CANVAS_HEIGHT)
+				)?;
+				self.last_drawn = Some(Instant::now());
+				self.shown = true;
+			}
+			None => self.hide(link)?
+		}
+
+		Ok(())
+	}
+
+	/// Removes the bar until the next draw.
+	pub fn hide(&mut self, link: &mut MpvLink) -> Result<(), CommandError<ParseError>> {
+		if self.shown {
+			link.run_command_named(&CmdOsdOverlay::remove(self.overlay_id))?;
+			self.shown = false;
+		}
+
+		Ok(())
+	}
+
+	/// Renders the bar as two ASS events with drawings, the background and the played part on top.
+	pub fn render(&self) -> Option<String> {
+		let progress = self.progress()?;
+
+		let left = self.margin;
+		let right = Self::CANVAS_WIDTH.saturating_sub(self.margin).max(left);
+		let bottom = Self::CANVAS_HEIGHT.saturating_sub(self.margin);
+		let top = bottom.saturating_sub(self.height);
+		let filled = left + ((right - left) as f64 * progress).round() as u32;
+
+		let mut ass = String::new();
+		let mut rectangle = |color: u32, right: u32| {
+			let _ = writeln!(
+				ass,
+				"{{\\an7\\pos(0,0)\\bord0\\shad0\\1c&H{}&\\p1}}m {} {} l {} {} {} {} {} {}{{\\p0}}",
+				ass_color(color),
+				left,
+				top,
+				right,
+				top,
+				right,
+				bottom,
+				left,
+				bottom
+			);
+		};
+		rectangle(self.background_color, right);
+		// an empty rectangle would still be drawn as a line
+		if filled > left {
+			rectangle(self.fill_color, filled);
+		}
+
+		Some(ass)
+	}
+}
+impl Default for ProgressBar {
+	fn default() -> Self {
+		ProgressBar::new()
+	}
+}
+
+/// Converts `0xRRGGBB` to the `BBGGRR` hex digits of ASS colors.
+fn ass_color(color: u32) -> String {
+	let [_, red, green, blue] = color.to_be_bytes();
+	format!("{:02X}{:02X}{:02X}", blue, green, red)
+}
+
+#[cfg(test)]
+mod test {
+	use super::{ass_escape, Menu, ProgressBar};
+
+	#[test]
+	fn test_ass_escape() {
+		assert_eq!(ass_escape("{\\b1}a\nb"), "\\{\\\u{feff}b1\\}a\\Nb");
+	}
+
+	#[test]
+	fn test_navigation() {
+		let mut menu = Menu::new(["a", "b", "c"]);
+		menu.select_previous();
+		assert_eq!(menu.selected(), 2);
+		menu.select_next();
+		assert_eq!(menu.selected(), 0);
+
+		let mut empty = Menu::new(Vec::<String>::new());
+		empty.select_next();
+		empty.select_previous();
+		assert_eq!(empty.selected(), 0);
+	}
+
+	#[test]
+	fn test_progress_bar() {
+		let mut bar = ProgressBar::new()
+			.with_size(10, 40)
+			.with_colors(0xFF8000, 0x000000);
+		assert_eq!(bar.render(), None);
+
+		bar.time_pos = Some(30.0);
+		bar.duration = Some(120.0);
+		assert_eq!(bar.progress(), Some(0.25));
+		assert_eq!(
+			bar.render().unwrap(),
+			"{\\an7\\pos(0,0)\\bord0\\shad0\\1c&H000000&\\p1}m 40 670 l 1240 670 1240 680 40 680{\\p0}\n\
+			 {\\an7\\pos(0,0)\\bord0\\shad0\\1c&H0080FF&\\p1}m 40 670 l 340 670 340 680 40 680{\\p0}\n"
+		);
+
+		bar.time_pos = Some(0.0);
+		assert_eq!(bar.render().unwrap().lines().count(), 1);
+	}
+
+	#[test]
+	fn test_render() {
+		let menu = Menu::new(["a", "b", "c", "d"])
+			.with_title("Tracks {1}")
+			.with_selected(3)
+			.with_max_visible(2);
+
+		assert_eq!(
+			menu.render(),
+			"{\\an7\\pos(30,30)\\fs28\\bord2}{\\b1}Tracks \\{1\\}{\\b0}\\N…\\N  c\\N{\\c&H00FFFF&}› d{\\c}\\N"
+		);
+	}
+}
@@ -0,0 +1,834 @@
+use std::{borrow::Cow, num::NonZeroI64, time::Duration};
+
+use thiserror::Error;
+
+use crate::{
+	command::{
+		commands::{
+			ChangeListOperation,
+			ChapterOutOfRangeError,
+			CmdChangeList,
+			CmdCycleValues,
+			CmdDeleteWatchLaterConfig,
+			CmdDumpCache,
+			CmdGetProperty,
+			CmdKeypress,
+			CmdLoadScript,
+			CmdLoadfile,
+			CmdObserveProperty,
+			CmdPlaylistNext,
+			CmdPlaylistPlayIndex,
+			CmdPlaylistPrev,
+			CmdSetChapter,
+			CmdSetEdition,
+			CmdSetProperty,
+			CmdStop,
+			CmdUnobserveProperty,
+			CmdWriteWatchLaterConfig,
+			DumpCacheBound,
+			InvalidDumpCacheBoundError,
+			UnknownEditionError
+		},
+		property::{self, ReadableMpvProperty, WritableMpvProperty},
+		response::{MpvResponseEvent, MpvResponseResultError},
+		Async,
+		MpvCommand,
+		ParseError
+	},
+	link::{CommandError, MpvLink, ReceiveError, SendError},
+	model::{
+		AudioDeviceList,
+		EndFileReason,
+		FileloadInfo,
+		Filter,
+		FilterChain,
+		LoadScriptInfo,
+		StreamQuality,
+		TrackId,
+		TrackType
+	},
+	property_cache::PropertyCache
+};
+
+/// Observer id of `idle-active` in `MpvPlayer::wait_until_idle`, above the ids of `PropertyCache::new`,
+/// property bindings and `PlayerModel`.
+const IDLE_OBSERVER_ID: u32 = 1 << 27;
+
+/// Bounds of the `speed` property.
+const MIN_SPEED: f64 = 0.01;
+const MAX_SPEED: f64 = 100.0;
+
+#[derive(Debug, Error)]
+pub enum PlayerError {
+	#[error(transparent)]
+	Send(#[from] SendError),
+	#[error(transparent)]
+	Receive(#[from] ReceiveError),
+	#[error("Received error response: {0}")]
+	Result(MpvResponseResultError),
+	#[error("Error while parsing response data: {0}")]
+	DataParse(Box<dyn std::error::Error + Send + Sync>),
+	#[error("The playlist is empty")]
+	NoPlaylist,
+	#[error("Playlist index {index} is out of range, the playlist has {count} entries")]
+	IndexOutOfRange { index: u32, count: u32 },
+	#[error(transparent)]
+	ChapterOutOfRange(#[from] ChapterOutOfRangeError),
+	#[error(transparent)]
+	UnknownEdition(#[from] UnknownEditionError),
+	#[error("Speed {0} is out of range, it must be between 0.01 and 100")]
+	SpeedOutOfRange(f64),
+	#[error("Unknown audio device {0:?}")]
+	UnknownAudioDevice(String),
+	#[error(transparent)]
+	InvalidDumpCacheBound(#[from] InvalidDumpCacheBoundError),
+	#[error("Dumping the cache with an unbounded end never completes, use start_dump_cache")]
+	UnboundedDumpCache,
+	#[error("The result of the cache dump was discarded before it was polled")]
+	DumpCacheResultLost,
+	#[error("mpv shut down")]
+	Shutdown
+}
+impl<E: std::error::Error + Send + Sync + 'static> From<CommandError<E>> for PlayerError {
+	fn from(err: CommandError<E>) -> Self {
+		match err {
+			CommandError::SendError(err) => PlayerError::Send(err),
+			CommandError::ReceiveError(err) => PlayerError::Receive(err),
+			CommandError::ResultError(err) => PlayerError::Result(err),
+			CommandError::DataParseError(err) => PlayerError::DataParse(Box::new(err))
+		}
+	}
+}
+
+/// How loading a file with `MpvPlayer::load_and_wait` turned out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadOutcome {
+	/// The file was loaded and playback started, or is ready to start if paused.
+	Started,
+	/// The file could not be opened or played.
+	Failed {
+		/// Description of the error, if mpv sent one.
+		file_error: Option<String>
+	},
+	/// Playback of the file ended before it started for another reason, for example it was stopped.
+	Ended { reason: Option<EndFileReason> }
+}
+
+/// Cache dump started with `MpvPlayer::start_dump_cache`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DumpCacheJob(NonZeroI64);
+impl DumpCacheJob {
+	/// Request id of the async `dump-cache` command.
+	pub fn request_id(&self) -> NonZeroI64 {
+		self.0
+	}
+}
+
+/// High-level facade over `MpvLink` with player operations built on the typed commands.
+///
+/// The link stays accessible for anything the facade does not cover.
+///
+/// With a property cache attached, operations read observed properties from it instead of asking mpv. The
+/// cache must be kept up to date by passing it the events of the link.
+pub struct MpvPlayer {
+	link: MpvLink,
+	cache: Option<PropertyCache>
+}
+impl MpvPlayer {
+	pub fn new(link: MpvLink) -> Self {
+		MpvPlayer { link, cache: None }
+	}
+
+	/// Attaches `cache` to read property values from.
+	pub fn with_cache(mut self, cache: PropertyCache) -> Self {
+		self.cache = Some(cache);
+		self
+	}
+
+	pub fn cache(&self) -> Option<&PropertyCache> {
+		self.cache.as_ref()
+	}
+
+	pub fn cache_mut(&mut self) -> Option<&mut PropertyCache> {
+		self.cache.as_mut()
+	}
+
+	pub fn link(&self) -> &MpvLink {
+		&self.link
+	}
+
+	pub fn link_mut(&mut self) -> &mut MpvLink {
+		&mut self.link
+	}
+
+	pub fn into_link(self) -> MpvLink {
+		self.link
+	}
+
+	/// Runs `command` on the link, converting the error into `PlayerError`.
+	pub fn run<C: MpvCommand + ?Sized>(&mut self, command: &C) -> Result<C::ParsedData, PlayerError>
+	where
+		C::Error: Send + Sync + 'static
+	{
+		Ok(self.link.run_command(command)?)
+	}
+
+	pub fn playlist_count(&mut self) -> Result<u32, PlayerError> {
+		self.run(&CmdGetProperty::new(property::PlaylistCount))
+	}
+
+	/// Stops playback, clearing the playlist unless `keep_playlist` is set.
+	pub fn stop(&mut self, keep_playlist: bool) -> Result<(), PlayerError> {
+		self.run(&CmdStop::new(keep_playlist))?;
+
+		Ok(())
+	}
+
+	/// Plays the next playlist entry.
+	///
+	/// Fails with `NoPlaylist` when the playlist is empty.
+	#[allow(clippy::should_implement_trait)]
+	pub fn next(&mut self) -> Result<(), PlayerError> {
+		self.require_playlist()?;
+		self.run(&CmdPlaylistNext::new(false))?;
+
+		Ok(())
+	}
+
+	/// Plays the previous playlist entry.
+	///
+	/// Fails with `NoPlaylist` when the playlist is empty.
+	pub fn previous(&mut self) -> Result<(), PlayerError> {
+		self.require_playlist()?;
+		self.run(&CmdPlaylistPrev::new(false))?;
+
+		Ok(())
+	}
+
+	/// Plays the playlist entry at the zero-based `index`.
+	///
+	/// Fails with `NoPlaylist` when the playlist is empty and with `IndexOutOfRange` when there is no such entry.
+	pub fn play_index(&mut self, index: u32) -> Result<(), PlayerError> {
+		let count = self.require_playlist()?;
+		if index >= count {
+			return Err(PlayerError::IndexOutOfRange { index, count })
+		}
+		self.run(&CmdPlaylistPlayIndex::new(index))?;
+
+		Ok(())
+	}
+
+	/// Plays `path`, replacing the current file, and waits until playback starts or the file fails.
+	///
+	/// Playback started once the file is loaded and the first `playback-restart` follows. Only events received
+	/// after `loadfile` was sent are considered, and on mpv 0.34 and later they are matched by playlist entry id,
+	/// so events of the replaced file are ignored. Other events stay queued on the link. If `timeout` is not
+	/// `None` the wait fails with `TimedOut` once it elapses.
+	pub fn load_and_wait(
+		&mut self,
+		path: &str,
+		timeout: Option<Duration>
+	) -> Result<LoadOutcome, PlayerError> {
+		let start = self.link.peek_events().len();
+		let entry_id = self
+			.run(&CmdLoadfile::new(Cow::Borrowed(path)))?
+			.map(|info| info.playlist_entry_id);
+
+		let mut started = false;
+		let mut loaded = false;
+		let event = self.link.wait_for_event_after(
+			start,
+			|event| match event {
+				MpvResponseEvent::StartFile(info) => {
+					started |= entry_id.is_none_or(|id| id == info.playlist_entry_id);
+					false
+				}
+				MpvResponseEvent::FileLoaded => {
+					loaded |= started;
+					false
+				}
+				MpvResponseEvent::PlaybackRestart => loaded,
+				MpvResponseEvent::EndFile {
+					playlist_entry_id, ..
+				} => match (entry_id, playlist_entry_id) {
+					(Some(id), Some(ended_id)) => id == *ended_id,
+					_ => started
+				},
+				_ => false
+			},
+			timeout
+		)?;
+
+		Ok(match event {
+			MpvResponseEvent::EndFile {
+				reason: Some(EndFileReason::Error),
+				file_error,
+				..
+			} => LoadOutcome::Failed { file_error },
+			MpvResponseEvent::EndFile { reason, .. } => LoadOutcome::Ended { reason },
+			_ => LoadOutcome::Started
+		})
+	}
+
+	/// Waits until mpv is idle, that is the playlist finished or was stopped and mpv waits for more files.
+	///
+	/// Returns right away if mpv is idle already. mpv only stays idle when started with `--idle`, otherwise
+	/// it quits at the end of the playlist and this fails with `Shutdown`. If `timeout` is not `None` the wait
+	/// fails with `TimedOut` once it elapses.
+	pub fn wait_until_idle(&mut self, timeout: Option<Duration>) -> Result<(), PlayerError> {
+		// the first change carries the current value
+		self.run(&CmdObserveProperty::new(
+			IDLE_OBSERVER_ID,
+			property::IdleActive
+		))?;
+		let result = self.link.wait_for_event(
+			|event| match event {
+				MpvResponseEvent::PropertyChange { id, data, .. } => {
+					*id == i64::from(IDLE_OBSERVER_ID) && data.as_bool() == Some(true)
+				}
+				MpvResponseEvent::Shutdown => true,
+				_ => false
+			},
+			timeout
+		);
+		// unobserve first so that changes received until then are removed as well, a stale change would
+		// satisfy the next wait right away
+		let unobserved = match result {
+			Ok(MpvResponseEvent::Shutdown) => Ok(()),
+			_ => self.run(&CmdUnobserveProperty::new(IDLE_OBSERVER_ID))
+		};
+		self.link.retain_events(|event| {
+			!matches!(
+				event,
+				MpvResponseEvent::PropertyChange { id, .. } if *id == i64::from(IDLE_OBSERVER_ID)
+			)
+		});
+
+		match result {
+			Ok(MpvResponseEvent::Shutdown) => Err(PlayerError::Shutdown),
+			Ok(_) => unobserved,
+			Err(err) => {
+				// do not leave the observer behind after a timeout
+				if let Err(unobserve_err) = unobserved {
+					log::debug!("Could not unobserve idle-active: {}", unobserve_err);
+				}
+				Err(err.into())
+			}
+		}
+	}
+
+	/// Waits until mpv shuts down, which is when it sends `shutdown`, the connection closes or the spawned
+	/// process exits.
+	///
+	/// Events received before stay queued on the link.
+	pub fn wait_for_shutdown(&mut self) -> Result<(), PlayerError> {
+		let result = self.link.wait_for_event(
+			|event| {
+				matches!(
+					event,
+					MpvResponseEvent::Shutdown | MpvResponseEvent::ProcessExited { .. }
+				)
+			},
+			None
+		);
+
+		match result {
+			Ok(_) => Ok(()),
+			Err(err) if err.is_disconnect() => Ok(()),
+			Err(err) => Err(err.into())
+		}
+	}
+
+	/// Plays `url` through youtube-dl in `quality`, replacing the current file.
+	///
+	/// The format is set as a per-file option, so it does not change the `ytdl-format` of other files.
+	pub fn load_url(
+		&mut self,
+		url: &str,
+		quality: &StreamQuality
+	) -> Result<Option<FileloadInfo>, PlayerError> {
+		self.run(
+			&CmdLoadfile::new(Cow::Borrowed(url))
+				.with_option(Cow::Borrowed("ytdl-format"), quality.ytdl_format())
+		)
+	}
+
+	/// Jumps to the chapter at the zero-based `index` of the current file.
+	///
+	/// Fails with `ChapterOutOfRange` when the file has no such chapter.
+	pub fn set_chapter(&mut self, index: u32) -> Result<(), PlayerError> {
+		let chapters = self.run(&CmdGetProperty::new(property::Chapters))?;
+		self.run(&CmdSetChapter::new(index, chapters)?)?;
+
+		Ok(())
+	}
+
+	/// Switches the current file to the edition with `id`, which reloads it.
+	///
+	/// Fails with `UnknownEdition` when the file has no such edition.
+	pub fn set_edition(&mut self, id: i64) -> Result<(), PlayerError> {
+		let editions = self.run(&CmdGetProperty::new(property::EditionList))?;
+		self.run(&CmdSetEdition::new(id, &editions)?)?;
+
+		Ok(())
+	}
+
+	/// Sets the playback speed, enabling `audio-pitch-correction` so that the audio keeps its pitch.
+	///
+	/// Fails with `SpeedOutOfRange` for speeds mpv does not accept.
+	pub fn set_speed(&mut self, speed: f64) -> Result<(), PlayerError> {
+		if !(MIN_SPEED ..= MAX_SPEED).contains(&speed) {
+			return Err(PlayerError::SpeedOutOfRange(speed))
+		}
+		self.run(&CmdSetProperty::new(property::AudioPitchCorrection, true))?;
+		self.run(&CmdSetProperty::new(property::Speed, speed))?;
+
+		Ok(())
+	}
+
+	/// Sets the speed after the current one in `speeds`, wrapping around, and returns it.
+	///
+	/// If the current speed is not in `speeds` the first one is set. Pitch correction is enabled like with
+	/// `set_speed`.
+	pub fn cycle_speed(&mut self, speeds: &[f64]) -> Result<f64, PlayerError> {
+		if let Some(&speed) = speeds
+			.iter()
+			.find(|speed| !(MIN_SPEED ..= MAX_SPEED).contains(*speed))
+		{
+			return Err(PlayerError::SpeedOutOfRange(speed))
+		}
+		self.run(&CmdSetProperty::new(property::AudioPitchCorrection, true))?;
+		self.run(&CmdCycleValues::new(property::Speed, speeds.to_vec()))?;
+
+		self.run(&CmdGetProperty::new(property::Speed))
+	}
+
+	/// Raises the volume by `step`, up to `volume-max`, and returns the new volume.
+	///
+	/// Mute is left as it is, unmute with `set_mute` to hear the change.
+	pub fn volume_up(&mut self, step: f64) -> Result<f64, PlayerError> {
+		self.change_volume(step)
+	}
+
+	/// Lowers the volume by `step`, down to zero, and returns the new volume.
+	///
+	/// Mute is left as it is like with `volume_up`.
+	pub fn volume_down(&mut self, step: f64) -> Result<f64, PlayerError> {
+		self.change_volume(-step)
+	}
+
+	fn change_volume(&mut self, step: f64) -> Result<f64, PlayerError> {
+		let volume = self.get_cached(property::Volume)?;
+		let volume_max = self.get_cached(property::VolumeMax)?;
+		let volume = (volume + step).clamp(0.0, volume_max);
+
+		self.set_cached(property::Volume, volume)?;
+
+		Ok(volume)
+	}
+
+	/// Mutes or unmutes the audio, doing nothing if it already is.
+	pub fn set_mute(&mut self, mute: bool) -> Result<(), PlayerError> {
+		if self.get_cached(property::Mute)? != mute {
+			self.set_cached(property::Mute, mute)?;
+		}
+
+		Ok(())
+	}
+
+	/// Toggles mute and returns whether the audio is muted now.
+	pub fn toggle_mute(&mut self) -> Result<bool, PlayerError> {
+		let mute = !self.get_cached(property::Mute)?;
+		self.set_cached(property::Mute, mute)?;
+
+		Ok(mute)
+	}
+
+	pub fn audio_devices(&mut self) -> Result<AudioDeviceList, PlayerError> {
+		self.get_cached(property::AudioDeviceList)
+	}
+
+	/// Switches audio output to the device named `name`, as listed by `audio_devices`.
+	///
+	/// Fails with `UnknownAudioDevice` if there is no such device, mpv itself would accept the name and only
+	/// fail to open the device later.
+	pub fn set_audio_device(&mut self, name: &str) -> Result<(), PlayerError> {
+		if self.audio_devices()?.get(name).is_none() {
+			return Err(PlayerError::UnknownAudioDevice(name.to_string()))
+		}
+
+		self.set_cached(property::AudioDevice, name.to_string())
+	}
+
+	/// Shifts the subtitles by `delta` seconds, positive values show them later, and returns the new delay.
+	pub fn adjust_sub_delay(&mut self, delta: f64) -> Result<f64, PlayerError> {
+		let delay = self.get_cached(property::SubDelay)? + delta;
+		self.set_cached(property::SubDelay, delay)?;
+
+		Ok(delay)
+	}
+
+	/// Sets the size of text subtitles relative to the default, `1.0` being the default size.
+	pub fn set_sub_scale(&mut self, scale: f64) -> Result<(), PlayerError> {
+		self.set_cached(property::SubScale, scale)
+	}
+
+	/// Selects the first subtitle track in `lang`, compared without regard to ASCII case.
+	///
+	/// Returns the id of the selected track, or `None` without changing the selection if there is no such
+	/// track. Language tags are compared as they are, so `en` does not match a track tagged `eng`.
+	pub fn select_subtitle_lang(&mut self, lang: &str) -> Result<Option<u32>, PlayerError> {
+		let tracks = self.get_cached(property::TrackList)?;
+		let id = match tracks.iter().find(|track| {
+			track.track_type == TrackType::Sub
+				&& track
+					.lang
+					.as_deref()
+					.is_some_and(|track_lang| track_lang.eq_ignore_ascii_case(lang))
+		}) {
+			None => return Ok(None),
+			Some(track) => track.id
+		};
+		self.run(&CmdSetProperty::new(property::Sid, TrackId::Index(id)))?;
+
+		Ok(Some(id))
+	}
+
+	/// Shows or hides the subtitles and returns whether they are visible now.
+	pub fn toggle_sub_visibility(&mut self) -> Result<bool, PlayerError> {
+		let visible = !self.get_cached(property::SubVisibility)?;
+		self.set_cached(property::SubVisibility, visible)?;
+
+		Ok(visible)
+	}
+
+	/// Enters or leaves fullscreen and returns whether the window is fullscreen now.
+	pub fn toggle_fullscreen(&mut self) -> Result<bool, PlayerError> {
+		let fullscreen = !self.get_cached(property::Fullscreen)?;
+		self.set_cached(property::Fullscreen, fullscreen)?;
+
+		Ok(fullscreen)
+	}
+
+	/// Resizes the window to `scale` times the video size, `1.0` being the native size.
+	///
+	/// Sets `current-window-scale`, so the `window-scale` option used for the next files stays unchanged.
+	pub fn set_window_scale(&mut self, scale: f64) -> Result<(), PlayerError> {
+		self.set_cached(property::CurrentWindowScale, scale)
+	}
+
+	/// Reads `property` from the cache if it has a value, otherwise from mpv.
+	fn get_cached<P: ReadableMpvProperty + Copy>(
+		&mut self,
+		property: P
+	) -> Result<P::Value, PlayerError>
+	where
+		P::Value: Clone + 'static
+	{
+		if let Some(value) = self
+			.cache
+			.as_ref()
+			.and_then(|cache| cache.get_value(&property))
+		{
+			return Ok(value.clone())
+		}
+
+		self.run(&CmdGetProperty::new(property))
+	}
+
+	/// Sets `property` and updates the cache, so that a following `get_cached` does not read a stale value.
+	fn set_cached<P: WritableMpvProperty + Copy>(
+		&mut self,
+		property: P,
+		value: P::Value
+	) -> Result<(), PlayerError>
+	where
+		P::Value: Clone + 'static
+	{
+		self.run(&CmdSetProperty::new(property, value.clone()))?;
+		if let Some(cache) = self.cache.as_mut() {
+			cache.set_value(&property, value);
+		}
+
+		Ok(())
+	}
+
+	/// Returns the playlist count, or `NoPlaylist` if it is zero.
+	fn require_playlist(&mut self) -> Result<u32, PlayerError> {
+		match self.playlist_count()? {
+			0 => Err(PlayerError::NoPlaylist),
+			count => Ok(count)
+		}
+	}
+
+	pub fn filters(&mut self, chain: FilterChain) -> Result<Vec<Filter>, PlayerError> {
+		match chain {
+			FilterChain::Audio => self.run(&CmdGetProperty::new(property::Af)),
+			FilterChain::Video => self.run(&CmdGetProperty::new(property::Vf))
+		}
+	}
+
+	/// Appends `filter` to the end of `chain`.
+	pub fn add_filter(&mut self, chain: FilterChain, filter: &Filter) -> Result<(), PlayerError> {
+		self.change_filters(
+			chain,
+			ChangeListOperation::Append,
+			filter.to_option_string()
+		)
+	}
+
+	/// Removes the filter labeled `label` from `chain`.
+	pub fn remove_filter(&mut self, chain: FilterChain, label: &str) -> Result<(), PlayerError> {
+		self.change_filters(chain, ChangeListOperation::Remove, format!("@{}", label))
+	}
+
+	/// Enables or disables the filter labeled `label` in `chain`.
+	pub fn toggle_filter(&mut self, chain: FilterChain, label: &str) -> Result<(), PlayerError> {
+		self.change_filters(chain, ChangeListOperation::Toggle, format!("@{}", label))
+	}
+
+	fn change_filters(
+		&mut self,
+		chain: FilterChain,
+		operation: ChangeListOperation,
+		value: String
+	) -> Result<(), PlayerError> {
+		self.run(&CmdChangeList::new(
+			Cow::Borrowed(chain.property_name()),
+			operation,
+			Cow::Owned(value)
+		))?;
+
+		Ok(())
+	}
+
+	/// Loads the script at `path` and waits until it sends `registration_message` with `script-message`.
+	///
+	/// This lets the caller know that the script has registered its bindings and message handlers.
+	/// Events received while waiting stay queued on the link. If `timeout` is not `None` the wait fails
+	/// with `TimedOut` once it elapses.
+	pub fn load_script(
+		&mut self,
+		path: &str,
+		registration_message: &str,
+		timeout: Option<Duration>
+	) -> Result<Option<LoadScriptInfo>, PlayerError> {
+		// a registration message of an earlier load of the script may still be queued
+		let start = self.link.peek_events().len();
+		let info = self.run(&CmdLoadScript::new(Cow::Borrowed(path)))?;
+
+		self.link.wait_for_event_after(
+			start,
+			|event| match event {
+				MpvResponseEvent::Unknown { event, rest } if event == "client-message" => {
+					rest.get("args")
+						.and_then(|args| args.get(0))
+						.and_then(|message| message.as_str())
+						== Some(registration_message)
+				}
+				_ => false
+			},
+			timeout
+		)?;
+
+		Ok(info)
+	}
+
+	/// Presses `key` as if it was typed, without waiting for its binding to run.
+	pub fn keypress(&mut self, key: &str) -> Result<(), PlayerError> {
+		self.link
+			.run_command_raw::<_, ParseError>(&CmdKeypress::new(Cow::Borrowed(key)))?;
+
+		Ok(())
+	}
+
+	/// Dumps the cached stream between `start` and `end` into `filename`, returning once the dump completed.
+	///
+	/// An unbounded `end` is rejected because the dump would only complete once aborted, use `start_dump_cache`
+	/// for it.
+	pub fn dump_cache(
+		&mut self,
+		start: DumpCacheBound,
+		end: DumpCacheBound,
+		filename: &str
+	) -> Result<(), PlayerError> {
+		if end == DumpCacheBound::Unbounded {
+			return Err(PlayerError::UnboundedDumpCache)
+		}
+		self.run(&CmdDumpCache::new(start, end, Cow::Borrowed(filename))?)?;
+
+		Ok(())
+	}
+
+	/// Starts dumping the cached stream between `start` and `end` into `filename` as an async command.
+	///
+	/// mpv notifies about the completion with the result of the command, check for it with `poll_dump_cache`.
+	/// A dump with an unbounded end keeps running until it is aborted, e.g. by `stop`.
+	pub fn start_dump_cache(
+		&mut self,
+		start: DumpCacheBound,
+		end: DumpCacheBound,
+		filename: &str
+	) -> Result<DumpCacheJob, PlayerError> {
+		let command = CmdDumpCache::new(start, end, Cow::Borrowed(filename))?;
+		let request_id = self
+			.link
+			.run_command_raw::<_, ParseError>(&Async(&command))?;
+
+		Ok(DumpCacheJob(request_id))
+	}
+
+	/// Polls for the completion of a dump started with `start_dump_cache`.
+	///
+	/// Returns `true` once the dump completed, or the error mpv reported for it.
+	pub fn poll_dump_cache(&mut self, job: &DumpCacheJob) -> Result<bool, PlayerError> {
+		self.link.poll_events()?;
+
+		match self.link.take_raw_result(job.0) {
+			Some(result) => {
+				result
+					.into_result()
+					.map_err(|err| PlayerError::Result(err.kind))?;
+
+				Ok(true)
+			}
+			None if self.link.is_raw_pending(job.0) => Ok(false),
+			None => Err(PlayerError::DumpCacheResultLost)
+		}
+	}
+
+	/// Saves the position in the current file, which mpv resumes from the next time the file is played.
+	pub fn save_position(&mut self) -> Result<(), PlayerError> {
+		self.run(&CmdWriteWatchLaterConfig::new())?;
+
+		Ok(())
+	}
+
+	/// Deletes the saved position of `file_path`, or of the current file if `None`.
+	pub fn forget_position(&mut self, file_path: Option<&str>) -> Result<(), PlayerError> {
+		self.run(&CmdDeleteWatchLaterConfig::new(
+			file_path.map(Cow::Borrowed)
+		))?;
+
+		Ok(())
+	}
+}
+impl From<MpvLink> for MpvPlayer {
+	fn from(link: MpvLink) -> Self {
+		MpvPlayer::new(link)
+	}
+}
+
+#[cfg(all(test, unix))]
+mod test {
+	use std::{io::Write, time::Duration};
+
+	use super::{LoadOutcome, MpvPlayer, IDLE_OBSERVER_ID};
+	use crate::{
+		command::{commands::CmdGetProperty, property},
+		link::{fake_mpv, MpvLink}
+	};
+
+	#[test]
+	fn test_load_and_wait_ignores_stale_events() {
+		let path = std::env::temp_dir().join(format!("mpvsock-player-{}.sock", std::process::id()));
+		let server = fake_mpv::listen(&path, |requests, socket| {
+			// events of the previous file, received before `loadfile`
+			socket
+				.write_all(
+					b"{\"event\":\"start-file\",\"playlist_entry_id\":1}\n{\"event\":\"file-loaded\"}\n{\"event\":\"playback-restart\"}\n"
+				)
+				.unwrap();
+			fake_mpv::answer(requests, &mut *socket, &["50.5"]);
+
+			// `loadfile` without data like older mpv versions, the file then fails to load
+			fake_mpv::answer(requests, &mut *socket, &["null"]);
+			socket
+				.write_all(b"{\"event\":\"start-file\",\"playlist_entry_id\":2}\n{\"event\":\"end-file\",\"reason\":\"error\",\"file_error\":\"unrecognized file format\"}\n")
+				.unwrap();
+		});
+
+		let link = MpvLink::connect(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		let mut player = MpvPlayer::new(link);
+		player.run(&CmdGetProperty::new(property::Volume)).unwrap();
+		assert_eq!(player.link().peek_events().len(), 3);
+
+		let outcome = player
+			.load_and_wait("/a.mkv", Some(Duration::from_secs(5)))
+			.unwrap();
+		assert_eq!(
+			outcome,
+			LoadOutcome::Failed {
+				file_error: Some("unrecognized file format".to_string())
+			}
+		);
+		assert_eq!(player.link().peek_events().len(), 4);
+		let _socket = server.join().unwrap();
+	}
+
+	#[test]
+	fn test_load_script_ignores_stale_registration() {
+		let path = std::env::temp_dir().join(format!(
+			"mpvsock-player-script-{}.sock",
+			std::process::id()
+		));
+		let server = fake_mpv::listen(&path, |requests, socket| {
+			// registration of an earlier load of the script, received before `load-script`
+			socket
+				.write_all(b"{\"event\":\"client-message\",\"args\":[\"registered\"]}\n")
+				.unwrap();
+			fake_mpv::answer(requests, &mut *socket, &["50.5"]);
+
+			fake_mpv::answer(requests, &mut *socket, &["{\"client_id\":3}"]);
+			std::thread::sleep(Duration::from_millis(50));
+			socket
+				.write_all(b"{\"event\":\"client-message\",\"args\":[\"registered\"]}\n")
+				.unwrap();
+		});
+
+		let link = MpvLink::connect(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		let mut player = MpvPlayer::new(link);
+		player.run(&CmdGetProperty::new(property::Volume)).unwrap();
+		assert_eq!(player.link().peek_events().len(), 1);
+
+		let info = player
+			.load_script("/script.lua", "registered", Some(Duration::from_secs(5)))
+			.unwrap();
+		assert_eq!(info.map(|info| info.client_id), Some(3));
+		// only the new registration was taken from the queue
+		assert_eq!(player.link().peek_events().len(), 1);
+		let _socket = server.join().unwrap();
+	}
+
+	#[test]
+	fn test_wait_until_idle_removes_late_changes() {
+		let path = std::env::temp_dir().join(format!(
+			"mpvsock-player-idle-{}.sock",
+			std::process::id()
+		));
+		let idle_change = format!(
+			"{{\"event\":\"property-change\",\"id\":{},\"name\":\"idle-active\",\"data\":true}}\n",
+			IDLE_OBSERVER_ID
+		);
+		let server = fake_mpv::listen(&path, move |requests, socket| {
+			fake_mpv::answer(requests, &mut *socket, &["null"]);
+			socket.write_all(idle_change.as_bytes()).unwrap();
+
+			// a change received while `unobserve_property` is in flight
+			let line = requests.next().unwrap().unwrap();
+			socket.write_all(idle_change.as_bytes()).unwrap();
+			fake_mpv::write_result(&mut *socket, &line, "null");
+		});
+
+		let link = MpvLink::connect(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		let mut player = MpvPlayer::new(link);
+		player
+			.wait_until_idle(Some(Duration::from_secs(5)))
+			.unwrap();
+		assert_eq!(player.link().peek_events().len(), 0);
+		let _socket = server.join().unwrap();
+	}
+}
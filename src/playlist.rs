@@ -0,0 +1,246 @@
+use std::{
+	borrow::Cow,
+	fs,
+	io::{self, Write},
+	path::PathBuf,
+	sync::atomic::{AtomicU32, Ordering}
+};
+
+use thiserror::Error;
+
+use crate::{
+	command::{
+		commands::{CmdGetProperty, CmdLoadlist, LoadlistMode},
+		property::{self, MpvProperty},
+		ParseError
+	},
+	link::{batch::BatchedValues, CommandError, MpvLink},
+	property_cache::PropertyCache
+};
+
+#[derive(Debug, Error)]
+pub enum LoadPathsError {
+	#[error("Path {0:?} contains a line break and cannot be written to a playlist")]
+	LineBreak(String),
+	#[error("Could not write the playlist file: {0}")]
+	Io(#[from] io::Error),
+	#[error(transparent)]
+	Command(#[from] CommandError<ParseError>)
+}
+
+/// Writes an extended m3u playlist with one entry per path.
+///
+/// Fails with `InvalidInput` if a path contains a line break, since it cannot be represented.
+pub fn write_m3u<S: AsRef<str>>(
+	mut w: impl Write,
+	paths: impl IntoIterator<Item = S>
+) -> io::Result<()> {
+	writeln!(w, "#EXTM3U")?;
+	for path in paths {
+		let path = path.as_ref();
+		if path.contains(['\n', '\r']) {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("path {:?} contains a line break", path)
+			))
+		}
+
+		writeln!(w, "{}", path)?;
+	}
+
+	Ok(())
+}
+
+/// Loads all `paths` at once by writing them into a temporary m3u playlist and loading it with `CmdLoadlist`.
+///
+/// mpv has no command to add many files atomically and `loadfile` for each of them takes a round trip per file.
+/// Relative paths are resolved against the current directory of this process, since mpv would resolve them
+/// against the directory of the temporary playlist. URLs are passed unchanged.
+///
+/// The playlist is read by mpv while running the command and is removed afterwards, so mpv has to share
+/// the temporary directory with this process.
+pub fn load_paths<S: AsRef<str>>(
+	link: &mut MpvLink,
+	paths: impl IntoIterator<Item = S>,
+	mode: LoadlistMode
+) -> Result<(), LoadPathsError> {
+	static PLAYLIST_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+	let current_dir = std::env::current_dir()?;
+	let mut entries = Vec::new();
+	for path in paths {
+		let path = path.as_ref();
+		if path.contains(['\n', '\r']) {
+			return Err(LoadPathsError::LineBreak(path.to_string()))
+		}
+
+		let entry = if path.contains("://") {
+			path.to_string()
+		} else {
+			current_dir.join(path).to_string_lossy().into_owned()
+		};
+		entries.push(entry);
+	}
+
+	let playlist_path: PathBuf = std::env::temp_dir().join(format!(
+		"mpvsock-{}-{}.m3u",
+		std::process::id(),
+		PLAYLIST_COUNTER.fetch_add(1, Ordering::Relaxed)
+	));
+	{
+		let mut file = io::BufWriter::new(fs::File::create(&playlist_path)?);
+		write_m3u(&mut file, &entries)?;
+		file.flush()?;
+	}
+
+	let result = link.run_command(&CmdLoadlist::new(
+		Cow::Borrowed(playlist_path.to_string_lossy().as_ref()),
+		mode
+	));
+	if let Err(err) = fs::remove_file(&playlist_path) {
+		log::warn!(
+			"Could not remove playlist {}: {}",
+			playlist_path.display(),
+			err
+		);
+	}
+	result?;
+
+	Ok(())
+}
+
+/// Reconciles the playlist position properties into one current index.
+///
+/// mpv exposes the position in several properties which differ while entries are loading:
+/// - `playlist-playing-pos` is the entry which is fully loaded and playing, it is `-1` while the next entry
+///   loads or when the current one failed to load.
+/// - `playlist-current-pos` is the entry being loaded or played, or the one which determines what plays next
+///   after it was removed. It was added in mpv 0.33.
+/// - `playlist-pos` is the same as `playlist-current-pos` on mpv 0.33 and later, and the only one on older
+///   versions.
+/// - `playlist-pos-1` is `playlist-pos` counted from one, `0` when there is no entry.
+///
+/// All of them are zero-based and `None` here when mpv reports no entry or the property is unavailable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlaylistCursor {
+	pub pos: Option<u32>,
+	pub pos_1: Option<u32>,
+	pub current_pos: Option<u32>,
+	pub playing_pos: Option<u32>
+}
+impl PlaylistCursor {
+	/// Fetches all positions in one batch, see `MpvLink::run_commands_batched`.
+	pub fn fetch(link: &mut MpvLink) -> Result<Self, CommandError<ParseError>> {
+		let commands = [
+			CmdGetProperty::new(property::PlaylistPos.name()),
+			CmdGetProperty::new(property::PlaylistPos1.name()),
+			CmdGetProperty::new(property::PlaylistCurrentPos.name()),
+			CmdGetProperty::new(property::PlaylistPlayingPos.name())
+		];
+
+		let mut values = BatchedValues::new(link.run_commands_batched(&commands)?);
+		Ok(PlaylistCursor {
+			pos: position(values.next_optional()),
+			pos_1: position(values.next_optional().map(|pos_1: i64| pos_1 - 1)),
+			current_pos: position(values.next_optional()),
+			playing_pos: position(values.next_optional())
+		})
+	}
+
+	/// Starts observing the positions in `cache`, see `from_cache`.
+	pub fn observe(
+		cache: &mut PropertyCache,
+		link: &mut MpvLink
+	) -> Result<(), CommandError<ParseError>> {
+		cache.observe(link, property::PlaylistPos)?;
+		cache.observe(link, property::PlaylistPos1)?;
+		// observing properties which older versions lack succeeds, they stay unavailable
+		cache.observe(link, property::PlaylistCurrentPos)?;
+		cache.observe(link, property::PlaylistPlayingPos)?;
+
+		Ok(())
+	}
+
+	/// Creates the cursor from the values in `cache`, which is kept up to date after calling `observe`.
+	pub fn from_cache(cache: &PropertyCache) -> Self {
+		PlaylistCursor {
+			pos: position(cache.get::<property::PlaylistPos>().copied()),
+			pos_1: position(cache.get::<property::PlaylistPos1>().map(|pos_1| pos_1 - 1)),
+			current_pos: position(cache.get::<property::PlaylistCurrentPos>().copied()),
+			playing_pos: position(cache.get::<property::PlaylistPlayingPos>().copied())
+		}
+	}
+
+	/// The current entry, which is the one playing or, while it loads, the one about to play.
+	///
+	/// Prefers `current_pos` and falls back to `pos` and `pos_1` on older mpv versions, so that the index
+	/// does not drop to `None` between entries like `playing_pos` does.
+	pub fn current(&self) -> Option<u32> {
+		self.current_pos.or(self.pos).or(self.pos_1)
+	}
+
+	/// Returns `true` if the current entry is not playing yet, because it is loading or failed to load.
+	///
+	/// Always `false` on mpv versions without `playlist-playing-pos`.
+	pub fn is_loading(&self) -> bool {
+		match self.current_pos {
+			// `playlist-playing-pos` is available whenever `playlist-current-pos` is
+			Some(current_pos) => self.playing_pos != Some(current_pos),
+			None => false
+		}
+	}
+}
+
+/// Converts a playlist position to an index, negative values mean there is no entry.
+fn position(value: Option<i64>) -> Option<u32> {
+	value.and_then(|value| u32::try_from(value).ok())
+}
+
+#[cfg(test)]
+mod test {
+	use std::io;
+
+	use super::{write_m3u, PlaylistCursor};
+
+	#[test]
+	fn test_playlist_cursor() {
+		// the second entry is loading
+		let cursor = PlaylistCursor {
+			pos: Some(1),
+			pos_1: Some(1),
+			current_pos: Some(1),
+			playing_pos: None
+		};
+		assert_eq!(cursor.current(), Some(1));
+		assert!(cursor.is_loading());
+
+		// before mpv 0.33
+		let cursor = PlaylistCursor {
+			pos: Some(2),
+			pos_1: Some(2),
+			..Default::default()
+		};
+		assert_eq!(cursor.current(), Some(2));
+		assert!(!cursor.is_loading());
+
+		assert_eq!(PlaylistCursor::default().current(), None);
+	}
+
+	#[test]
+	fn test_write_m3u() {
+		let mut out = Vec::new();
+		write_m3u(&mut out, ["/music/a b.flac", "https://example.com/stream"]).unwrap();
+
+		assert_eq!(
+			String::from_utf8(out).unwrap(),
+			"#EXTM3U\n/music/a b.flac\nhttps://example.com/stream\n"
+		);
+	}
+
+	#[test]
+	fn test_write_m3u_line_break() {
+		let err = write_m3u(Vec::new(), ["/music/a\nb.flac"]).unwrap_err();
+
+		assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+	}
+}
@@ -0,0 +1,335 @@
+use std::{any::Any, collections::HashMap};
+
+use crate::{
+	command::{
+		commands::{CmdObserveProperty, CmdUnobserveProperty},
+		property::{self, MpvProperty, ObservableMpvProperty},
+		response::MpvResponseEvent,
+		ParseError
+	},
+	link::{CommandError, MpvLink},
+	model::IdleState
+};
+
+type ParseFn = fn(serde_json::Value) -> Result<Box<dyn Any>, serde_json::Error>;
+
+struct CacheEntry {
+	name: String,
+	/// Last value, `None` if the property is unavailable or no change has been received yet.
+	value: Option<Box<dyn Any>>,
+	parse: ParseFn
+}
+
+/// Cache of property values kept up to date by observing the properties.
+///
+/// Values are updated from `property-change` events passed to `update`, usually while draining events
+/// from the link, so reading them with `get` does not need a round trip to mpv.
+pub struct PropertyCache {
+	entries: HashMap<u32, CacheEntry>,
+	observer_ids: HashMap<String, u32>,
+	next_observer_id: u32
+}
+impl PropertyCache {
+	/// First observer id used by `new`, chosen high enough not to collide with manually chosen ids.
+	pub const DEFAULT_FIRST_OBSERVER_ID: u32 = 1 << 24;
+
+	pub fn new() -> Self {
+		Self::with_first_observer_id(Self::DEFAULT_FIRST_OBSERVER_ID)
+	}
+
+	/// Creates a cache which allocates observer ids starting at `first_observer_id`.
+	pub fn with_first_observer_id(first_observer_id: u32) -> Self {
+		PropertyCache {
+			entries: HashMap::new(),
+			observer_ids: HashMap::new(),
+			next_observer_id: first_observer_id
+		}
+	}
+
+	/// Starts observing `property` and caching its value.
+	///
+	/// Observing an already observed property does nothing.
+	pub fn observe<P: ObservableMpvProperty>(
+		&mut self,
+		link: &mut MpvLink,
+		property: P
+	) -> Result<(), CommandError<ParseError>>
+	where
+		P::Value: 'static
+	{
+		if self.observer_ids.contains_key(property.name().as_ref()) {
+			return Ok(())
+		}
+
+		let observer_id = self.register(&property);
+		if let Err(err) = link.run_command(&CmdObserveProperty::new(observer_id, property)) {
+			self.remove(observer_id);
+			return Err(err)
+		}
+
+		Ok(())
+	}
+
+	/// Stops observing `property` and removes its value from the cache.
+	pub fn unobserve<P: MpvProperty>(
+		&mut self,
+		link: &mut MpvLink,
+		property: P
+	) -> Result<(), CommandError<ParseError>> {
+		let observer_id = match self.observer_ids.get(property.name().as_ref()) {
+			None => return Ok(()),
+			Some(&id) => id
+		};
+
+		link.run_command(&CmdUnobserveProperty::new(observer_id))?;
+		self.remove(observer_id);
+
+		Ok(())
+	}
+
+	/// Updates the cache from an event.
+	///
+	/// Returns `true` if the event was a change of a property observed by this cache.
+	pub fn update(&mut self, event: &MpvResponseEvent) -> bool {
+		let (id, data) = match event {
+			MpvResponseEvent::PropertyChange { id, data, .. } => (id, data),
+			_ => return false
+		};
+
+		let entry = match u32::try_from(*id)
+			.ok()
+			.and_then(|id| self.entries.get_mut(&id))
+		{
+			None => return false,
+			Some(entry) => entry
+		};
+
+		entry.value = match data {
+			serde_json::Value::Null => None,
+			data => match (entry.parse)(data.clone()) {
+				Ok(value) => Some(value),
+				Err(err) => {
+					log::warn!("Could not parse value of {}: {}", entry.name, err);
+					None
+				}
+			}
+		};
+
+		true
+	}
+
+	/// Returns the cached value of the known property `P`.
+	pub fn get<P: MpvProperty + Default>(&self) -> Option<&P::Value>
+	where
+		P::Value: 'static
+	{
+		self.get_value(&P::default())
+	}
+
+	/// Returns the cached value of `property`.
+	///
+	/// Returns `None` if the property is not observed, unavailable or observed with a different value type.
+	pub fn get_value<P: MpvProperty>(&self, property: &P) -> Option<&P::Value>
+	where
+		P::Value: 'static
+	{
+		self.observer_ids
+			.get(property.name().as_ref())
+			.and_then(|id| self.entries.get(id))
+			.and_then(|entry| entry.value.as_ref())
+			.and_then(|value| value.downcast_ref())
+	}
+
+	/// Replaces the cached value of `property` ahead of its `property-change` event, for example right
+	/// after setting it.
+	///
+	/// Returns `false` and does nothing if the property is not observed.
+	pub fn set_value<P: MpvProperty>(&mut self, property: &P, value: P::Value) -> bool
+	where
+		P::Value: 'static
+	{
+		let entry = match self
+			.observer_ids
+			.get(property.name().as_ref())
+			.and_then(|id| self.entries.get_mut(id))
+		{
+			None => return false,
+			Some(entry) => entry
+		};
+		entry.value = Some(Box::new(value));
+
+		true
+	}
+
+	/// Starts observing the flags `idle_state` is derived from.
+	pub fn observe_idle_state(
+		&mut self,
+		link: &mut MpvLink
+	) -> Result<(), CommandError<ParseError>> {
+		self.observe(link, property::IdleActive)?;
+		self.observe(link, property::Seeking)?;
+		self.observe(link, property::PausedForCache)?;
+		self.observe(link, property::Pause)?;
+		self.observe(link, property::CoreIdle)?;
+
+		Ok(())
+	}
+
+	/// Derives the `IdleState` from the cached flags, after calling `observe_idle_state`.
+	///
+	/// Returns `None` until `idle-active` and `core-idle` are known, the other flags are unavailable while
+	/// idle and count as `false` then.
+	pub fn idle_state(&self) -> Option<IdleState> {
+		let flag = |value: Option<&bool>| value.copied().unwrap_or(false);
+
+		Some(IdleState::from_flags(
+			*self.get::<property::IdleActive>()?,
+			flag(self.get::<property::Seeking>()),
+			flag(self.get::<property::PausedForCache>()),
+			flag(self.get::<property::Pause>()),
+			*self.get::<property::CoreIdle>()?
+		))
+	}
+
+	/// Returns `true` if `property` is observed by this cache.
+	pub fn is_observed<P: MpvProperty>(&self, property: &P) -> bool {
+		self.observer_ids.contains_key(property.name().as_ref())
+	}
+
+	fn register<P: MpvProperty>(&mut self, property: &P) -> u32
+	where
+		P::Value: 'static
+	{
+		fn parse<V: serde::de::DeserializeOwned + 'static>(
+			value: serde_json::Value
+		) -> Result<Box<dyn Any>, serde_json::Error> {
+			Ok(Box::new(serde_json::from_value::<V>(value)?))
+		}
+
+		let observer_id = self.next_observer_id;
+		self.next_observer_id = self.next_observer_id.wrapping_add(1);
+
+		let name = property.name().into_owned();
+		self.observer_ids.insert(name.clone(), observer_id);
+		self.entries.insert(
+			observer_id,
+			CacheEntry {
+				name,
+				value: None,
+				parse: parse::<P::Value>
+			}
+		);
+
+		observer_id
+	}
+
+	fn remove(&mut self, observer_id: u32) {
+		if let Some(entry) = self.entries.remove(&observer_id) {
+			self.observer_ids.remove(&entry.name);
+		}
+	}
+}
+impl Default for PropertyCache {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{
+		command::{
+			property::{CoreIdle, IdleActive, Pause, PausedForCache, Seeking, TimePos, Volume},
+			response::MpvResponseEvent
+		},
+		model::IdleState
+	};
+
+	use super::PropertyCache;
+
+	fn property_change(id: u32, data: serde_json::Value) -> MpvResponseEvent {
+		serde_json::from_value(serde_json::json!(
+			{
+				"event": "property-change",
+				"id": id,
+				"name": "whatever",
+				"data": data
+			}
+		))
+		.unwrap()
+	}
+
+	#[test]
+	fn test_property_cache_update() {
+		let mut cache = PropertyCache::new();
+		let pause_id = cache.register(&Pause);
+		let time_pos_id = cache.register(&TimePos);
+
+		assert_eq!(cache.get::<Pause>(), None);
+
+		assert!(cache.update(&property_change(pause_id, serde_json::json!(true))));
+		assert!(cache.update(&property_change(time_pos_id, serde_json::json!(1.5))));
+
+		assert_eq!(cache.get::<Pause>(), Some(&true));
+		assert_eq!(cache.get::<TimePos>(), Some(&1.5));
+		assert_eq!(cache.get::<Volume>(), None);
+
+		// unavailable
+		assert!(cache.update(&property_change(time_pos_id, serde_json::Value::Null)));
+		assert_eq!(cache.get::<TimePos>(), None);
+
+		assert!(cache.set_value(&TimePos, 2.5));
+		assert_eq!(cache.get::<TimePos>(), Some(&2.5));
+		assert!(!cache.set_value(&Volume, 50.0));
+		assert_eq!(cache.get::<Volume>(), None);
+	}
+
+	#[test]
+	fn test_property_cache_ignores_foreign_observers() {
+		let mut cache = PropertyCache::new();
+		cache.register(&Pause);
+
+		assert!(!cache.update(&property_change(1, serde_json::json!(true))));
+		assert_eq!(cache.get::<Pause>(), None);
+	}
+
+	#[test]
+	fn test_idle_state() {
+		let mut cache = PropertyCache::new();
+		let idle_active_id = cache.register(&IdleActive);
+		let core_idle_id = cache.register(&CoreIdle);
+		let paused_for_cache_id = cache.register(&PausedForCache);
+		cache.register(&Seeking);
+		cache.register(&Pause);
+		assert_eq!(cache.idle_state(), None);
+
+		cache.update(&property_change(idle_active_id, serde_json::json!(true)));
+		cache.update(&property_change(core_idle_id, serde_json::json!(true)));
+		assert_eq!(cache.idle_state(), Some(IdleState::Idle));
+
+		cache.update(&property_change(idle_active_id, serde_json::json!(false)));
+		assert_eq!(cache.idle_state(), Some(IdleState::Loading));
+		cache.update(&property_change(
+			paused_for_cache_id,
+			serde_json::json!(true)
+		));
+		assert_eq!(cache.idle_state(), Some(IdleState::PausedForCache));
+
+		cache.update(&property_change(
+			paused_for_cache_id,
+			serde_json::json!(false)
+		));
+		cache.update(&property_change(core_idle_id, serde_json::json!(false)));
+		assert_eq!(cache.idle_state(), Some(IdleState::Playing));
+	}
+
+	#[test]
+	fn test_property_cache_raw_name() {
+		let mut cache = PropertyCache::new();
+		let id = cache.register(&"chapter");
+
+		cache.update(&property_change(id, serde_json::json!(3)));
+
+		assert_eq!(cache.get_value(&"chapter"), Some(&serde_json::json!(3)));
+	}
+}
@@ -0,0 +1,254 @@
+use std::{borrow::Cow, sync::mpsc, task::Poll};
+
+use crate::{
+	command::{
+		commands::{CmdLoadfile, CmdPlaylistNext},
+		property::{self, KnownMpvProperty},
+		response::{MpvResponseEvent, MpvResponseEventPropertyName},
+		ParseError
+	},
+	link::{CommandError, MpvLink},
+	property_cache::PropertyCache
+};
+
+/// Source of the items `QueueController` appends, such as a radio station or a recommendation service.
+///
+/// Fetching an item may take a while, so the source is polled without blocking. It typically fetches
+/// items on another thread or task and hands them over when they are ready.
+pub trait QueueSource {
+	/// Returns `Ready(Some(item))` with the path or URL of the next item, `Pending` if it is not ready yet
+	/// and `Ready(None)` once the source is exhausted.
+	fn poll_next_item(&mut self) -> Poll<Option<String>>;
+}
+/// Items sent through the channel, exhausted once all senders are dropped.
+impl QueueSource for mpsc::Receiver<String> {
+	fn poll_next_item(&mut self) -> Poll<Option<String>> {
+		match self.try_recv() {
+			Ok(item) => Poll::Ready(Some(item)),
+			Err(mpsc::TryRecvError::Empty) => Poll::Pending,
+			Err(mpsc::TryRecvError::Disconnected) => Poll::Ready(None)
+		}
+	}
+}
+
+/// Keeps the playlist from running out by appending items from a `QueueSource` ahead of its end.
+///
+/// The controller follows `playlist-pos`, `playlist-count` and `eof-reached`. Whenever no more than
+/// `prefetch` entries follow the current one, it appends the next ready item, so that mpv can open it
+/// without a gap. Call `observe` once, then pass all events to `update` and call `poll` whenever the source
+/// may have new items, such as periodically or when woken up by the source.
+///
+/// An empty or finished playlist is restarted with the next item, also with `keep-open` where mpv stays
+/// on the last entry.
+#[derive(Debug)]
+pub struct QueueController<S: QueueSource> {
+	source: S,
+	prefetch: u32,
+	playlist_pos: Option<i64>,
+	playlist_count: Option<u32>,
+	eof_reached: bool,
+	exhausted: bool
+}
+impl<S: QueueSource> QueueController<S> {
+	pub fn new(source: S) -> Self {
+		QueueController {
+			source,
+			prefetch: 1,
+			playlist_pos: None,
+			playlist_count: None,
+			eof_reached: false,
+			exhausted: false
+		}
+	}
+
+	/// Sets how many entries may follow the current one before an item is appended, `1` by default.
+	///
+	/// `0` appends only once the last entry plays, which leaves mpv little time to open the next one.
+	pub fn with_prefetch(mut self, prefetch: u32) -> Self {
+		self.prefetch = prefetch;
+		self
+	}
+
+	pub fn source(&self) -> &S {
+		&self.source
+	}
+
+	pub fn source_mut(&mut self) -> &mut S {
+		&mut self.source
+	}
+
+	/// Returns `true` once the source returned `Ready(None)`, nothing is appended afterwards.
+	pub fn is_exhausted(&self) -> bool {
+		self.exhausted
+	}
+
+	/// Starts observing the properties the controller follows in `cache`.
+	pub fn observe(
+		cache: &mut PropertyCache,
+		link: &mut MpvLink
+	) -> Result<(), CommandError<ParseError>> {
+		cache.observe(link, property::PlaylistPos)?;
+		cache.observe(link, property::PlaylistCount)?;
+		cache.observe(link, property::EofReached)?;
+
+		Ok(())
+	}
+
+	/// Updates the controller from `event` and appends items if the playlist nears its end.
+	///
+	/// Returns the number of appended items.
+	pub fn update(
+		&mut self,
+		link: &mut MpvLink,
+		event: &MpvResponseEvent
+	) -> Result<u32, CommandError<ParseError>> {
+		let (property, data) = match event {
+			MpvResponseEvent::PropertyChange {
+				name: MpvResponseEventPropertyName::Known(property),
+				data,
+				..
+			} => (property, data),
+			_ => return Ok(0)
+		};
+		match property {
+			KnownMpvProperty::PlaylistPos => self.playlist_pos = data.as_i64(),
+			KnownMpvProperty::PlaylistCount => {
+				self.playlist_count = data.as_u64().and_then(|count| u32::try_from(count).ok())
+			}
+			KnownMpvProperty::EofReached => self.eof_reached = data.as_bool().unwrap_or(false),
+			_ => return Ok(0)
+		}
+
+		self.poll(link)
+	}
+
+	/// Appends the items which are ready while the playlist nears its end, returns their number.
+	pub fn poll(&mut self, link: &mut MpvLink) -> Result<u32, CommandError<ParseError>> {
+		let mut appended = 0;
+		while !self.exhausted
+			&& self
+				.remaining()
+				.is_some_and(|remaining| remaining <= self.prefetch)
+		{
+			let item = match self.source.poll_next_item() {
+				Poll::Ready(Some(item)) => item,
+				Poll::Ready(None) => {
+					log::debug!("Queue source is exhausted");
+					self.exhausted = true;
+					break
+				}
+				Poll::Pending => break
+			};
+			self.append(link, item)?;
+			appended += 1;
+		}
+
+		Ok(appended)
+	}
+
+	/// Number of entries after the current one, `None` until both playlist properties are known.
+	fn remaining(&self) -> Option<u32> {
+		let count = self.playlist_count?;
+		Some(match self.playlist_pos? {
+			pos if pos >= 0 => {
+				count.saturating_sub(u32::try_from(pos).unwrap_or(u32::MAX).saturating_add(1))
+			}
+			// nothing is playing
+			_ => 0
+		})
+	}
+
+	fn append(&mut self, link: &mut MpvLink, item: String) -> Result<(), CommandError<ParseError>> {
+		log::debug!("Appending {} to the queue", item);
+
+		// `playlist-pos` is -1 when nothing is playing
+		if self.playlist_pos.is_some_and(|pos| pos < 0) {
+			// plain appending to an idle playlist does not start it, and replacing it would drop the entries
+			// kept after it was stopped
+			link.run_command(&CmdLoadfile::new_append_play(Cow::Owned(item)))?;
+			let count = self.playlist_count.unwrap_or(0);
+			self.playlist_pos = Some(i64::from(count));
+			self.playlist_count = Some(count + 1);
+			return Ok(())
+		}
+
+		let last_ended = self.eof_reached && self.remaining() == Some(0);
+		link.run_command(&CmdLoadfile::new_append(Cow::Owned(item)))?;
+		self.playlist_count = self.playlist_count.map(|count| count + 1);
+		// with `keep-open` mpv stays on the ended entry instead of moving on
+		if last_ended {
+			link.run_command(&CmdPlaylistNext::new(false))?;
+			self.eof_reached = false;
+			self.playlist_pos = self.playlist_pos.map(|pos| pos + 1);
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{sync::mpsc, task::Poll};
+
+	use super::{QueueController, QueueSource};
+	use crate::link::{fake_mpv, MpvLink};
+
+	#[test]
+	fn test_remaining() {
+		let (_sender, receiver) = mpsc::channel::<String>();
+		let mut queue = QueueController::new(receiver);
+		assert_eq!(queue.remaining(), None);
+		queue.playlist_count = Some(3);
+		assert_eq!(queue.remaining(), None);
+
+		queue.playlist_pos = Some(0);
+		assert_eq!(queue.remaining(), Some(2));
+		queue.playlist_pos = Some(2);
+		assert_eq!(queue.remaining(), Some(0));
+
+		queue.playlist_pos = Some(-1);
+		assert_eq!(queue.remaining(), Some(0));
+	}
+
+	#[test]
+	fn test_append_to_stopped_playlist() {
+		let path = std::env::temp_dir().join(format!("mpvsock-queue-{}.sock", std::process::id()));
+		let server = fake_mpv::listen(&path, |requests, socket| {
+			let line = requests.next().unwrap().unwrap();
+			let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+			// appended and started, keeping the stopped entries
+			assert_eq!(
+				request["command"],
+				serde_json::json!(["loadfile", "/next.mkv", "append-play"])
+			);
+			fake_mpv::write_result(&mut *socket, &line, "{\"playlist_entry_id\":3}");
+		});
+
+		let mut link = MpvLink::connect(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		let (sender, receiver) = mpsc::channel();
+		let mut queue = QueueController::new(receiver);
+		queue.playlist_pos = Some(-1);
+		queue.playlist_count = Some(2);
+
+		sender.send("/next.mkv".to_string()).unwrap();
+		assert_eq!(queue.poll(&mut link).unwrap(), 1);
+		assert_eq!(queue.playlist_pos, Some(2));
+		assert_eq!(queue.playlist_count, Some(3));
+		let _socket = server.join().unwrap();
+	}
+
+	#[test]
+	fn test_channel_source() {
+		let (sender, mut receiver) = mpsc::channel();
+		assert_eq!(receiver.poll_next_item(), Poll::Pending);
+
+		sender.send("https://example.com/next".to_string()).unwrap();
+		std::mem::drop(sender);
+		assert_eq!(
+			receiver.poll_next_item(),
+			Poll::Ready(Some("https://example.com/next".to_string()))
+		);
+		assert_eq!(receiver.poll_next_item(), Poll::Ready(None));
+	}
+}
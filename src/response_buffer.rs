@@ -1,8 +1,26 @@
 use std::io::{self, Read};
 
+/// Growable line buffer for responses read from the stream.
+///
+/// Data is read into the unused tail of the buffer and lines are consumed from the front by advancing
+/// a cursor. Consumed space is reclaimed lazily when the tail runs out, either by compacting the unconsumed
+/// data to the front or by growing the buffer, which keeps both reading and consumption amortized O(1).
+///
+/// With a maximum line length, longer lines are dropped instead, see `set_max_line_length`.
+#[derive(Debug)]
 pub struct ResponseBuffer {
 	buffer: Vec<u8>,
-	position: usize
+	/// Start of the unconsumed data.
+	start: usize,
+	/// End of the valid data.
+	end: usize,
+	/// Position up to which the unconsumed data is known not to contain a line delimiter.
+	scanned: usize,
+	max_line_length: Option<usize>,
+	/// Set while dropping the rest of a line which is too long.
+	discarding: bool,
+	/// Number of lines dropped since the last `take_dropped_lines`.
+	dropped_lines: usize
 }
 impl ResponseBuffer {
 	const LINE_DELIM: u8 = b'\n';
@@ -10,85 +28,386 @@ impl ResponseBuffer {
 
 	pub fn new() -> Self {
 		ResponseBuffer {
-			buffer: Vec::with_capacity(Self::RESERVE_SIZE),
-			position: 0
+			buffer: vec![0; Self::RESERVE_SIZE],
+			start: 0,
+			end: 0,
+			scanned: 0,
+			max_line_length: None,
+			discarding: false,
+			dropped_lines: 0
 		}
 	}
 
-	pub fn read_nonblocking(&mut self, mut stream: impl Read) -> Result<(), io::Error> {
-		match stream.read_to_end(&mut self.buffer) {
-			Ok(_) => (),
-			Err(err) if err.kind() == io::ErrorKind::WouldBlock => (),
-			Err(err) => return Err(err)
-		}
+	/// Sets the maximum length of a line without the delimiter, unlimited (`None`) by default.
+	///
+	/// Longer lines are dropped up to the next delimiter, where consuming lines resumes. Partial lines are dropped
+	/// as soon as they are too long and reading stops once a whole line is buffered, so a peer which never sends
+	/// a delimiter cannot grow the buffer beyond about twice the maximum.
+	pub fn set_max_line_length(&mut self, max_line_length: Option<usize>) {
+		self.max_line_length = max_line_length;
+	}
+
+	pub fn max_line_length(&self) -> Option<usize> {
+		self.max_line_length
+	}
+
+	/// Returns the number of lines dropped for being too long since the last call.
+	pub fn take_dropped_lines(&mut self) -> usize {
+		std::mem::take(&mut self.dropped_lines)
+	}
 
-		Ok(())
+	/// Returns the number of buffered bytes which have not been consumed yet.
+	pub fn len(&self) -> usize {
+		self.end - self.start
 	}
 
-	pub fn read_blocking(&mut self, stream: impl Read) -> Result<(), io::Error> {
-		for byte in stream.bytes() {
-			let byte = match byte {
-				Ok(byte) => byte,
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Reads from the stream until it would block or reaches EOF.
+	///
+	/// Returns the number of bytes read or `UnexpectedEof` if the stream reached EOF before anything was read.
+	pub fn read_nonblocking(&mut self, mut stream: impl Read) -> Result<usize, io::Error> {
+		let mut total = 0;
+
+		loop {
+			match self.read_from(&mut stream) {
+				Ok(0) if total == 0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+				Ok(0) => break,
+				// lines have to be consumed before more is read to keep the buffer bounded
+				Ok(count) if self.max_line_length.is_some() && self.has_line() => {
+					total += count;
+					break
+				}
+				Ok(count) => total += count,
 				Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
 				Err(err) => return Err(err)
-			};
-
-			if byte == Self::LINE_DELIM {
-				break
 			}
-
-			self.buffer.push(byte);
 		}
 
-		Ok(())
+		Ok(total)
 	}
 
-	pub fn read_from(&mut self, mut stream: impl Read) -> Result<(), io::Error> {
-		if self.buffer.len() + Self::RESERVE_SIZE >= self.buffer.capacity() {
-			self.buffer
-				.resize(self.buffer.len() + Self::RESERVE_SIZE, 0);
+	/// Reads from the stream until at least one whole line is buffered, the stream would block or reaches EOF.
+	///
+	/// Returns the number of bytes read or `UnexpectedEof` if the stream reached EOF before anything was read.
+	/// With a read timeout set on a blocking stream, each read waits at most for the timeout.
+	pub fn read_blocking(&mut self, mut stream: impl Read) -> Result<usize, io::Error> {
+		let mut total = 0;
+
+		while !self.has_line() {
+			match self.read_from(&mut stream) {
+				Ok(0) if total == 0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+				Ok(0) => break,
+				Ok(count) => total += count,
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+				Err(err) => return Err(err)
+			}
 		}
 
-		match stream.read(&mut self.buffer) {
-			Ok(_) => (),
-			Err(err) if err.kind() == io::ErrorKind::WouldBlock => (),
-			Err(err) => return Err(err)
+		Ok(total)
+	}
+
+	/// Performs one read from the stream into the unused tail of the buffer.
+	///
+	/// Returns the result of the underlying read call.
+	pub fn read_from(&mut self, mut stream: impl Read) -> Result<usize, io::Error> {
+		self.reserve_tail();
+
+		let count = stream.read(&mut self.buffer[self.end ..])?;
+		self.end += count;
+
+		if self.max_line_length.is_some() {
+			// drops a partial line which is already too long
+			self.find_delim();
 		}
 
-		Ok(())
+		Ok(count)
+	}
+
+	/// Returns `true` if a whole line is buffered and can be consumed.
+	pub fn has_line(&mut self) -> bool {
+		self.find_delim().is_some()
 	}
 
 	pub fn consume_line(&mut self) -> Option<&[u8]> {
-		let next_newline = self.buffer[self.position ..]
-			.iter()
-			.position(|&b| b == Self::LINE_DELIM);
-
-		match next_newline {
-			None => None,
-			Some(end) => {
-				let line = &self.buffer[self.position ..][.. end];
-				self.position += end + 1;
-
-				if log::log_enabled!(log::Level::Debug) {
-					match std::str::from_utf8(line) {
-						Ok(line) => {
-							log::debug!("Consumed line: {}", line);
-						}
-						Err(_err) => {
-							log::debug!("Consumed line: {:?}", line)
+		let end = self.find_delim()?;
+
+		let line = &self.buffer[self.start .. end];
+		self.start = end + 1;
+		self.scanned = self.start;
+
+		if log::log_enabled!(log::Level::Debug) {
+			match std::str::from_utf8(line) {
+				Ok(line) => {
+					log::debug!("Consumed line: {}", line);
+				}
+				Err(_err) => {
+					log::debug!("Consumed line: {:?}", line)
+				}
+			}
+		}
+
+		Some(line)
+	}
+
+	/// Returns the absolute position of the next line delimiter, remembering how far the search got.
+	///
+	/// Lines which are too long are dropped on the way.
+	fn find_delim(&mut self) -> Option<usize> {
+		loop {
+			let from = self.scanned.max(self.start);
+
+			match self.buffer[from .. self.end]
+				.iter()
+				.position(|&b| b == Self::LINE_DELIM)
+			{
+				Some(offset) => {
+					let end = from + offset;
+					if !self.discarding && !self.is_too_long(end - self.start) {
+						return Some(end)
+					}
+
+					log::debug!("Dropping line of {} bytes", end - self.start);
+					if !self.discarding {
+						self.dropped_lines += 1;
+					}
+					self.discarding = false;
+					self.start = end + 1;
+					self.scanned = self.start;
+				}
+				None => {
+					self.scanned = self.end;
+					if self.discarding || self.is_too_long(self.end - self.start) {
+						if !self.discarding {
+							self.dropped_lines += 1;
+							self.discarding = true;
 						}
+						self.start = self.end;
+						self.scanned = self.end;
 					}
+
+					return None
 				}
+			}
+		}
+	}
+
+	fn is_too_long(&self, length: usize) -> bool {
+		self.max_line_length.is_some_and(|max| length > max)
+	}
+
+	/// Makes sure there is free space at the tail of the buffer.
+	///
+	/// Consumed space is reclaimed by compaction only once it makes up at least half of the used buffer,
+	/// otherwise the buffer grows. Both cases keep the copying amortized over the consumed bytes.
+	fn reserve_tail(&mut self) {
+		if self.start == self.end {
+			// nothing to keep, reset for free
+			self.start = 0;
+			self.end = 0;
+			self.scanned = 0;
+		}
 
-				Some(line)
+		if self.end < self.buffer.len() {
+			return
+		}
+
+		if self.start > 0 && self.start >= self.len() {
+			log::trace!("Compacting buffer by {} elements", self.start);
+
+			self.buffer.copy_within(self.start .. self.end, 0);
+			self.scanned -= self.start;
+			self.end -= self.start;
+			self.start = 0;
+		} else {
+			let new_len = (self.buffer.len() * 2).max(Self::RESERVE_SIZE);
+			log::trace!("Growing buffer to {} elements", new_len);
+
+			self.buffer.resize(new_len, 0);
+		}
+	}
+}
+impl Default for ResponseBuffer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{collections::VecDeque, io};
+
+	use super::ResponseBuffer;
+
+	/// Reader which returns the given chunks one per read call, then `WouldBlock`.
+	struct ChunkedReader(VecDeque<Vec<u8>>);
+	impl ChunkedReader {
+		pub fn new(chunks: &[&[u8]]) -> Self {
+			ChunkedReader(chunks.iter().map(|c| c.to_vec()).collect())
+		}
+	}
+	impl io::Read for ChunkedReader {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			let chunk = match self.0.front_mut() {
+				None => return Err(io::ErrorKind::WouldBlock.into()),
+				Some(chunk) => chunk
+			};
+
+			let count = chunk.len().min(buf.len());
+			buf[.. count].copy_from_slice(&chunk[.. count]);
+			chunk.drain(.. count);
+			if chunk.is_empty() {
+				self.0.pop_front();
 			}
+
+			Ok(count)
 		}
 	}
 
-	pub fn shift(&mut self) {
-		log::trace!("Shifting buffer by {} elements", self.position);
+	#[test]
+	fn test_partial_line() {
+		let mut buffer = ResponseBuffer::new();
+
+		buffer
+			.read_nonblocking(ChunkedReader::new(&[b"{\"event\":"]))
+			.unwrap();
+		assert_eq!(buffer.consume_line(), None);
+
+		buffer
+			.read_nonblocking(ChunkedReader::new(&[b"\"idle\"}\n{\"ev"]))
+			.unwrap();
+		assert_eq!(
+			buffer.consume_line(),
+			Some(b"{\"event\":\"idle\"}".as_ref())
+		);
+		assert_eq!(buffer.consume_line(), None);
+
+		buffer
+			.read_nonblocking(ChunkedReader::new(&[b"ent\":\"seek\"}\n"]))
+			.unwrap();
+		assert_eq!(
+			buffer.consume_line(),
+			Some(b"{\"event\":\"seek\"}".as_ref())
+		);
+		assert_eq!(buffer.consume_line(), None);
+		assert!(buffer.is_empty());
+	}
+
+	#[test]
+	fn test_eof() {
+		let mut buffer = ResponseBuffer::new();
+
+		let mut stream: &[u8] = b"line\n";
+		assert_eq!(buffer.read_nonblocking(&mut stream).unwrap(), 5);
+		assert_eq!(
+			buffer.read_nonblocking(&mut stream).unwrap_err().kind(),
+			io::ErrorKind::UnexpectedEof
+		);
+		assert_eq!(buffer.consume_line(), Some(b"line".as_ref()));
+	}
+
+	#[test]
+	fn test_multiple_lines_in_one_read() {
+		let mut buffer = ResponseBuffer::new();
+
+		buffer
+			.read_nonblocking(ChunkedReader::new(&[b"a\nbb\n\nccc"]))
+			.unwrap();
+
+		assert_eq!(buffer.consume_line(), Some(b"a".as_ref()));
+		assert_eq!(buffer.consume_line(), Some(b"bb".as_ref()));
+		assert_eq!(buffer.consume_line(), Some(b"".as_ref()));
+		assert_eq!(buffer.consume_line(), None);
+		assert_eq!(buffer.len(), 3);
+	}
+
+	#[test]
+	fn test_read_into_tail() {
+		let mut buffer = ResponseBuffer::new();
+
+		buffer.read_from(ChunkedReader::new(&[b"first "])).unwrap();
+		buffer
+			.read_from(ChunkedReader::new(&[b"second\n"]))
+			.unwrap();
+
+		assert_eq!(buffer.consume_line(), Some(b"first second".as_ref()));
+	}
+
+	#[test]
+	fn test_long_line_grows_buffer() {
+		let mut line = vec![b'x'; ResponseBuffer::RESERVE_SIZE * 5 + 3];
+		line.push(b'\n');
+
+		let mut buffer = ResponseBuffer::new();
+		let read = buffer
+			.read_nonblocking(ChunkedReader::new(&[&line[.. 200], &line[200 ..]]))
+			.unwrap();
+
+		assert_eq!(read, line.len());
+		assert_eq!(buffer.consume_line(), Some(&line[.. line.len() - 1]));
+	}
+
+	#[test]
+	fn test_max_line_length() {
+		let mut buffer = ResponseBuffer::new();
+		buffer.set_max_line_length(Some(4));
+
+		buffer
+			.read_nonblocking(ChunkedReader::new(&[b"abcd\nabcde\nab"]))
+			.unwrap();
+		assert_eq!(buffer.consume_line(), Some(b"abcd".as_ref()));
+		assert_eq!(buffer.consume_line(), None);
+		assert_eq!(buffer.take_dropped_lines(), 1);
+		assert_eq!(buffer.take_dropped_lines(), 0);
+
+		// the partial line is dropped once it is too long and its end is dropped as well
+		buffer
+			.read_nonblocking(ChunkedReader::new(&[b"cde"]))
+			.unwrap();
+		assert_eq!(buffer.take_dropped_lines(), 1);
+		assert!(buffer.is_empty());
+		buffer
+			.read_nonblocking(ChunkedReader::new(&[b"fgh\nok\n"]))
+			.unwrap();
+		assert_eq!(buffer.consume_line(), Some(b"ok".as_ref()));
+		assert_eq!(buffer.take_dropped_lines(), 0);
+	}
+
+	#[test]
+	fn test_max_line_length_bounds_buffer() {
+		let mut buffer = ResponseBuffer::new();
+		buffer.set_max_line_length(Some(ResponseBuffer::RESERVE_SIZE));
+
+		let flood = vec![b'x'; ResponseBuffer::RESERVE_SIZE];
+		let chunks = vec![flood.as_slice(); 64];
+		buffer
+			.read_nonblocking(ChunkedReader::new(&chunks))
+			.unwrap();
+
+		assert_eq!(buffer.take_dropped_lines(), 1);
+		assert!(buffer.buffer.len() <= ResponseBuffer::RESERVE_SIZE * 2);
+	}
+
+	#[test]
+	fn test_compaction_keeps_partial_line() {
+		let mut buffer = ResponseBuffer::new();
+
+		// fill the buffer exactly, leaving a short partial line at the end
+		let mut data = vec![b'a'; ResponseBuffer::RESERVE_SIZE - 8];
+		data[ResponseBuffer::RESERVE_SIZE - 13] = b'\n';
+		buffer.read_from(ChunkedReader::new(&[&data])).unwrap();
+		buffer.read_from(ChunkedReader::new(&[b"tail"])).unwrap();
+		buffer.read_from(ChunkedReader::new(&[b"tail"])).unwrap();
+		assert!(buffer.consume_line().is_some());
+		assert_eq!(buffer.consume_line(), None);
 
-		self.buffer.drain(.. self.position);
-		self.position = 0;
+		// next read has to compact
+		buffer.read_from(ChunkedReader::new(&[b"end\n"])).unwrap();
+		assert_eq!(buffer.consume_line(), Some(b"aaaatailtailend".as_ref()));
+		assert_eq!(buffer.buffer.len(), ResponseBuffer::RESERVE_SIZE);
 	}
 }
@@ -0,0 +1,326 @@
+use std::{
+	collections::HashMap,
+	fmt,
+	fs,
+	io::{self, Read}
+};
+
+use thiserror::Error;
+
+use crate::{
+	command::{
+		commands::{CmdGetProperty, CmdSeek, InvalidSeekTargetError},
+		property::{self, KnownMpvProperty},
+		response::{MpvResponseEvent, MpvResponseEventPropertyName},
+		ParseError
+	},
+	link::{CommandError, MpvLink},
+	model::EndFileReason,
+	property_cache::PropertyCache
+};
+
+/// Bytes from the start of a file hashed by `KeyStrategy::ContentHash`.
+const HASHED_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Error)]
+pub enum ResumeError {
+	#[error("Resume store failed: {0}")]
+	Store(#[from] io::Error),
+	#[error(transparent)]
+	Command(#[from] CommandError<ParseError>),
+	#[error("Stored position is invalid: {0}")]
+	InvalidPosition(#[from] InvalidSeekTargetError)
+}
+
+/// Key positions are stored under.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ResumeKey {
+	/// Path or URL as mpv reports it in `path`.
+	Path(String),
+	/// Hash of the size and the first 64 KiB of the file, which stays the same when the file is moved
+	/// or copied to another machine.
+	Hash(u64)
+}
+impl ResumeKey {
+	/// Hashes the contents of the file at `path` with 64-bit FNV-1a, see `ResumeKey::Hash`.
+	///
+	/// The hash does not depend on the platform or the Rust version, so it can be shared.
+	pub fn content_hash(path: &str) -> io::Result<Self> {
+		let file = fs::File::open(path)?;
+		let size = file.metadata()?.len();
+
+		let mut hash = Fnv1a::new();
+		hash.write(&size.to_le_bytes());
+		let mut head = Vec::new();
+		file.take(HASHED_BYTES).read_to_end(&mut head)?;
+		hash.write(&head);
+
+		Ok(ResumeKey::Hash(hash.0))
+	}
+}
+/// Formats the key as `path:<path>` or `hash:<16 hex digits>`, such as for stores keyed by strings.
+impl fmt::Display for ResumeKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ResumeKey::Path(path) => write!(f, "path:{}", path),
+			ResumeKey::Hash(hash) => write!(f, "hash:{:016x}", hash)
+		}
+	}
+}
+
+/// How `ResumeTracker` derives the key of a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyStrategy {
+	#[default]
+	Path,
+	/// Hashes local files, URLs and files which cannot be read fall back to their path.
+	ContentHash
+}
+impl KeyStrategy {
+	pub fn key(&self, path: &str) -> ResumeKey {
+		match self {
+			KeyStrategy::ContentHash if !path.contains("://") => {
+				match ResumeKey::content_hash(path) {
+					Ok(key) => key,
+					Err(err) => {
+						log::debug!("Could not hash {}, using its path: {}", path, err);
+						ResumeKey::Path(path.to_string())
+					}
+				}
+			}
+			_ => ResumeKey::Path(path.to_string())
+		}
+	}
+}
+
+/// Storage of resume positions in seconds, such as a file, a database or a service shared by several
+/// machines.
+pub trait ResumeStore {
+	fn load(&mut self, key: &ResumeKey) -> io::Result<Option<f64>>;
+
+	fn save(&mut self, key: &ResumeKey, position: f64) -> io::Result<()>;
+
+	/// Removes the position, removing a key which is not stored succeeds.
+	fn remove(&mut self, key: &ResumeKey) -> io::Result<()>;
+}
+/// In-memory store.
+impl ResumeStore for HashMap<ResumeKey, f64> {
+	fn load(&mut self, key: &ResumeKey) -> io::Result<Option<f64>> {
+		Ok(self.get(key).copied())
+	}
+
+	fn save(&mut self, key: &ResumeKey, position: f64) -> io::Result<()> {
+		self.insert(key.clone(), position);
+		Ok(())
+	}
+
+	fn remove(&mut self, key: &ResumeKey) -> io::Result<()> {
+		HashMap::remove(self, key);
+		Ok(())
+	}
+}
+
+/// Saves the playback position of files into a `ResumeStore` and seeks to it when they are loaded again.
+///
+/// This works independently of the watch-later files of mpv, so the positions can be shared between
+/// machines and controllers. Call `observe` once, then pass all events to `update`:
+/// - `file-loaded` looks up the position of the loaded file and seeks to it.
+/// - `end-file` saves the last position, or removes it if the file played to its end.
+///
+/// Positions before `min_position` are not saved, so that opening a file briefly does not store a
+/// position near its start.
+#[derive(Debug)]
+pub struct ResumeTracker<S: ResumeStore> {
+	store: S,
+	strategy: KeyStrategy,
+	min_position: f64,
+	current: Option<ResumeKey>,
+	last_position: Option<f64>
+}
+impl<S: ResumeStore> ResumeTracker<S> {
+	pub const DEFAULT_MIN_POSITION: f64 = 5.0;
+
+	pub fn new(store: S) -> Self {
+		ResumeTracker {
+			store,
+			strategy: KeyStrategy::default(),
+			min_position: Self::DEFAULT_MIN_POSITION,
+			current: None,
+			last_position: None
+		}
+	}
+
+	pub fn with_key_strategy(mut self, strategy: KeyStrategy) -> Self {
+		self.strategy = strategy;
+		self
+	}
+
+	/// Sets the smallest position in seconds which is saved.
+	pub fn with_min_position(mut self, min_position: f64) -> Self {
+		self.min_position = min_position;
+		self
+	}
+
+	pub fn store(&self) -> &S {
+		&self.store
+	}
+
+	pub fn store_mut(&mut self) -> &mut S {
+		&mut self.store
+	}
+
+	pub fn into_store(self) -> S {
+		self.store
+	}
+
+	/// Key of the file loaded now, `None` before the first `file-loaded` and between files.
+	pub fn current(&self) -> Option<&ResumeKey> {
+		self.current.as_ref()
+	}
+
+	/// Starts observing `time-pos` in `cache`, its changes are then passed to `update` together with the
+	/// file events.
+	pub fn observe(
+		cache: &mut PropertyCache,
+		link: &mut MpvLink
+	) -> Result<(), CommandError<ParseError>> {
+		cache.observe(link, property::TimePos)
+	}
+
+	/// Updates the tracker from `event`, restoring or saving the position of the file.
+	pub fn update(
+		&mut self,
+		link: &mut MpvLink,
+		event: &MpvResponseEvent
+	) -> Result<(), ResumeError> {
+		match event {
+			MpvResponseEvent::FileLoaded => {
+				let path = link.run_command(&CmdGetProperty::new(property::Path))?;
+				let key = self.strategy.key(&path);
+				if let Some(position) = self.store.load(&key)? {
+					log::debug!("Resuming {} at {}", key, position);
+					link.run_command(&CmdSeek::time(position, true)?)?;
+				}
+
+				self.current = Some(key);
+				self.last_position = None;
+				Ok(())
+			}
+			event => Ok(self.update_position(event)?)
+		}
+	}
+
+	/// Saves the last position of the current file now, such as periodically in case the controller or mpv
+	/// crashes.
+	pub fn save_current(&mut self) -> io::Result<()> {
+		match (self.current.as_ref(), self.last_position) {
+			(Some(key), Some(position)) if position >= self.min_position => {
+				self.store.save(key, position)
+			}
+			_ => Ok(())
+		}
+	}
+
+	/// Handles the events which do not need the link.
+	fn update_position(&mut self, event: &MpvResponseEvent) -> io::Result<()> {
+		match event {
+			MpvResponseEvent::PropertyChange {
+				name: MpvResponseEventPropertyName::Known(KnownMpvProperty::TimePos),
+				data,
+				..
+			} => {
+				// `time-pos` becomes unavailable before `end-file`, keep the last known position
+				if let Some(position) = data.as_f64() {
+					self.last_position = Some(position);
+				}
+			}
+			MpvResponseEvent::EndFile { reason, .. } => {
+				if *reason == Some(EndFileReason::Eof) {
+					if let Some(key) = self.current.as_ref() {
+						self.store.remove(key)?;
+					}
+				} else {
+					self.save_current()?;
+				}
+				self.current = None;
+				self.last_position = None;
+			}
+			_ => ()
+		}
+
+		Ok(())
+	}
+}
+
+/// 64-bit FNV-1a hash.
+struct Fnv1a(u64);
+impl Fnv1a {
+	fn new() -> Self {
+		Fnv1a(0xcbf29ce484222325)
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.0 ^= u64::from(byte);
+			self.0 = self.0.wrapping_mul(0x100000001b3);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::HashMap;
+
+	use super::{Fnv1a, ResumeKey, ResumeTracker};
+	use crate::command::response::MpvResponseEvent;
+
+	fn event(line: &str) -> MpvResponseEvent {
+		serde_json::from_str(line).unwrap()
+	}
+
+	#[test]
+	fn test_fnv1a() {
+		let mut hash = Fnv1a::new();
+		hash.write(b"foobar");
+		assert_eq!(hash.0, 0x85944171f73967e8);
+
+		assert_eq!(ResumeKey::Hash(0xab).to_string(), "hash:00000000000000ab");
+	}
+
+	#[test]
+	fn test_save_and_remove() {
+		let key = ResumeKey::Path("/video/a.mkv".to_string());
+		let mut tracker = ResumeTracker::new(HashMap::new());
+
+		tracker.current = Some(key.clone());
+		for line in [
+			r#"{"event":"property-change","id":1,"name":"time-pos","data":42.5}"#,
+			r#"{"event":"property-change","id":1,"name":"time-pos","data":null}"#,
+			r#"{"event":"end-file","reason":"stop","playlist_entry_id":1}"#
+		] {
+			tracker.update_position(&event(line)).unwrap();
+		}
+		assert_eq!(tracker.store().get(&key), Some(&42.5));
+		assert_eq!(tracker.current(), None);
+
+		// too close to the start
+		tracker.current = Some(key.clone());
+		tracker.store_mut().clear();
+		tracker
+			.update_position(&event(
+				r#"{"event":"property-change","id":1,"name":"time-pos","data":1.0}"#
+			))
+			.unwrap();
+		tracker.save_current().unwrap();
+		assert!(tracker.store().is_empty());
+
+		// finished files start from the beginning next time
+		tracker.store_mut().insert(key.clone(), 10.0);
+		tracker
+			.update_position(&event(
+				r#"{"event":"end-file","reason":"eof","playlist_entry_id":1}"#
+			))
+			.unwrap();
+		assert!(tracker.store().is_empty());
+	}
+}
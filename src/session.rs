@@ -0,0 +1,304 @@
+use std::{
+	collections::HashMap,
+	time::{Duration, Instant}
+};
+
+use crate::{
+	command::{
+		property::{self, KnownMpvProperty},
+		response::{MpvResponseEvent, MpvResponseEventPropertyName},
+		ParseError
+	},
+	link::{CommandError, MpvLink},
+	model::{EndFileReason, PlaylistEntry},
+	property_cache::PropertyCache
+};
+
+type SessionStartCallback = Box<dyn FnMut(&SessionStart) + Send>;
+type SessionEndCallback = Box<dyn FnMut(&SessionEnd) + Send>;
+
+/// A playlist entry started playing, passed to `PlaybackSession::on_session_start`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionStart {
+	pub playlist_entry_id: i64,
+	/// Filename of the entry, `None` if it was not in the playlist known to the session.
+	pub filename: Option<String>
+}
+
+/// Playback of a playlist entry ended, passed to `PlaybackSession::on_session_end`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionEnd {
+	pub playlist_entry_id: i64,
+	pub filename: Option<String>,
+	pub reason: Option<EndFileReason>,
+	/// Description of the error if `reason` is `Error`.
+	pub file_error: Option<String>,
+	/// Wall-clock time since `start-file`, `None` if the session started before it was tracked.
+	///
+	/// This includes the time spent loading and paused, it is not the played duration.
+	pub elapsed: Option<Duration>
+}
+
+/// Session which started or ended, returned by `PlaybackSession::update`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+	Started(SessionStart),
+	Ended(SessionEnd)
+}
+
+struct ActiveSession {
+	filename: Option<String>,
+	started: Instant
+}
+
+/// Pairs `start-file` and `end-file` events by `playlist_entry_id` into sessions, such as for scrobblers
+/// and history loggers.
+///
+/// Filenames are resolved from the `playlist` property, which the session reads from the property changes
+/// passed to `update`. Use `observe` to observe it in a `PropertyCache`, or `set_playlist` to seed it from
+/// a fetched playlist. The filename is remembered when the session starts, so removing the entry before
+/// it ends does not lose it.
+///
+/// mpv before 0.34 sends `end-file` without `playlist_entry_id`, it is then paired with the only active
+/// session, if there is exactly one.
+#[derive(Default)]
+pub struct PlaybackSession {
+	/// Filenames of the playlist entries by id.
+	playlist: HashMap<i64, String>,
+	active: HashMap<i64, ActiveSession>,
+	on_start: Option<SessionStartCallback>,
+	on_end: Option<SessionEndCallback>
+}
+impl PlaybackSession {
+	pub fn new() -> Self {
+		PlaybackSession::default()
+	}
+
+	/// Calls `callback` whenever a session starts.
+	pub fn on_session_start(
+		mut self,
+		callback: impl FnMut(&SessionStart) + Send + 'static
+	) -> Self {
+		self.on_start = Some(Box::new(callback));
+		self
+	}
+
+	/// Calls `callback` whenever a session ends.
+	pub fn on_session_end(mut self, callback: impl FnMut(&SessionEnd) + Send + 'static) -> Self {
+		self.on_end = Some(Box::new(callback));
+		self
+	}
+
+	/// Starts observing the `playlist` property in `cache`, its changes are then passed to `update`
+	/// together with the file events.
+	pub fn observe(
+		cache: &mut PropertyCache,
+		link: &mut MpvLink
+	) -> Result<(), CommandError<ParseError>> {
+		cache.observe(link, property::Playlist)
+	}
+
+	/// Replaces the known playlist, entries without an id are skipped.
+	pub fn set_playlist(&mut self, entries: &[PlaylistEntry]) {
+		self.playlist = entries
+			.iter()
+			.filter_map(|entry| Some((entry.id?, entry.filename.clone())))
+			.collect();
+	}
+
+	/// Ids of the entries which started and did not end yet.
+	pub fn active(&self) -> impl Iterator<Item = i64> + '_ {
+		self.active.keys().copied()
+	}
+
+	pub fn is_active(&self, playlist_entry_id: i64) -> bool {
+		self.active.contains_key(&playlist_entry_id)
+	}
+
+	/// Updates the sessions from `event`, calling the callbacks if a session started or ended.
+	///
+	/// Returns the session which started or ended, if any.
+	pub fn update(&mut self, event: &MpvResponseEvent) -> Option<SessionEvent> {
+		match event {
+			MpvResponseEvent::PropertyChange {
+				name: MpvResponseEventPropertyName::Known(KnownMpvProperty::Playlist),
+				data,
+				..
+			} => {
+				match serde_json::from_value::<Vec<PlaylistEntry>>(data.clone()) {
+					Ok(entries) => self.set_playlist(&entries),
+					Err(err) => log::warn!("Could not parse value of playlist: {}", err)
+				}
+				None
+			}
+			MpvResponseEvent::StartFile(info) => {
+				Some(SessionEvent::Started(self.start(info.playlist_entry_id)))
+			}
+			MpvResponseEvent::EndFile {
+				reason,
+				playlist_entry_id,
+				file_error
+			} => {
+				let playlist_entry_id = match playlist_entry_id {
+					Some(id) => *id,
+					None if self.active.len() == 1 => *self.active.keys().next().unwrap(),
+					None => {
+						log::debug!("Skipping end-file without playlist_entry_id");
+						return None
+					}
+				};
+				Some(SessionEvent::Ended(self.end(
+					playlist_entry_id,
+					*reason,
+					file_error.clone()
+				)))
+			}
+			_ => None
+		}
+	}
+
+	fn start(&mut self, playlist_entry_id: i64) -> SessionStart {
+		let filename = self.playlist.get(&playlist_entry_id).cloned();
+		let start = SessionStart {
+			playlist_entry_id,
+			filename
+		};
+		if let Some(callback) = self.on_start.as_mut() {
+			callback(&start);
+		}
+
+		self.active.insert(
+			playlist_entry_id,
+			ActiveSession {
+				filename: start.filename.clone(),
+				started: Instant::now()
+			}
+		);
+
+		start
+	}
+
+	fn end(
+		&mut self,
+		playlist_entry_id: i64,
+		reason: Option<EndFileReason>,
+		file_error: Option<String>
+	) -> SessionEnd {
+		let session = self.active.remove(&playlist_entry_id);
+		// the playlist may have been updated since the start
+		let filename = session
+			.as_ref()
+			.and_then(|session| session.filename.clone())
+			.or_else(|| self.playlist.get(&playlist_entry_id).cloned());
+
+		let end = SessionEnd {
+			playlist_entry_id,
+			filename,
+			reason,
+			file_error,
+			elapsed: session.map(|session| session.started.elapsed())
+		};
+		if let Some(callback) = self.on_end.as_mut() {
+			callback(&end);
+		}
+
+		end
+	}
+}
+impl std::fmt::Debug for PlaybackSession {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("PlaybackSession")
+			.field("playlist", &self.playlist)
+			.field("active", &self.active.keys().collect::<Vec<_>>())
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::{Arc, Mutex};
+
+	use super::{PlaybackSession, SessionEnd, SessionStart};
+	use crate::{command::response::MpvResponseEvent, model::EndFileReason};
+
+	fn event(line: &str) -> MpvResponseEvent {
+		serde_json::from_str(line).unwrap()
+	}
+
+	#[test]
+	fn test_sessions() {
+		let starts = Arc::new(Mutex::new(Vec::new()));
+		let ends = Arc::new(Mutex::new(Vec::new()));
+		let mut session = {
+			let starts = starts.clone();
+			let ends = ends.clone();
+			PlaybackSession::new()
+				.on_session_start(move |start: &SessionStart| {
+					starts.lock().unwrap().push(start.clone())
+				})
+				.on_session_end(move |end: &SessionEnd| ends.lock().unwrap().push(end.clone()))
+		};
+
+		for line in [
+			r#"{"event":"property-change","id":1,"name":"playlist","data":[{"filename":"a.mkv","id":1,"current":true},{"filename":"b.mkv","id":2}]}"#,
+			r#"{"event":"start-file","playlist_entry_id":1}"#,
+			// the entry is removed while playing
+			r#"{"event":"property-change","id":1,"name":"playlist","data":[{"filename":"b.mkv","id":2}]}"#,
+			r#"{"event":"end-file","reason":"stop","playlist_entry_id":1}"#,
+			r#"{"event":"start-file","playlist_entry_id":2}"#,
+			// before mpv 0.34
+			r#"{"event":"end-file","reason":"eof"}"#
+		] {
+			session.update(&event(line));
+		}
+
+		assert_eq!(
+			*starts.lock().unwrap(),
+			[
+				SessionStart {
+					playlist_entry_id: 1,
+					filename: Some("a.mkv".to_string())
+				},
+				SessionStart {
+					playlist_entry_id: 2,
+					filename: Some("b.mkv".to_string())
+				}
+			]
+		);
+		let ends = ends.lock().unwrap();
+		assert_eq!(ends.len(), 2);
+		assert_eq!(ends[0].filename.as_deref(), Some("a.mkv"));
+		assert_eq!(ends[0].reason, Some(EndFileReason::Stop));
+		assert!(ends[0].elapsed.is_some());
+		assert_eq!(ends[1].playlist_entry_id, 2);
+		assert_eq!(ends[1].reason, Some(EndFileReason::Eof));
+		assert_eq!(session.active().count(), 0);
+	}
+
+	#[test]
+	fn test_untracked_end() {
+		let ends = Arc::new(Mutex::new(Vec::new()));
+		let mut session = {
+			let ends = ends.clone();
+			PlaybackSession::new()
+				.on_session_end(move |end: &SessionEnd| ends.lock().unwrap().push(end.clone()))
+		};
+
+		// ambiguous without an id and nothing active
+		session.update(&event(r#"{"event":"end-file","reason":"eof"}"#));
+		session.update(&event(
+			r#"{"event":"end-file","reason":"error","playlist_entry_id":4,"file_error":"unrecognized file format"}"#
+		));
+
+		assert_eq!(
+			*ends.lock().unwrap(),
+			[SessionEnd {
+				playlist_entry_id: 4,
+				filename: None,
+				reason: Some(EndFileReason::Error),
+				file_error: Some("unrecognized file format".to_string()),
+				elapsed: None
+			}]
+		);
+	}
+}
@@ -0,0 +1,196 @@
+use serde::Serialize;
+
+use crate::{
+	command::{
+		commands::CmdGetProperty,
+		property::{self, MpvProperty},
+		ParseError
+	},
+	link::{batch::BatchedValues, CommandError, MpvLink},
+	property_cache::PropertyCache
+};
+
+/// Snapshot of the playback state, as shown by a now-playing summary.
+///
+/// Properties which are unavailable, such as `duration` while idle, are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlaybackSnapshot {
+	pub media_title: Option<String>,
+	pub time_pos: Option<f64>,
+	pub duration: Option<f64>,
+	pub pause: Option<bool>,
+	pub volume: Option<f64>
+}
+impl PlaybackSnapshot {
+	/// Fetches all properties in one batch, see `MpvLink::run_commands_batched`.
+	pub fn fetch(link: &mut MpvLink) -> Result<Self, CommandError<ParseError>> {
+		let commands = [
+			CmdGetProperty::new(property::MediaTitle.name()),
+			CmdGetProperty::new(property::TimePos.name()),
+			CmdGetProperty::new(property::Duration.name()),
+			CmdGetProperty::new(property::Pause.name()),
+			CmdGetProperty::new(property::Volume.name())
+		];
+
+		let mut values = BatchedValues::new(link.run_commands_batched(&commands)?);
+		Ok(PlaybackSnapshot {
+			media_title: values.next_optional(),
+			time_pos: values.next_optional(),
+			duration: values.next_optional(),
+			pause: values.next_optional(),
+			volume: values.next_optional()
+		})
+	}
+
+	/// Starts observing the snapshot properties in `cache`, see `from_cache`.
+	pub fn observe(
+		cache: &mut PropertyCache,
+		link: &mut MpvLink
+	) -> Result<(), CommandError<ParseError>> {
+		cache.observe(link, property::MediaTitle)?;
+		cache.observe(link, property::TimePos)?;
+		cache.observe(link, property::Duration)?;
+		cache.observe(link, property::Pause)?;
+		cache.observe(link, property::Volume)?;
+
+		Ok(())
+	}
+
+	/// Creates the snapshot from the values in `cache`, which is kept up to date after calling `observe`.
+	pub fn from_cache(cache: &PropertyCache) -> Self {
+		PlaybackSnapshot {
+			media_title: cache.get::<property::MediaTitle>().cloned(),
+			time_pos: cache.get::<property::TimePos>().copied(),
+			duration: cache.get::<property::Duration>().copied(),
+			pause: cache.get::<property::Pause>().copied(),
+			volume: cache.get::<property::Volume>().copied()
+		}
+	}
+}
+
+/// Playback health statistics, as shown by a monitoring dashboard.
+///
+/// Properties which are unavailable, such as the video statistics of audio-only files, are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlaybackStats {
+	/// Frames dropped by the video output, because they were late.
+	pub frame_drop_count: Option<u32>,
+	/// Frames dropped by the decoder, usually with `framedrop=decoder`.
+	pub decoder_frame_drop_count: Option<u32>,
+	/// Frames the video output displayed late.
+	pub vo_delayed_frame_count: Option<u32>,
+	/// Frame rate after the video filters, estimated from the timestamps.
+	pub estimated_vf_fps: Option<f64>,
+	/// Difference between audio and video position in seconds.
+	pub avsync: Option<f64>,
+	/// Bytes per second the cache is filled with.
+	pub cache_speed: Option<f64>,
+	/// Seconds of media buffered ahead by the demuxer.
+	pub demuxer_cache_duration: Option<f64>
+}
+impl PlaybackStats {
+	/// Fetches all properties in one batch, see `MpvLink::run_commands_batched`.
+	pub fn fetch(link: &mut MpvLink) -> Result<Self, CommandError<ParseError>> {
+		let commands = [
+			CmdGetProperty::new(property::FrameDropCount.name()),
+			CmdGetProperty::new(property::DecoderFrameDropCount.name()),
+			CmdGetProperty::new(property::VoDelayedFrameCount.name()),
+			CmdGetProperty::new(property::EstimatedVfFps.name()),
+			CmdGetProperty::new(property::Avsync.name()),
+			CmdGetProperty::new(property::CacheSpeed.name()),
+			CmdGetProperty::new(property::DemuxerCacheDuration.name())
+		];
+
+		let mut values = BatchedValues::new(link.run_commands_batched(&commands)?);
+		Ok(PlaybackStats {
+			frame_drop_count: values.next_optional(),
+			decoder_frame_drop_count: values.next_optional(),
+			vo_delayed_frame_count: values.next_optional(),
+			estimated_vf_fps: values.next_optional(),
+			avsync: values.next_optional(),
+			cache_speed: values.next_optional(),
+			demuxer_cache_duration: values.next_optional()
+		})
+	}
+
+	/// Starts observing the statistics properties in `cache`, see `from_cache`.
+	///
+	/// Some of them change with every frame, so observing them produces a steady stream of events.
+	pub fn observe(
+		cache: &mut PropertyCache,
+		link: &mut MpvLink
+	) -> Result<(), CommandError<ParseError>> {
+		cache.observe(link, property::FrameDropCount)?;
+		cache.observe(link, property::DecoderFrameDropCount)?;
+		cache.observe(link, property::VoDelayedFrameCount)?;
+		cache.observe(link, property::EstimatedVfFps)?;
+		cache.observe(link, property::Avsync)?;
+		cache.observe(link, property::CacheSpeed)?;
+		cache.observe(link, property::DemuxerCacheDuration)?;
+
+		Ok(())
+	}
+
+	/// Creates the statistics from the values in `cache`, which is kept up to date after calling `observe`.
+	pub fn from_cache(cache: &PropertyCache) -> Self {
+		PlaybackStats {
+			frame_drop_count: cache.get::<property::FrameDropCount>().copied(),
+			decoder_frame_drop_count: cache.get::<property::DecoderFrameDropCount>().copied(),
+			vo_delayed_frame_count: cache.get::<property::VoDelayedFrameCount>().copied(),
+			estimated_vf_fps: cache.get::<property::EstimatedVfFps>().copied(),
+			avsync: cache.get::<property::Avsync>().copied(),
+			cache_speed: cache.get::<property::CacheSpeed>().copied(),
+			demuxer_cache_duration: cache.get::<property::DemuxerCacheDuration>().copied()
+		}
+	}
+
+	/// Frames dropped by the video output and the decoder together, `None` if neither is available.
+	pub fn dropped_frames(&self) -> Option<u32> {
+		match (self.frame_drop_count, self.decoder_frame_drop_count) {
+			(None, None) => None,
+			(vo, decoder) => Some(vo.unwrap_or(0).saturating_add(decoder.unwrap_or(0)))
+		}
+	}
+}
+
+/// What a loaded URL resolved to, for showing streams opened through youtube-dl.
+///
+/// Until the stream is opened only `path` is available and `media-title` is derived from the URL, once
+/// opened `stream-path` is the URL actually played and `media-title` is the title of the video.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StreamInfo {
+	/// The URL as loaded.
+	pub path: Option<String>,
+	pub stream_path: Option<String>,
+	pub media_title: Option<String>
+}
+impl StreamInfo {
+	/// Fetches all properties in one batch, see `MpvLink::run_commands_batched`.
+	pub fn fetch(link: &mut MpvLink) -> Result<Self, CommandError<ParseError>> {
+		let commands = [
+			CmdGetProperty::new(property::Path.name()),
+			CmdGetProperty::new(property::StreamPath.name()),
+			CmdGetProperty::new(property::MediaTitle.name())
+		];
+
+		let mut values = BatchedValues::new(link.run_commands_batched(&commands)?);
+		Ok(StreamInfo {
+			path: values.next_optional(),
+			stream_path: values.next_optional(),
+			media_title: values.next_optional()
+		})
+	}
+
+	/// Returns `true` once the stream was opened.
+	pub fn is_opened(&self) -> bool {
+		self.stream_path.is_some()
+	}
+
+	/// The title of the stream, `None` until it is opened since the title would only be the end of the URL.
+	pub fn title(&self) -> Option<&str> {
+		match self.is_opened() {
+			true => self.media_title.as_deref(),
+			false => None
+		}
+	}
+}
@@ -0,0 +1,189 @@
+use std::{
+	fs,
+	io::{self, Read, Write},
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+	sync::atomic::{AtomicU32, Ordering},
+	time::Duration
+};
+
+/// Sample rate of generated audio, as low as possible while still being accepted everywhere.
+const SAMPLE_RATE: u32 = 8000;
+/// Frame size and rate of generated video.
+const VIDEO_SIZE: &str = "64x64";
+const VIDEO_RATE: u32 = 10;
+
+/// Test media generated in the temporary directory, removed when dropped.
+///
+/// Generating media at test time keeps tests of `loadfile`, seeking or track selection hermetic without
+/// binary assets in the repository.
+#[derive(Debug)]
+pub struct MediaFixture {
+	path: PathBuf
+}
+impl MediaFixture {
+	/// Writes a WAV file with `duration` of mono silence, which needs no external tools.
+	pub fn silence(duration: Duration) -> io::Result<Self> {
+		let fixture = MediaFixture {
+			path: fixture_path("wav")
+		};
+		write_silence_wav(
+			io::BufWriter::new(fs::File::create(&fixture.path)?),
+			duration
+		)?;
+
+		Ok(fixture)
+	}
+
+	/// Generates a Matroska file with a black video track and a silent audio track lasting `duration`.
+	///
+	/// Uses `ffmpeg` if it is installed and otherwise encodes with `mpv`, fails with `NotFound` if neither is.
+	pub fn color_video(duration: Duration) -> io::Result<Self> {
+		let fixture = MediaFixture {
+			path: fixture_path("mkv")
+		};
+		let seconds = format!("{:.3}", duration.as_secs_f64());
+
+		let mut ffmpeg = Command::new("ffmpeg");
+		ffmpeg
+			.args(["-nostdin", "-loglevel", "error", "-y", "-f", "lavfi", "-i"])
+			.arg(format!(
+				"color=c=black:s={}:r={}:d={}",
+				VIDEO_SIZE, VIDEO_RATE, seconds
+			))
+			.args(["-f", "lavfi", "-i"])
+			.arg(format!("anullsrc=r={}:cl=mono", SAMPLE_RATE))
+			.args(["-t", &seconds, "-c:v", "ffv1", "-c:a", "pcm_s16le"])
+			.arg(&fixture.path);
+
+		let mut mpv = Command::new("mpv");
+		mpv.args(["--no-config", "--really-quiet"])
+			.arg(format!(
+				"av://lavfi:color=c=black:s={}:r={}:d={}",
+				VIDEO_SIZE, VIDEO_RATE, seconds
+			))
+			.arg(format!(
+				"--audio-file=av://lavfi:anullsrc=r={}:cl=mono",
+				SAMPLE_RATE
+			))
+			.arg(format!("--length={}", seconds))
+			.args(["--ovc=ffv1", "--oac=pcm_s16le"])
+			.arg(format!("--o={}", fixture.path.display()));
+
+		match run_tool(ffmpeg) {
+			Err(err) if err.kind() == io::ErrorKind::NotFound => run_tool(mpv)?,
+			result => result?
+		}
+
+		Ok(fixture)
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// The path as a string, as passed to `CmdLoadfile`.
+	pub fn path_str(&self) -> String {
+		self.path.to_string_lossy().into_owned()
+	}
+}
+impl Drop for MediaFixture {
+	fn drop(&mut self) {
+		// the file does not exist when generating it failed
+		match fs::remove_file(&self.path) {
+			Err(err) if err.kind() != io::ErrorKind::NotFound => {
+				log::warn!("Could not remove fixture {}: {}", self.path.display(), err)
+			}
+			_ => ()
+		}
+	}
+}
+
+/// Writes a mono 16-bit PCM WAV file of `duration` of silence.
+pub fn write_silence_wav(mut w: impl Write, duration: Duration) -> io::Result<()> {
+	let samples = (duration.as_secs_f64() * SAMPLE_RATE as f64).round() as u32;
+	let data_len = samples * 2;
+
+	w.write_all(b"RIFF")?;
+	w.write_all(&(36 + data_len).to_le_bytes())?;
+	w.write_all(b"WAVEfmt ")?;
+	w.write_all(&16u32.to_le_bytes())?;
+	// PCM, one channel
+	w.write_all(&1u16.to_le_bytes())?;
+	w.write_all(&1u16.to_le_bytes())?;
+	w.write_all(&SAMPLE_RATE.to_le_bytes())?;
+	w.write_all(&(SAMPLE_RATE * 2).to_le_bytes())?;
+	// block align and bits per sample
+	w.write_all(&2u16.to_le_bytes())?;
+	w.write_all(&16u16.to_le_bytes())?;
+	w.write_all(b"data")?;
+	w.write_all(&data_len.to_le_bytes())?;
+	io::copy(&mut io::repeat(0).take(data_len as u64), &mut w)?;
+
+	w.flush()
+}
+
+/// Generates a path unique within this process in the temporary directory.
+fn fixture_path(extension: &str) -> PathBuf {
+	static FIXTURE_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+	std::env::temp_dir().join(format!(
+		"mpvsock-fixture-{}-{}.{}",
+		std::process::id(),
+		FIXTURE_COUNTER.fetch_add(1, Ordering::Relaxed),
+		extension
+	))
+}
+
+/// Runs `command` to completion, failing if it exits unsuccessfully.
+fn run_tool(mut command: Command) -> io::Result<()> {
+	let output = command
+		.stdin(Stdio::null())
+		.stdout(Stdio::null())
+		.stderr(Stdio::piped())
+		.output()?;
+
+	if !output.status.success() {
+		return Err(io::Error::other(format!(
+			"{:?} failed with {}: {}",
+			command.get_program(),
+			output.status,
+			String::from_utf8_lossy(&output.stderr).trim()
+		)))
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use super::{write_silence_wav, MediaFixture};
+
+	#[test]
+	fn test_write_silence_wav() {
+		let mut out = Vec::new();
+		write_silence_wav(&mut out, Duration::from_millis(500)).unwrap();
+
+		assert_eq!(&out[.. 4], b"RIFF");
+		assert_eq!(&out[8 .. 16], b"WAVEfmt ");
+		// 4000 samples of 2 bytes after the 44 byte header
+		assert_eq!(out.len(), 44 + 8000);
+		assert_eq!(u32::from_le_bytes(out[40 .. 44].try_into().unwrap()), 8000);
+		assert_eq!(
+			u32::from_le_bytes(out[4 .. 8].try_into().unwrap()),
+			36 + 8000
+		);
+	}
+
+	#[test]
+	fn test_fixture_removed_on_drop() {
+		let fixture = MediaFixture::silence(Duration::from_millis(100)).unwrap();
+		let path = fixture.path().to_path_buf();
+		assert!(path.exists());
+
+		std::mem::drop(fixture);
+		assert!(!path.exists());
+	}
+}
@@ -0,0 +1,3 @@
+//! Helpers for tests of code using this crate.
+
+pub mod fixtures;
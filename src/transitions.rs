@@ -0,0 +1,258 @@
+//! Experimental transitions between playlist entries, the API may still change.
+
+use std::{collections::VecDeque, f64::consts::FRAC_PI_2, time::Duration};
+
+use crate::{
+	command::{
+		commands::CmdSetProperty,
+		property::{self, KnownMpvProperty},
+		response::{MpvResponseEvent, MpvResponseEventPropertyName},
+		ParseError
+	},
+	link::{CommandError, MpvLink},
+	property_cache::PropertyCache
+};
+
+/// Smallest change of `volume` which is set during a fade, smaller steps are not audible.
+const MIN_VOLUME_STEP: f64 = 0.5;
+
+/// Shape of a fade, mapping the progress of a fade-in from `0` to `1` to a gain from `0` to `1`.
+///
+/// Fade-outs use the same curve backwards. mpv maps `volume` to amplitude with a cubic curve, so
+/// `Linear` already sounds even.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FadeCurve {
+	#[default]
+	Linear,
+	/// Sine curve which rises quickly and levels off, keeping the sum of two overlapping fades constant.
+	EqualPower,
+	/// Smoothstep curve which starts and ends gently.
+	SCurve
+}
+impl FadeCurve {
+	pub fn gain(&self, progress: f64) -> f64 {
+		let t = progress.clamp(0.0, 1.0);
+		match self {
+			FadeCurve::Linear => t,
+			FadeCurve::EqualPower => (t * FRAC_PI_2).sin(),
+			FadeCurve::SCurve => t * t * (3.0 - 2.0 * t)
+		}
+	}
+}
+
+/// Software crossfade which ramps `volume` down at the end of each file and up at the start of the next.
+///
+/// mpv plays one file at a time, so the files do not overlap, the fades only smooth the cut between them.
+/// The ramps follow `time-pos`, so they pause with playback and adapt to seeking. Call `observe` once,
+/// then pass all events to `update`.
+///
+/// Only `volume` is ramped, so `replaygain` and `volume-gain` keep adjusting the loudness of each file.
+/// Changes of `volume` which the crossfade did not make are taken as the new full volume, including ones
+/// made during a fade.
+#[derive(Debug, Clone)]
+pub struct Crossfade {
+	fade_in: f64,
+	fade_out: f64,
+	curve: FadeCurve,
+	base_volume: Option<f64>,
+	last_volume: Option<f64>,
+	/// Volumes set by the crossfade whose changes were not received yet.
+	pending: VecDeque<f64>,
+	duration: Option<f64>
+}
+impl Crossfade {
+	/// Creates a crossfade which fades out and in over `duration` each.
+	pub fn new(duration: Duration) -> Self {
+		Crossfade {
+			fade_in: duration.as_secs_f64(),
+			fade_out: duration.as_secs_f64(),
+			curve: FadeCurve::default(),
+			base_volume: None,
+			last_volume: None,
+			pending: VecDeque::new(),
+			duration: None
+		}
+	}
+
+	pub fn with_fade_in(mut self, fade_in: Duration) -> Self {
+		self.fade_in = fade_in.as_secs_f64();
+		self
+	}
+
+	pub fn with_fade_out(mut self, fade_out: Duration) -> Self {
+		self.fade_out = fade_out.as_secs_f64();
+		self
+	}
+
+	pub fn with_curve(mut self, curve: FadeCurve) -> Self {
+		self.curve = curve;
+		self
+	}
+
+	/// Full volume restored between the fades, `None` until the first change of `volume` is received.
+	pub fn base_volume(&self) -> Option<f64> {
+		self.base_volume
+	}
+
+	/// Starts observing the properties the crossfade follows in `cache`.
+	pub fn observe(
+		cache: &mut PropertyCache,
+		link: &mut MpvLink
+	) -> Result<(), CommandError<ParseError>> {
+		cache.observe(link, property::Volume)?;
+		cache.observe(link, property::Duration)?;
+		cache.observe(link, property::TimePos)?;
+
+		Ok(())
+	}
+
+	/// Updates the crossfade from `event`, setting `volume` if the ramp moved.
+	pub fn update(
+		&mut self,
+		link: &mut MpvLink,
+		event: &MpvResponseEvent
+	) -> Result<(), CommandError<ParseError>> {
+		match event {
+			MpvResponseEvent::PropertyChange {
+				name: MpvResponseEventPropertyName::Known(property),
+				data,
+				..
+			} => match property {
+				KnownMpvProperty::Volume => {
+					if let Some(volume) = data.as_f64() {
+						self.volume_changed(volume);
+					}
+				}
+				KnownMpvProperty::Duration => self.duration = data.as_f64(),
+				KnownMpvProperty::TimePos => {
+					if let Some(time_pos) = data.as_f64() {
+						let gain = self.gain_at(time_pos);
+						self.set_gain(link, gain, false)?;
+					}
+				}
+				_ => ()
+			},
+			MpvResponseEvent::StartFile(_) => {
+				self.duration = None;
+				if self.fade_in > 0.0 {
+					self.set_gain(link, self.curve.gain(0.0), true)?;
+				}
+			}
+			// restored between files so that stopping does not leave the volume faded out
+			MpvResponseEvent::EndFile { .. } => self.set_gain(link, 1.0, true)?,
+			_ => ()
+		}
+
+		Ok(())
+	}
+
+	fn volume_changed(&mut self, volume: f64) {
+		// changes of own sets may arrive after the next set was made
+		match self
+			.pending
+			.iter()
+			.position(|pending| (pending - volume).abs() < 1e-6)
+		{
+			Some(index) => {
+				self.pending.drain(..= index);
+			}
+			None => {
+				self.base_volume = Some(volume);
+				self.last_volume = None;
+				self.pending.clear();
+			}
+		}
+	}
+
+	/// Gain of both fades at `time_pos`.
+	fn gain_at(&self, time_pos: f64) -> f64 {
+		let fade_in = match self.fade_in > 0.0 {
+			true => self.curve.gain(time_pos / self.fade_in),
+			false => 1.0
+		};
+		let fade_out = match self.duration {
+			Some(duration) if self.fade_out > 0.0 => {
+				self.curve.gain((duration - time_pos) / self.fade_out)
+			}
+			_ => 1.0
+		};
+
+		fade_in.min(fade_out)
+	}
+
+	fn set_gain(
+		&mut self,
+		link: &mut MpvLink,
+		gain: f64,
+		force: bool
+	) -> Result<(), CommandError<ParseError>> {
+		let base_volume = match self.base_volume {
+			None => return Ok(()),
+			Some(base_volume) => base_volume
+		};
+		let volume = base_volume * gain;
+		let current = self.last_volume.unwrap_or(base_volume);
+		// the ends of the ramps are always reached exactly
+		let at_end = (gain == 0.0 || gain == 1.0) && volume != current;
+		if !force && !at_end && (volume - current).abs() < MIN_VOLUME_STEP {
+			return Ok(())
+		}
+
+		link.run_command(&CmdSetProperty::new(property::Volume, volume))?;
+		self.last_volume = Some(volume);
+		self.pending.push_back(volume);
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use super::{Crossfade, FadeCurve};
+
+	#[test]
+	fn test_curves() {
+		for curve in [FadeCurve::Linear, FadeCurve::EqualPower, FadeCurve::SCurve] {
+			assert_eq!(curve.gain(-1.0), 0.0);
+			assert_eq!(curve.gain(0.0), 0.0);
+			assert!((curve.gain(1.0) - 1.0).abs() < 1e-9);
+			assert!(curve.gain(0.25) < curve.gain(0.75));
+		}
+		assert!((FadeCurve::EqualPower.gain(0.5) - 0.5f64.sqrt()).abs() < 1e-9);
+		assert_eq!(FadeCurve::SCurve.gain(0.5), 0.5);
+	}
+
+	#[test]
+	fn test_gain_at() {
+		let mut fade = Crossfade::new(Duration::from_secs(2)).with_fade_out(Duration::from_secs(4));
+		assert_eq!(fade.gain_at(1.0), 0.5);
+		// the end is not known yet
+		assert_eq!(fade.gain_at(50.0), 1.0);
+
+		fade.duration = Some(60.0);
+		assert_eq!(fade.gain_at(30.0), 1.0);
+		assert_eq!(fade.gain_at(58.0), 0.5);
+		assert_eq!(fade.gain_at(60.0), 0.0);
+	}
+
+	#[test]
+	fn test_volume_changed() {
+		let mut fade = Crossfade::new(Duration::from_secs(2));
+		fade.volume_changed(80.0);
+		assert_eq!(fade.base_volume(), Some(80.0));
+
+		fade.pending.extend([40.0, 60.0]);
+		fade.last_volume = Some(60.0);
+		// own changes, possibly received late, do not change the full volume
+		fade.volume_changed(40.0);
+		fade.volume_changed(60.0);
+		assert_eq!(fade.base_volume(), Some(80.0));
+		assert!(fade.pending.is_empty());
+
+		fade.volume_changed(50.0);
+		assert_eq!(fade.base_volume(), Some(50.0));
+		assert_eq!(fade.last_volume, None);
+	}
+}
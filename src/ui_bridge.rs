@@ -0,0 +1,348 @@
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+use crate::{
+	command::{
+		commands::CmdObserveProperty,
+		property::KnownMpvProperty,
+		response::{MpvResponseEvent, MpvResponseEventRef},
+		ParseError
+	},
+	link::{CommandError, MpvLink, ReceiveError}
+};
+
+/// Fields of `PlayerModel` changed since the flags were last cleared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlayerModelDirty {
+	pub media_title: bool,
+	pub time_pos: bool,
+	pub duration: bool,
+	pub pause: bool,
+	pub volume: bool,
+	pub playlist_pos: bool,
+	pub playlist_count: bool
+}
+impl PlayerModelDirty {
+	/// Returns `true` if any field changed.
+	pub fn any(&self) -> bool {
+		*self != PlayerModelDirty::default()
+	}
+}
+
+/// Observed properties in the order of their observer ids.
+const PROPERTIES: [KnownMpvProperty; 7] = [
+	KnownMpvProperty::MediaTitle,
+	KnownMpvProperty::TimePos,
+	KnownMpvProperty::Duration,
+	KnownMpvProperty::Pause,
+	KnownMpvProperty::Volume,
+	KnownMpvProperty::PlaylistPos,
+	KnownMpvProperty::PlaylistCount
+];
+
+/// Data of a `property-change` event, borrowed from the received line or from a queued event.
+trait ChangeData<'a> {
+	/// Deserializes the data, `None` if it is missing or null.
+	fn parse<T: Deserialize<'a>>(&self) -> Result<Option<T>, serde_json::Error>;
+
+	fn parse_str(&self) -> Result<Option<Cow<'a, str>>, serde_json::Error>;
+}
+impl<'a> ChangeData<'a> for &MpvResponseEventRef<'a> {
+	fn parse<T: Deserialize<'a>>(&self) -> Result<Option<T>, serde_json::Error> {
+		self.parse_data::<Option<T>>().map(Option::flatten)
+	}
+
+	fn parse_str(&self) -> Result<Option<Cow<'a, str>>, serde_json::Error> {
+		self.parse_data_str()
+	}
+}
+impl<'a> ChangeData<'a> for &'a serde_json::Value {
+	fn parse<T: Deserialize<'a>>(&self) -> Result<Option<T>, serde_json::Error> {
+		Option::<T>::deserialize(*self)
+	}
+
+	fn parse_str(&self) -> Result<Option<Cow<'a, str>>, serde_json::Error> {
+		Option::<&str>::deserialize(*self).map(|value| value.map(Cow::Borrowed))
+	}
+}
+
+/// Player state for immediate-mode GUIs such as egui, kept up to date from observed properties.
+///
+/// The model is plain data owned by the UI thread, so it can be read every frame without locking. Call
+/// `poll` once per frame, then check `dirty` to redraw only what changed and `clear_dirty` afterwards.
+/// Updates do not allocate, except when the media title grows longer than any before.
+///
+/// Properties which are unavailable, such as `duration` while idle, are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerModel {
+	pub media_title: Option<String>,
+	pub time_pos: Option<f64>,
+	pub duration: Option<f64>,
+	pub pause: Option<bool>,
+	pub volume: Option<f64>,
+	pub playlist_pos: Option<i64>,
+	pub playlist_count: Option<u32>,
+	dirty: PlayerModelDirty
+}
+impl PlayerModel {
+	/// First observer id used by the model, above the ids of `PropertyCache::new` and property bindings.
+	pub const FIRST_OBSERVER_ID: u32 = 1 << 26;
+
+	/// Creates the model and starts observing its properties on `link`.
+	///
+	/// The initial values arrive as property changes with the next `poll`.
+	pub fn observe(link: &mut MpvLink) -> Result<Self, CommandError<ParseError>> {
+		for (index, property) in PROPERTIES.into_iter().enumerate() {
+			link.run_command(&CmdObserveProperty::new(
+				Self::FIRST_OBSERVER_ID + index as u32,
+				property
+			))?;
+		}
+
+		Ok(PlayerModel::default())
+	}
+
+	/// Polls events from `link` and updates the model, returning `true` if any field changed.
+	///
+	/// Events are read with `MpvLink::poll_events_with`, so they are not added to the queue of the link.
+	/// Use `poll_with` to handle the other events. Changes which were queued while the link waited for a
+	/// result are applied and removed from the queue.
+	pub fn poll(&mut self, link: &mut MpvLink) -> Result<bool, ReceiveError> {
+		self.poll_with(link, |_| ())
+	}
+
+	/// Polls events like `poll`, passing events which did not update the model to `callback`.
+	pub fn poll_with(
+		&mut self,
+		link: &mut MpvLink,
+		mut callback: impl FnMut(MpvResponseEventRef<'_>)
+	) -> Result<bool, ReceiveError> {
+		let mut changed = false;
+		link.retain_events(|event| match self.apply(event) {
+			Some(event_changed) => {
+				changed |= event_changed;
+				false
+			}
+			None => true
+		});
+		link.poll_events_with(|event| match self.apply_ref(&event) {
+			Some(event_changed) => changed |= event_changed,
+			None => callback(event)
+		})?;
+
+		Ok(changed)
+	}
+
+	/// Updates the model from `event`, returning `true` if a field changed.
+	pub fn update(&mut self, event: &MpvResponseEvent) -> bool {
+		self.apply(event).unwrap_or(false)
+	}
+
+	/// Updates the model from the borrowed `event`, returning `true` if a field changed.
+	pub fn update_ref(&mut self, event: &MpvResponseEventRef) -> bool {
+		self.apply_ref(event).unwrap_or(false)
+	}
+
+	/// Returns the fields changed since the last `clear_dirty`.
+	pub fn dirty(&self) -> PlayerModelDirty {
+		self.dirty
+	}
+
+	pub fn clear_dirty(&mut self) {
+		self.dirty = PlayerModelDirty::default();
+	}
+
+	/// Updates the model from `event`, returning whether a field changed or `None` if the event is not
+	/// a change of the model properties.
+	fn apply(&mut self, event: &MpvResponseEvent) -> Option<bool> {
+		match event {
+			MpvResponseEvent::PropertyChange { id, data, .. } => {
+				let property = Self::property_of(*id)?;
+				Some(self.update_property(property, data))
+			}
+			_ => None
+		}
+	}
+
+	fn apply_ref(&mut self, event: &MpvResponseEventRef) -> Option<bool> {
+		if event.event != "property-change" {
+			return None
+		}
+
+		let property = Self::property_of(event.id?)?;
+		Some(self.update_property(property, event))
+	}
+
+	fn property_of(observer_id: i64) -> Option<KnownMpvProperty> {
+		let index = observer_id.checked_sub(i64::from(Self::FIRST_OBSERVER_ID))?;
+		PROPERTIES.get(usize::try_from(index).ok()?).copied()
+	}
+
+	fn update_property<'a>(
+		&mut self,
+		property: KnownMpvProperty,
+		data: impl ChangeData<'a>
+	) -> bool {
+		let dirty = &mut self.dirty;
+		match property {
+			KnownMpvProperty::MediaTitle => match data.parse_str() {
+				Ok(value) => set_str(
+					&mut self.media_title,
+					value.as_deref(),
+					&mut dirty.media_title
+				),
+				Err(err) => parse_failed(property, err)
+			},
+			KnownMpvProperty::TimePos => {
+				set_parsed(&mut self.time_pos, property, &data, &mut dirty.time_pos)
+			}
+			KnownMpvProperty::Duration => {
+				set_parsed(&mut self.duration, property, &data, &mut dirty.duration)
+			}
+			KnownMpvProperty::Pause => {
+				set_parsed(&mut self.pause, property, &data, &mut dirty.pause)
+			}
+			KnownMpvProperty::Volume => {
+				set_parsed(&mut self.volume, property, &data, &mut dirty.volume)
+			}
+			KnownMpvProperty::PlaylistPos => set_parsed(
+				&mut self.playlist_pos,
+				property,
+				&data,
+				&mut dirty.playlist_pos
+			),
+			KnownMpvProperty::PlaylistCount => set_parsed(
+				&mut self.playlist_count,
+				property,
+				&data,
+				&mut dirty.playlist_count
+			),
+			_ => false
+		}
+	}
+}
+
+/// Sets `value` parsed from `data`, marking `dirty` if it changed.
+fn set_parsed<'a, T: Deserialize<'a> + PartialEq>(
+	value: &mut Option<T>,
+	property: KnownMpvProperty,
+	data: &impl ChangeData<'a>,
+	dirty: &mut bool
+) -> bool {
+	// missing data and null both mean unavailable
+	let new_value = match data.parse() {
+		Ok(new_value) => new_value,
+		Err(err) => return parse_failed(property, err)
+	};
+	if *value == new_value {
+		return false
+	}
+
+	*value = new_value;
+	*dirty = true;
+	true
+}
+
+/// Sets `value` reusing the allocated string, marking `dirty` if it changed.
+fn set_str(value: &mut Option<String>, new_value: Option<&str>, dirty: &mut bool) -> bool {
+	match (value.as_mut(), new_value) {
+		(Some(value), Some(new_value)) if value == new_value => return false,
+		(Some(value), Some(new_value)) => {
+			value.clear();
+			value.push_str(new_value);
+		}
+		(None, None) => return false,
+		(_, new_value) => *value = new_value.map(String::from)
+	}
+
+	*dirty = true;
+	true
+}
+
+fn parse_failed(property: KnownMpvProperty, err: serde_json::Error) -> bool {
+	log::warn!(
+		"Could not parse value of {}: {}",
+		property.proeprty_name(),
+		err
+	);
+	false
+}
+
+#[cfg(test)]
+mod test {
+	use crate::command::response::{MpvResponseEvent, MpvResponseRef};
+
+	use super::{PlayerModel, PlayerModelDirty};
+
+	fn update(model: &mut PlayerModel, line: &str) -> bool {
+		match MpvResponseRef::from_slice(line.as_bytes()).unwrap() {
+			MpvResponseRef::Event(event) => model.update_ref(&event),
+			MpvResponseRef::Result(_) => unreachable!()
+		}
+	}
+
+	#[test]
+	fn test_update() {
+		let mut model = PlayerModel::default();
+
+		// observer ids start at 1 << 26
+		assert!(update(
+			&mut model,
+			r#"{"event":"property-change","id":67108864,"name":"media-title","data":"Title \"1\""}"#
+		));
+		assert!(update(
+			&mut model,
+			r#"{"event":"property-change","id":67108868,"name":"volume","data":50.0}"#
+		));
+		assert_eq!(model.media_title.as_deref(), Some("Title \"1\""));
+		assert_eq!(model.volume, Some(50.0));
+		assert_eq!(
+			model.dirty(),
+			PlayerModelDirty {
+				media_title: true,
+				volume: true,
+				..Default::default()
+			}
+		);
+
+		model.clear_dirty();
+		// unchanged values do not mark the field dirty
+		assert!(!update(
+			&mut model,
+			r#"{"event":"property-change","id":67108868,"name":"volume","data":50.0}"#
+		));
+		// duration was not known to begin with
+		assert!(!update(
+			&mut model,
+			r#"{"event":"property-change","id":67108866,"name":"duration"}"#
+		));
+		assert!(update(
+			&mut model,
+			r#"{"event":"property-change","id":67108864,"name":"media-title","data":null}"#
+		));
+		assert_eq!(model.media_title, None);
+		// other observers and events are ignored
+		assert!(!update(
+			&mut model,
+			r#"{"event":"property-change","id":7,"name":"volume","data":10.0}"#
+		));
+		assert!(!update(&mut model, r#"{"event":"seek"}"#));
+
+		// queued events update the model as well
+		let event: MpvResponseEvent = serde_json::from_str(
+			r#"{"event":"property-change","id":67108867,"name":"pause","data":true}"#
+		)
+		.unwrap();
+		assert!(model.update(&event));
+		assert_eq!(model.pause, Some(true));
+		assert_eq!(
+			model.dirty(),
+			PlayerModelDirty {
+				media_title: true,
+				pause: true,
+				..Default::default()
+			}
+		);
+	}
+}
@@ -0,0 +1,103 @@
+use std::fmt;
+
+/// Version of the mpv server, parsed from the `mpv-version` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MpvVersion {
+	pub major: u16,
+	pub minor: u16,
+	pub patch: u16
+}
+impl MpvVersion {
+	pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+		MpvVersion {
+			major,
+			minor,
+			patch
+		}
+	}
+
+	/// Parses values such as `mpv 0.38.0`, `mpv v0.37.0-dirty` or `mpv 0.36.0-540-g5dd2d19e5e`.
+	///
+	/// Builds from git without a release tag (e.g. `mpv v0.38.0-12-gabc` counts as `0.38.0`) are treated as
+	/// the release they are based on.
+	pub fn parse(value: &str) -> Option<Self> {
+		let value = value.trim();
+		let value = value.strip_prefix("mpv ").unwrap_or(value);
+		let value = value.strip_prefix('v').unwrap_or(value);
+
+		let end = value
+			.find(|c: char| !c.is_ascii_digit() && c != '.')
+			.unwrap_or(value.len());
+		let mut parts = value[.. end].split('.');
+
+		let major = parts.next()?.parse().ok()?;
+		let minor = parts.next()?.parse().ok()?;
+		let patch = match parts.next() {
+			None | Some("") => 0,
+			Some(patch) => patch.parse().ok()?
+		};
+
+		Some(MpvVersion::new(major, minor, patch))
+	}
+}
+impl fmt::Display for MpvVersion {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+	}
+}
+
+/// Behavior introduced in a specific mpv version, used by commands to adapt to the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionGate {
+	pub since: MpvVersion
+}
+impl VersionGate {
+	pub const fn since(major: u16, minor: u16, patch: u16) -> Self {
+		VersionGate {
+			since: MpvVersion::new(major, minor, patch)
+		}
+	}
+
+	/// Whether a server of `version` has the behavior.
+	///
+	/// An unknown version is assumed to be recent enough, since the version is only unknown when
+	/// the `mpv-version` property could not be read.
+	pub fn is_open(&self, version: Option<MpvVersion>) -> bool {
+		match version {
+			None => true,
+			Some(version) => version >= self.since
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{MpvVersion, VersionGate};
+
+	#[test]
+	fn test_parse_version() {
+		assert_eq!(
+			MpvVersion::parse("mpv 0.38.0"),
+			Some(MpvVersion::new(0, 38, 0))
+		);
+		assert_eq!(
+			MpvVersion::parse("mpv v0.37.0-dirty"),
+			Some(MpvVersion::new(0, 37, 0))
+		);
+		assert_eq!(
+			MpvVersion::parse("mpv 0.36.0-540-g5dd2d19e5e"),
+			Some(MpvVersion::new(0, 36, 0))
+		);
+		assert_eq!(MpvVersion::parse("mpv git-2023"), None);
+	}
+
+	#[test]
+	fn test_version_gate() {
+		let gate = VersionGate::since(0, 38, 0);
+
+		assert!(gate.is_open(Some(MpvVersion::new(0, 38, 0))));
+		assert!(gate.is_open(Some(MpvVersion::new(1, 0, 0))));
+		assert!(!gate.is_open(Some(MpvVersion::new(0, 37, 1))));
+		assert!(gate.is_open(None));
+	}
+}
@@ -0,0 +1,211 @@
+use serde_json::json;
+
+use crate::{
+	command::{
+		commands::{CmdGetProperty, CmdSetProperty},
+		property::{self, MpvProperty},
+		ParseError
+	},
+	link::{batch::BatchedValues, CommandError, MpvLink}
+};
+
+/// Zoom, pan, rotation and alignment of the video within the window, for building zoom and pan controls.
+///
+/// The fields mirror the properties of the same names and `Default` is the value mpv starts with. Zoom is
+/// logarithmic, `1.0` doubles the size. Pan and align are relative to the video size and the window, see
+/// the mpv manual for the exact semantics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoTransform {
+	/// `video-zoom`
+	pub zoom: f64,
+	/// `video-pan-x`
+	pub pan_x: f64,
+	/// `video-pan-y`
+	pub pan_y: f64,
+	/// `video-rotate` in degrees clockwise.
+	pub rotate: u32,
+	/// `video-align-x`, from `-1.0` for left to `1.0` for right.
+	pub align_x: f64,
+	/// `video-align-y`, from `-1.0` for top to `1.0` for bottom.
+	pub align_y: f64,
+	/// `panscan`, how much of the video is cropped to fill the window, from `0.0` to `1.0`.
+	pub panscan: f64,
+	/// `keepaspect`
+	pub keepaspect: bool
+}
+impl VideoTransform {
+	/// Fetches all properties in one batch, see `MpvLink::run_commands_batched`.
+	pub fn fetch(link: &mut MpvLink) -> Result<Self, CommandError<ParseError>> {
+		let commands = [
+			CmdGetProperty::new(property::VideoZoom.name()),
+			CmdGetProperty::new(property::VideoPanX.name()),
+			CmdGetProperty::new(property::VideoPanY.name()),
+			CmdGetProperty::new(property::VideoRotate.name()),
+			CmdGetProperty::new(property::VideoAlignX.name()),
+			CmdGetProperty::new(property::VideoAlignY.name()),
+			CmdGetProperty::new(property::Panscan.name()),
+			CmdGetProperty::new(property::Keepaspect.name())
+		];
+
+		let mut values = BatchedValues::new(link.run_commands_batched(&commands)?);
+		Ok(VideoTransform {
+			zoom: values.next_required()?,
+			pan_x: values.next_required()?,
+			pan_y: values.next_required()?,
+			rotate: values.next_required()?,
+			align_x: values.next_required()?,
+			align_y: values.next_required()?,
+			panscan: values.next_required()?,
+			keepaspect: values.next_required()?
+		})
+	}
+
+	/// Sets all properties in one batch.
+	///
+	/// Every property is set even if some fail, the first error is returned.
+	pub fn apply(&self, link: &mut MpvLink) -> Result<(), CommandError<ParseError>> {
+		let commands = [
+			CmdSetProperty::new(property::VideoZoom.name(), json!(self.zoom)),
+			CmdSetProperty::new(property::VideoPanX.name(), json!(self.pan_x)),
+			CmdSetProperty::new(property::VideoPanY.name(), json!(self.pan_y)),
+			CmdSetProperty::new(property::VideoRotate.name(), json!(self.rotate)),
+			CmdSetProperty::new(property::VideoAlignX.name(), json!(self.align_x)),
+			CmdSetProperty::new(property::VideoAlignY.name(), json!(self.align_y)),
+			CmdSetProperty::new(property::Panscan.name(), json!(self.panscan)),
+			CmdSetProperty::new(property::Keepaspect.name(), json!(self.keepaspect))
+		];
+		for result in link.run_commands_batched(&commands)? {
+			result?;
+		}
+
+		Ok(())
+	}
+
+	/// Applies the default transform and returns it.
+	pub fn reset(link: &mut MpvLink) -> Result<Self, CommandError<ParseError>> {
+		let transform = VideoTransform::default();
+		transform.apply(link)?;
+
+		Ok(transform)
+	}
+}
+impl Default for VideoTransform {
+	fn default() -> Self {
+		VideoTransform {
+			zoom: 0.0,
+			pan_x: 0.0,
+			pan_y: 0.0,
+			rotate: 0,
+			align_x: 0.0,
+			align_y: 0.0,
+			panscan: 0.0,
+			keepaspect: true
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{io::Write, os::unix::net::UnixStream, thread::JoinHandle};
+
+	use super::VideoTransform;
+	use crate::{
+		command::{response::MpvResponseResultError, ParseError},
+		link::{fake_mpv, CommandError, MpvLink}
+	};
+
+	/// Link to a fake mpv answering the batch of `VideoTransform::fetch` with `answers`, where `None` answers
+	/// that the property is unavailable.
+	fn fake_link(
+		name: &str,
+		answers: [Option<&'static str>; 8]
+	) -> (MpvLink, JoinHandle<UnixStream>) {
+		let path = std::env::temp_dir().join(format!(
+			"mpvsock-video-{}-{}.sock",
+			name,
+			std::process::id()
+		));
+		let server = fake_mpv::listen(&path, move |requests, socket| {
+			for answer in answers {
+				let line = requests.next().unwrap().unwrap();
+				match answer {
+					Some(data) => fake_mpv::write_result(&mut *socket, &line, data),
+					None => {
+						let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+						writeln!(
+							socket,
+							"{{\"error\":\"property unavailable\",\"request_id\":{}}}",
+							request["request_id"]
+						)
+						.unwrap();
+					}
+				}
+			}
+		});
+
+		let link = MpvLink::connect(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+
+		(link, server)
+	}
+
+	#[test]
+	fn test_fetch() {
+		let (mut link, server) = fake_link(
+			"fetch",
+			[
+				Some("0.5"),
+				Some("-0.25"),
+				Some("0"),
+				Some("90"),
+				Some("0"),
+				Some("1"),
+				Some("0.0"),
+				Some("false")
+			]
+		);
+
+		assert_eq!(
+			VideoTransform::fetch(&mut link).unwrap(),
+			VideoTransform {
+				zoom: 0.5,
+				pan_x: -0.25,
+				rotate: 90,
+				align_y: 1.0,
+				keepaspect: false,
+				..Default::default()
+			}
+		);
+		let _socket = server.join().unwrap();
+	}
+
+	#[test]
+	fn test_fetch_unavailable() {
+		let mut answers = [Some("0"); 8];
+		answers[3] = None;
+		answers[7] = Some("true");
+		let (mut link, server) = fake_link("unavailable", answers);
+
+		assert!(matches!(
+			VideoTransform::fetch(&mut link),
+			Err(CommandError::ResultError(
+				MpvResponseResultError::PropertyUnavailable
+			))
+		));
+		let _socket = server.join().unwrap();
+	}
+
+	#[test]
+	fn test_fetch_null() {
+		let mut answers = [Some("0"); 8];
+		answers[0] = Some("null");
+		answers[7] = Some("true");
+		let (mut link, server) = fake_link("null", answers);
+
+		assert!(matches!(
+			VideoTransform::fetch(&mut link),
+			Err(CommandError::DataParseError(ParseError::MissingData))
+		));
+		let _socket = server.join().unwrap();
+	}
+}
@@ -0,0 +1,334 @@
+//! Tests running the bundled commands against a real mpv found in `PATH`.
+//!
+//! Enabled with the `live-tests` feature, each test is skipped when mpv is not installed.
+#![cfg(feature = "live-tests")]
+
+use std::{borrow::Cow, collections::HashMap, io, path::Path, time::Duration};
+
+use mpvsock::{
+	command::{
+		commands::{
+			CmdGetProperty,
+			CmdLoadfile,
+			CmdObserveProperty,
+			CmdPlaylistNext,
+			CmdRawJsonArgs,
+			CmdSeek,
+			CmdSetProperty,
+			CmdStop,
+			SeekMode
+		},
+		property::{self, KnownMpvProperty},
+		response::{MpvResponseEvent, MpvResponseEventPropertyName, MpvResponseResultError}
+	},
+	link::{spawn::MpvSpawnBuilder, CommandError, MpvLink, MpvLinkInitError},
+	model::TrackId,
+	player::{LoadOutcome, MpvPlayer},
+	playlist::PlaylistCursor,
+	property_cache::PropertyCache,
+	resume::{ResumeKey, ResumeTracker},
+	testing::fixtures::MediaFixture,
+	video::VideoTransform
+};
+
+const EVENT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Length of the generated test media.
+const MEDIA_DURATION: Duration = Duration::from_secs(3);
+
+/// Spawns mpv without any output, or returns `None` if mpv is not installed.
+fn spawn_mpv() -> Option<MpvLink> {
+	let builder =
+		MpvSpawnBuilder::new().args(["--no-config", "--no-video", "--ao=null", "--keep-open=yes"]);
+
+	match builder.spawn_client() {
+		Ok(link) => Some(link),
+		Err(MpvLinkInitError::Spawn(err)) if err.kind() == io::ErrorKind::NotFound => {
+			eprintln!("mpv not found in PATH, skipping");
+			None
+		}
+		Err(err) => panic!("Failed to spawn mpv: {}", err)
+	}
+}
+
+/// Loads `fixture` paused, waiting until it is loaded.
+fn load_media(link: &mut MpvLink, fixture: &MediaFixture) {
+	link.run_command(&CmdSetProperty::new(property::Pause, true))
+		.unwrap();
+	link.run_command(&CmdLoadfile::new(Cow::Owned(fixture.path_str())))
+		.unwrap();
+	link.wait_for_event(
+		|event| matches!(event, MpvResponseEvent::FileLoaded),
+		Some(EVENT_TIMEOUT)
+	)
+	.unwrap();
+}
+
+#[test]
+fn test_get_set_property() {
+	let Some(mut link) = spawn_mpv() else { return };
+
+	link.run_command(&CmdSetProperty::new(property::Volume, 42.0))
+		.unwrap();
+	let volume = link
+		.run_command(&CmdGetProperty::new(property::Volume))
+		.unwrap();
+	assert_eq!(volume, 42.0);
+
+	let count = link
+		.run_command(&CmdGetProperty::new(property::PlaylistCount))
+		.unwrap();
+	assert_eq!(count, 0);
+}
+
+#[test]
+fn test_try_get_property() {
+	let Some(mut link) = spawn_mpv() else { return };
+
+	assert_eq!(link.try_get_property(property::Duration).unwrap(), None);
+	assert_eq!(link.get_property_or(property::Duration, 0.0).unwrap(), 0.0);
+	assert_eq!(link.try_get_property("no-such-property").unwrap(), None);
+	assert!(link.try_get_property(property::Volume).unwrap().is_some());
+}
+
+#[test]
+fn test_error_result() {
+	let Some(mut link) = spawn_mpv() else { return };
+
+	match link.run_command(&CmdRawJsonArgs::new(
+		r#"["get_property", "no-such-property"]"#
+	)) {
+		Err(CommandError::ResultError(MpvResponseResultError::PropertyNotFound)) => (),
+		result => panic!("Expected PropertyNotFound but found {:?}", result)
+	}
+	// the link stays usable after an error result
+	link.run_command(&CmdGetProperty::new(property::Pause))
+		.unwrap();
+}
+
+#[test]
+fn test_observe_property() {
+	let Some(mut link) = spawn_mpv() else { return };
+
+	link.run_command(&CmdObserveProperty::new(7, property::Pause))
+		.unwrap();
+	link.run_command(&CmdSetProperty::new(property::Pause, true))
+		.unwrap();
+
+	let event = link
+		.wait_for_event(
+			|event| {
+				matches!(
+					event,
+					MpvResponseEvent::PropertyChange {
+						id: 7,
+						name: MpvResponseEventPropertyName::Known(KnownMpvProperty::Pause),
+						data: serde_json::Value::Bool(true)
+					}
+				)
+			},
+			Some(EVENT_TIMEOUT)
+		)
+		.unwrap();
+	assert_eq!(event.name(), "property-change");
+}
+
+#[test]
+fn test_loadfile_and_seek() {
+	let Some(mut link) = spawn_mpv() else { return };
+	let fixture = MediaFixture::silence(MEDIA_DURATION).unwrap();
+	load_media(&mut link, &fixture);
+
+	let duration = link
+		.run_command(&CmdGetProperty::new(property::Duration))
+		.unwrap();
+	assert!(
+		(duration - MEDIA_DURATION.as_secs_f64()).abs() < 0.1,
+		"{}",
+		duration
+	);
+	let loaded_path = link
+		.run_command(&CmdGetProperty::new(property::Path))
+		.unwrap();
+	assert_eq!(Path::new(&loaded_path), fixture.path());
+
+	link.run_command(&CmdSeek::new(1.5, SeekMode::Absolute).unwrap())
+		.unwrap();
+	link.wait_for_event(
+		|event| matches!(event, MpvResponseEvent::PlaybackRestart),
+		Some(EVENT_TIMEOUT)
+	)
+	.unwrap();
+	let time_pos = link
+		.run_command(&CmdGetProperty::new(property::TimePos))
+		.unwrap();
+	assert!((time_pos - 1.5).abs() < 0.1, "{}", time_pos);
+}
+
+#[test]
+fn test_load_and_wait() {
+	let Some(link) = spawn_mpv() else { return };
+	let mut player = MpvPlayer::new(link);
+
+	let fixture = MediaFixture::silence(MEDIA_DURATION).unwrap();
+	let outcome = player
+		.load_and_wait(&fixture.path_str(), Some(EVENT_TIMEOUT))
+		.unwrap();
+	assert_eq!(outcome, LoadOutcome::Started);
+
+	let outcome = player
+		.load_and_wait("/nonexistent/file.mkv", Some(EVENT_TIMEOUT))
+		.unwrap();
+	assert!(
+		matches!(outcome, LoadOutcome::Failed { .. }),
+		"{:?}",
+		outcome
+	);
+}
+
+#[test]
+fn test_wait_until_idle() {
+	let Some(link) = spawn_mpv() else { return };
+	let mut player = MpvPlayer::new(link);
+	player.wait_until_idle(Some(EVENT_TIMEOUT)).unwrap();
+
+	let fixture = MediaFixture::silence(MEDIA_DURATION).unwrap();
+	// the file ends instead of pausing on its last frame
+	player
+		.run(&CmdSetProperty::new(property::KeepOpen, false))
+		.unwrap();
+	player
+		.load_and_wait(&fixture.path_str(), Some(EVENT_TIMEOUT))
+		.unwrap();
+	player.wait_until_idle(Some(EVENT_TIMEOUT)).unwrap();
+
+	// mpv may close the connection before replying to quit
+	let _ = player.run(&CmdRawJsonArgs::new(r#"["quit"]"#));
+	player.wait_for_shutdown().unwrap();
+}
+
+/// Waits until `time-pos` reaches `position`, returning the change.
+fn wait_for_time_pos(link: &mut MpvLink, position: f64) -> MpvResponseEvent {
+	link.wait_for_event(
+		|event| match event {
+			MpvResponseEvent::PropertyChange {
+				name: MpvResponseEventPropertyName::Known(KnownMpvProperty::TimePos),
+				data,
+				..
+			} => data.as_f64().is_some_and(|time_pos| time_pos >= position),
+			_ => false
+		},
+		Some(EVENT_TIMEOUT)
+	)
+	.unwrap()
+}
+
+#[test]
+fn test_resume_tracker() {
+	let Some(mut link) = spawn_mpv() else { return };
+	let mut cache = PropertyCache::new();
+	ResumeTracker::<HashMap<ResumeKey, f64>>::observe(&mut cache, &mut link).unwrap();
+	let mut tracker = ResumeTracker::new(HashMap::new()).with_min_position(1.0);
+
+	let fixture = MediaFixture::silence(MEDIA_DURATION).unwrap();
+	load_media(&mut link, &fixture);
+	tracker
+		.update(&mut link, &MpvResponseEvent::FileLoaded)
+		.unwrap();
+	link.run_command(&CmdSeek::time(2.0, true).unwrap())
+		.unwrap();
+	let change = wait_for_time_pos(&mut link, 1.9);
+	tracker.update(&mut link, &change).unwrap();
+
+	link.run_command(&CmdStop::new(false)).unwrap();
+	let end = link
+		.wait_for_event(
+			|event| matches!(event, MpvResponseEvent::EndFile { .. }),
+			Some(EVENT_TIMEOUT)
+		)
+		.unwrap();
+	tracker.update(&mut link, &end).unwrap();
+	let key = ResumeKey::Path(fixture.path_str());
+	let position = tracker.store()[&key];
+	assert!((position - 2.0).abs() < 0.1, "{}", position);
+
+	load_media(&mut link, &fixture);
+	link.drain_events().for_each(drop);
+	tracker
+		.update(&mut link, &MpvResponseEvent::FileLoaded)
+		.unwrap();
+	wait_for_time_pos(&mut link, 1.9);
+}
+
+#[test]
+fn test_playlist_cursor() {
+	let Some(mut link) = spawn_mpv() else { return };
+	assert_eq!(PlaylistCursor::fetch(&mut link).unwrap().current(), None);
+
+	let first = MediaFixture::silence(MEDIA_DURATION).unwrap();
+	let second = MediaFixture::silence(MEDIA_DURATION).unwrap();
+	load_media(&mut link, &first);
+	link.run_command(&CmdLoadfile::new_append(Cow::Owned(second.path_str())))
+		.unwrap();
+	let cursor = PlaylistCursor::fetch(&mut link).unwrap();
+	assert_eq!(cursor.current(), Some(0), "{:?}", cursor);
+	assert_eq!(cursor.pos_1, Some(0), "{:?}", cursor);
+	assert!(!cursor.is_loading(), "{:?}", cursor);
+
+	link.run_command(&CmdPlaylistNext::new(false)).unwrap();
+	link.wait_for_event(
+		|event| matches!(event, MpvResponseEvent::FileLoaded),
+		Some(EVENT_TIMEOUT)
+	)
+	.unwrap();
+	let cursor = PlaylistCursor::fetch(&mut link).unwrap();
+	assert_eq!(cursor.current(), Some(1), "{:?}", cursor);
+	assert_eq!(cursor.playing_pos, Some(1), "{:?}", cursor);
+}
+
+#[test]
+fn test_tracks() {
+	let Some(mut link) = spawn_mpv() else { return };
+	let fixture = match MediaFixture::color_video(MEDIA_DURATION) {
+		Ok(fixture) => fixture,
+		Err(err) if err.kind() == io::ErrorKind::NotFound => {
+			eprintln!("ffmpeg not found in PATH, skipping");
+			return
+		}
+		Err(err) => panic!("Failed to generate test media: {}", err)
+	};
+	load_media(&mut link, &fixture);
+
+	let vid = link
+		.run_command(&CmdGetProperty::new(property::Vid))
+		.unwrap();
+	assert!(matches!(vid, TrackId::Index(1)), "{:?}", vid);
+	let aid = link
+		.run_command(&CmdGetProperty::new(property::Aid))
+		.unwrap();
+	assert!(matches!(aid, TrackId::Index(1)), "{:?}", aid);
+}
+
+#[test]
+fn test_video_transform() {
+	let Some(mut link) = spawn_mpv() else { return };
+	assert_eq!(
+		VideoTransform::fetch(&mut link).unwrap(),
+		VideoTransform::default()
+	);
+
+	let transform = VideoTransform {
+		zoom: 0.5,
+		pan_x: -0.25,
+		rotate: 90,
+		keepaspect: false,
+		..Default::default()
+	};
+	transform.apply(&mut link).unwrap();
+	assert_eq!(VideoTransform::fetch(&mut link).unwrap(), transform);
+
+	VideoTransform::reset(&mut link).unwrap();
+	assert_eq!(
+		VideoTransform::fetch(&mut link).unwrap(),
+		VideoTransform::default()
+	);
+}